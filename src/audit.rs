@@ -0,0 +1,64 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Something the bot did that others can see
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    CommentPosted,
+    CommentEdited,
+    CommentDeleted,
+    SelfAssigned,
+    Reacted,
+    Unassigned,
+    Claimed,
+    ClaimResumed,
+    BranchCreated,
+    DraftPrOpened,
+    StateCleared,
+    Withdrawn,
+}
+
+/// One line of the append-only audit log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub at: DateTime<Utc>,
+    pub action: AuditAction,
+    pub repo: String,
+    pub issue_number: u64,
+    pub success: bool,
+    /// What GitHub answered, or why the action failed
+    pub detail: String,
+}
+
+impl AuditEntry {
+    pub fn new(
+        action: AuditAction,
+        owner: &str,
+        repo: &str,
+        issue_number: u64,
+        detail: impl Into<String>,
+    ) -> Self {
+        Self {
+            at: Utc::now(),
+            action,
+            repo: format!("{}/{}", owner, repo),
+            issue_number,
+            success: true,
+            detail: detail.into(),
+        }
+    }
+
+    pub fn failure(
+        action: AuditAction,
+        owner: &str,
+        repo: &str,
+        issue_number: u64,
+        error: &impl std::fmt::Display,
+    ) -> Self {
+        Self {
+            success: false,
+            ..Self::new(action, owner, repo, issue_number, error.to_string())
+        }
+    }
+}
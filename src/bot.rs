@@ -1,16 +1,40 @@
-use anyhow::Result;
-use chrono::{DateTime, Duration, Utc};
-use log::{debug, info, warn};
-use rand::{seq::SliceRandom, thread_rng, Rng};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, FixedOffset, Utc};
+use rand::{seq::SliceRandom, thread_rng};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::Duration as StdDuration;
 use tokio::time;
+use tracing::{debug, error, info, instrument, warn, Span};
 
-use crate::config::{Config, Repository};
-use crate::github::{GitHubClient, Issue};
+use crate::audit::{AuditAction, AuditEntry};
+use crate::config::{ClaimMethod, Config, LostClaimComment, Repository};
+use crate::conventions::{self, RepoConventions};
+use crate::cursor::{self, RepoCursor, RepoCursors};
+use crate::daytime;
+use crate::difficulty;
+use crate::forge::Forge;
+use crate::github::{
+    github_error, matches_labels_and_title, parse_issue_url, Comment, GitHubError, Issue,
+    LinkedPullRequest, PullRequestState,
+};
+use crate::health::Health;
+use crate::history::{HistoryEntry, Outcome, Responsiveness, TemplateStats};
+use crate::hooks::{Decision, Hooks};
+use crate::leader::Leader;
+use crate::lint;
+use crate::notify::{Action, Event, IssueInfo, Notifications};
+use crate::pacing;
 use crate::persistence::Persistence;
+use crate::processed::{self, ProcessedIssue, ProcessedIssues, ProcessedReason};
+use crate::queue::{CandidateQueue, CandidateStatus};
+use crate::ratelimit::RateBudget;
+use crate::replies::{self, Verdict};
+use crate::rules::{self, Rule, RuleAction};
+use crate::team::Team;
+use crate::workspace::{self, WorkBranch};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ActiveIssue {
@@ -18,217 +42,3763 @@ pub struct ActiveIssue {
     pub repo_name: String,
     pub issue_number: u64,
     pub issue_url: String,
+    #[serde(default)]
+    pub issue_title: String,
+    #[serde(default)]
+    pub labels: Vec<String>,
     pub requested_at: DateTime<Utc>,
     pub timeout: DateTime<Utc>,
+    #[serde(default)]
+    pub comment_id: Option<u64>,
+    /// When we nudged the maintainers with `ping_comment`
+    #[serde(default)]
+    pub pinged_at: Option<DateTime<Utc>>,
+    /// The comment template the claim was made with, for `history`
+    #[serde(default)]
+    pub template: Option<String>,
+    /// Who had the issue when we asked if it's still being worked on, empty
+    /// for a regular claim
+    #[serde(default)]
+    pub stale_assignees: Vec<String>,
+    /// The rule the issue was claimed through
+    #[serde(default)]
+    pub rule: Option<String>,
 }
 
-pub struct Bot<T: GitHubClient, P: Persistence> {
+/// Written before a claim comment is posted and removed once the claim is
+/// saved, so a crash in between can be sorted out on the next start
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClaimIntent {
+    pub repo_owner: String,
+    pub repo_name: String,
+    pub issue_number: u64,
+    pub created_at: DateTime<Utc>,
+    /// Who was assigned, when asking about a stale assignment
+    #[serde(default)]
+    pub stale_assignees: Vec<String>,
+}
+
+/// An issue we got assigned to and are now working on
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssignedIssue {
+    pub repo_owner: String,
+    pub repo_name: String,
+    pub issue_number: u64,
+    pub issue_url: String,
+    pub issue_title: String,
+    pub requested_at: DateTime<Utc>,
+    pub assigned_at: DateTime<Utc>,
+    pub due_at: DateTime<Utc>,
+    #[serde(default)]
+    pub branch: Option<WorkBranch>,
+    #[serde(default)]
+    pub draft_pr_url: Option<String>,
+    #[serde(default)]
+    pub linked_prs: Vec<LinkedPullRequest>,
+    #[serde(default)]
+    pub prs_checked_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub deadline_reminder: Option<DeadlineReminder>,
+    #[serde(default)]
+    pub template: Option<String>,
+    /// Taken over from a quiet assignee
+    #[serde(default)]
+    pub stale: bool,
+    #[serde(default)]
+    pub rule: Option<String>,
+}
+
+impl AssignedIssue {
+    /// Whether a pull request for the issue is open or merged. The draft
+    /// from `draft_pr_on_assignment` only counts once it's marked ready,
+    /// until then it's an empty placeholder.
+    pub fn has_pull_request(&self) -> bool {
+        self.linked_prs.iter().any(|pr| match pr.state {
+            PullRequestState::Closed => false,
+            PullRequestState::Draft => self.draft_pr_url.as_ref() != Some(&pr.html_url),
+            PullRequestState::Open | PullRequestState::Merged => true,
+        })
+    }
+}
+
+/// The last deadline reminder sent for an assigned issue
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeadlineReminder {
+    Approaching,
+    Passed,
+}
+
+/// What one poll got up to, logged in a single line once it's over
+#[derive(Debug, Default)]
+struct CycleSummary {
+    repos_polled: usize,
+    /// Issues that got through every filter
+    candidates: usize,
+    /// The first of them, which the poll tried to claim first
+    top_candidate: Option<String>,
+    /// What the poll did about it, None if it found nothing to do
+    action: Option<String>,
+    /// As `get_rate_limit` reported it during the poll
+    rate_limit_remaining: Option<u32>,
+}
+
+/// How a reply in the comments settled a claim
+enum ClaimReply {
+    /// A maintainer handed the issue over without assigning it
+    Accepted(Comment),
+    /// Turned down, with the reason to give
+    Rejected(String),
+}
+
+/// How often to look for pull requests linked to assigned issues
+const PR_REFRESH_MINUTES: i64 = 15;
+
+/// How often to check for notification button presses
+const ACTIONS_POLL_SECS: u64 = 10;
+
+pub struct Bot<T: Forge, P: Persistence> {
     config: Config,
-    github_client: T,
+    forge: T,
     persistence: P,
     active_issue: Arc<Mutex<Option<ActiveIssue>>>,
-    processed_issues: Arc<Mutex<HashSet<u64>>>,
+    processed_issues: Arc<Mutex<ProcessedIssues>>,
+    cursors: Arc<Mutex<RepoCursors>>,
+    conventions: Arc<Mutex<HashMap<String, RepoConventions>>>,
+    /// Maintainers' UTC offsets guessed from commit times, None for the
+    /// repositories without a clear one
+    utc_offsets: Arc<Mutex<HashMap<String, Option<FixedOffset>>>>,
+    /// Claim templates, from `comment_templates_dir` when it has any
+    templates: Arc<Mutex<Vec<String>>>,
+    assigned_issues: Arc<Mutex<Vec<AssignedIssue>>>,
+    history: Arc<Mutex<Vec<HistoryEntry>>>,
+    notifications: Notifications,
+    notifications_since: Arc<Mutex<DateTime<Utc>>>,
+    rate_budget: Arc<RateBudget>,
+    last_error: Arc<Mutex<Option<String>>>,
+    snoozed_repos: Arc<Mutex<HashMap<String, DateTime<Utc>>>>,
+    /// Repositories GitHub says are gone or off limits, skipped until restart
+    disabled_repos: Arc<Mutex<HashSet<String>>>,
+    /// Repositories with eligible issues left behind, listed again whatever
+    /// their events feed says
+    unsettled_repos: Arc<Mutex<HashSet<String>>>,
+    health: Arc<Health>,
+    hooks: Hooks,
+    team: Option<Team>,
+    /// Set when replicas take turns, only the leader polls
+    leader: Option<Arc<Leader>>,
+    /// Whether we led during the last poll, to notice taking over
+    led_last_poll: Mutex<bool>,
+    /// Polls so far, numbering the `cycle_id` of each one's log lines
+    cycles: AtomicU64,
+    /// Whether a `repos_failed` notification went out for the ongoing
+    /// failures, so they're only reported once
+    failure_alert_sent: Mutex<bool>,
+    /// The poll underway
+    cycle: Mutex<CycleSummary>,
+    /// Repositories where pull requests of ours were merged, lowercase,
+    /// looked up on the first poll that needs them
+    contributed_repos: Mutex<Option<HashSet<String>>>,
+}
+
+/// Sets up a `Bot` with hooks that watch what it does, or veto it
+pub struct BotBuilder<T: Forge, P: Persistence> {
+    config: Config,
+    forge: T,
+    persistence: P,
+    hooks: Hooks,
+    health: Option<Arc<Health>>,
+}
+
+impl<T: Forge, P: Persistence> BotBuilder<T, P> {
+    pub fn new(config: Config, forge: T, persistence: P) -> Self {
+        Self {
+            config,
+            forge,
+            persistence,
+            hooks: Hooks::default(),
+            health: None,
+        }
+    }
+
+    /// Called for every unprocessed issue that passed the filters. A veto
+    /// skips the issue for this poll.
+    pub fn on_candidate<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&Repository, &Issue) -> Decision + Send + Sync + 'static,
+    {
+        self.hooks.on_candidate.push(Box::new(hook));
+        self
+    }
+
+    /// Called right before requesting assignment. A veto skips the issue for
+    /// this poll.
+    pub fn on_claim<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&Repository, &Issue) -> Decision + Send + Sync + 'static,
+    {
+        self.hooks.on_claim.push(Box::new(hook));
+        self
+    }
+
+    /// Called once we got assigned to an issue we claimed
+    pub fn on_assignment<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&AssignedIssue) + Send + Sync + 'static,
+    {
+        self.hooks.on_assignment.push(Box::new(hook));
+        self
+    }
+
+    /// Called when a poll fails
+    pub fn on_error<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&anyhow::Error) + Send + Sync + 'static,
+    {
+        self.hooks.on_error.push(Box::new(hook));
+        self
+    }
+
+    /// Report polls to this instead of a health tracker of the bot's own, so
+    /// several bots can share the health endpoints
+    pub fn health(mut self, health: Arc<Health>) -> Self {
+        self.health = Some(health);
+        self
+    }
+
+    pub fn build(self) -> Bot<T, P> {
+        let BotBuilder {
+            config,
+            forge,
+            persistence,
+            hooks,
+            health,
+        } = self;
+        let notifications = Notifications::new(config.notifications.clone(), &config.http);
+        let rate_budget = RateBudget::new(config.rate_limit_reserve, config.tick_interval_secs());
+        let templates = config.comment_templates.clone();
+
+        Bot {
+            config,
+            forge,
+            persistence,
+            active_issue: Arc::new(Mutex::new(None)),
+            processed_issues: Arc::new(Mutex::new(ProcessedIssues::default())),
+            cursors: Arc::new(Mutex::new(RepoCursors::new())),
+            conventions: Arc::new(Mutex::new(HashMap::new())),
+            utc_offsets: Arc::new(Mutex::new(HashMap::new())),
+            templates: Arc::new(Mutex::new(templates)),
+            assigned_issues: Arc::new(Mutex::new(Vec::new())),
+            history: Arc::new(Mutex::new(Vec::new())),
+            notifications,
+            notifications_since: Arc::new(Mutex::new(Utc::now())),
+            rate_budget: Arc::new(rate_budget),
+            last_error: Arc::new(Mutex::new(None)),
+            snoozed_repos: Arc::new(Mutex::new(HashMap::new())),
+            disabled_repos: Arc::new(Mutex::new(HashSet::new())),
+            unsettled_repos: Arc::new(Mutex::new(HashSet::new())),
+            health: health.unwrap_or_default(),
+            hooks,
+            team: None,
+            leader: None,
+            led_last_poll: Mutex::new(false),
+            cycles: AtomicU64::new(0),
+            failure_alert_sent: Mutex::new(false),
+            cycle: Mutex::new(CycleSummary::default()),
+            contributed_repos: Mutex::new(None),
+        }
+    }
 }
 
-impl<T: GitHubClient, P: Persistence> Bot<T, P> {
-    pub fn new(config: Config, github_client: T, persistence: P) -> Self {
-        Self {
-            config,
-            github_client,
-            persistence,
-            active_issue: Arc::new(Mutex::new(None)),
-            processed_issues: Arc::new(Mutex::new(HashSet::new())),
+impl<T: Forge, P: Persistence> Bot<T, P> {
+    pub fn new(config: Config, forge: T, persistence: P) -> Self {
+        BotBuilder::new(config, forge, persistence).build()
+    }
+
+    /// Poll status for the health check endpoints
+    pub fn health(&self) -> Arc<Health> {
+        self.health.clone()
+    }
+
+    /// The forge the bot talks to, e.g. to inspect a mock
+    pub fn forge(&self) -> &T {
+        &self.forge
+    }
+
+    /// Share claims with the team configured, if any
+    pub fn join_team(&mut self) -> Result<()> {
+        if let Some(ref team) = self.config.team {
+            let team = Team::new(team.clone(), &self.config.user_login)?;
+            info!("Sharing claims with the team as {}", team.member());
+            self.team = Some(team);
+        }
+        Ok(())
+    }
+
+    pub async fn initialize(&mut self) -> Result<()> {
+        self.join_team()?;
+
+        if let Some(ref ha) = self.config.ha {
+            let leader = Leader::new(ha, &self.config.user_login)?;
+            if let Err(e) = leader.campaign().await {
+                warn!("Failed to run for leader: {}", e);
+            }
+            if !leader.is_leader() {
+                info!("{} is standing by for the leader", leader.instance());
+            }
+            self.leader = Some(Arc::new(leader));
+        }
+
+        self.load_state().await?;
+        self.reload_templates().await;
+
+        // A standby catches up once it takes over
+        if self.is_leader() {
+            self.catch_up().await;
+            *self.led_last_poll.lock().unwrap() = true;
+        }
+
+        self.flush().await;
+        Ok(())
+    }
+
+    /// Bring our state in line with what happened on GitHub while we
+    /// weren't looking
+    async fn catch_up(&self) {
+        // Finish a claim interrupted by a crash before anything else
+        if let Err(e) = self.reconcile_claim_intent().await {
+            warn!("Failed to check on an interrupted claim: {}", e);
+        }
+
+        if let Err(e) = self.reconcile_active_issue().await {
+            warn!("Failed to check on the active issue: {}", e);
+        }
+
+        // Pick up claims made before our state was lost, or by the leader
+        // we took over from
+        if let Err(e) = self.reconcile_existing_claims().await {
+            warn!("Failed to reconcile existing claim comments: {}", e);
+        }
+    }
+
+    /// Whether we may poll and comment, always when not running replicas
+    fn is_leader(&self) -> bool {
+        self.leader.as_ref().is_none_or(|leader| leader.is_leader())
+    }
+
+    /// Load state from persistence. Missing files load as empty, but any
+    /// other failure is an error: starting empty would overwrite the state
+    /// we couldn't read on the next save
+    pub async fn load_state(&mut self) -> Result<()> {
+        let active = self
+            .persistence
+            .load_active_issue()
+            .await
+            .context("Failed to load the active issue")?;
+        *self.active_issue.lock().unwrap() = active;
+
+        let processed = self
+            .persistence
+            .load_processed_issues()
+            .await
+            .context("Failed to load processed issues")?;
+        *self.processed_issues.lock().unwrap() = processed;
+
+        let cursors = self
+            .persistence
+            .load_repo_cursors()
+            .await
+            .context("Failed to load repository cursors")?;
+        *self.cursors.lock().unwrap() = cursors;
+
+        let assigned = self
+            .persistence
+            .load_assigned_issues()
+            .await
+            .context("Failed to load assigned issues")?;
+        *self.assigned_issues.lock().unwrap() = assigned;
+
+        let history = self
+            .persistence
+            .load_history()
+            .await
+            .context("Failed to load history")?;
+        *self.history.lock().unwrap() = history;
+
+        Ok(())
+    }
+
+    /// Give up on an issue we claimed or got assigned to, letting the
+    /// maintainers know and freeing the slot for new claims
+    #[instrument(level = "debug", skip(self, owner, repo), fields(repo = %format!("{}/{}", owner, repo)))]
+    pub async fn abandon(&self, owner: &str, repo: &str, issue_number: u64) -> Result<()> {
+        let assigned = {
+            let mut assigned_lock = self.assigned_issues.lock().unwrap();
+            let index = assigned_lock.iter().position(|issue| {
+                issue.repo_owner == owner
+                    && issue.repo_name == repo
+                    && issue.issue_number == issue_number
+            });
+            index.map(|index| assigned_lock.remove(index))
+        };
+
+        let active = self.active_issue.lock().unwrap().clone().filter(|active| {
+            active.repo_owner == owner
+                && active.repo_name == repo
+                && active.issue_number == issue_number
+        });
+
+        let entry = match (&assigned, &active) {
+            (Some(assigned), _) => HistoryEntry::finished(assigned, Outcome::Abandoned, None),
+            (None, Some(active)) => {
+                HistoryEntry::unassigned(active, Outcome::Abandoned, Utc::now())
+            }
+            (None, None) => {
+                return Err(anyhow::anyhow!(
+                    "Issue #{} in {}/{} is not claimed or assigned",
+                    issue_number,
+                    owner,
+                    repo
+                ))
+            }
+        };
+
+        let issue = self.forge.get_issue(owner, repo, issue_number).await.ok();
+        let locked = issue.as_ref().is_some_and(|issue| issue.locked);
+        // Go by GitHub when we can, someone may have assigned or unassigned
+        // us since we last looked
+        let is_assignee = issue.as_ref().map_or(assigned.is_some(), |issue| {
+            issue.is_assigned_to(&self.config.user_login)
+        });
+        if locked {
+            info!("Not posting a withdrawal comment, the conversation is locked");
+        } else {
+            let text = if is_assignee {
+                &self.config.abandon_comment
+            } else {
+                &self.config.withdraw_comment
+            };
+            let comment = self
+                .post_comment(owner, repo, issue_number, text, &[])
+                .await;
+            self.audit_result(
+                AuditAction::CommentPosted,
+                owner,
+                repo,
+                issue_number,
+                &comment,
+                |id| format!("withdrawal comment {}", id),
+            )
+            .await;
+            if let Err(e) = comment {
+                warn!("Failed to post withdrawal comment: {}", e);
+            }
+        }
+
+        if is_assignee {
+            let unassigned = self.forge.unassign_self(owner, repo, issue_number).await;
+            self.audit_result(
+                AuditAction::Unassigned,
+                owner,
+                repo,
+                issue_number,
+                &unassigned,
+                |_| "removed ourselves from the assignees".to_string(),
+            )
+            .await;
+            if let Err(e) = unassigned {
+                warn!("Failed to unassign ourselves: {}", e);
+            }
+        }
+
+        if let Some(ref team) = self.team {
+            if let Err(e) = team.release(owner, repo, issue_number).await {
+                warn!("Failed to release the issue to the team: {}", e);
+            }
+        }
+
+        if assigned.is_some() {
+            let assigned_issues = self.assigned_issues.lock().unwrap().clone();
+            self.persistence
+                .save_assigned_issues(&assigned_issues)
+                .await?;
+        } else {
+            *self.active_issue.lock().unwrap() = None;
+            self.persistence.clear_active_issue().await?;
+        }
+
+        self.record_history(entry).await?;
+        self.audit(AuditEntry::new(
+            AuditAction::Withdrawn,
+            owner,
+            repo,
+            issue_number,
+            if assigned.is_some() {
+                "dropped assigned issue"
+            } else {
+                "dropped pending claim"
+            },
+        ))
+        .await;
+
+        info!("Abandoned issue #{} in {}/{}", issue_number, owner, repo);
+
+        Ok(())
+    }
+
+    /// A claim intent left behind means we stopped between posting a claim
+    /// comment and saving the claim. Resume the claim if the comment went
+    /// out, otherwise forget it.
+    async fn reconcile_claim_intent(&self) -> Result<()> {
+        let Some(intent) = self.persistence.load_claim_intent().await? else {
+            return Ok(());
+        };
+        let (owner, repo) = (intent.repo_owner.as_str(), intent.repo_name.as_str());
+
+        let already_active = self
+            .active_issue
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|active| {
+                active.repo_owner == owner
+                    && active.repo_name == repo
+                    && active.issue_number == intent.issue_number
+            });
+        if already_active {
+            return self.persistence.clear_claim_intent().await;
+        }
+
+        let issue = match self.forge.get_issue(owner, repo, intent.issue_number).await {
+            Ok(issue) => issue,
+            Err(GitHubError::NotFound(_)) => {
+                info!(
+                    "Issue #{} in {}/{} we were claiming was deleted",
+                    intent.issue_number, owner, repo
+                );
+                return self.persistence.clear_claim_intent().await;
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let (owner, repo) = match transferred_to(owner, repo, intent.issue_number, &issue) {
+            Some((owner, repo, _)) => (owner, repo),
+            None => (owner.to_string(), repo.to_string()),
+        };
+        let (owner, repo) = (owner.as_str(), repo.as_str());
+        match self.find_own_comment(owner, repo, issue.number).await? {
+            Some(comment) => {
+                info!(
+                    "Claim comment on issue #{} in {}/{} was posted before the bot stopped, resuming that claim",
+                    issue.number, owner, repo
+                );
+                self.mark_issue_as_active(
+                    owner,
+                    repo,
+                    &issue,
+                    comment.created_at,
+                    Some((comment.id, &comment.body)),
+                    AuditAction::ClaimResumed,
+                )
+                .await?;
+                if intent.stale_assignees.is_empty() {
+                    return Ok(());
+                }
+                self.set_stale_assignees(intent.stale_assignees).await
+            }
+            None => {
+                info!(
+                    "Claim on issue #{} in {}/{} was interrupted before commenting",
+                    issue.number, owner, repo
+                );
+                self.persistence.clear_claim_intent().await
+            }
+        }
+    }
+
+    /// The active issue may have been closed or assigned while the bot was
+    /// stopped, in which case there's no point waiting out its timeout
+    async fn reconcile_active_issue(&self) -> Result<()> {
+        let Some(active) = self.active_issue.lock().unwrap().clone() else {
+            return Ok(());
+        };
+
+        self.check_active_issue(&active).await?;
+        Ok(())
+    }
+
+    async fn reconcile_existing_claims(&self) -> Result<()> {
+        let mut found = Vec::new();
+
+        // Keep search queries well under GitHub's length limit
+        for chunk in self.config.repositories.chunks(5) {
+            let mut query = format!("is:issue is:open commenter:{}", self.config.user_login);
+            for repo in chunk {
+                query.push_str(&format!(" repo:{}/{}", repo.owner, repo.repo));
+            }
+
+            found.extend(self.forge.search_issues(&query).await?);
+        }
+
+        if let Err(e) = self.restore_claims(&found).await {
+            warn!("Failed to restore claims found on GitHub: {}", e);
+        }
+
+        let processed_issues = {
+            let mut processed_lock = self.processed_issues.lock().unwrap();
+            let before = processed_lock.len();
+            for issue in &found {
+                let Ok((owner, repo, _)) = parse_issue_url(&issue.html_url) else {
+                    continue;
+                };
+                if !processed_lock.contains(&owner, &repo, issue) {
+                    processed_lock.insert(ProcessedIssue::new(
+                        &owner,
+                        &repo,
+                        issue,
+                        ProcessedReason::Claimed,
+                    ));
+                }
+            }
+            if processed_lock.len() == before {
+                return Ok(());
+            }
+
+            info!(
+                "Found {} issues we already commented on, marking them as processed",
+                processed_lock.len() - before
+            );
+            processed_lock.clone()
+        };
+
+        self.persistence
+            .save_processed_issues(&processed_issues)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Claims found on GitHub that our state lost track of. Issues we got
+    /// assigned to move on to the assigned issues, and a claim still waiting
+    /// for assignment becomes the active issue again, so we don't go and
+    /// claim a second one.
+    async fn restore_claims(&self, found: &[Issue]) -> Result<()> {
+        if self.active_issue.lock().unwrap().is_some() {
+            return Ok(());
+        }
+
+        // Assigned issues go through the active slot, so handle them before
+        // a pending claim takes it
+        let (assigned, pending): (Vec<&Issue>, Vec<&Issue>) = found
+            .iter()
+            .partition(|issue| issue.is_assigned_to(&self.config.user_login));
+        let pending = pending
+            .into_iter()
+            .filter(|issue| issue.assignee_logins().is_empty());
+
+        for issue in assigned.into_iter().chain(pending) {
+            let Ok((owner, repo, _)) = parse_issue_url(&issue.html_url) else {
+                continue;
+            };
+            let tracked = self.assigned_issues.lock().unwrap().iter().any(|assigned| {
+                assigned.repo_owner == owner
+                    && assigned.repo_name == repo
+                    && assigned.issue_number == issue.number
+            });
+            if tracked {
+                continue;
+            }
+            let Some(comment) = self.find_own_comment(&owner, &repo, issue.number).await? else {
+                continue;
+            };
+
+            let is_assigned = issue.is_assigned_to(&self.config.user_login);
+            if !is_assigned && comment.created_at + self.claim_timeout(&owner, &repo) < Utc::now() {
+                continue;
+            }
+
+            info!(
+                "Found our claim on issue #{} in {}/{}, resuming it",
+                issue.number, owner, repo
+            );
+            self.mark_issue_as_active(
+                &owner,
+                &repo,
+                issue,
+                comment.created_at,
+                Some((comment.id, &comment.body)),
+                AuditAction::ClaimResumed,
+            )
+            .await?;
+
+            if !is_assigned {
+                return Ok(());
+            }
+            // Move it on to the assigned issues straight away
+            self.reconcile_active_issue().await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn start(&self) -> Result<()> {
+        info!("Starting GitHub issue assignment bot");
+        info!("Monitoring {} repositories", self.config.repositories.len());
+
+        let mut interval = time::interval(StdDuration::from_secs(self.config.tick_interval_secs()));
+        let mut actions_interval = time::interval(StdDuration::from_secs(ACTIONS_POLL_SECS));
+        let mut notifications_interval =
+            time::interval(StdDuration::from_secs(self.config.notifications_poll_secs));
+
+        // Work in progress always finishes, the signal is only noticed between tasks
+        let shutdown = shutdown_signal();
+        tokio::pin!(shutdown);
+
+        let campaign = self.leader.as_ref().map(Leader::spawn);
+
+        let mut fatal = None;
+        loop {
+            // Out of API requests, so sleep through to the reset instead of
+            // waking up every poll only to bail out
+            let resume_in = self
+                .rate_budget
+                .paused_until()
+                .and_then(|until| (until - Utc::now()).to_std().ok());
+
+            tokio::select! {
+                _ = &mut shutdown => {
+                    info!("Shutting down, saving state");
+                    break;
+                }
+                _ = interval.tick(), if resume_in.is_none() => {
+                    if let Err(e) = self.run_poll().await {
+                        fatal = Some(e);
+                        break;
+                    }
+                }
+                _ = time::sleep(resume_in.unwrap_or_default()), if resume_in.is_some() => {
+                    interval.reset();
+                    if let Err(e) = self.run_poll().await {
+                        fatal = Some(e);
+                        break;
+                    }
+                }
+                _ = notifications_interval.tick(), if self.config.watch_notifications && self.is_leader() => {
+                    if let Err(e) = self.poll_notifications().await {
+                        warn!("Error while checking notifications: {}", e);
+                    }
+                }
+                _ = actions_interval.tick(), if self.notifications.has_actions() && self.is_leader() => {
+                    if let Err(e) = self.handle_actions().await {
+                        warn!("Error while handling notification actions: {}", e);
+                    }
+                }
+            }
+        }
+
+        if let Some(campaign) = campaign {
+            campaign.abort();
+        }
+        if let Some(ref leader) = self.leader {
+            // Let a standby take over without waiting for the lease to run out
+            if let Err(e) = leader.resign().await {
+                warn!("Failed to hand over the lead: {}", e);
+            }
+        }
+
+        self.save_state().await?;
+        if let Some(e) = fatal {
+            return Err(e);
+        }
+        info!("State saved, goodbye");
+
+        Ok(())
+    }
+
+    /// A single poll right away, without the pacing of `start`, then save
+    /// the state, as `run --once` does
+    pub async fn run_once(&self) -> Result<()> {
+        info!(
+            "Polling {} repositories once",
+            self.config.repositories.len()
+        );
+
+        let result = self.poll_repositories().await;
+        self.save_state().await?;
+        self.save_heartbeat().await;
+
+        result
+    }
+
+    /// Write everything we keep in memory to disk
+    async fn save_state(&self) -> Result<()> {
+        let active = self.active_issue.lock().unwrap().clone();
+        match active {
+            Some(active) => self.persistence.save_active_issue(&active).await?,
+            None => self.persistence.clear_active_issue().await?,
+        }
+
+        let processed_issues = self.processed_issues.lock().unwrap().clone();
+        self.persistence
+            .save_processed_issues(&processed_issues)
+            .await?;
+
+        let cursors = self.cursors.lock().unwrap().clone();
+        self.persistence.save_repo_cursors(&cursors).await?;
+
+        let assigned_issues = self.assigned_issues.lock().unwrap().clone();
+        self.persistence
+            .save_assigned_issues(&assigned_issues)
+            .await?;
+
+        let history = self.history.lock().unwrap().clone();
+        self.persistence.save_history(&history).await?;
+
+        self.persistence.flush().await
+    }
+
+    /// Carry out what the user asked for through notification buttons
+    async fn handle_actions(&self) -> Result<()> {
+        for action in self.notifications.poll_actions().await? {
+            match action {
+                Action::Withdraw {
+                    owner,
+                    repo,
+                    issue_number,
+                } => {
+                    if let Err(e) = self.abandon(&owner, &repo, issue_number).await {
+                        warn!(
+                            "Failed to withdraw from {}/{}#{}: {}",
+                            owner, repo, issue_number, e
+                        );
+                    }
+                }
+                Action::SnoozeRepo { owner, repo } => {
+                    let until = Utc::now() + Duration::hours(self.config.snooze_repo_hours as i64);
+                    info!("Snoozing {}/{} until {}", owner, repo, until);
+                    self.snoozed_repos
+                        .lock()
+                        .unwrap()
+                        .insert(format!("{}/{}", owner, repo), until);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Stop polling until GitHub lets us make requests again
+    async fn back_off(&self, retry_at: DateTime<Utc>) {
+        warn!(
+            "GitHub is rate limiting us, not sending requests until {}",
+            retry_at
+        );
+        self.rate_budget.pause_until(retry_at);
+        self.notifications
+            .send(Event::RateLimited { retry_at })
+            .await;
+    }
+
+    /// Tell teammates we claimed an issue we reserved, for good
+    async fn confirm_reservation(&self, repo: &Repository, issue: &Issue) {
+        let Some(ref team) = self.team else {
+            return;
+        };
+        if let Err(e) = team.claim(&repo.owner, &repo.repo, issue.number).await {
+            warn!(
+                "Failed to record the claim on issue #{} with the team: {}",
+                issue.number, e
+            );
+        }
+    }
+
+    /// Let teammates claim an issue we reserved but couldn't claim
+    async fn release_reservation(&self, repo: &Repository, issue: &Issue) {
+        let Some(ref team) = self.team else {
+            return;
+        };
+        if let Err(e) = team.release(&repo.owner, &repo.repo, issue.number).await {
+            warn!(
+                "Failed to release issue #{} to the team: {}",
+                issue.number, e
+            );
+        }
+    }
+
+    /// Stop polling a repository that was deleted, renamed or made private,
+    /// rather than failing on it every poll
+    async fn disable_repo(&self, repo: &Repository, error: &anyhow::Error) {
+        warn!(
+            "Not polling {}/{} again until restarted: {}",
+            repo.owner, repo.repo, error
+        );
+        self.disabled_repos
+            .lock()
+            .unwrap()
+            .insert(cursor::key(repo));
+        self.notify_error(format!(
+            "Stopped polling {}/{}: {}",
+            repo.owner, repo.repo, error
+        ))
+        .await;
+    }
+
+    /// One poll, with its pacing, bookkeeping and error handling. Only errors
+    /// that should stop the bot are returned.
+    async fn run_poll(&self) -> Result<()> {
+        if !self.is_leader() {
+            debug!("Standing by while another replica leads");
+            *self.led_last_poll.lock().unwrap() = false;
+            // A standby isn't a stalled bot either
+            self.health.record_poll(true);
+            self.save_heartbeat().await;
+            return Ok(());
+        }
+        let took_over = !std::mem::replace(&mut *self.led_last_poll.lock().unwrap(), true);
+        if took_over {
+            info!("Took over as leader, catching up on the claims made meanwhile");
+            self.catch_up().await;
+        }
+        self.reload_templates().await;
+
+        let Some(delay) =
+            pacing::delay_before_poll(self.config.jitter_secs, self.config.human_profile.as_ref())
+        else {
+            debug!("Sitting this poll out");
+            // A skipped poll isn't a stalled bot
+            self.health.record_poll(true);
+            self.save_heartbeat().await;
+            return Ok(());
+        };
+        time::sleep(delay).await;
+
+        let result = self.poll_repositories().await;
+        self.health.record_poll(result.is_ok());
+        match result {
+            Ok(()) => *self.last_error.lock().unwrap() = None,
+            Err(e) => {
+                self.hooks.error(&e);
+                match github_error(&e) {
+                    Some(GitHubError::RateLimited { retry_at }) => self.back_off(*retry_at).await,
+                    // Nothing will work until the token is replaced
+                    Some(GitHubError::Unauthorized(_)) => {
+                        error!("GitHub rejected the token, stopping: {}", e);
+                        self.notify_error(e.to_string()).await;
+                        self.save_heartbeat().await;
+                        return Err(e);
+                    }
+                    _ => {
+                        warn!("Error during polling: {}", e);
+                        self.notify_error(e.to_string()).await;
+                    }
+                }
+            }
+        }
+
+        if let Some(until) = self.rate_budget.paused_until() {
+            if until > Utc::now() {
+                info!("Sleeping until {}, when the rate limit resets", until);
+                self.health.sleep_until(until);
+            }
+        }
+        self.compact_processed_issues().await;
+        self.flush().await;
+        self.save_heartbeat().await;
+
+        Ok(())
+    }
+
+    /// Keep only the last few months of processed issues in full, so a bot
+    /// that runs for years doesn't keep every record it ever made in memory
+    async fn compact_processed_issues(&self) {
+        let cutoff = processed::compaction_cutoff(Utc::now());
+        let processed_issues = {
+            let mut processed_lock = self.processed_issues.lock().unwrap();
+            if !processed_lock.compact(&cutoff) {
+                return;
+            }
+            processed_lock.clone()
+        };
+
+        debug!("Compacted processed issues from before {}", cutoff);
+        if let Err(e) = self
+            .persistence
+            .save_processed_issues(&processed_issues)
+            .await
+        {
+            warn!("Failed to save processed issues: {}", e);
+        }
+    }
+
+    /// Write out what the persistence backend held back during the poll
+    async fn flush(&self) {
+        if let Err(e) = self.persistence.flush().await {
+            warn!("Failed to write state: {}", e);
+        }
+    }
+
+    async fn save_heartbeat(&self) {
+        if let Some(heartbeat) = self.health.heartbeat() {
+            if let Err(e) = self.persistence.save_heartbeat(&heartbeat).await {
+                warn!("Failed to write heartbeat: {}", e);
+            }
+        }
+    }
+
+    /// Notify about an error unless it's the same one we reported last time
+    async fn notify_error(&self, message: String) {
+        {
+            let mut last_error = self.last_error.lock().unwrap();
+            if last_error.as_deref() == Some(message.as_str()) {
+                return;
+            }
+            *last_error = Some(message.clone());
+        }
+
+        self.notifications.send(Event::Error { message }).await;
+    }
+
+    /// Look for replies on the issues we claimed or are working on
+    async fn poll_notifications(&self) -> Result<()> {
+        let since = *self.notifications_since.lock().unwrap();
+        let checked_at = Utc::now();
+
+        let threads = self.forge.get_notifications(since).await?;
+        *self.notifications_since.lock().unwrap() = checked_at;
+
+        for thread in threads {
+            let Some((owner, repo, issue_number)) = thread.issue() else {
+                continue;
+            };
+
+            let active = self.active_issue.lock().unwrap().clone().filter(|active| {
+                active.repo_owner == owner
+                    && active.repo_name == repo
+                    && active.issue_number == issue_number
+            });
+            let tracked = active.as_ref().map(IssueInfo::from).or_else(|| {
+                let assigned_lock = self.assigned_issues.lock().unwrap();
+                assigned_lock
+                    .iter()
+                    .find(|issue| {
+                        issue.repo_owner == owner
+                            && issue.repo_name == repo
+                            && issue.issue_number == issue_number
+                    })
+                    .map(IssueInfo::from)
+            });
+            let Some(issue) = tracked else {
+                continue;
+            };
+
+            let Some(comment_id) = thread.latest_comment_id() else {
+                continue;
+            };
+            let comment = self.forge.get_comment(&owner, &repo, comment_id).await?;
+            if comment
+                .user
+                .login
+                .eq_ignore_ascii_case(&self.config.user_login)
+            {
+                continue;
+            }
+
+            self.notifications
+                .send(Event::Reply {
+                    issue,
+                    author: comment.user.login.clone(),
+                    url: comment.html_url.clone(),
+                    body: comment.body.clone(),
+                })
+                .await;
+
+            // A reply often comes with an assignment, check right away
+            if let Some(active) = active {
+                self.check_active_issue(&active).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// One poll of the configured repositories, as `start` runs on every
+    /// tick, for driving the bot step by step
+    #[instrument(level = "debug", skip(self), fields(cycle_id = self.cycles.fetch_add(1, Ordering::Relaxed) + 1))]
+    pub async fn poll_repositories(&self) -> Result<()> {
+        let requests = self.forge.api_usage().requests;
+        *self.cycle.lock().unwrap() = CycleSummary::default();
+
+        let result = self.poll_cycle().await;
+
+        let usage = self.forge.api_usage();
+        let summary = std::mem::take(&mut *self.cycle.lock().unwrap());
+        let action = match (summary.action, &result) {
+            (Some(action), _) => action,
+            (None, Ok(())) => "nothing to claim".to_string(),
+            (None, Err(_)) => "stopped by an error".to_string(),
+        };
+        info!(
+            repos_polled = summary.repos_polled,
+            api_calls = usage.requests.saturating_sub(requests),
+            candidates = summary.candidates,
+            top_candidate = summary.top_candidate.as_deref().unwrap_or("none"),
+            action = ?action,
+            rate_limit_remaining = %usage
+                .remaining
+                .or(summary.rate_limit_remaining)
+                .map(|remaining| remaining.to_string())
+                .unwrap_or_else(|| "unknown".to_string()),
+            "Poll finished"
+        );
+
+        result
+    }
+
+    /// What the summary of this poll says it did
+    fn cycle_action(&self, action: String) {
+        self.cycle.lock().unwrap().action = Some(action);
+    }
+
+    /// The poll itself, without its summary
+    async fn poll_cycle(&self) -> Result<()> {
+        if let Some(until) = self.rate_budget.paused_until() {
+            if Utc::now() < until {
+                debug!("Out of API requests, polling resumes at {}", until);
+                self.cycle_action(format!("waiting for the rate limit to reset at {}", until));
+                return Ok(());
+            }
+            info!("GitHub API rate limit has reset, resuming polling");
+            self.rate_budget.resume();
+        }
+
+        if let Err(e) = self.refresh_assigned_issues().await {
+            warn!("Failed to refresh assigned issues: {}", e);
+        }
+
+        // Check if we're currently waiting for an assignment
+        let active = self.active_issue.lock().unwrap().clone();
+        if let Some(active) = active {
+            // Still waiting on this issue
+            if Utc::now() < active.timeout {
+                if let Some((active, issue)) = self.check_active_issue(&active).await? {
+                    debug!(
+                        "Waiting for assignment on issue #{} in {}/{}",
+                        active.issue_number, active.repo_owner, active.repo_name
+                    );
+                    if let Err(e) = self.ping(&active, &issue).await {
+                        warn!("Failed to ping issue #{}: {}", active.issue_number, e);
+                    }
+                    self.cycle_action(format!(
+                        "waiting for an answer on {}/{}#{}",
+                        active.repo_owner, active.repo_name, active.issue_number
+                    ));
+                    return Ok(());
+                }
+            } else {
+                // Timeout has expired
+                info!(
+                    "Assignment request for issue #{} in {}/{} has timed out",
+                    active.issue_number, active.repo_owner, active.repo_name
+                );
+                self.notifications
+                    .send(Event::TimedOut {
+                        issue: IssueInfo::from(&active),
+                    })
+                    .await;
+                self.record_history(HistoryEntry::timed_out(&active))
+                    .await?;
+                self.update_processed_reason(&active, ProcessedReason::TimedOut)
+                    .await?;
+                *self.active_issue.lock().unwrap() = None;
+                self.persistence.clear_active_issue().await?;
+                self.audit(AuditEntry::new(
+                    AuditAction::StateCleared,
+                    &active.repo_owner,
+                    &active.repo_name,
+                    active.issue_number,
+                    "claim timed out",
+                ))
+                .await;
+            }
+        }
+
+        // Claim after unanswered claim across repositories looks like a script
+        if let Some(until) = self.timeout_backoff_until() {
+            if Utc::now() < until {
+                debug!("A claim timed out recently, not claiming until {}", until);
+                self.cycle_action(format!(
+                    "backing off after a timed-out claim until {}",
+                    until
+                ));
+                return Ok(());
+            }
+        }
+
+        // Check rate limits before making requests
+        let rate_limit = self.forge.get_rate_limit().await?;
+        self.cycle.lock().unwrap().rate_limit_remaining = Some(rate_limit.remaining);
+        debug!(
+            "GitHub API rate limit: {} remaining, resets at {}",
+            rate_limit.remaining, rate_limit.reset_at
+        );
+
+        let Some(allowance) = self
+            .rate_budget
+            .allowance(&rate_limit, self.config.repositories.len())
+        else {
+            warn!(
+                "GitHub API rate limit is low: {} remaining. Polling resumes at {}.",
+                rate_limit.remaining, rate_limit.reset_at
+            );
+            // Polling pauses until the reset, so this is only sent once
+            self.notifications
+                .send(Event::RateLimitLow {
+                    remaining: rate_limit.remaining,
+                    resets_at: rate_limit.reset_at,
+                })
+                .await;
+            self.cycle_action(format!(
+                "pausing until the rate limit resets at {}",
+                rate_limit.reset_at
+            ));
+            return Ok(());
+        };
+
+        // Don't overcommit, whatever our own state says
+        if let Some(cap) = self.config.max_open_assignments {
+            let query = format!("is:issue is:open assignee:{}", self.config.user_login);
+            let open_assignments = self.forge.count_issues(&query).await?;
+            if open_assignments >= cap as u64 {
+                info!(
+                    "You have {} open assigned issues (cap is {}), not claiming anything new",
+                    open_assignments, cap
+                );
+                self.cycle_action(format!(
+                    "not claiming with {} open assignments",
+                    open_assignments
+                ));
+                return Ok(());
+            }
+        }
+
+        if !self.config.wishlist.is_empty() && self.check_wishlist().await? {
+            self.cycle_action("claimed a wishlist issue".to_string());
+            return Ok(());
+        }
+
+        // Start with the repositories that waited longest, so a claim in one
+        // doesn't starve the ones after it, across restarts too
+        let mut repositories: Vec<&Repository> = self.config.repositories.iter().collect();
+        {
+            let cursors = self.cursors.lock().unwrap();
+            repositories.sort_by_key(|repo| {
+                cursors
+                    .get(&cursor::key(repo))
+                    .and_then(|cursor| cursor.last_polled_at)
+            });
+        }
+        if self.config.adaptive_polling {
+            self.drop_repos_not_due(&mut repositories);
+        }
+        if self.config.prefer_responsive_repos {
+            self.rank_by_responsiveness(&mut repositories);
+        }
+        if self.config.prefer_contributed_repos {
+            self.rank_by_contributions(&mut repositories).await;
+        }
+        if allowance < repositories.len() {
+            info!(
+                "Rate limit budget allows polling {} of {} repositories this cycle",
+                allowance,
+                repositories.len()
+            );
+            repositories.truncate(allowance);
+        }
+
+        // No active issue or timeout expired, so we can look for a new issue
+        let mut polled = 0;
+        let mut failures = Vec::new();
+        let mut claimed = false;
+        let mut fatal = None;
+        for &repo in &repositories {
+            polled += 1;
+            match self.process_repository(repo).await {
+                Ok(true) => {
+                    // Successfully processed an issue, stop for this cycle
+                    claimed = true;
+                    break;
+                }
+                Ok(false) => {
+                    // No eligible issues found
+                    continue;
+                }
+                Err(e) => {
+                    self.update_cursor(repo, |cursor| cursor.failed(&e)).await;
+                    failures.push((format!("{}/{}", repo.owner, repo.repo), e.to_string()));
+                    match github_error(&e) {
+                        // Other repositories won't fare any better
+                        Some(GitHubError::RateLimited { .. } | GitHubError::Unauthorized(_)) => {
+                            fatal = Some(e);
+                            break;
+                        }
+                        Some(GitHubError::NotFound(_) | GitHubError::Forbidden(_)) => {
+                            self.disable_repo(repo, &e).await
+                        }
+                        // Transient errors were already retried, try again next poll
+                        _ => {}
+                    }
+                }
+            }
+        }
+        self.cycle.lock().unwrap().repos_polled = polled;
+        self.report_failures(failures, polled).await;
+        if let Some(e) = fatal {
+            return Err(e);
+        }
+        if claimed {
+            return Ok(());
+        }
+
+        if self.config.stale_assignments && self.ask_about_stale_assignment(&repositories).await? {
+            self.cycle_action("asked about a stale assignment".to_string());
+            return Ok(());
+        }
+
+        debug!("No eligible issues found in this cycle");
+        Ok(())
+    }
+
+    /// Log the repositories that failed this poll in one warning, and send
+    /// a `repos_failed` notification when there are more than
+    /// `failed_repos_alert` of them, once until there are fewer again
+    async fn report_failures(&self, failures: Vec<(String, String)>, polled: usize) {
+        if !failures.is_empty() {
+            warn!(
+                failed = failures.len(),
+                polled,
+                "Repositories failed this poll: {}",
+                failures
+                    .iter()
+                    .map(|(repo, error)| format!("{} ({})", repo, error))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+
+        let Some(threshold) = self.config.failed_repos_alert else {
+            return;
+        };
+        let alert = failures.len() > threshold;
+        let already_sent = std::mem::replace(&mut *self.failure_alert_sent.lock().unwrap(), alert);
+        if alert && !already_sent {
+            self.notifications
+                .send(Event::ReposFailed { failures, polled })
+                .await;
+        }
+    }
+
+    /// When claiming can resume after the latest claim that timed out, with
+    /// `timeout_backoff_hours`
+    fn timeout_backoff_until(&self) -> Option<DateTime<Utc>> {
+        if self.config.timeout_backoff_hours == 0 {
+            return None;
+        }
+        self.history
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|entry| entry.outcome == Outcome::TimedOut)
+            .map(|entry| entry.finished_at)
+            .max()
+            .map(|timed_out| timed_out + Duration::hours(self.config.timeout_backoff_hours as i64))
+    }
+
+    /// How long to wait for an answer to a claim. A repository's own
+    /// cooldown always wins, then its track record with `adaptive_timeouts`.
+    fn claim_timeout(&self, owner: &str, repo: &str) -> Duration {
+        if let Some(timeout) = self.config.repo_claim_timeout(owner, repo) {
+            return timeout;
+        }
+
+        if self.config.adaptive_timeouts {
+            let responsiveness = Responsiveness::by_repo(&self.history.lock().unwrap());
+            if let Some(timeout) = responsiveness
+                .get(&format!("{}/{}", owner, repo))
+                .and_then(Responsiveness::suggested_timeout)
+            {
+                return timeout;
+            }
+        }
+
+        self.config.claim_timeout(owner, repo)
+    }
+
+    /// With `adaptive_polling`, leave out the repositories polled more
+    /// recently than their issue arrival rate calls for
+    fn drop_repos_not_due(&self, repositories: &mut Vec<&Repository>) {
+        let cursors = self.cursors.lock().unwrap();
+        let secs = |secs: u64| Duration::seconds(secs as i64);
+        let (base, min, max) = (
+            secs(self.config.poll_interval_secs),
+            secs(self.config.min_poll_interval_secs),
+            secs(self.config.max_poll_interval_secs),
+        );
+        // Polls never land exactly on time, so don't make a due one wait a whole tick
+        let slack = secs(self.config.tick_interval_secs()) / 2;
+        let now = Utc::now();
+
+        repositories.retain(|repo| {
+            let Some(cursor) = cursors.get(&cursor::key(repo)) else {
+                return true;
+            };
+            let interval = cursor.poll_interval(base, min, max);
+            let due = cursor
+                .last_polled_at
+                .is_none_or(|at| at + interval <= now + slack);
+            if !due {
+                debug!(
+                    "Not polling {}/{} yet, it's polled every {} minutes",
+                    repo.owner,
+                    repo.repo,
+                    interval.num_minutes()
+                );
+            }
+            due
+        });
+    }
+
+    /// Move repositories whose maintainers answer claims to the front, and
+    /// ones that keep ignoring them to the back. The sort is stable, so
+    /// equally responsive ones keep their turn.
+    fn rank_by_responsiveness(&self, repositories: &mut [&Repository]) {
+        let responsiveness = Responsiveness::by_repo(&self.history.lock().unwrap());
+        let limit = self.config.ignored_claims_limit as usize;
+        let rank = |repo: &Repository| match responsiveness.get(&cursor::key(repo)) {
+            Some(repo) if limit > 0 && repo.ignored_in_a_row >= limit => -1.0,
+            Some(repo) => repo.score(),
+            None => Responsiveness::default().score(),
+        };
+        repositories.sort_by(|a, b| rank(b).total_cmp(&rank(a)));
+    }
+
+    /// Put the repositories we contributed to first, keeping the order
+    /// within both groups
+    async fn rank_by_contributions(&self, repositories: &mut [&Repository]) {
+        let Some(contributed) = self.contributed_repos().await else {
+            return;
+        };
+        repositories.sort_by_key(|repo| !contributed.contains(&cursor::key(repo).to_lowercase()));
+    }
+
+    /// Repositories among our latest 100 merged pull requests, looked up
+    /// once per run. None if the lookup should be tried again next poll.
+    async fn contributed_repos(&self) -> Option<HashSet<String>> {
+        if let Some(ref contributed) = *self.contributed_repos.lock().unwrap() {
+            return Some(contributed.clone());
+        }
+
+        let login = &self.config.user_login;
+        let pulls = self
+            .forge
+            .search_issues(&format!("is:pr is:merged author:{}", login))
+            .await
+            .map(|pulls| {
+                pulls
+                    .iter()
+                    .filter_map(|pull| parse_issue_url(&pull.html_url).ok())
+                    .map(|(owner, repo, _)| format!("{}/{}", owner, repo))
+                    .collect::<Vec<_>>()
+            });
+        // Catches commits that landed without a pull request of ours, e.g.
+        // patches a maintainer pushed or cherry-picked
+        let commits = self
+            .forge
+            .search_commit_repos(&format!("author:{}", login))
+            .await;
+
+        let mut contributed = HashSet::new();
+        for result in [pulls, commits] {
+            match result {
+                Ok(repos) => contributed.extend(repos.iter().map(|repo| repo.to_lowercase())),
+                Err(e) if e.is_retryable() => {
+                    warn!(
+                        "Failed to look up the repositories you contributed to, trying again next poll: {}",
+                        e
+                    );
+                    return None;
+                }
+                Err(e) => warn!(
+                    "Can't look up some of the repositories you contributed to: {}",
+                    e
+                ),
+            }
+        }
+        debug!(
+            "You had pull requests merged or commits in {} repositories",
+            contributed.len()
+        );
+        *self.contributed_repos.lock().unwrap() = Some(contributed.clone());
+        Some(contributed)
+    }
+
+    /// Refresh the pull requests linked to the issues we're working on and
+    /// move finished issues into the history
+    async fn refresh_assigned_issues(&self) -> Result<()> {
+        let due: Vec<AssignedIssue> = {
+            let assigned_lock = self.assigned_issues.lock().unwrap();
+            assigned_lock
+                .iter()
+                .filter(|issue| {
+                    issue.prs_checked_at.is_none_or(|checked| {
+                        Utc::now() - checked >= Duration::minutes(PR_REFRESH_MINUTES)
+                    })
+                })
+                .cloned()
+                .collect()
+        };
+
+        if due.is_empty() {
+            return Ok(());
+        }
+
+        for mut issue in due {
+            let state = match self
+                .forge
+                .get_issue(&issue.repo_owner, &issue.repo_name, issue.issue_number)
+                .await
+            {
+                Ok(state) => state,
+                Err(GitHubError::NotFound(_)) => {
+                    info!(
+                        "Issue #{} in {}/{} was deleted, dropping it",
+                        issue.issue_number, issue.repo_owner, issue.repo_name
+                    );
+                    self.record_history(HistoryEntry::finished(&issue, Outcome::Deleted, None))
+                        .await?;
+                    self.assigned_issues
+                        .lock()
+                        .unwrap()
+                        .retain(|entry| !is_same_issue(entry, &issue));
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+
+            if let Some((owner, repo, issue_number)) = transferred_to(
+                &issue.repo_owner,
+                &issue.repo_name,
+                issue.issue_number,
+                &state,
+            ) {
+                info!(
+                    "Issue #{} in {}/{} was transferred to {}/{}#{}",
+                    issue.issue_number,
+                    issue.repo_owner,
+                    issue.repo_name,
+                    owner,
+                    repo,
+                    issue_number
+                );
+                let moved = AssignedIssue {
+                    repo_owner: owner,
+                    repo_name: repo,
+                    issue_number,
+                    issue_url: state.html_url.clone(),
+                    ..issue.clone()
+                };
+                let mut assigned_lock = self.assigned_issues.lock().unwrap();
+                if let Some(entry) = assigned_lock
+                    .iter_mut()
+                    .find(|entry| is_same_issue(entry, &issue))
+                {
+                    *entry = moved.clone();
+                }
+                issue = moved;
+            }
+
+            let linked_prs = self
+                .forge
+                .get_linked_pull_requests(&issue.repo_owner, &issue.repo_name, issue.issue_number)
+                .await?;
+
+            for pr in &linked_prs {
+                let known = issue.linked_prs.iter().find(|p| p.html_url == pr.html_url);
+                if known.map(|p| p.state) != Some(pr.state) {
+                    info!(
+                        "Pull request {} for issue #{} in {}/{} is {}",
+                        pr.html_url,
+                        issue.issue_number,
+                        issue.repo_owner,
+                        issue.repo_name,
+                        pr.state
+                    );
+                }
+            }
+            issue.linked_prs = linked_prs;
+            issue.prs_checked_at = Some(Utc::now());
+
+            match self.work_outcome(&issue, &state) {
+                Some((outcome, pull_request_url)) => {
+                    info!(
+                        "Issue #{} in {}/{} is {}",
+                        issue.issue_number, issue.repo_owner, issue.repo_name, outcome
+                    );
+                    self.record_history(HistoryEntry::finished(&issue, outcome, pull_request_url))
+                        .await?;
+                    self.assigned_issues
+                        .lock()
+                        .unwrap()
+                        .retain(|entry| !is_same_issue(entry, &issue));
+                }
+                None => {
+                    self.check_deadline(&mut issue).await;
+
+                    let mut assigned_lock = self.assigned_issues.lock().unwrap();
+                    if let Some(entry) = assigned_lock
+                        .iter_mut()
+                        .find(|entry| is_same_issue(entry, &issue))
+                    {
+                        *entry = issue;
+                    }
+                }
+            }
+        }
+
+        let assigned_issues = self.assigned_issues.lock().unwrap().clone();
+        self.persistence
+            .save_assigned_issues(&assigned_issues)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Remind the user when an issue without a PR gets close to or past its due date
+    #[instrument(level = "debug", skip_all, fields(repo = %format!("{}/{}", issue.repo_owner, issue.repo_name), issue_number = issue.issue_number))]
+    async fn check_deadline(&self, issue: &mut AssignedIssue) {
+        if issue.has_pull_request() {
+            return;
+        }
+
+        let now = Utc::now();
+        let reminder = if now >= issue.due_at {
+            DeadlineReminder::Passed
+        } else if issue.due_at - now <= Duration::hours(self.config.deadline_reminder_hours as i64)
+        {
+            DeadlineReminder::Approaching
+        } else {
+            return;
+        };
+
+        // Each reminder is only sent once
+        if issue.deadline_reminder == Some(reminder) {
+            return;
+        }
+        issue.deadline_reminder = Some(reminder);
+
+        let info = IssueInfo::from(&*issue);
+        let event = match reminder {
+            DeadlineReminder::Approaching => Event::DeadlineApproaching {
+                issue: info,
+                due_at: issue.due_at,
+            },
+            DeadlineReminder::Passed => Event::DeadlinePassed {
+                issue: info,
+                due_at: issue.due_at,
+            },
+        };
+        self.notifications.send(event).await;
+    }
+
+    /// Decide whether work on an assigned issue has finished
+    fn work_outcome(
+        &self,
+        assigned: &AssignedIssue,
+        issue: &Issue,
+    ) -> Option<(Outcome, Option<String>)> {
+        // Anyone's pull request can mention the issue, only ours finish it
+        let merged_pr = assigned.linked_prs.iter().find(|pr| {
+            pr.state == PullRequestState::Merged
+                && pr.author.eq_ignore_ascii_case(&self.config.user_login)
+        });
+
+        if let Some(pr) = merged_pr {
+            return Some((Outcome::Completed, Some(pr.html_url.clone())));
+        }
+
+        if issue.state != "closed" {
+            return None;
+        }
+
+        if issue.state_reason.as_deref() == Some("not_planned") {
+            Some((Outcome::Closed, None))
+        } else {
+            Some((Outcome::Completed, None))
+        }
+    }
+
+    async fn record_history(&self, entry: HistoryEntry) -> Result<()> {
+        let history = {
+            let mut history_lock = self.history.lock().unwrap();
+            history_lock.push(entry);
+            history_lock.clone()
+        };
+
+        self.persistence.save_history(&history).await
+    }
+
+    /// Note how a claim ended, so we can tell later why the issue was skipped
+    async fn update_processed_reason(
+        &self,
+        active: &ActiveIssue,
+        reason: ProcessedReason,
+    ) -> Result<()> {
+        let processed_issues = {
+            let mut processed_lock = self.processed_issues.lock().unwrap();
+            let record =
+                processed_lock.get_mut(&active.repo_owner, &active.repo_name, active.issue_number);
+            let Some(record) = record else {
+                return Ok(());
+            };
+            record.reason = reason;
+            record.processed_at = Utc::now();
+            processed_lock.clone()
+        };
+
+        self.persistence
+            .save_processed_issues(&processed_issues)
+            .await
+    }
+
+    /// Remember how polling a repository went, so a restart picks up from there
+    async fn update_cursor(&self, repo: &Repository, update: impl FnOnce(&mut RepoCursor)) {
+        let cursors = {
+            let mut cursors_lock = self.cursors.lock().unwrap();
+            update(cursors_lock.entry(cursor::key(repo)).or_default());
+            cursors_lock.clone()
+        };
+
+        if let Err(e) = self.persistence.save_repo_cursors(&cursors).await {
+            warn!("Failed to save polling cursors: {}", e);
+        }
+    }
+
+    /// Record an action in the audit log. A failed write never stops the bot.
+    async fn audit(&self, entry: AuditEntry) {
+        if let Err(e) = self.persistence.append_audit(&entry).await {
+            warn!("Failed to write to the audit log: {}", e);
+        }
+    }
+
+    /// Record the outcome of a GitHub call in the audit log
+    async fn audit_result<R, E: std::fmt::Display>(
+        &self,
+        action: AuditAction,
+        owner: &str,
+        repo: &str,
+        issue_number: u64,
+        result: &std::result::Result<R, E>,
+        summary: impl FnOnce(&R) -> String,
+    ) {
+        let entry = match result {
+            Ok(response) => AuditEntry::new(action, owner, repo, issue_number, summary(response)),
+            Err(e) => AuditEntry::failure(action, owner, repo, issue_number, e),
+        };
+        self.audit(entry).await;
+    }
+
+    /// Check how the maintainers responded to our claim on the active issue.
+    /// Returns the claim and the issue while we're still waiting on it.
+    #[instrument(level = "debug", skip_all, fields(repo = %format!("{}/{}", active.repo_owner, active.repo_name), issue_number = active.issue_number))]
+    async fn check_active_issue(
+        &self,
+        active: &ActiveIssue,
+    ) -> Result<Option<(ActiveIssue, Issue)>> {
+        let Some((active, issue)) = self.fetch_active_issue(active).await? else {
+            return Ok(None);
+        };
+
+        if self.settle_active_issue(&active, &issue).await? {
+            return Ok(None);
+        }
+        Ok(Some((active, issue)))
+    }
+
+    /// Fetch the active issue, following it if it was transferred to another
+    /// repository. A deleted issue ends the claim and gives None.
+    async fn fetch_active_issue(
+        &self,
+        active: &ActiveIssue,
+    ) -> Result<Option<(ActiveIssue, Issue)>> {
+        let issue = match self
+            .forge
+            .get_issue(&active.repo_owner, &active.repo_name, active.issue_number)
+            .await
+        {
+            Ok(issue) => issue,
+            Err(GitHubError::NotFound(_)) => {
+                self.reject_claim(
+                    active,
+                    "the issue was deleted".to_string(),
+                    Outcome::Deleted,
+                    ProcessedReason::Deleted,
+                )
+                .await?;
+                return Ok(None);
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let Some((owner, repo, issue_number)) = transferred_to(
+            &active.repo_owner,
+            &active.repo_name,
+            active.issue_number,
+            &issue,
+        ) else {
+            return Ok(Some((active.clone(), issue)));
+        };
+
+        info!(
+            "Issue #{} in {}/{} was transferred to {}/{}#{}",
+            active.issue_number, active.repo_owner, active.repo_name, owner, repo, issue_number
+        );
+        let moved = ActiveIssue {
+            repo_owner: owner.clone(),
+            repo_name: repo.clone(),
+            issue_number,
+            issue_url: issue.html_url.clone(),
+            ..active.clone()
+        };
+        *self.active_issue.lock().unwrap() = Some(moved.clone());
+        self.persistence.save_active_issue(&moved).await?;
+
+        let processed_issues = {
+            let mut processed_lock = self.processed_issues.lock().unwrap();
+            processed_lock.insert(ProcessedIssue::new(
+                &owner,
+                &repo,
+                &issue,
+                ProcessedReason::Claimed,
+            ));
+            processed_lock.clone()
+        };
+        self.persistence
+            .save_processed_issues(&processed_issues)
+            .await?;
+
+        Ok(Some((moved, issue)))
+    }
+
+    /// End the wait if we got the issue, it went to someone else or it was
+    /// closed as a duplicate or won't fix
+    async fn settle_active_issue(&self, active: &ActiveIssue, issue: &Issue) -> Result<bool> {
+        if issue.state == "closed" {
+            let reason = match issue.state_reason.as_deref() {
+                Some("not_planned") => "the issue was closed as not planned".to_string(),
+                Some("duplicate") => "the issue was closed as a duplicate".to_string(),
+                _ => "the issue was closed".to_string(),
+            };
+            self.reject_claim(active, reason, Outcome::Closed, ProcessedReason::Closed)
+                .await?;
+            return Ok(true);
+        }
+
+        if issue.is_assigned_to(&self.config.user_login) {
+            self.start_work(active, issue, None).await?;
+            return Ok(true);
+        }
+
+        // The quiet assignee we asked about doesn't count
+        let other = issue.assignee_logins().into_iter().find(|login| {
+            !active
+                .stale_assignees
+                .iter()
+                .any(|stale| stale.eq_ignore_ascii_case(login))
+        });
+        if let Some(assignee) = other {
+            self.retract_claim_comment(active, &assignee).await;
+            self.reject_claim(
+                active,
+                format!("assigned to {}", assignee),
+                Outcome::Lost,
+                ProcessedReason::AssignedToOther,
+            )
+            .await?;
+            return Ok(true);
+        }
+
+        if let Some(label) = self.assignment_label(active, issue) {
+            info!(
+                "Issue #{} in {}/{} was labeled {:?}, which counts as being assigned",
+                active.issue_number, active.repo_owner, active.repo_name, label
+            );
+            self.start_work(active, issue, None).await?;
+            return Ok(true);
+        }
+
+        match self.claim_reply(active).await? {
+            Some(ClaimReply::Accepted(reply)) => {
+                info!(
+                    "{} handed over issue #{} in {}/{} in a reply, which counts as being assigned",
+                    reply.user.login, active.issue_number, active.repo_owner, active.repo_name
+                );
+                self.start_work(active, issue, Some(&reply)).await?;
+                Ok(true)
+            }
+            Some(ClaimReply::Rejected(reason)) => {
+                self.reject_claim(active, reason, Outcome::Rejected, ProcessedReason::Rejected)
+                    .await?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// A label the repository uses instead of assigning, added since we
+    /// claimed the issue. One that was there all along says nothing about us.
+    fn assignment_label<'a>(&self, active: &ActiveIssue, issue: &'a Issue) -> Option<&'a str> {
+        let assigned_labels = self
+            .config
+            .assigned_labels(&active.repo_owner, &active.repo_name);
+
+        issue
+            .labels
+            .iter()
+            .map(|label| label.name.as_str())
+            .filter(|name| {
+                !active
+                    .labels
+                    .iter()
+                    .any(|had| had.eq_ignore_ascii_case(name))
+            })
+            .find(|name| {
+                assigned_labels
+                    .iter()
+                    .any(|assigned| assigned.eq_ignore_ascii_case(name))
+            })
+    }
+
+    /// Remind the maintainers of our claim once, part way through the wait.
+    /// Only claims made with a comment are pinged, and never after anyone
+    /// else commented, so a conversation is never interrupted.
+    #[instrument(level = "debug", skip_all, fields(repo = %format!("{}/{}", active.repo_owner, active.repo_name), issue_number = active.issue_number))]
+    async fn ping(&self, active: &ActiveIssue, issue: &Issue) -> Result<()> {
+        let Some(ref template) = self.config.ping_comment else {
+            return Ok(());
+        };
+        if active.comment_id.is_none() || active.pinged_at.is_some() || issue.locked {
+            return Ok(());
+        }
+
+        let wait = (active.timeout - active.requested_at).num_seconds() as f64;
+        let due = active.requested_at + Duration::seconds((wait * self.config.ping_after) as i64);
+        if Utc::now() < due {
+            return Ok(());
+        }
+
+        let (owner, repo, issue_number) = (
+            active.repo_owner.as_str(),
+            active.repo_name.as_str(),
+            active.issue_number,
+        );
+
+        let comments = self
+            .forge
+            .get_issue_comments(owner, repo, issue_number)
+            .await?;
+        let answered = comments.iter().any(|comment| {
+            comment.created_at > active.requested_at
+                && !comment
+                    .user
+                    .login
+                    .eq_ignore_ascii_case(&self.config.user_login)
+        });
+
+        // Whatever happens, this claim has had its one chance at a ping
+        let pinged = ActiveIssue {
+            pinged_at: Some(Utc::now()),
+            ..active.clone()
+        };
+        *self.active_issue.lock().unwrap() = Some(pinged.clone());
+        self.persistence.save_active_issue(&pinged).await?;
+
+        if answered {
+            debug!(
+                "Not pinging issue #{}, the conversation has moved on",
+                issue_number
+            );
+            return Ok(());
+        }
+
+        info!(
+            "No answer on issue #{} in {}/{} yet, pinging the maintainers",
+            issue_number, owner, repo
+        );
+        let posted = self
+            .post_comment(owner, repo, issue_number, template, &[])
+            .await;
+        self.audit_result(
+            AuditAction::CommentPosted,
+            owner,
+            repo,
+            issue_number,
+            &posted,
+            |id| format!("ping comment {}", id),
+        )
+        .await;
+        posted?;
+
+        Ok(())
+    }
+
+    /// Post a comment, unless it fails the checks in `lint`. Our previous
+    /// comment is looked up so the same text never goes out twice in a row.
+    async fn post_comment(
+        &self,
+        owner: &str,
+        repo: &str,
+        issue_number: u64,
+        comment: &str,
+        allowed_mentions: &[&str],
+    ) -> Result<u64> {
+        let previous = match self
+            .forge
+            .get_issue_comments(owner, repo, issue_number)
+            .await
+        {
+            Ok(comments) => comments
+                .into_iter()
+                .rev()
+                .find(|comment| {
+                    comment
+                        .user
+                        .login
+                        .eq_ignore_ascii_case(&self.config.user_login)
+                })
+                .map(|comment| comment.body),
+            Err(e) => {
+                debug!(
+                    "Could not look up our previous comment on issue #{}: {}",
+                    issue_number, e
+                );
+                None
+            }
+        };
+
+        if let Err(e) = lint::check(comment, previous.as_deref(), allowed_mentions) {
+            return Err(anyhow::anyhow!(
+                "Refusing to post a comment on {}: {}",
+                processed::key(owner, repo, issue_number),
+                e
+            ));
+        }
+
+        Ok(self
+            .forge
+            .comment_on_issue(owner, repo, issue_number, comment)
+            .await?)
+    }
+
+    /// Tidy up the claim comment on an issue someone else got, as configured
+    async fn retract_claim_comment(&self, active: &ActiveIssue, assignee: &str) {
+        let Some(comment_id) = active.comment_id else {
+            return;
+        };
+        let (owner, repo, issue_number) = (
+            active.repo_owner.as_str(),
+            active.repo_name.as_str(),
+            active.issue_number,
+        );
+
+        let (action, result) = match self.config.lost_claim_comment {
+            LostClaimComment::Keep => return,
+            LostClaimComment::Edit => {
+                let comment = self.config.lost_claim_edit.replace("{assignee}", assignee);
+                let edited = match lint::check(&comment, None, &[assignee]) {
+                    Ok(()) => self
+                        .forge
+                        .edit_comment(owner, repo, comment_id, &comment)
+                        .await
+                        .map_err(anyhow::Error::from),
+                    Err(e) => Err(anyhow::anyhow!("Refusing to edit the comment: {}", e)),
+                };
+                (AuditAction::CommentEdited, edited)
+            }
+            LostClaimComment::Delete => {
+                let deleted = self.forge.delete_comment(owner, repo, comment_id).await;
+                (
+                    AuditAction::CommentDeleted,
+                    deleted.map_err(anyhow::Error::from),
+                )
+            }
+        };
+
+        self.audit_result(action, owner, repo, issue_number, &result, |_| {
+            format!("claim comment {}", comment_id)
+        })
+        .await;
+        if let Err(e) = result {
+            warn!("Failed to update claim comment {}: {}", comment_id, e);
+        }
+    }
+
+    /// The reply since our claim that settles it, the latest one if several
+    /// do. Anyone can turn a claim down, but only maintainers can hand the
+    /// issue over.
+    async fn claim_reply(&self, active: &ActiveIssue) -> Result<Option<ClaimReply>> {
+        let comments = self
+            .forge
+            .get_issue_comments(&active.repo_owner, &active.repo_name, active.issue_number)
+            .await?;
+        let interpret = |comment: &Comment| {
+            replies::interpret(
+                &comment.body,
+                &self.config.accept_phrases,
+                &self.config.reject_phrases,
+            )
+        };
+
+        // Any word from a quiet assignee means they're still on it, unless
+        // they're handing it over
+        let still_on_it = comments.iter().find(|comment| {
+            comment.created_at > active.requested_at
+                && active
+                    .stale_assignees
+                    .iter()
+                    .any(|stale| stale.eq_ignore_ascii_case(&comment.user.login))
+                && interpret(comment) != Some(Verdict::Accepted)
+        });
+        if let Some(comment) = still_on_it {
+            return Ok(Some(ClaimReply::Rejected(format!(
+                "{} is still working on it: {}",
+                comment.user.login,
+                comment.body.trim()
+            ))));
+        }
+
+        let reply = comments
+            .into_iter()
+            .filter(|comment| {
+                comment.created_at > active.requested_at
+                    && !comment
+                        .user
+                        .login
+                        .eq_ignore_ascii_case(&self.config.user_login)
+            })
+            .filter_map(|comment| match interpret(&comment)? {
+                Verdict::Accepted if comment.is_from_maintainer() => {
+                    Some(ClaimReply::Accepted(comment))
+                }
+                Verdict::Accepted => None,
+                Verdict::Rejected => Some(ClaimReply::Rejected(format!(
+                    "{} replied: {}",
+                    comment.user.login,
+                    comment.body.trim()
+                ))),
+            })
+            .last();
+
+        Ok(reply)
+    }
+
+    async fn reject_claim(
+        &self,
+        active: &ActiveIssue,
+        reason: String,
+        outcome: Outcome,
+        processed_reason: ProcessedReason,
+    ) -> Result<()> {
+        info!(
+            "Claim on issue #{} in {}/{} was turned down ({})",
+            active.issue_number, active.repo_owner, active.repo_name, reason
+        );
+
+        let detail = format!("claim turned down, {}", reason);
+        self.notifications
+            .send(Event::Rejected {
+                issue: IssueInfo::from(active),
+                reason,
+            })
+            .await;
+
+        self.record_history(HistoryEntry::unassigned(active, outcome, Utc::now()))
+            .await?;
+        self.update_processed_reason(active, processed_reason)
+            .await?;
+
+        *self.active_issue.lock().unwrap() = None;
+        self.persistence.clear_active_issue().await?;
+        self.audit(AuditEntry::new(
+            AuditAction::StateCleared,
+            &active.repo_owner,
+            &active.repo_name,
+            active.issue_number,
+            detail,
+        ))
+        .await;
+
+        Ok(())
+    }
+
+    /// Move a claim to the in-progress list, once we're assigned or a
+    /// maintainer's `reply` handed the issue over
+    async fn start_work(
+        &self,
+        active: &ActiveIssue,
+        issue: &Issue,
+        reply: Option<&Comment>,
+    ) -> Result<()> {
+        info!(
+            "Assigned to issue #{} in {}/{}",
+            active.issue_number, active.repo_owner, active.repo_name
+        );
+
+        let mut assigned = AssignedIssue {
+            repo_owner: active.repo_owner.clone(),
+            repo_name: active.repo_name.clone(),
+            issue_number: active.issue_number,
+            issue_url: active.issue_url.clone(),
+            issue_title: issue.title.clone(),
+            requested_at: active.requested_at,
+            assigned_at: Utc::now(),
+            due_at: Utc::now() + Duration::days(self.config.expected_turnaround_days as i64),
+            branch: None,
+            draft_pr_url: None,
+            linked_prs: Vec::new(),
+            prs_checked_at: None,
+            deadline_reminder: None,
+            template: active.template.clone(),
+            stale: !active.stale_assignees.is_empty(),
+            rule: active.rule.clone(),
+        };
+
+        let (owner, repo, issue_number) = (
+            active.repo_owner.as_str(),
+            active.repo_name.as_str(),
+            active.issue_number,
+        );
+
+        if self.config.create_branch_on_assignment {
+            let branch = workspace::prepare_branch(&self.forge, &assigned).await;
+            self.audit_result(
+                AuditAction::BranchCreated,
+                owner,
+                repo,
+                issue_number,
+                &branch,
+                |branch| {
+                    format!(
+                        "{}/{}:{}",
+                        branch.fork_owner, branch.fork_name, branch.branch
+                    )
+                },
+            )
+            .await;
+            match branch {
+                Ok(branch) => assigned.branch = Some(branch),
+                Err(e) => warn!("Failed to prepare a branch for the issue: {}", e),
+            }
+        }
+
+        if let (true, Some(branch)) = (self.config.draft_pr_on_assignment, &assigned.branch) {
+            let pull_request = workspace::open_draft_pr(
+                &self.forge,
+                &assigned,
+                branch,
+                &self.config.draft_pr_body,
+            )
+            .await;
+            self.audit_result(
+                AuditAction::DraftPrOpened,
+                owner,
+                repo,
+                issue_number,
+                &pull_request,
+                |pr| pr.html_url.clone(),
+            )
+            .await;
+            match pull_request {
+                Ok(pull_request) => assigned.draft_pr_url = Some(pull_request.html_url),
+                Err(e) => warn!("Failed to open a draft pull request: {}", e),
+            }
+        }
+
+        // Only thank maintainers when they assigned us in response to a comment
+        if let (Some(template), Some(_), false) = (
+            &self.config.follow_up_comment,
+            active.comment_id,
+            issue.locked,
+        ) {
+            let comment =
+                template.replace("{due_date}", &assigned.due_at.format("%B %-d").to_string());
+            let posted = self
+                .post_comment(owner, repo, issue_number, &comment, &[])
+                .await;
+            self.audit_result(
+                AuditAction::CommentPosted,
+                owner,
+                repo,
+                issue_number,
+                &posted,
+                |id| format!("follow-up comment {}", id),
+            )
+            .await;
+            if let Err(e) = posted {
+                warn!("Failed to post follow-up comment: {}", e);
+            }
+        }
+
+        let event = match reply {
+            Some(reply) => Event::Accepted {
+                issue: IssueInfo::from(active),
+                author: reply.user.login.clone(),
+                url: reply.html_url.clone(),
+                body: reply.body.clone(),
+            },
+            None => Event::Assigned {
+                issue: IssueInfo::from(active),
+            },
+        };
+        self.notifications.send(event).await;
+        self.hooks.assignment(&assigned);
+
+        let assigned_issues = {
+            let mut assigned_lock = self.assigned_issues.lock().unwrap();
+            assigned_lock.push(assigned);
+            assigned_lock.clone()
+        };
+        self.persistence
+            .save_assigned_issues(&assigned_issues)
+            .await?;
+
+        // Free the slot so we can look for the next issue
+        *self.active_issue.lock().unwrap() = None;
+        self.persistence.clear_active_issue().await?;
+        self.audit(AuditEntry::new(
+            AuditAction::StateCleared,
+            owner,
+            repo,
+            issue_number,
+            "assigned, moved to in progress",
+        ))
+        .await;
+
+        Ok(())
+    }
+
+    #[instrument(level = "debug", skip_all, fields(repo = %format!("{}/{}", repo.owner, repo.repo), issue_number = tracing::field::Empty))]
+    async fn process_repository(&self, repo: &Repository) -> Result<bool> {
+        let snoozed_until = self
+            .snoozed_repos
+            .lock()
+            .unwrap()
+            .get(&format!("{}/{}", repo.owner, repo.repo))
+            .copied();
+        if let Some(until) = snoozed_until.filter(|until| *until > Utc::now()) {
+            debug!(
+                "Skipping {}/{}: snoozed until {}",
+                repo.owner, repo.repo, until
+            );
+            return Ok(false);
+        }
+        if self
+            .disabled_repos
+            .lock()
+            .unwrap()
+            .contains(&cursor::key(repo))
+        {
+            debug!("Skipping {}/{}: disabled", repo.owner, repo.repo);
+            return Ok(false);
+        }
+
+        info!("Checking for issues in {}/{}", repo.owner, repo.repo);
+
+        if let Some(conventions) = self.repo_conventions(repo).await {
+            if !conventions.claims_welcome {
+                debug!(
+                    "Skipping {}/{}: contributing guide asks not to request assignment",
+                    repo.owner, repo.repo
+                );
+                return Ok(false);
+            }
+        }
+
+        let candidates = if self.config.reviews_issues() {
+            self.persistence.load_candidates().await?
+        } else {
+            CandidateQueue::default()
+        };
+
+        // Review decisions and issues we passed over don't show up as events
+        let repo_key = cursor::key(repo);
+        if self.config.event_polling {
+            let reviewed = candidates.iter().any(|candidate| {
+                candidate.repo_owner == repo.owner
+                    && candidate.repo_name == repo.repo
+                    && candidate.status != CandidateStatus::Pending
+            });
+            let unsettled = self.unsettled_repos.lock().unwrap().contains(&repo_key);
+            if !reviewed
+                && !unsettled
+                && !self
+                    .forge
+                    .has_issue_activity(&repo.owner, &repo.repo)
+                    .await?
+            {
+                debug!("No issue activity in {}/{}", repo.owner, repo.repo);
+                self.update_cursor(repo, |cursor| cursor.polled(&[])).await;
+                return Ok(false);
+            }
+            // Until we've seen the issues through, e.g. if listing them fails
+            self.unsettled_repos
+                .lock()
+                .unwrap()
+                .insert(repo_key.clone());
+        }
+
+        let issues = self.forge.get_open_issues(repo).await?;
+        self.update_cursor(repo, |cursor| cursor.polled(&issues))
+            .await;
+        debug!(
+            "Found {} issues in {}/{}",
+            issues.len(),
+            repo.owner,
+            repo.repo
+        );
+
+        // Process issues sorted by creation date (oldest first to be fair)
+        let mut sorted_issues = issues;
+        sorted_issues.sort_by_key(|issue| issue.created_at);
+
+        // Get a copy of the processed issues set
+        let processed = {
+            let processed_lock = self.processed_issues.lock().unwrap();
+            processed_lock.clone()
+        };
+        // Read every time, `ignore` and `snooze` add to them while we run
+        let ignored = self.persistence.load_ignored_issues().await?;
+        let snoozed = self.persistence.load_snoozed_issues().await?;
+
+        // Find first eligible issue
+        let mut passed_over = false;
+        for issue in sorted_issues {
+            Span::current().record("issue_number", issue.number);
+
+            // Skip already processed issues
+            if processed.contains(&repo.owner, &repo.repo, &issue) {
+                continue;
+            }
+
+            if ignored.contains(&repo.owner, &repo.repo, issue.number)
+                || self.config.ignores(&repo.owner, &repo.repo, issue.number)
+            {
+                debug!("Skipping issue #{}: ignored", issue.number);
+                continue;
+            }
+
+            let difficulty = difficulty::estimate(&issue);
+            if !self.config.allows_difficulty(difficulty) {
+                debug!(
+                    "Skipping issue #{}: estimated difficulty {} is out of range",
+                    issue.number, difficulty
+                );
+                continue;
+            }
+
+            if let Some(until) = snoozed.until(&repo.owner, &repo.repo, issue.number) {
+                debug!("Skipping issue #{}: snoozed until {}", issue.number, until);
+                // Keep listing the repo so the issue is seen again once it wakes
+                passed_over = true;
+                continue;
+            }
+
+            if self.hooks.candidate(repo, &issue) == Decision::Veto {
+                debug!("Skipping issue #{}: vetoed by a hook", issue.number);
+                passed_over = true;
+                continue;
+            }
+
+            let rule = rules::find(
+                &self.config.rules,
+                &repo.owner,
+                &repo.repo,
+                &issue,
+                Utc::now(),
+            );
+            if let Some(rule) = rule {
+                match rule.action {
+                    RuleAction::Claim | RuleAction::Review => {}
+                    RuleAction::Skip => {
+                        debug!("Skipping issue #{}: rule {}", issue.number, rule.name);
+                        continue;
+                    }
+                    RuleAction::Notify => {
+                        info!("Issue #{} matches rule {}", issue.number, rule.name);
+                        let processed_issues = {
+                            let mut processed_lock = self.processed_issues.lock().unwrap();
+                            processed_lock.insert(ProcessedIssue::new(
+                                &repo.owner,
+                                &repo.repo,
+                                &issue,
+                                ProcessedReason::Notified,
+                            ));
+                            processed_lock.clone()
+                        };
+                        self.persistence
+                            .save_processed_issues(&processed_issues)
+                            .await?;
+                        self.notifications
+                            .send(Event::Matched {
+                                issue: IssueInfo::new(&repo.owner, &repo.repo, &issue),
+                                rule: rule.name.clone(),
+                            })
+                            .await;
+                        continue;
+                    }
+                }
+
+                if rule
+                    .claims_per_week
+                    .is_some_and(|budget| self.rule_claims_this_week(rule) >= budget as usize)
+                {
+                    debug!(
+                        "Skipping issue #{}: budget of rule {} for this week is used up",
+                        issue.number, rule.name
+                    );
+                    passed_over = true;
+                    continue;
+                }
+            }
+
+            // Found an eligible issue
+            info!(
+                "Found eligible issue: #{} - {} (difficulty {})",
+                issue.number, issue.title, difficulty
+            );
+            {
+                let mut cycle = self.cycle.lock().unwrap();
+                cycle.candidates += 1;
+                cycle.top_candidate.get_or_insert_with(|| {
+                    format!("{}/{}#{}", repo.owner, repo.repo, issue.number)
+                });
+            }
+
+            // Don't comment twice if we already claimed it in a previous life
+            if let Some(existing) = self
+                .find_own_comment(&repo.owner, &repo.repo, issue.number)
+                .await?
+            {
+                // Went unanswered while we weren't looking, so resuming it
+                // would only time out right away
+                if existing.created_at + self.claim_timeout(&repo.owner, &repo.repo) < Utc::now() {
+                    info!(
+                        "Skipping issue #{}: our claim at {} went unanswered",
+                        issue.number, existing.html_url
+                    );
+                    let processed_issues = {
+                        let mut processed_lock = self.processed_issues.lock().unwrap();
+                        processed_lock.insert(ProcessedIssue::new(
+                            &repo.owner,
+                            &repo.repo,
+                            &issue,
+                            ProcessedReason::TimedOut,
+                        ));
+                        processed_lock.clone()
+                    };
+                    self.persistence
+                        .save_processed_issues(&processed_issues)
+                        .await?;
+                    self.dequeue(repo, &issue).await?;
+                    continue;
+                }
+
+                info!(
+                    "Already commented on issue #{} at {}, resuming that claim",
+                    issue.number, existing.html_url
+                );
+                self.mark_issue_as_active(
+                    &repo.owner,
+                    &repo.repo,
+                    &issue,
+                    existing.created_at,
+                    Some((existing.id, &existing.body)),
+                    AuditAction::ClaimResumed,
+                )
+                .await?;
+                self.record_rule(rule).await?;
+                self.cycle_action(format!(
+                    "resumed the claim on {}/{}#{}",
+                    repo.owner, repo.repo, issue.number
+                ));
+                return Ok(true);
+            }
+
+            if issue.locked {
+                info!(
+                    "Skipping issue #{}: the conversation is locked",
+                    issue.number
+                );
+                let processed_issues = {
+                    let mut processed_lock = self.processed_issues.lock().unwrap();
+                    processed_lock.insert(ProcessedIssue::new(
+                        &repo.owner,
+                        &repo.repo,
+                        &issue,
+                        ProcessedReason::Locked,
+                    ));
+                    processed_lock.clone()
+                };
+                self.persistence
+                    .save_processed_issues(&processed_issues)
+                    .await?;
+                self.dequeue(repo, &issue).await?;
+                continue;
+            }
+
+            if let Some(pr) = self.open_linked_pull_request(repo, &issue).await {
+                info!(
+                    "Skipping issue #{}: {} already opened {}",
+                    issue.number, pr.author, pr.html_url
+                );
+                let processed_issues = {
+                    let mut processed_lock = self.processed_issues.lock().unwrap();
+                    processed_lock.insert(ProcessedIssue::new(
+                        &repo.owner,
+                        &repo.repo,
+                        &issue,
+                        ProcessedReason::HasPullRequest,
+                    ));
+                    processed_lock.clone()
+                };
+                self.persistence
+                    .save_processed_issues(&processed_issues)
+                    .await?;
+                self.dequeue(repo, &issue).await?;
+                continue;
+            }
+
+            if self.hooks.claim(repo, &issue) == Decision::Veto {
+                info!("Not claiming issue #{}: vetoed by a hook", issue.number);
+                passed_over = true;
+                continue;
+            }
+
+            let review = self.config.review_queue
+                || rule.is_some_and(|rule| rule.action == RuleAction::Review);
+            if review && !self.approved(repo, &issue, &candidates).await? {
+                continue;
+            }
+
+            // No other issue here can be claimed before morning either
+            if !self.maintainers_awake(repo).await {
+                passed_over = true;
+                break;
+            }
+
+            if let Some(ref team) = self.team {
+                match team.reserve(&repo.owner, &repo.repo, &issue).await {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        debug!("Leaving issue #{} to a teammate", issue.number);
+                        passed_over = true;
+                        continue;
+                    }
+                    // Claiming blind could mean claiming an issue twice
+                    Err(e) => {
+                        warn!("Team claim queue unavailable, not claiming: {}", e);
+                        return Ok(false);
+                    }
+                }
+            }
+
+            // Try to comment on the issue
+            let comment = match self.request_assignment(repo, &issue, rule).await {
+                Ok(comment) => comment,
+                Err(e) => {
+                    self.release_reservation(repo, &issue).await;
+
+                    // Trying the next issue right away is the last thing to do
+                    if matches!(
+                        github_error(&e),
+                        Some(GitHubError::RateLimited { .. } | GitHubError::Unauthorized(_))
+                    ) {
+                        return Err(e);
+                    }
+
+                    warn!("Failed to request assignment: {}", e);
+                    // GitHub won't take a claim on this issue, e.g. because
+                    // it's locked, so don't try again every poll
+                    if github_error(&e).is_some_and(|e| !e.is_retryable()) {
+                        let processed_issues = {
+                            let mut processed_lock = self.processed_issues.lock().unwrap();
+                            processed_lock.insert(ProcessedIssue::new(
+                                &repo.owner,
+                                &repo.repo,
+                                &issue,
+                                ProcessedReason::Rejected,
+                            ));
+                            processed_lock.clone()
+                        };
+                        self.persistence
+                            .save_processed_issues(&processed_issues)
+                            .await?;
+                        self.dequeue(repo, &issue).await?;
+                    } else {
+                        passed_over = true;
+                    }
+                    continue;
+                }
+            };
+            self.confirm_reservation(repo, &issue).await;
+
+            // Update our state
+            self.mark_issue_as_active(
+                &repo.owner,
+                &repo.repo,
+                &issue,
+                Utc::now(),
+                comment.as_ref().map(|(id, body)| (*id, body.as_str())),
+                AuditAction::Claimed,
+            )
+            .await?;
+            self.record_rule(rule).await?;
+            let comment_id = comment.map(|(id, _)| id);
+            self.dequeue(repo, &issue).await?;
+
+            self.notifications
+                .send(Event::Claimed {
+                    issue: IssueInfo::new(&repo.owner, &repo.repo, &issue).with_comment(comment_id),
+                })
+                .await;
+            self.cycle_action(format!(
+                "claimed {}/{}#{}",
+                repo.owner, repo.repo, issue.number
+            ));
+
+            return Ok(true);
+        }
+
+        if !passed_over {
+            self.unsettled_repos.lock().unwrap().remove(&repo_key);
+        }
+
+        Ok(false)
+    }
+
+    /// Claim the first wishlist issue nobody is assigned to, whatever the
+    /// filters say. Returns whether one was claimed.
+    async fn check_wishlist(&self) -> Result<bool> {
+        let processed = self.processed_issues.lock().unwrap().clone();
+
+        for url in &self.config.wishlist {
+            // Checked when the config was loaded
+            let (owner, repo_name, issue_number) = parse_issue_url(url)?;
+            let issue = match self.forge.get_issue(&owner, &repo_name, issue_number).await {
+                Ok(issue) => issue,
+                Err(e) => {
+                    if matches!(
+                        e,
+                        GitHubError::RateLimited { .. } | GitHubError::Unauthorized(_)
+                    ) {
+                        return Err(e.into());
+                    }
+                    warn!("Failed to check wishlist issue {}: {}", url, e);
+                    continue;
+                }
+            };
+
+            if issue.state != "open"
+                || issue.locked
+                || processed.contains(&owner, &repo_name, &issue)
+            {
+                continue;
+            }
+            let (owner, repo_name, issue_number) =
+                match transferred_to(&owner, &repo_name, issue_number, &issue) {
+                    Some(moved) => {
+                        info!(
+                            "Wishlist issue {} was transferred to {}, consider updating the config",
+                            url, issue.html_url
+                        );
+                        moved
+                    }
+                    None => (owner, repo_name, issue_number),
+                };
+            let assignees = issue.assignee_logins();
+            if !assignees.is_empty() {
+                debug!(
+                    "Wishlist issue {}/{}#{} is still assigned to {}",
+                    owner,
+                    repo_name,
+                    issue_number,
+                    assignees.join(", ")
+                );
+                continue;
+            }
+
+            info!(
+                "Wishlist issue {}/{}#{} has nobody assigned",
+                owner, repo_name, issue_number
+            );
+            // Keep a repository's own keyword and cooldown if we watch it anyway
+            let repo = self
+                .config
+                .repositories
+                .iter()
+                .find(|r| r.owner == owner && r.repo == repo_name)
+                .cloned()
+                .unwrap_or_else(|| Repository {
+                    owner: owner.clone(),
+                    repo: repo_name.clone(),
+                    ..Default::default()
+                });
+
+            // The pull request may still be closed, so keep watching
+            if let Some(pr) = self.open_linked_pull_request(&repo, &issue).await {
+                debug!(
+                    "Wishlist issue {}/{}#{} has a pull request by {}",
+                    owner, repo_name, issue_number, pr.author
+                );
+                continue;
+            }
+
+            if let Some(existing) = self
+                .find_own_comment(&owner, &repo_name, issue_number)
+                .await?
+            {
+                info!(
+                    "Already commented on issue #{} at {}, resuming that claim",
+                    issue_number, existing.html_url
+                );
+                self.mark_issue_as_active(
+                    &owner,
+                    &repo_name,
+                    &issue,
+                    existing.created_at,
+                    Some((existing.id, &existing.body)),
+                    AuditAction::ClaimResumed,
+                )
+                .await?;
+                return Ok(true);
+            }
+
+            if let Some(ref team) = self.team {
+                match team.reserve(&owner, &repo_name, &issue).await {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        debug!("Leaving issue #{} to a teammate", issue_number);
+                        continue;
+                    }
+                    Err(e) => {
+                        warn!("Team claim queue unavailable, not claiming: {}", e);
+                        return Ok(false);
+                    }
+                }
+            }
+
+            let comment = match self.request_assignment(&repo, &issue, None).await {
+                Ok(comment) => comment,
+                Err(e) => {
+                    self.release_reservation(&repo, &issue).await;
+                    warn!("Failed to request assignment on wishlist issue: {}", e);
+                    continue;
+                }
+            };
+            self.confirm_reservation(&repo, &issue).await;
+            self.mark_issue_as_active(
+                &owner,
+                &repo_name,
+                &issue,
+                Utc::now(),
+                comment.as_ref().map(|(id, body)| (*id, body.as_str())),
+                AuditAction::Claimed,
+            )
+            .await?;
+
+            self.notifications
+                .send(Event::WishGranted {
+                    issue: IssueInfo::new(&owner, &repo_name, &issue)
+                        .with_comment(comment.map(|(id, _)| id)),
+                })
+                .await;
+
+            return Ok(true);
         }
+
+        Ok(false)
     }
 
-    pub async fn initialize(&mut self) -> Result<()> {
-        // Load state from persistence
-        if let Ok(active) = self.persistence.load_active_issue().await {
-            let mut lock = self.active_issue.lock().unwrap();
-            *lock = active;
-        }
+    /// Stale claims made in the last seven days, however they turned out
+    fn stale_claims_this_week(&self) -> usize {
+        let since = Utc::now() - Duration::days(7);
+        let active = self
+            .active_issue
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|active| !active.stale_assignees.is_empty() && active.requested_at > since)
+            .count();
+        let assigned = self
+            .assigned_issues
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|issue| issue.stale && issue.requested_at > since)
+            .count();
+        let finished = self
+            .history
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|entry| entry.stale && entry.requested_at > since)
+            .count();
+        active + assigned + finished
+    }
 
-        if let Ok(processed) = self.persistence.load_processed_issues().await {
-            let mut lock = self.processed_issues.lock().unwrap();
-            *lock = processed;
+    /// Claims made through a rule in the last seven days, however they
+    /// turned out
+    fn rule_claims_this_week(&self, rule: &Rule) -> usize {
+        let since = Utc::now() - Duration::days(7);
+        let through_rule = |name: &Option<String>| name.as_ref() == Some(&rule.name);
+        let active = self
+            .active_issue
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|active| through_rule(&active.rule) && active.requested_at > since)
+            .count();
+        let assigned = self
+            .assigned_issues
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|issue| through_rule(&issue.rule) && issue.requested_at > since)
+            .count();
+        let finished = self
+            .history
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|entry| through_rule(&entry.rule) && entry.requested_at > since)
+            .count();
+        active + assigned + finished
+    }
+
+    /// Ask whether an assigned issue without activity or a linked PR for
+    /// `stale_after_weeks` is still being worked on, and wait for it like a
+    /// claim. Returns whether we asked.
+    async fn ask_about_stale_assignment(&self, repositories: &[&Repository]) -> Result<bool> {
+        if self.stale_claims_this_week() >= self.config.stale_claims_per_week as usize {
+            debug!("Stale assignment budget for this week is used up");
+            return Ok(false);
         }
 
-        Ok(())
-    }
+        let cutoff = Utc::now() - Duration::weeks(self.config.stale_after_weeks as i64);
+        let processed = self.processed_issues.lock().unwrap().clone();
+        let ignored = self.persistence.load_ignored_issues().await?;
+        let snoozed = self.persistence.load_snoozed_issues().await?;
 
-    pub async fn start(&self) -> Result<()> {
-        info!("Starting GitHub issue assignment bot");
-        info!("Monitoring {} repositories", self.config.repositories.len());
+        for repo in repositories {
+            let query = format!(
+                "repo:{}/{} is:issue is:open -linked:pr updated:<{}",
+                repo.owner,
+                repo.repo,
+                cutoff.format("%Y-%m-%d")
+            );
+            let issues = match self.forge.search_issues(&query).await {
+                Ok(issues) => issues,
+                Err(e) => {
+                    debug!(
+                        "Could not look for stale assignments in {}/{}: {}",
+                        repo.owner, repo.repo, e
+                    );
+                    continue;
+                }
+            };
+
+            for issue in issues {
+                let assignees = issue.assignee_logins();
+                if assignees.is_empty()
+                    || issue.locked
+                    || issue.is_assigned_to(&self.config.user_login)
+                    || issue.updated_at > cutoff
+                    || !matches_labels_and_title(repo, &issue)
+                    || processed.contains(&repo.owner, &repo.repo, &issue)
+                    || ignored.contains(&repo.owner, &repo.repo, issue.number)
+                    || self.config.ignores(&repo.owner, &repo.repo, issue.number)
+                    || snoozed
+                        .until(&repo.owner, &repo.repo, issue.number)
+                        .is_some()
+                {
+                    continue;
+                }
 
-        let mut interval = time::interval(StdDuration::from_secs(self.config.poll_interval_secs));
+                if self.hooks.claim(repo, &issue) == Decision::Veto {
+                    debug!("Not asking about issue #{}: vetoed by a hook", issue.number);
+                    continue;
+                }
 
-        loop {
-            interval.tick().await;
+                // No other issue here can be asked about before morning either
+                if !self.maintainers_awake(repo).await {
+                    break;
+                }
+
+                if !self.is_leader() {
+                    return Ok(false);
+                }
+
+                if let Some(ref team) = self.team {
+                    match team.reserve(&repo.owner, &repo.repo, &issue).await {
+                        Ok(true) => {}
+                        Ok(false) => {
+                            debug!("Leaving issue #{} to a teammate", issue.number);
+                            continue;
+                        }
+                        // Claiming blind could mean claiming an issue twice
+                        Err(e) => {
+                            warn!("Team claim queue unavailable, not claiming: {}", e);
+                            return Ok(false);
+                        }
+                    }
+                }
+
+                info!(
+                    "Issue #{} in {}/{} has been quiet since {}, asking if {} is still on it",
+                    issue.number,
+                    repo.owner,
+                    repo.repo,
+                    issue.updated_at.format("%Y-%m-%d"),
+                    assignees.join(", ")
+                );
+                let comment = self
+                    .config
+                    .stale_templates
+                    .choose(&mut thread_rng())
+                    .cloned()
+                    .unwrap_or_else(default_stale_comment);
 
-            // Add some jitter to appear more human-like
-            let jitter = thread_rng().gen_range(0..30);
-            time::sleep(StdDuration::from_secs(jitter)).await;
+                let comment_id = match self
+                    .post_claim_comment(repo, &issue, &comment, &[], assignees.clone())
+                    .await
+                {
+                    Ok(id) => id,
+                    Err(e) => {
+                        self.release_reservation(repo, &issue).await;
+                        warn!("Failed to ask about issue #{}: {}", issue.number, e);
+                        continue;
+                    }
+                };
+                self.confirm_reservation(repo, &issue).await;
 
-            if let Err(e) = self.poll_repositories().await {
-                warn!("Error during polling: {}", e);
+                self.mark_issue_as_active(
+                    &repo.owner,
+                    &repo.repo,
+                    &issue,
+                    Utc::now(),
+                    Some((comment_id, &comment)),
+                    AuditAction::Claimed,
+                )
+                .await?;
+                self.set_stale_assignees(assignees).await?;
+
+                self.notifications
+                    .send(Event::Claimed {
+                        issue: IssueInfo::new(&repo.owner, &repo.repo, &issue)
+                            .with_comment(Some(comment_id)),
+                    })
+                    .await;
+
+                return Ok(true);
             }
         }
+
+        Ok(false)
     }
 
-    async fn poll_repositories(&self) -> Result<()> {
-        // Check if we're currently waiting for an assignment
+    /// Note who we asked about on the active issue, otherwise that assignee
+    /// ends the claim as lost
+    async fn set_stale_assignees(&self, assignees: Vec<String>) -> Result<()> {
+        let active = {
+            let mut active_lock = self.active_issue.lock().unwrap();
+            let active = active_lock.as_mut().expect("just marked as active");
+            active.stale_assignees = assignees;
+            active.clone()
+        };
+        self.persistence.save_active_issue(&active).await
+    }
+
+    /// A pull request someone is working on the issue in, even if nobody is
+    /// assigned. If we can't tell, we go ahead and claim.
+    async fn open_linked_pull_request(
+        &self,
+        repo: &Repository,
+        issue: &Issue,
+    ) -> Option<LinkedPullRequest> {
+        let prs = match self
+            .forge
+            .get_linked_pull_requests(&repo.owner, &repo.repo, issue.number)
+            .await
         {
-            let active_lock = self.active_issue.lock().unwrap();
-            if let Some(ref active) = *active_lock {
-                // Still waiting on this issue
-                if Utc::now() < active.timeout {
-                    debug!(
-                        "Waiting for assignment on issue #{} in {}/{}",
-                        active.issue_number, active.repo_owner, active.repo_name
-                    );
-                    return Ok(());
-                }
+            Ok(prs) => prs,
+            Err(e) => {
+                debug!(
+                    "Could not check issue #{} for linked pull requests: {}",
+                    issue.number, e
+                );
+                return None;
+            }
+        };
 
-                // Timeout has expired
+        prs.into_iter()
+            .find(|pr| matches!(pr.state, PullRequestState::Open | PullRequestState::Draft))
+    }
+
+    /// Whether the issue was approved in the review queue. Issues seen for
+    /// the first time are queued, rejected ones are set aside for good.
+    async fn approved(
+        &self,
+        repo: &Repository,
+        issue: &Issue,
+        candidates: &CandidateQueue,
+    ) -> Result<bool> {
+        let status = candidates
+            .get(&repo.owner, &repo.repo, issue.number)
+            .map(|candidate| candidate.status);
+
+        match status {
+            Some(CandidateStatus::Approved) => return Ok(true),
+            Some(CandidateStatus::Pending) => {}
+            Some(CandidateStatus::Rejected) => {
+                info!("Issue #{} was rejected in review", issue.number);
+                let processed_issues = {
+                    let mut processed_lock = self.processed_issues.lock().unwrap();
+                    processed_lock.insert(ProcessedIssue::new(
+                        &repo.owner,
+                        &repo.repo,
+                        issue,
+                        ProcessedReason::Declined,
+                    ));
+                    processed_lock.clone()
+                };
+                self.persistence
+                    .save_processed_issues(&processed_issues)
+                    .await?;
+                self.dequeue(repo, issue).await?;
+            }
+            None => {
+                // Reload so decisions made since the poll started aren't lost
+                let mut candidates = self.persistence.load_candidates().await?;
+                let id = candidates.push(&repo.owner, &repo.repo, issue);
+                self.persistence.save_candidates(&candidates).await?;
                 info!(
-                    "Assignment request for issue #{} in {}/{} has timed out",
-                    active.issue_number, active.repo_owner, active.repo_name
+                    "Queued issue #{} for review as candidate {}",
+                    issue.number, id
                 );
             }
         }
 
-        // Check rate limits before making requests
-        let remaining = self.github_client.get_rate_limit().await?;
-        debug!("GitHub API rate limit: {} remaining", remaining);
+        Ok(false)
+    }
 
-        if remaining < 50 {
-            warn!(
-                "GitHub API rate limit is low: {} remaining. Waiting for reset.",
-                remaining
-            );
+    /// Drop an issue we're done with from the review queue
+    async fn dequeue(&self, repo: &Repository, issue: &Issue) -> Result<()> {
+        if !self.config.reviews_issues() {
             return Ok(());
         }
 
-        // No active issue or timeout expired, so we can look for a new issue
-        for repo in &self.config.repositories {
-            match self.process_repository(repo).await {
-                Ok(true) => {
-                    // Successfully processed an issue, stop for this cycle
-                    return Ok(());
-                }
-                Ok(false) => {
-                    // No eligible issues found
-                    continue;
-                }
-                Err(e) => {
-                    warn!(
-                        "Error processing repository {}/{}: {}",
-                        repo.owner, repo.repo, e
+        let mut candidates = self.persistence.load_candidates().await?;
+        candidates.remove(&repo.owner, &repo.repo, issue.number);
+        self.persistence.save_candidates(&candidates).await
+    }
+
+    async fn find_own_comment(
+        &self,
+        owner: &str,
+        repo: &str,
+        issue_number: u64,
+    ) -> Result<Option<Comment>> {
+        let comments = self
+            .forge
+            .get_issue_comments(owner, repo, issue_number)
+            .await?;
+
+        Ok(comments.into_iter().find(|comment| {
+            comment
+                .user
+                .login
+                .eq_ignore_ascii_case(&self.config.user_login)
+        }))
+    }
+
+    /// Claim the issue, returning the ID and text of the claim comment if one
+    /// was posted
+    async fn request_assignment(
+        &self,
+        repo: &Repository,
+        issue: &Issue,
+        rule: Option<&Rule>,
+    ) -> Result<Option<(u64, String)>> {
+        let (owner, repo_name) = (repo.owner.as_str(), repo.repo.as_str());
+
+        // The lease may have run out during a long poll
+        if !self.is_leader() {
+            return Err(anyhow::anyhow!(
+                "No longer the leader, leaving issue #{} in {}/{} alone",
+                issue.number,
+                owner,
+                repo_name
+            ));
+        }
+
+        let methods = self.config.claim_methods(owner, repo_name);
+        let mut reacted = false;
+        for method in methods {
+            match method {
+                // Assign ourselves directly if we have the permission to do
+                // so, which leaves nothing to ask for
+                ClaimMethod::Assign => match self.forge.can_assign_self(owner, repo_name).await {
+                    Ok(true) => {
+                        info!(
+                            "Assigning ourselves to issue #{} in {}/{}",
+                            issue.number, owner, repo_name
+                        );
+                        let assigned = self.forge.assign_self(owner, repo_name, issue.number).await;
+                        self.audit_result(
+                            AuditAction::SelfAssigned,
+                            owner,
+                            repo_name,
+                            issue.number,
+                            &assigned,
+                            |_| "added ourselves to the assignees".to_string(),
+                        )
+                        .await;
+                        assigned?;
+                        return Ok(None);
+                    }
+                    Ok(false) => {}
+                    Err(e) => {
+                        debug!(
+                            "Could not check assignee permission in {}/{}: {}",
+                            owner, repo_name, e
+                        );
+                    }
+                },
+                ClaimMethod::Reaction => {
+                    let reaction = self.config.claim_reaction(owner, repo_name);
+                    info!(
+                        "Reacting with {} to issue #{} in {}/{}",
+                        reaction.content(),
+                        issue.number,
+                        owner,
+                        repo_name
                     );
+                    let added = self
+                        .forge
+                        .add_reaction(owner, repo_name, issue.number, reaction.content())
+                        .await;
+                    self.audit_result(
+                        AuditAction::Reacted,
+                        owner,
+                        repo_name,
+                        issue.number,
+                        &added,
+                        |_| format!("{} reaction", reaction.content()),
+                    )
+                    .await;
+                    added?;
+                    reacted = true;
                 }
+                ClaimMethod::Comment => {}
             }
         }
 
-        debug!("No eligible issues found in this cycle");
-        Ok(())
+        if !methods.contains(&ClaimMethod::Comment) {
+            if reacted {
+                return Ok(None);
+            }
+            return Err(anyhow::anyhow!(
+                "Can't claim issue #{} in {}/{}: not allowed to assign ourselves, and \
+                 claim_method has no other way",
+                issue.number,
+                owner,
+                repo_name
+            ));
+        }
+
+        let comment = self.claim_comment(repo, rule).await;
+
+        info!(
+            "Requesting assignment for issue #{} in {}/{}",
+            issue.number, owner, repo_name
+        );
+        // Keywords like "@rustbot claim" address the repository's own bot
+        let keyword = self.claim_keyword(repo).await;
+        let allowed_mentions: Vec<&str> = self
+            .config
+            .claim_mention(owner, repo_name)
+            .into_iter()
+            .chain(keyword.as_deref())
+            .flat_map(lint::mentions)
+            .collect();
+        let comment_id = self
+            .post_claim_comment(repo, issue, &comment, &allowed_mentions, Vec::new())
+            .await?;
+
+        Ok(Some((comment_id, comment)))
     }
 
-    async fn process_repository(&self, repo: &Repository) -> Result<bool> {
-        info!("Checking for issues in {}/{}", repo.owner, repo.repo);
-        
-        let issues = self.github_client.get_open_issues(repo).await?;
-        debug!("Found {} issues in {}/{}", issues.len(), repo.owner, repo.repo);
-        
-        // Process issues sorted by creation date (oldest first to be fair)
-        let mut sorted_issues = issues;
-        sorted_issues.sort_by(|a, b| a.created_at.cmp(&b.created_at));
-        
-        // Get a copy of the processed issues set
-        let processed = {
-            let processed_lock = self.processed_issues.lock().unwrap();
-            processed_lock.clone()
+    /// Post a claim comment, keeping a claim intent on disk while it's on
+    /// its way
+    async fn post_claim_comment(
+        &self,
+        repo: &Repository,
+        issue: &Issue,
+        comment: &str,
+        allowed_mentions: &[&str],
+        stale_assignees: Vec<String>,
+    ) -> Result<u64> {
+        let (owner, repo_name) = (repo.owner.as_str(), repo.repo.as_str());
+
+        // Without the intent on disk a crash could lead to a second comment
+        self.persistence
+            .save_claim_intent(&ClaimIntent {
+                repo_owner: owner.to_string(),
+                repo_name: repo_name.to_string(),
+                issue_number: issue.number,
+                created_at: Utc::now(),
+                stale_assignees,
+            })
+            .await?;
+
+        let comment_id = self
+            .post_comment(owner, repo_name, issue.number, comment, allowed_mentions)
+            .await;
+        if comment_id.is_err() {
+            // Comments are looked for before every claim, so a comment that
+            // went out anyway can't be posted twice while we're running
+            if let Err(e) = self.persistence.clear_claim_intent().await {
+                warn!("Failed to clear claim intent: {}", e);
+            }
+        }
+        self.audit_result(
+            AuditAction::CommentPosted,
+            owner,
+            repo_name,
+            issue.number,
+            &comment_id,
+            |id| format!("claim comment {}", id),
+        )
+        .await;
+
+        comment_id
+    }
+
+    async fn claim_comment(&self, repo: &Repository, rule: Option<&Rule>) -> String {
+        if let Some(keyword) = self.claim_keyword(repo).await {
+            return keyword;
+        }
+
+        // Choose a random comment template, favoring the ones that worked
+        let templates = match rule {
+            Some(rule) if !rule.templates.is_empty() => rule.templates.clone(),
+            _ => self.templates.lock().unwrap().clone(),
         };
-        
-        // Find first eligible issue
-        for issue in sorted_issues {
-            // Skip already processed issues
-            if processed.contains(&issue.id) {
-                continue;
+        let mut rng = thread_rng();
+        let template = if self.config.weight_templates {
+            let stats = TemplateStats::by_template(
+                &self.history.lock().unwrap(),
+                &self.assigned_issues.lock().unwrap(),
+            );
+            templates
+                .choose_weighted(&mut rng, |template| {
+                    stats.get(template).copied().unwrap_or_default().weight()
+                })
+                .ok()
+        } else {
+            templates.choose(&mut rng)
+        };
+        let comment = match template {
+            Some(template) => template.clone(),
+            None => "Hi, I'd like to work on this issue!".to_string(),
+        };
+
+        // Some projects ask claims to ping their triage team or say a
+        // certain phrase
+        match self.config.claim_mention(&repo.owner, &repo.repo) {
+            Some(mention) => format!("{}\n\n{}", comment, mention.trim()),
+            None => comment,
+        }
+    }
+
+    /// The keyword to claim with instead of a template, if the repository
+    /// has one
+    async fn claim_keyword(&self, repo: &Repository) -> Option<String> {
+        // An explicit keyword in the config always wins
+        if let Some(ref keyword) = repo.claim_keyword {
+            return Some(keyword.clone());
+        }
+
+        self.repo_conventions(repo)
+            .await
+            .and_then(|conventions| conventions.keyword)
+            .map(|keyword| keyword.keyword().to_string())
+    }
+
+    /// Pick up edits to `comment_templates_dir`. Until it holds a template,
+    /// or when it can't be read, the templates we have are kept.
+    async fn reload_templates(&self) {
+        let Some(ref dir) = self.config.comment_templates_dir else {
+            return;
+        };
+
+        let templates = match read_templates(dir).await {
+            Ok(templates) if templates.is_empty() => {
+                debug!("No templates in {} yet", dir.display());
+                return;
             }
-            
-            // Found an eligible issue
-            info!("Found eligible issue: #{} - {}", issue.number, issue.title);
-            
-            // Try to comment on the issue
-            if let Err(e) = self.request_assignment(&repo.owner, &repo.repo, &issue).await {
-                warn!("Failed to request assignment: {}", e);
-                continue;
+            Ok(templates) => templates,
+            Err(e) => {
+                warn!("Failed to read templates from {}: {}", dir.display(), e);
+                return;
             }
-            
-            // Update our state
-            self.mark_issue_as_active(&repo.owner, &repo.repo, &issue).await?;
-            
-            return Ok(true);
+        };
+
+        let mut current = self.templates.lock().unwrap();
+        if *current != templates {
+            info!(
+                "Loaded {} comment templates from {}",
+                templates.len(),
+                dir.display()
+            );
+            *current = templates;
         }
-        
-        Ok(false)
     }
 
-    async fn request_assignment(&self, owner: &str, repo: &str, issue: &Issue) -> Result<()> {
-        // Choose a random comment template
-        let mut rng = thread_rng();
-        let comment = match self.config.comment_templates.choose(&mut rng) {
-            Some(template) => template,
-            None => "Hi, I'd like to work on this issue!",
+    /// Whether it's daytime for the repository's maintainers, or we can't
+    /// tell, with `maintainer_hours` set
+    async fn maintainers_awake(&self, repo: &Repository) -> bool {
+        let Some(ref hours) = self.config.maintainer_hours else {
+            return true;
+        };
+        let Some(offset) = self.maintainer_offset(repo).await else {
+            return true;
         };
 
-        info!(
-            "Requesting assignment for issue #{} in {}/{}",
-            issue.number, owner, repo
-        );
-        self.github_client
-            .comment_on_issue(owner, repo, issue.number, comment)
-            .await?;
+        let awake = hours.is_daytime(Utc::now(), offset);
+        if !awake {
+            debug!(
+                "Waiting for daytime in {}/{} (UTC{}) before claiming",
+                repo.owner, repo.repo, offset
+            );
+        }
+        awake
+    }
 
-        Ok(())
+    async fn maintainer_offset(&self, repo: &Repository) -> Option<FixedOffset> {
+        if let Some(offset) = self.config.utc_offset(&repo.owner, &repo.repo) {
+            return Some(offset);
+        }
+
+        let key = format!("{}/{}", repo.owner, repo.repo);
+        if let Some(cached) = self.utc_offsets.lock().unwrap().get(&key) {
+            return *cached;
+        }
+
+        let commit_times = match self.forge.get_commit_times(&repo.owner, &repo.repo).await {
+            Ok(commit_times) => commit_times,
+            Err(e) => {
+                // Don't cache failures so we try again next time
+                debug!("Could not look at the commits of {}: {}", key, e);
+                return None;
+            }
+        };
+
+        let offset = daytime::infer_offset(&commit_times);
+        match offset {
+            Some(offset) => info!("Maintainers of {} seem to be at UTC{}", key, offset),
+            None => info!(
+                "Commits to {} don't point to a timezone, claiming there at any hour",
+                key
+            ),
+        }
+        self.utc_offsets.lock().unwrap().insert(key, offset);
+
+        offset
+    }
+
+    async fn repo_conventions(&self, repo: &Repository) -> Option<RepoConventions> {
+        let key = format!("{}/{}", repo.owner, repo.repo);
+
+        if let Some(cached) = self.conventions.lock().unwrap().get(&key) {
+            return Some(*cached);
+        }
+
+        let conventions = match conventions::detect(&self.forge, &repo.owner, &repo.repo).await {
+            Ok(conventions) => conventions,
+            Err(e) => {
+                // Don't cache failures so we try again next time
+                debug!("Could not detect claim conventions for {}: {}", key, e);
+                return None;
+            }
+        };
+
+        if let Some(keyword) = conventions.keyword {
+            info!(
+                "Detected claim keyword \"{}\" for {}",
+                keyword.keyword(),
+                key
+            );
+        }
+        if !conventions.claims_welcome {
+            info!(
+                "{} asks contributors not to request assignment, it will be skipped",
+                key
+            );
+        }
+
+        self.conventions.lock().unwrap().insert(key, conventions);
+
+        Some(conventions)
     }
 
-    async fn mark_issue_as_active(&self, owner: &str, repo: &str, issue: &Issue) -> Result<()> {
-        let timeout = Utc::now() + Duration::hours(self.config.cooldown_hours as i64);
-        
+    async fn mark_issue_as_active(
+        &self,
+        owner: &str,
+        repo: &str,
+        issue: &Issue,
+        requested_at: DateTime<Utc>,
+        comment: Option<(u64, &str)>,
+        action: AuditAction,
+    ) -> Result<()> {
+        let timeout = requested_at + self.claim_timeout(owner, repo);
+        let templates = self.templates.lock().unwrap().clone();
+        let mention = self.config.claim_mention(owner, repo);
+        let template = comment.and_then(|(_, body)| {
+            let body = body.trim();
+            let body = mention
+                .and_then(|mention| body.strip_suffix(mention.trim()))
+                .map_or(body, str::trim_end);
+            templates
+                .iter()
+                .chain(&self.config.stale_templates)
+                .chain(self.config.rules.iter().flat_map(|rule| &rule.templates))
+                .find(|template| template.trim() == body)
+                .cloned()
+        });
+
         let active = ActiveIssue {
             repo_owner: owner.to_string(),
             repo_name: repo.to_string(),
             issue_number: issue.number,
             issue_url: issue.html_url.clone(),
-            requested_at: Utc::now(),
+            issue_title: issue.title.clone(),
+            labels: issue.labels.iter().map(|l| l.name.clone()).collect(),
+            requested_at,
             timeout,
+            comment_id: comment.map(|(id, _)| id),
+            pinged_at: None,
+            template,
+            stale_assignees: vec![],
+            rule: None,
         };
-        
+
         // Update in-memory state
         {
             let mut active_lock = self.active_issue.lock().unwrap();
             *active_lock = Some(active.clone());
         }
-        
+
         {
             let mut processed_lock = self.processed_issues.lock().unwrap();
-            processed_lock.insert(issue.id);
+            processed_lock.insert(ProcessedIssue::new(
+                owner,
+                repo,
+                issue,
+                ProcessedReason::Claimed,
+            ));
         }
-        
+
         // Update persistent state
         self.persistence.save_active_issue(&active).await?;
-        
+
         // Clone the processed issues set for persistence
         let processed_issues = {
             let processed_lock = self.processed_issues.lock().unwrap();
             processed_lock.clone()
         };
-        self.persistence.save_processed_issues(&processed_issues).await?;
-        
-        info!("Issue #{} in {}/{} marked as active until {}", 
-             issue.number, owner, repo, timeout);
-        
+        self.persistence
+            .save_processed_issues(&processed_issues)
+            .await?;
+        self.persistence.clear_claim_intent().await?;
+
+        info!(
+            "Issue #{} in {}/{} marked as active until {}",
+            issue.number, owner, repo, timeout
+        );
+        self.audit(AuditEntry::new(
+            action,
+            owner,
+            repo,
+            issue.number,
+            format!("waiting for assignment until {}", timeout),
+        ))
+        .await;
+
         Ok(())
     }
+
+    /// Note the rule the active claim was made through, for its budget
+    async fn record_rule(&self, rule: Option<&Rule>) -> Result<()> {
+        let Some(rule) = rule else {
+            return Ok(());
+        };
+        let active = {
+            let mut active_lock = self.active_issue.lock().unwrap();
+            let active = active_lock.as_mut().expect("just marked as active");
+            active.rule = Some(rule.name.clone());
+            active.clone()
+        };
+        self.persistence.save_active_issue(&active).await
+    }
+}
+
+/// Every non-empty file in the directory is a template, in file name order.
+/// Hidden files, e.g. editor swap files, are skipped.
+async fn read_templates(dir: &std::path::Path) -> Result<Vec<String>> {
+    let mut files = Vec::new();
+    let mut entries = tokio::fs::read_dir(dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let hidden = entry.file_name().to_string_lossy().starts_with('.');
+        if !hidden && entry.file_type().await?.is_file() {
+            files.push(entry.path());
+        }
+    }
+    files.sort();
+
+    let mut templates = Vec::new();
+    for file in files {
+        let template = tokio::fs::read_to_string(&file)
+            .await
+            .with_context(|| format!("Failed to read {}", file.display()))?;
+        let template = template.trim();
+        if !template.is_empty() {
+            templates.push(template.to_string());
+        }
+    }
+
+    Ok(templates)
+}
+
+fn default_stale_comment() -> String {
+    "Hi! Is this still being worked on? If not, I'd be happy to pick it up.".to_string()
+}
+
+/// Resolves on the first SIGINT or SIGTERM
+#[cfg(unix)]
+async fn shutdown_signal() {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    match (
+        signal(SignalKind::interrupt()),
+        signal(SignalKind::terminate()),
+    ) {
+        (Ok(mut interrupt), Ok(mut terminate)) => {
+            tokio::select! {
+                _ = interrupt.recv() => {}
+                _ = terminate.recv() => {}
+            }
+        }
+        (Err(e), _) | (_, Err(e)) => {
+            warn!("Failed to listen for shutdown signals: {}", e);
+            std::future::pending::<()>().await;
+        }
+    }
+}
+
+#[cfg(not(unix))]
+async fn shutdown_signal() {
+    if let Err(e) = tokio::signal::ctrl_c().await {
+        warn!("Failed to listen for shutdown signals: {}", e);
+        std::future::pending::<()>().await;
+    }
+}
+
+/// Where an issue lives now if GitHub redirected us there, because it was
+/// transferred to another repository
+fn transferred_to(
+    owner: &str,
+    repo: &str,
+    issue_number: u64,
+    issue: &Issue,
+) -> Option<(String, String, u64)> {
+    let (new_owner, new_repo, new_number) = parse_issue_url(&issue.html_url).ok()?;
+    let moved = !new_owner.eq_ignore_ascii_case(owner)
+        || !new_repo.eq_ignore_ascii_case(repo)
+        || new_number != issue_number;
+    moved.then_some((new_owner, new_repo, new_number))
+}
+
+fn is_same_issue(a: &AssignedIssue, b: &AssignedIssue) -> bool {
+    a.repo_owner == b.repo_owner && a.repo_name == b.repo_name && a.issue_number == b.issue_number
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assigned(draft_pr_url: Option<&str>, linked_prs: Vec<LinkedPullRequest>) -> AssignedIssue {
+        let now = Utc::now();
+        AssignedIssue {
+            repo_owner: "owner".to_string(),
+            repo_name: "repo".to_string(),
+            issue_number: 1,
+            issue_url: "https://github.com/owner/repo/issues/1".to_string(),
+            issue_title: "Issue".to_string(),
+            requested_at: now,
+            assigned_at: now,
+            due_at: now,
+            branch: None,
+            draft_pr_url: draft_pr_url.map(str::to_string),
+            linked_prs,
+            prs_checked_at: None,
+            deadline_reminder: None,
+            template: None,
+            stale: false,
+            rule: None,
+        }
+    }
+
+    fn pr(number: u64, state: PullRequestState) -> LinkedPullRequest {
+        LinkedPullRequest {
+            number,
+            html_url: format!("https://github.com/owner/repo/pull/{}", number),
+            author: "me".to_string(),
+            state,
+        }
+    }
+
+    #[test]
+    fn counts_open_and_merged_pull_requests() {
+        assert!(assigned(None, vec![pr(2, PullRequestState::Draft)]).has_pull_request());
+        assert!(assigned(None, vec![pr(2, PullRequestState::Merged)]).has_pull_request());
+        assert!(!assigned(None, vec![pr(2, PullRequestState::Closed)]).has_pull_request());
+        assert!(!assigned(None, vec![]).has_pull_request());
+    }
+
+    #[test]
+    fn leaves_out_our_own_placeholder_draft() {
+        let draft = "https://github.com/owner/repo/pull/2";
+        let issue = assigned(Some(draft), vec![pr(2, PullRequestState::Draft)]);
+        assert!(!issue.has_pull_request());
+
+        let issue = assigned(
+            Some(draft),
+            vec![
+                pr(2, PullRequestState::Draft),
+                pr(3, PullRequestState::Open),
+            ],
+        );
+        assert!(issue.has_pull_request());
+
+        let issue = assigned(Some(draft), vec![pr(2, PullRequestState::Open)]);
+        assert!(issue.has_pull_request());
+    }
 }
@@ -0,0 +1,178 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use std::sync::Mutex;
+
+use crate::audit::AuditEntry;
+use crate::bot::{ActiveIssue, AssignedIssue, ClaimIntent};
+use crate::cursor::RepoCursors;
+use crate::health::Heartbeat;
+use crate::history::HistoryEntry;
+use crate::ignored::{IgnoredIssues, SnoozedIssues};
+use crate::notes::Notes;
+use crate::persistence::Persistence;
+use crate::processed::ProcessedIssues;
+use crate::queue::CandidateQueue;
+
+/// Write-behind for the state the bot rewrites many times a poll. The
+/// processed issues and repository cursors are kept in memory until
+/// `flush()`, so a poll writes each of them once at most; everything else,
+/// and the active issue and claim intent in particular, goes straight
+/// through. Losing the buffered state in a crash only costs a repeat look at
+/// some issues, as claims are checked against our own comments anyway.
+pub struct BufferedPersistence<P> {
+    inner: P,
+    processed_issues: Mutex<Option<ProcessedIssues>>,
+    repo_cursors: Mutex<Option<RepoCursors>>,
+}
+
+impl<P> BufferedPersistence<P> {
+    pub fn new(inner: P) -> Self {
+        Self {
+            inner,
+            processed_issues: Mutex::new(None),
+            repo_cursors: Mutex::new(None),
+        }
+    }
+
+    pub fn inner(&self) -> &P {
+        &self.inner
+    }
+}
+
+#[async_trait]
+impl<P: Persistence> Persistence for BufferedPersistence<P> {
+    async fn save_active_issue(&self, issue: &ActiveIssue) -> Result<()> {
+        self.inner.save_active_issue(issue).await
+    }
+
+    async fn load_active_issue(&self) -> Result<Option<ActiveIssue>> {
+        self.inner.load_active_issue().await
+    }
+
+    async fn clear_active_issue(&self) -> Result<()> {
+        self.inner.clear_active_issue().await
+    }
+
+    async fn save_claim_intent(&self, intent: &ClaimIntent) -> Result<()> {
+        self.inner.save_claim_intent(intent).await
+    }
+
+    async fn load_claim_intent(&self) -> Result<Option<ClaimIntent>> {
+        self.inner.load_claim_intent().await
+    }
+
+    async fn clear_claim_intent(&self) -> Result<()> {
+        self.inner.clear_claim_intent().await
+    }
+
+    async fn save_processed_issues(&self, issues: &ProcessedIssues) -> Result<()> {
+        *self.processed_issues.lock().unwrap() = Some(issues.clone());
+        Ok(())
+    }
+
+    async fn load_processed_issues(&self) -> Result<ProcessedIssues> {
+        let pending = self.processed_issues.lock().unwrap().clone();
+        match pending {
+            Some(issues) => Ok(issues),
+            None => self.inner.load_processed_issues().await,
+        }
+    }
+
+    async fn save_repo_cursors(&self, cursors: &RepoCursors) -> Result<()> {
+        *self.repo_cursors.lock().unwrap() = Some(cursors.clone());
+        Ok(())
+    }
+
+    async fn load_repo_cursors(&self) -> Result<RepoCursors> {
+        let pending = self.repo_cursors.lock().unwrap().clone();
+        match pending {
+            Some(cursors) => Ok(cursors),
+            None => self.inner.load_repo_cursors().await,
+        }
+    }
+
+    async fn save_assigned_issues(&self, issues: &[AssignedIssue]) -> Result<()> {
+        self.inner.save_assigned_issues(issues).await
+    }
+
+    async fn load_assigned_issues(&self) -> Result<Vec<AssignedIssue>> {
+        self.inner.load_assigned_issues().await
+    }
+
+    async fn save_history(&self, history: &[HistoryEntry]) -> Result<()> {
+        self.inner.save_history(history).await
+    }
+
+    async fn load_history(&self) -> Result<Vec<HistoryEntry>> {
+        self.inner.load_history().await
+    }
+
+    async fn save_candidates(&self, queue: &CandidateQueue) -> Result<()> {
+        self.inner.save_candidates(queue).await
+    }
+
+    async fn load_candidates(&self) -> Result<CandidateQueue> {
+        self.inner.load_candidates().await
+    }
+
+    async fn save_notes(&self, notes: &Notes) -> Result<()> {
+        self.inner.save_notes(notes).await
+    }
+
+    async fn load_notes(&self) -> Result<Notes> {
+        self.inner.load_notes().await
+    }
+
+    async fn save_ignored_issues(&self, issues: &IgnoredIssues) -> Result<()> {
+        self.inner.save_ignored_issues(issues).await
+    }
+
+    async fn load_ignored_issues(&self) -> Result<IgnoredIssues> {
+        self.inner.load_ignored_issues().await
+    }
+
+    async fn save_snoozed_issues(&self, issues: &SnoozedIssues) -> Result<()> {
+        self.inner.save_snoozed_issues(issues).await
+    }
+
+    async fn load_snoozed_issues(&self) -> Result<SnoozedIssues> {
+        self.inner.load_snoozed_issues().await
+    }
+
+    async fn append_audit(&self, entry: &AuditEntry) -> Result<()> {
+        self.inner.append_audit(entry).await
+    }
+
+    async fn check(&self) -> Result<()> {
+        self.inner.check().await
+    }
+
+    async fn save_heartbeat(&self, heartbeat: &Heartbeat) -> Result<()> {
+        self.inner.save_heartbeat(heartbeat).await
+    }
+
+    async fn load_heartbeat(&self) -> Result<Option<Heartbeat>> {
+        self.inner.load_heartbeat().await
+    }
+
+    async fn flush(&self) -> Result<()> {
+        let processed_issues = self.processed_issues.lock().unwrap().take();
+        if let Some(issues) = processed_issues {
+            if let Err(e) = self.inner.save_processed_issues(&issues).await {
+                // Try again next time, unless something newer came in since
+                self.processed_issues.lock().unwrap().get_or_insert(issues);
+                return Err(e);
+            }
+        }
+
+        let repo_cursors = self.repo_cursors.lock().unwrap().take();
+        if let Some(cursors) = repo_cursors {
+            if let Err(e) = self.inner.save_repo_cursors(&cursors).await {
+                self.repo_cursors.lock().unwrap().get_or_insert(cursors);
+                return Err(e);
+            }
+        }
+
+        self.inner.flush().await
+    }
+}
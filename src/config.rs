@@ -1,38 +1,425 @@
 use anyhow::{Context, Result};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+use crate::daytime::{self, MaintainerHours};
+use crate::filter::Filter;
+use crate::leader::HaConfig;
+use crate::logging::LoggingConfig;
+use crate::notify::NotificationsConfig;
+use crate::pacing::HumanProfile;
+use crate::replies;
+use crate::rules::{Rule, RuleAction};
+use crate::secret::Secret;
+use crate::team::TeamConfig;
+
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
+#[schemars(deny_unknown_fields)]
 pub struct Config {
-    pub auth_token: String,
+    pub auth_token: Secret,
     pub user_login: String,
     #[serde(default = "default_poll_interval")]
     pub poll_interval_secs: u64,
+    /// Poll busy repositories more often and quiet ones less, between
+    /// `min_poll_interval_secs` and `max_poll_interval_secs`
+    #[serde(default)]
+    pub adaptive_polling: bool,
+    #[serde(default = "default_min_poll_interval")]
+    pub min_poll_interval_secs: u64,
+    #[serde(default = "default_max_poll_interval")]
+    pub max_poll_interval_secs: u64,
+    /// Longest random delay before each poll
+    #[serde(default = "default_jitter_secs")]
+    pub jitter_secs: u64,
+    /// Poll on a less regular schedule, see `pacing`
+    #[serde(default)]
+    pub human_profile: Option<HumanProfile>,
+    /// Only claim while it's daytime for a repository's maintainers
+    #[serde(default)]
+    pub maintainer_hours: Option<MaintainerHours>,
+    #[serde(default)]
+    pub watch_notifications: bool,
+    #[serde(default = "default_notifications_poll_secs")]
+    pub notifications_poll_secs: u64,
     #[serde(default = "default_max_retries")]
     pub max_retries: u32,
     #[serde(default = "default_cooldown_hours")]
     pub cooldown_hours: u32,
+    /// Used instead of `cooldown_hours` for timeouts under an hour
+    #[serde(default)]
+    pub cooldown_minutes: Option<u32>,
+    #[serde(default)]
+    pub max_open_assignments: Option<u32>,
+    /// Send a `repos_failed` notification when more repositories than this
+    /// fail in one poll
+    #[serde(default)]
+    pub failed_repos_alert: Option<usize>,
+    /// Wait for claims in a repository about twice as long as its
+    /// maintainers usually take to answer
+    #[serde(default)]
+    pub adaptive_timeouts: bool,
+    /// After a claim timed out, wait this long before claiming anything
+    /// anywhere, 0 to claim again right away
+    #[serde(default)]
+    pub timeout_backoff_hours: u32,
+    /// Only list a repository's issues after its events feed shows issue
+    /// activity
+    #[serde(default)]
+    pub event_polling: bool,
+    /// Poll repositories whose maintainers answer claims first
+    #[serde(default)]
+    pub prefer_responsive_repos: bool,
+    /// With `prefer_responsive_repos`, poll repositories last after this
+    /// many claims in a row went unanswered
+    #[serde(default = "default_ignored_claims_limit")]
+    pub ignored_claims_limit: u32,
+    /// Poll repositories we already had pull requests merged or commits in first
+    #[serde(default)]
+    pub prefer_contributed_repos: bool,
+    /// URLs of issues never to claim, on top of the ones added with `ignore`
+    #[serde(default)]
+    pub ignored_issues: Vec<String>,
+    /// Only claim issues estimated at least this hard, from 1 to 5
+    #[serde(default)]
+    pub min_difficulty: Option<u8>,
+    /// Only claim issues estimated at most this hard, from 1 to 5
+    #[serde(default)]
+    pub max_difficulty: Option<u8>,
+    /// URLs of issues to claim as soon as nobody is assigned to them
+    #[serde(default)]
+    pub wishlist: Vec<String>,
+    /// Queue eligible issues for `queue approve` instead of claiming them
+    #[serde(default)]
+    pub review_queue: bool,
+    /// Let repositories add their `claim_mention` to claim comments
+    #[serde(default)]
+    pub claim_mentions: bool,
+    /// Phrases in a maintainer's reply that hand us the claimed issue
+    #[serde(default = "default_accept_phrases")]
+    pub accept_phrases: Vec<String>,
+    /// Phrases in a reply that turn our claim down
+    #[serde(default = "default_reject_phrases")]
+    pub reject_phrases: Vec<String>,
     #[serde(default)]
     pub comment_templates: Vec<String>,
+    /// Directory with one template per file, read again every poll and used
+    /// instead of `comment_templates` when it has any
+    #[serde(default)]
+    pub comment_templates_dir: Option<PathBuf>,
+    /// Pick templates that got us assigned more often
+    #[serde(default)]
+    pub weight_templates: bool,
+    #[serde(default)]
+    pub follow_up_comment: Option<String>,
+    /// Posted once on a claim nobody answered, `ping_after` into the wait
+    #[serde(default)]
+    pub ping_comment: Option<String>,
+    /// How far into the claim timeout to ping, from 0 to 1
+    #[serde(default = "default_ping_after")]
+    pub ping_after: f64,
+    /// Also ask about issues whose assignee went quiet
+    #[serde(default)]
+    pub stale_assignments: bool,
+    /// Weeks without activity before an assigned issue counts as stale
+    #[serde(default = "default_stale_after_weeks")]
+    pub stale_after_weeks: u32,
+    #[serde(default = "default_stale_templates")]
+    pub stale_templates: Vec<String>,
+    /// At most this many stale issues asked about in any seven days
+    #[serde(default = "default_stale_claims_per_week")]
+    pub stale_claims_per_week: u32,
+    #[serde(default = "default_abandon_comment")]
+    pub abandon_comment: String,
+    /// Posted instead of `abandon_comment` when we were never assigned
+    #[serde(default = "default_withdraw_comment")]
+    pub withdraw_comment: String,
+    /// What to do with the claim comment when someone else gets the issue
+    #[serde(default)]
+    pub lost_claim_comment: LostClaimComment,
+    /// The claim comment's new text with `lost_claim_comment = "edit"`
+    #[serde(default = "default_lost_claim_edit")]
+    pub lost_claim_edit: String,
+    #[serde(default = "default_expected_turnaround_days")]
+    pub expected_turnaround_days: u32,
+    #[serde(default = "default_deadline_reminder_hours")]
+    pub deadline_reminder_hours: u32,
+    #[serde(default)]
+    pub create_branch_on_assignment: bool,
+    #[serde(default)]
+    pub draft_pr_on_assignment: bool,
+    #[serde(default = "default_draft_pr_body")]
+    pub draft_pr_body: String,
+    #[serde(default)]
+    pub notifications: NotificationsConfig,
+    #[serde(default = "default_snooze_repo_hours")]
+    pub snooze_repo_hours: u32,
+    /// API requests to leave untouched for manual use of the token
+    #[serde(default = "default_rate_limit_reserve")]
+    pub rate_limit_reserve: u32,
+    /// OTLP collector to export traces to, needs the otel feature
+    #[serde(default)]
+    pub otlp_endpoint: Option<String>,
+    #[serde(default)]
+    pub logging: LoggingConfig,
+    /// Port to serve /healthz and /readyz on
+    #[serde(default)]
+    pub health_port: Option<u16>,
+    #[serde(default)]
+    pub http: HttpConfig,
+    pub repositories: Vec<Repository>,
+    /// What to do with the issues that got through the filters, the first
+    /// rule an issue matches deciding
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+    /// More GitHub identities to run in the same process
+    #[serde(default)]
+    pub accounts: Vec<Account>,
+    /// Share claims with teammates through Redis
+    #[serde(default)]
+    pub team: Option<TeamConfig>,
+    /// Run several replicas of the bot, of which only the leader comments
+    #[serde(default)]
+    pub ha: Option<HaConfig>,
+}
+
+/// What happens to our claim comment once the issue went to someone else
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LostClaimComment {
+    #[default]
+    Keep,
+    /// Replace it with `lost_claim_edit`
+    Edit,
+    Delete,
+}
+
+/// A way of telling maintainers we'd like an issue
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ClaimMethod {
+    /// Add ourselves to the assignees, where we have the permission
+    Assign,
+    /// React to the issue with `claim_reaction`
+    Reaction,
+    /// Post one of the comment templates
+    Comment,
+}
+
+/// Assign ourselves when we may, otherwise comment
+const DEFAULT_CLAIM_METHOD: [ClaimMethod; 2] = [ClaimMethod::Assign, ClaimMethod::Comment];
+
+/// The reactions GitHub offers that suit a claim
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Reaction {
+    #[serde(rename = "+1")]
+    ThumbsUp,
+    Heart,
+    Hooray,
+    Rocket,
+    #[default]
+    Eyes,
+}
+
+impl Reaction {
+    /// The name GitHub's API knows the reaction by
+    pub fn content(self) -> &'static str {
+        match self {
+            Reaction::ThumbsUp => "+1",
+            Reaction::Heart => "heart",
+            Reaction::Hooray => "hooray",
+            Reaction::Rocket => "rocket",
+            Reaction::Eyes => "eyes",
+        }
+    }
+}
+
+/// Another GitHub identity with its own repositories, state and rate limit,
+/// sharing every other setting with the top-level account
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct Account {
+    /// Names the account's state directory inside the data dir
+    pub name: String,
+    pub auth_token: Secret,
+    pub user_login: String,
+    #[serde(default)]
     pub repositories: Vec<Repository>,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+/// Connection settings for talking to GitHub
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct HttpConfig {
+    /// Give up on a request that hasn't completed after this long
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    #[serde(default = "default_connect_timeout_secs")]
+    pub connect_timeout_secs: u64,
+    /// Close pooled connections that have been idle this long
+    #[serde(default = "default_pool_idle_timeout_secs")]
+    pub pool_idle_timeout_secs: u64,
+    #[serde(default)]
+    pub pool_max_idle_per_host: Option<usize>,
+    /// Proxy for all requests, instead of the one from `HTTPS_PROXY` and friends
+    #[serde(default)]
+    pub proxy: Option<String>,
+    #[serde(default)]
+    pub proxy_username: Option<String>,
+    #[serde(default)]
+    pub proxy_password: Option<Secret>,
+    /// Comma-separated hosts to reach without the configured proxy
+    #[serde(default)]
+    pub no_proxy: Option<String>,
+    #[serde(default = "default_user_agent")]
+    pub user_agent: String,
+    /// Sent as `X-GitHub-Api-Version`, GitHub picks its default when unset
+    #[serde(default)]
+    pub api_version: Option<String>,
+    /// REST API root, e.g. `https://github.example.com/api/v3` for GitHub
+    /// Enterprise Server
+    #[serde(default = "default_api_url")]
+    pub api_url: String,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            timeout_secs: default_timeout_secs(),
+            connect_timeout_secs: default_connect_timeout_secs(),
+            pool_idle_timeout_secs: default_pool_idle_timeout_secs(),
+            pool_max_idle_per_host: None,
+            proxy: None,
+            proxy_username: None,
+            proxy_password: None,
+            no_proxy: None,
+            user_agent: default_user_agent(),
+            api_version: None,
+            api_url: default_api_url(),
+        }
+    }
+}
+
+impl HttpConfig {
+    /// A client builder with these timeouts and proxy settings. Without an
+    /// explicit proxy, reqwest picks one up from the environment.
+    pub fn client_builder(&self) -> Result<reqwest::ClientBuilder> {
+        let mut builder = reqwest::Client::builder()
+            .user_agent(&self.user_agent)
+            .timeout(Duration::from_secs(self.timeout_secs))
+            .connect_timeout(Duration::from_secs(self.connect_timeout_secs))
+            .pool_idle_timeout(Duration::from_secs(self.pool_idle_timeout_secs));
+        if let Some(max_idle) = self.pool_max_idle_per_host {
+            builder = builder.pool_max_idle_per_host(max_idle);
+        }
+
+        if let Some(ref url) = self.proxy {
+            let mut proxy =
+                reqwest::Proxy::all(url).with_context(|| format!("Invalid proxy URL {}", url))?;
+            if let Some(ref username) = self.proxy_username {
+                let password = self.proxy_password.as_ref().map_or("", Secret::expose);
+                proxy = proxy.basic_auth(username, password);
+            }
+            let no_proxy = match self.no_proxy {
+                Some(ref hosts) => reqwest::NoProxy::from_string(hosts),
+                None => reqwest::NoProxy::from_env(),
+            };
+            builder = builder.proxy(proxy.no_proxy(no_proxy));
+        }
+
+        Ok(builder)
+    }
+}
+
+fn default_timeout_secs() -> u64 {
+    30
+}
+
+fn default_connect_timeout_secs() -> u64 {
+    10
+}
+
+fn default_pool_idle_timeout_secs() -> u64 {
+    90
+}
+
+fn default_api_url() -> String {
+    "https://api.github.com".to_string()
+}
+
+fn default_user_agent() -> String {
+    "gh-issues-bot".to_string()
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default, JsonSchema)]
+#[schemars(deny_unknown_fields)]
 pub struct Repository {
     pub owner: String,
     pub repo: String,
+    #[serde(default)]
     pub labels: Vec<String>,
     #[serde(default)]
     pub title_regex: Option<String>,
     #[serde(default)]
     pub exclude_labels: Vec<String>,
+    /// Expression issues have to match on top of the other filters, e.g.
+    /// `label:bug and not label:blocked and comments < 5`
+    #[serde(default)]
+    pub filter: Option<Filter>,
+    /// Keyword to post instead of a template, overriding auto-detection
+    #[serde(default)]
+    pub claim_keyword: Option<String>,
+    /// Mention or phrase the contribution guide asks claims to include, e.g.
+    /// `cc @org/triage`, added below the template with `claim_mentions` on
+    #[serde(default)]
+    pub claim_mention: Option<String>,
+    /// Labels this project adds to an issue instead of assigning it, e.g.
+    /// `status: claimed`
+    #[serde(default)]
+    pub assigned_labels: Vec<String>,
+    /// How to claim issues, e.g. `["reaction", "comment"]`. Assigning
+    /// ourselves, where allowed, leaves nothing to ask for, so the others are
+    /// skipped. Assign, otherwise comment, if not set.
+    #[serde(default)]
+    pub claim_method: Option<Vec<ClaimMethod>>,
+    /// Reaction added with the `reaction` claim method
+    #[serde(default)]
+    pub claim_reaction: Reaction,
+    /// The maintainers' UTC offset, e.g. `-05:00`, for `maintainer_hours`
+    /// instead of guessing it from commit times
+    #[serde(default)]
+    pub utc_offset: Option<String>,
+    /// How long this project gets to answer a claim, instead of the global
+    /// cooldown
+    #[serde(default)]
+    pub cooldown_hours: Option<u32>,
+    #[serde(default)]
+    pub cooldown_minutes: Option<u32>,
 }
 
 fn default_poll_interval() -> u64 {
     45
 }
 
+fn default_min_poll_interval() -> u64 {
+    30
+}
+
+fn default_max_poll_interval() -> u64 {
+    3600
+}
+
+fn default_jitter_secs() -> u64 {
+    30
+}
+
+fn default_notifications_poll_secs() -> u64 {
+    120
+}
+
 fn default_max_retries() -> u32 {
     3
 }
@@ -41,11 +428,78 @@ fn default_cooldown_hours() -> u32 {
     24
 }
 
+fn default_stale_after_weeks() -> u32 {
+    8
+}
+
+fn default_stale_templates() -> Vec<String> {
+    vec!["Hi! Is this still being worked on? If not, I'd be happy to pick it up.".to_string()]
+}
+
+fn default_accept_phrases() -> Vec<String> {
+    replies::ACCEPTANCE_PHRASES.map(String::from).to_vec()
+}
+
+fn default_reject_phrases() -> Vec<String> {
+    replies::REJECTION_PHRASES.map(String::from).to_vec()
+}
+
+fn default_stale_claims_per_week() -> u32 {
+    1
+}
+
+fn default_abandon_comment() -> String {
+    "Unfortunately I can't continue working on this, so I'm unassigning myself. Sorry for the trouble!"
+        .to_string()
+}
+
+fn default_withdraw_comment() -> String {
+    "Unfortunately I can't work on this after all, so I'm withdrawing my request. Sorry for the trouble!"
+        .to_string()
+}
+
+fn default_ignored_claims_limit() -> u32 {
+    3
+}
+
+fn default_ping_after() -> f64 {
+    0.75
+}
+
+fn default_lost_claim_edit() -> String {
+    "Never mind, looks like {assignee} has this one!".to_string()
+}
+
+fn default_snooze_repo_hours() -> u32 {
+    24
+}
+
+fn default_rate_limit_reserve() -> u32 {
+    50
+}
+
+fn default_expected_turnaround_days() -> u32 {
+    7
+}
+
+fn default_deadline_reminder_hours() -> u32 {
+    24
+}
+
+fn default_draft_pr_body() -> String {
+    "Fixes #{issue_number}\n\nWork in progress, more to come soon.".to_string()
+}
+
 impl Config {
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
         let content = fs::read_to_string(path).context("Failed to read config file")?;
 
-        let mut config: Config = toml::from_str(&content).context("Failed to parse config file")?;
+        Self::from_toml(&content)
+    }
+
+    /// Parse and check the content of a config file
+    pub fn from_toml(content: &str) -> Result<Self> {
+        let mut config: Config = toml::from_str(content).context("Failed to parse config file")?;
 
         // If no comment templates provided, add some defaults
         if config.comment_templates.is_empty() {
@@ -56,14 +510,277 @@ impl Config {
             ];
         }
 
+        for url in &config.ignored_issues {
+            crate::github::parse_issue_url(url)
+                .with_context(|| format!("Invalid entry {:?} in ignored_issues", url))?;
+        }
+        for difficulty in [config.min_difficulty, config.max_difficulty]
+            .into_iter()
+            .flatten()
+        {
+            if !(crate::difficulty::MIN..=crate::difficulty::MAX).contains(&difficulty) {
+                return Err(anyhow::anyhow!(
+                    "Difficulty {} is out of range, use {} to {}",
+                    difficulty,
+                    crate::difficulty::MIN,
+                    crate::difficulty::MAX
+                ));
+            }
+        }
+
+        if config.adaptive_polling && config.min_poll_interval_secs > config.max_poll_interval_secs
+        {
+            return Err(anyhow::anyhow!(
+                "min_poll_interval_secs is above max_poll_interval_secs"
+            ));
+        }
+
+        for url in &config.wishlist {
+            crate::github::parse_issue_url(url)
+                .with_context(|| format!("Invalid entry {:?} in wishlist", url))?;
+        }
+
+        let repositories = config
+            .repositories
+            .iter()
+            .chain(config.accounts.iter().flat_map(|a| &a.repositories));
+        for repo in repositories {
+            if let Some(ref offset) = repo.utc_offset {
+                daytime::parse_offset(offset)
+                    .with_context(|| format!("In {}/{}", repo.owner, repo.repo))?;
+            }
+            if repo.claim_method.as_ref().is_some_and(Vec::is_empty) {
+                return Err(anyhow::anyhow!(
+                    "claim_method of {}/{} is empty, leave it out to assign or comment",
+                    repo.owner,
+                    repo.repo
+                ));
+            }
+        }
+
+        let mut rule_names = std::collections::HashSet::new();
+        for rule in &config.rules {
+            if !rule_names.insert(&rule.name) {
+                return Err(anyhow::anyhow!("Duplicate rule name {:?}", rule.name));
+            }
+            if rule.repos.iter().any(|repo| !repo.contains('/')) {
+                return Err(anyhow::anyhow!(
+                    "repos of rule {:?} has to be owner/repo or owner/*",
+                    rule.name
+                ));
+            }
+        }
+
+        if let Some(ref hours) = config.maintainer_hours {
+            if hours.start_hour > 23 || hours.end_hour > 24 {
+                return Err(anyhow::anyhow!(
+                    "maintainer_hours runs from 0 to 24, not {} to {}",
+                    hours.start_hour,
+                    hours.end_hour
+                ));
+            }
+        }
+
+        if config.ha.as_ref().is_some_and(|ha| ha.lease_secs < 3) {
+            return Err(anyhow::anyhow!("ha.lease_secs must be at least 3"));
+        }
+
+        let mut names = std::collections::HashSet::new();
+        for account in &config.accounts {
+            let valid = !account.name.is_empty()
+                && account
+                    .name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+            if !valid {
+                return Err(anyhow::anyhow!(
+                    "Invalid account name {:?}, use letters, digits, - and _",
+                    account.name
+                ));
+            }
+            if !names.insert(&account.name) {
+                return Err(anyhow::anyhow!("Duplicate account name {:?}", account.name));
+            }
+        }
+
         Ok(config)
     }
 
+    /// The config of each account to run, with the name of the account for
+    /// all but the top-level one. The top-level account is left out when it
+    /// has no repositories of its own and other accounts are configured.
+    pub fn split_accounts(&self) -> Vec<(Option<String>, Config)> {
+        let base = Config {
+            accounts: Vec::new(),
+            ..self.clone()
+        };
+
+        let mut configs = Vec::new();
+        if self.accounts.is_empty() || !self.repositories.is_empty() {
+            configs.push((None, base.clone()));
+        }
+        for account in &self.accounts {
+            configs.push((
+                Some(account.name.clone()),
+                Config {
+                    auth_token: account.auth_token.clone(),
+                    user_login: account.user_login.clone(),
+                    repositories: account.repositories.clone(),
+                    ..base.clone()
+                },
+            ));
+        }
+
+        configs
+    }
+
+    /// How long to wait for an answer to a claim in a repository, from its
+    /// own cooldown if it has one. Minutes win over hours.
+    pub fn claim_timeout(&self, owner: &str, repo: &str) -> chrono::Duration {
+        self.repo_claim_timeout(owner, repo).unwrap_or_else(|| {
+            let minutes = self.cooldown_minutes.unwrap_or(self.cooldown_hours * 60);
+            chrono::Duration::minutes(minutes.max(1) as i64)
+        })
+    }
+
+    /// Whether the issue is in `ignored_issues`
+    pub fn ignores(&self, owner: &str, repo: &str, issue_number: u64) -> bool {
+        self.ignored_issues.iter().any(|url| {
+            crate::github::parse_issue_url(url).is_ok_and(|(o, r, n)| {
+                o.eq_ignore_ascii_case(owner) && r.eq_ignore_ascii_case(repo) && n == issue_number
+            })
+        })
+    }
+
+    /// Whether an issue's estimated difficulty is within `min_difficulty`
+    /// and `max_difficulty`
+    pub fn allows_difficulty(&self, difficulty: u8) -> bool {
+        self.min_difficulty.is_none_or(|min| difficulty >= min)
+            && self.max_difficulty.is_none_or(|max| difficulty <= max)
+    }
+
+    /// The cooldown a repository sets for itself, if any
+    pub fn repo_claim_timeout(&self, owner: &str, repo: &str) -> Option<chrono::Duration> {
+        let repo = self
+            .repositories
+            .iter()
+            .find(|r| r.owner == owner && r.repo == repo)?;
+        let minutes = repo
+            .cooldown_minutes
+            .or_else(|| repo.cooldown_hours.map(|h| h * 60))?;
+
+        Some(chrono::Duration::minutes(minutes.max(1) as i64))
+    }
+
+    /// What to add to claim comments in a repository, None unless
+    /// `claim_mentions` is on
+    pub fn claim_mention(&self, owner: &str, repo: &str) -> Option<&str> {
+        if !self.claim_mentions {
+            return None;
+        }
+        self.repositories
+            .iter()
+            .find(|r| r.owner == owner && r.repo == repo)?
+            .claim_mention
+            .as_deref()
+    }
+
+    /// How to claim issues in a repository
+    pub fn claim_methods(&self, owner: &str, repo: &str) -> &[ClaimMethod] {
+        self.repositories
+            .iter()
+            .find(|r| r.owner == owner && r.repo == repo)
+            .and_then(|r| r.claim_method.as_deref())
+            .unwrap_or(&DEFAULT_CLAIM_METHOD)
+    }
+
+    /// Reaction to claim issues with in a repository
+    pub fn claim_reaction(&self, owner: &str, repo: &str) -> Reaction {
+        self.repositories
+            .iter()
+            .find(|r| r.owner == owner && r.repo == repo)
+            .map(|r| r.claim_reaction)
+            .unwrap_or_default()
+    }
+
+    /// The configured UTC offset of a repository's maintainers
+    pub fn utc_offset(&self, owner: &str, repo: &str) -> Option<chrono::FixedOffset> {
+        let offset = self
+            .repositories
+            .iter()
+            .find(|r| r.owner == owner && r.repo == repo)?
+            .utc_offset
+            .as_deref()?;
+        // Checked when the config was loaded
+        daytime::parse_offset(offset).ok()
+    }
+
+    /// Whether issues may end up in the review queue, through `review_queue`
+    /// or a rule
+    pub fn reviews_issues(&self) -> bool {
+        self.review_queue
+            || self
+                .rules
+                .iter()
+                .any(|rule| rule.action == RuleAction::Review)
+    }
+
+    /// Labels that mean an issue was given to whoever claimed it
+    pub fn assigned_labels(&self, owner: &str, repo: &str) -> &[String] {
+        self.repositories
+            .iter()
+            .find(|r| r.owner == owner && r.repo == repo)
+            .map_or(&[], |r| r.assigned_labels.as_slice())
+    }
+
+    /// The config of one account, the top-level one for None
+    pub fn account(&self, name: Option<&str>) -> Result<Config> {
+        let Some(name) = name else {
+            return Ok(Config {
+                accounts: Vec::new(),
+                ..self.clone()
+            });
+        };
+
+        self.split_accounts()
+            .into_iter()
+            .find(|(account, _)| account.as_deref() == Some(name))
+            .map(|(_, config)| config)
+            .ok_or_else(|| anyhow::anyhow!("No account named {} in the config", name))
+    }
+
+    /// How often the bot wakes up to poll. With `adaptive_polling` that's as
+    /// often as the busiest repository may be polled.
+    pub fn tick_interval_secs(&self) -> u64 {
+        if self.adaptive_polling {
+            self.min_poll_interval_secs.min(self.poll_interval_secs)
+        } else {
+            self.poll_interval_secs
+        }
+    }
+
+    /// The longest a poll can be put off by jitter and the human profile
+    pub fn max_poll_delay(&self) -> Duration {
+        let extra = self
+            .human_profile
+            .as_ref()
+            .map_or(0, |profile| profile.max_delay_secs);
+        Duration::from_secs(self.jitter_secs + extra)
+    }
+
+    /// JSON Schema for the config file, for editors to validate and
+    /// complete it with
+    pub fn schema() -> Result<String> {
+        let schema = schemars::schema_for!(Config);
+        Ok(serde_json::to_string_pretty(&schema)?)
+    }
+
     pub fn from_env() -> Result<Self> {
         dotenv::dotenv().ok();
 
-        let auth_token =
-            std::env::var("GITHUB_TOKEN").context("GITHUB_TOKEN environment variable not set")?;
+        let auth_token = std::env::var("GITHUB_TOKEN")
+            .map(Secret::new)
+            .context("GITHUB_TOKEN environment variable not set")?;
 
         let user_login = std::env::var("GITHUB_USERNAME")
             .context("GITHUB_USERNAME environment variable not set")?;
@@ -74,14 +791,68 @@ impl Config {
             auth_token,
             user_login,
             poll_interval_secs: default_poll_interval(),
+            adaptive_polling: false,
+            min_poll_interval_secs: default_min_poll_interval(),
+            max_poll_interval_secs: default_max_poll_interval(),
+            jitter_secs: default_jitter_secs(),
+            human_profile: None,
+            maintainer_hours: None,
+            watch_notifications: false,
+            notifications_poll_secs: default_notifications_poll_secs(),
             max_retries: default_max_retries(),
             cooldown_hours: default_cooldown_hours(),
+            cooldown_minutes: None,
+            max_open_assignments: None,
+            failed_repos_alert: None,
+            adaptive_timeouts: false,
+            timeout_backoff_hours: 0,
+            event_polling: false,
+            prefer_responsive_repos: false,
+            prefer_contributed_repos: false,
+            ignored_claims_limit: default_ignored_claims_limit(),
+            ignored_issues: vec![],
+            min_difficulty: None,
+            max_difficulty: None,
+            wishlist: vec![],
+            review_queue: false,
+            claim_mentions: false,
+            accept_phrases: default_accept_phrases(),
+            reject_phrases: default_reject_phrases(),
             comment_templates: vec![
                 "Hi, I'd love to take this one!".to_string(),
                 "This looks interesting, may I work on it?".to_string(),
                 "I'd like to contribute to this issue, thanks!".to_string(),
             ],
+            comment_templates_dir: None,
+            weight_templates: false,
+            follow_up_comment: None,
+            ping_comment: None,
+            ping_after: default_ping_after(),
+            stale_assignments: false,
+            stale_after_weeks: default_stale_after_weeks(),
+            stale_templates: default_stale_templates(),
+            stale_claims_per_week: default_stale_claims_per_week(),
+            abandon_comment: default_abandon_comment(),
+            withdraw_comment: default_withdraw_comment(),
+            lost_claim_comment: LostClaimComment::default(),
+            lost_claim_edit: default_lost_claim_edit(),
+            expected_turnaround_days: default_expected_turnaround_days(),
+            deadline_reminder_hours: default_deadline_reminder_hours(),
+            create_branch_on_assignment: false,
+            draft_pr_on_assignment: false,
+            draft_pr_body: default_draft_pr_body(),
+            notifications: NotificationsConfig::default(),
+            snooze_repo_hours: default_snooze_repo_hours(),
+            rate_limit_reserve: default_rate_limit_reserve(),
+            otlp_endpoint: std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT").ok(),
+            logging: LoggingConfig::default(),
+            health_port: None,
+            http: HttpConfig::default(),
             repositories: vec![],
+            rules: vec![],
+            accounts: vec![],
+            team: None,
+            ha: None,
         })
     }
 }
@@ -0,0 +1,131 @@
+//! Changes to the repository list of a config file that keep everything
+//! else in it, comments and formatting included, as it was.
+
+use anyhow::{Context, Result};
+use std::path::Path;
+use toml_edit::{Array, ArrayOfTables, DocumentMut, InlineTable, Item, Table, Value};
+
+use crate::config::{Config, Repository};
+use crate::persistence;
+
+/// A config file, read to be edited and written back
+pub struct ConfigFile {
+    document: DocumentMut,
+}
+
+impl ConfigFile {
+    pub fn parse(content: &str) -> Result<Self> {
+        let document = content
+            .parse::<DocumentMut>()
+            .context("Failed to parse config file")?;
+        Ok(Self { document })
+    }
+
+    pub async fn read(path: &Path) -> Result<Self> {
+        let content = tokio::fs::read_to_string(path)
+            .await
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::parse(&content)
+    }
+
+    /// Check the edited config still loads, then replace the file with it
+    pub async fn write(&self, path: &Path) -> Result<()> {
+        let content = self.to_string();
+        Config::from_toml(&content).context("The edited config doesn't load")?;
+
+        persistence::write_atomic(path, &content)
+            .await
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Add a repository to the end of `repositories`
+    pub fn add_repository(&mut self, repo: &Repository) -> Result<()> {
+        if self.position(&repo.owner, &repo.repo).is_some() {
+            return Err(anyhow::anyhow!(
+                "{}/{} is already in the config",
+                repo.owner,
+                repo.repo
+            ));
+        }
+
+        let mut table = Table::new();
+        table.insert("owner", toml_edit::value(&repo.owner));
+        table.insert("repo", toml_edit::value(&repo.repo));
+        table.insert("labels", toml_edit::value(array(&repo.labels)));
+        if !repo.exclude_labels.is_empty() {
+            table.insert(
+                "exclude_labels",
+                toml_edit::value(array(&repo.exclude_labels)),
+            );
+        }
+
+        match self.document.get_mut("repositories") {
+            None => {
+                let mut repositories = ArrayOfTables::new();
+                repositories.push(table);
+                self.document
+                    .insert("repositories", Item::ArrayOfTables(repositories));
+            }
+            Some(Item::ArrayOfTables(repositories)) => repositories.push(table),
+            // `repositories = [{ owner = ... }]`
+            Some(Item::Value(Value::Array(repositories))) => {
+                let mut inline = InlineTable::new();
+                inline.extend(
+                    table
+                        .iter()
+                        .filter_map(|(key, item)| Some((key, item.as_value()?.clone()))),
+                );
+                repositories.push(inline);
+            }
+            Some(_) => return Err(anyhow::anyhow!("repositories isn't a list")),
+        }
+
+        Ok(())
+    }
+
+    /// Remove a repository from `repositories`, the owner and name matched
+    /// ignoring case as GitHub does
+    pub fn remove_repository(&mut self, owner: &str, repo: &str) -> Result<()> {
+        let index = self
+            .position(owner, repo)
+            .with_context(|| format!("{}/{} isn't in the config", owner, repo))?;
+
+        match self.document.get_mut("repositories") {
+            Some(Item::ArrayOfTables(repositories)) => repositories.remove(index),
+            Some(Item::Value(Value::Array(repositories))) => {
+                repositories.remove(index);
+            }
+            _ => unreachable!("found the repository in a list"),
+        }
+
+        Ok(())
+    }
+
+    fn position(&self, owner: &str, repo: &str) -> Option<usize> {
+        let matches = |entry: &dyn toml_edit::TableLike| {
+            let field = |key| entry.get(key).and_then(Item::as_str);
+            field("owner").is_some_and(|o| o.eq_ignore_ascii_case(owner))
+                && field("repo").is_some_and(|r| r.eq_ignore_ascii_case(repo))
+        };
+
+        match self.document.get("repositories")? {
+            Item::ArrayOfTables(repositories) => {
+                repositories.iter().position(|table| matches(table))
+            }
+            Item::Value(Value::Array(repositories)) => repositories
+                .iter()
+                .position(|value| value.as_inline_table().is_some_and(|table| matches(table))),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for ConfigFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.document)
+    }
+}
+
+fn array(items: &[String]) -> Array {
+    items.iter().map(String::as_str).collect()
+}
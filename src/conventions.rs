@@ -0,0 +1,213 @@
+use anyhow::Result;
+use std::collections::HashMap;
+
+use crate::forge::Forge;
+use crate::github::Comment;
+
+/// How many matching comments we need to see before trusting a pattern
+const MIN_KEYWORD_MATCHES: usize = 2;
+
+/// Where projects usually document how to contribute
+const CONTRIBUTING_FILES: [&str; 4] = [
+    "CONTRIBUTING.md",
+    ".github/CONTRIBUTING.md",
+    "docs/CONTRIBUTING.md",
+    ".github/ISSUE_TEMPLATE/config.yml",
+];
+
+/// Phrases used by projects that don't want people asking for assignment
+const NO_CLAIM_PHRASES: [&str; 5] = [
+    "don't ask to be assigned",
+    "do not ask to be assigned",
+    "do not ask for assignment",
+    "we don't assign issues",
+    "we do not assign issues",
+];
+
+/// Phrases that only say the same in a sentence about assignment. On their
+/// own they're as likely to be about asking questions or opening issues.
+const NO_CLAIM_HINTS: [&str; 4] = [
+    "no need to ask",
+    "don't need to ask",
+    "do not need to ask",
+    "just open a pr",
+];
+
+/// What we know about how a repository wants issues to be claimed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RepoConventions {
+    pub keyword: Option<ClaimConvention>,
+    pub claims_welcome: bool,
+}
+
+/// A repository-specific way of claiming issues, usually backed by a bot
+/// that assigns whoever posts the magic keyword.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ClaimConvention {
+    /// Kubernetes Prow and friends
+    Prow,
+    /// Rust's triagebot
+    Rustbot,
+    /// The "take" keyword used by pandas, Arrow and others
+    Take,
+}
+
+impl ClaimConvention {
+    const ALL: [ClaimConvention; 3] = [
+        ClaimConvention::Prow,
+        ClaimConvention::Rustbot,
+        ClaimConvention::Take,
+    ];
+
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            ClaimConvention::Prow => "/assign",
+            ClaimConvention::Rustbot => "@rustbot claim",
+            ClaimConvention::Take => "take",
+        }
+    }
+
+    /// Config files whose presence gives the convention away
+    fn config_files(&self) -> &'static [&'static str] {
+        match self {
+            ClaimConvention::Prow => &[".prow.yaml"],
+            ClaimConvention::Rustbot => &["triagebot.toml"],
+            ClaimConvention::Take => &[],
+        }
+    }
+
+    fn matches(&self, comment: &Comment) -> bool {
+        let first_line = comment.body.lines().next().unwrap_or("").trim();
+        first_line.eq_ignore_ascii_case(self.keyword())
+    }
+}
+
+/// Detect how a repository wants issues to be claimed.
+///
+/// Contributing guides are checked for a "don't ask, just open a PR" policy,
+/// then known bot config files, then recent issue comments are scanned for a
+/// keyword that people keep posting.
+pub async fn detect<T: Forge + ?Sized>(
+    client: &T,
+    owner: &str,
+    repo: &str,
+) -> Result<RepoConventions> {
+    let mut claims_welcome = true;
+    for path in CONTRIBUTING_FILES {
+        if let Some(content) = client.get_file_content(owner, repo, path).await? {
+            if discourages_claims(&content) {
+                claims_welcome = false;
+                break;
+            }
+        }
+    }
+
+    Ok(RepoConventions {
+        keyword: detect_keyword(client, owner, repo).await?,
+        claims_welcome,
+    })
+}
+
+async fn detect_keyword<T: Forge + ?Sized>(
+    client: &T,
+    owner: &str,
+    repo: &str,
+) -> Result<Option<ClaimConvention>> {
+    for convention in ClaimConvention::ALL {
+        for path in convention.config_files() {
+            if client.get_file_content(owner, repo, path).await?.is_some() {
+                return Ok(Some(convention));
+            }
+        }
+    }
+
+    // Plenty of projects keep an OWNERS file without running Prow, so only
+    // trust one written in Prow's schema
+    if let Some(content) = client.get_file_content(owner, repo, "OWNERS").await? {
+        if is_prow_owners(&content) {
+            return Ok(Some(ClaimConvention::Prow));
+        }
+    }
+
+    let comments = client.get_recent_comments(owner, repo).await?;
+
+    Ok(detect_from_comments(&comments))
+}
+
+pub fn is_prow_owners(owners: &str) -> bool {
+    owners.lines().any(|line| {
+        let line = line.trim_end();
+        line == "approvers:" || line == "reviewers:"
+    })
+}
+
+pub fn discourages_claims(contributing: &str) -> bool {
+    // Normalize curly apostrophes and line breaks before matching
+    let text = contributing
+        .to_lowercase()
+        .replace('\u{2019}', "'")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    text.split(['.', '!', '?', ';']).any(|sentence| {
+        NO_CLAIM_PHRASES
+            .iter()
+            .any(|phrase| sentence.contains(phrase))
+            || (sentence.contains("assign")
+                && NO_CLAIM_HINTS.iter().any(|hint| sentence.contains(hint)))
+    })
+}
+
+pub fn detect_from_comments(comments: &[Comment]) -> Option<ClaimConvention> {
+    let mut counts: HashMap<ClaimConvention, usize> = HashMap::new();
+
+    for comment in comments {
+        if let Some(convention) = ClaimConvention::ALL.iter().find(|c| c.matches(comment)) {
+            *counts.entry(*convention).or_insert(0) += 1;
+        }
+    }
+
+    counts
+        .into_iter()
+        .filter(|(_, count)| *count >= MIN_KEYWORD_MATCHES)
+        .max_by_key(|(_, count)| *count)
+        .map(|(convention, _)| convention)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spots_policies_against_asking_for_assignment() {
+        assert!(discourages_claims(
+            "Please don\u{2019}t ask to be\nassigned, we review whatever comes in."
+        ));
+        assert!(discourages_claims(
+            "We do not assign issues to contributors."
+        ));
+        assert!(discourages_claims(
+            "There's no need to ask to be assigned to an issue."
+        ));
+        assert!(discourages_claims(
+            "Just open a PR, you don't need to be assigned first!"
+        ));
+    }
+
+    #[test]
+    fn ignores_the_same_words_about_other_things() {
+        assert!(!discourages_claims(
+            "If something is unclear, no need to ask on the mailing list first, open an issue."
+        ));
+        assert!(!discourages_claims(
+            "You don't need to ask before reporting a bug. Issues are assigned by the triage team."
+        ));
+        assert!(!discourages_claims(
+            "For typo fixes, just open a PR. For anything larger, please open an issue first."
+        ));
+        assert!(!discourages_claims(
+            "Comment on the issue and a maintainer will assign it to you."
+        ));
+    }
+}
@@ -0,0 +1,141 @@
+use anyhow::{Context, Result};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde::{Deserialize, Serialize};
+
+/// Environment variable holding the hex-encoded 32 byte state key
+pub const STATE_KEY_ENV: &str = "GH_ISSUES_BOT_STATE_KEY";
+
+const ALGORITHM: &str = "chacha20poly1305";
+
+/// Key used to encrypt state files at rest
+pub struct StateKey {
+    cipher: ChaCha20Poly1305,
+}
+
+/// What an encrypted state file looks like on disk. It stays valid JSON so
+/// backups and tooling can still tell an intact file from a damaged one.
+#[derive(Serialize, Deserialize)]
+struct Sealed {
+    encrypted: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+impl StateKey {
+    pub fn from_hex(hex_key: &str) -> Result<Self> {
+        let bytes = hex::decode(hex_key.trim()).context("State key is not valid hex")?;
+        if bytes.len() != 32 {
+            return Err(anyhow::anyhow!(
+                "State key must be 32 bytes (64 hex characters), got {} bytes",
+                bytes.len()
+            ));
+        }
+
+        Ok(Self {
+            cipher: ChaCha20Poly1305::new(Key::from_slice(&bytes)),
+        })
+    }
+
+    /// Read the key from the environment, if one is set
+    pub fn from_env() -> Result<Option<Self>> {
+        match std::env::var(STATE_KEY_ENV) {
+            Ok(hex_key) if !hex_key.trim().is_empty() => Self::from_hex(&hex_key)
+                .with_context(|| format!("Invalid {}", STATE_KEY_ENV))
+                .map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    pub fn seal(&self, plaintext: &str) -> Result<String> {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, plaintext.as_bytes())
+            .map_err(|_| anyhow::anyhow!("Failed to encrypt state"))?;
+
+        Ok(serde_json::to_string(&Sealed {
+            encrypted: ALGORITHM.to_string(),
+            nonce: hex::encode(nonce),
+            ciphertext: hex::encode(ciphertext),
+        })?)
+    }
+
+    pub fn open(&self, content: &str) -> Result<String> {
+        let Some(sealed) = parse_sealed(content) else {
+            // Written before encryption was turned on, re-encrypted on the next save
+            return Ok(content.to_string());
+        };
+
+        let nonce = hex::decode(&sealed.nonce).context("Invalid nonce in encrypted state")?;
+        if nonce.len() != 12 {
+            return Err(anyhow::anyhow!("Invalid nonce in encrypted state"));
+        }
+        let ciphertext =
+            hex::decode(&sealed.ciphertext).context("Invalid ciphertext in encrypted state")?;
+        let plaintext = self
+            .cipher
+            .decrypt(Nonce::from_slice(&nonce), ciphertext.as_ref())
+            .map_err(|_| {
+                anyhow::anyhow!("Failed to decrypt state, was it written with a different key?")
+            })?;
+
+        String::from_utf8(plaintext).context("Decrypted state is not valid UTF-8")
+    }
+}
+
+/// Fail with a helpful message if a file is encrypted but no key is set
+pub fn ensure_plaintext(content: &str) -> Result<()> {
+    match parse_sealed(content) {
+        Some(_) => Err(anyhow::anyhow!(
+            "State is encrypted, set {} to read it",
+            STATE_KEY_ENV
+        )),
+        None => Ok(()),
+    }
+}
+
+fn parse_sealed(content: &str) -> Option<Sealed> {
+    serde_json::from_str::<Sealed>(content)
+        .ok()
+        .filter(|sealed| sealed.encrypted == ALGORITHM)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+
+    #[test]
+    fn round_trips_through_seal_and_open() {
+        let key = StateKey::from_hex(KEY).unwrap();
+
+        let sealed = key.seal(r#"{"version":3}"#).unwrap();
+        assert!(!sealed.contains("version"));
+        assert_eq!(key.open(&sealed).unwrap(), r#"{"version":3}"#);
+        assert!(ensure_plaintext(&sealed).is_err());
+    }
+
+    #[test]
+    fn reads_files_written_before_encryption() {
+        let key = StateKey::from_hex(KEY).unwrap();
+
+        assert_eq!(key.open("[1, 2]").unwrap(), "[1, 2]");
+        assert!(ensure_plaintext("[1, 2]").is_ok());
+    }
+
+    #[test]
+    fn fails_to_open_with_another_key() {
+        let sealed = StateKey::from_hex(KEY).unwrap().seal("secret").unwrap();
+        let other = StateKey::from_hex(&"ff".repeat(32)).unwrap();
+
+        assert!(other.open(&sealed).is_err());
+    }
+
+    #[test]
+    fn rejects_keys_of_the_wrong_length() {
+        assert!(StateKey::from_hex("0011").is_err());
+        assert!(StateKey::from_hex("not hex").is_err());
+    }
+}
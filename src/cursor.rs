@@ -0,0 +1,91 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::config::Repository;
+use crate::github::Issue;
+
+/// Days of issue arrivals to go by, older ones fade out
+const ARRIVAL_WINDOW_DAYS: f64 = 30.0;
+
+/// Polling progress per repository, keyed by `owner/repo`
+pub type RepoCursors = BTreeMap<String, RepoCursor>;
+
+/// Where polling of a repository left off
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RepoCursor {
+    #[serde(default)]
+    pub last_polled_at: Option<DateTime<Utc>>,
+    /// Highest issue ID seen so far, newer issues have higher IDs
+    #[serde(default)]
+    pub last_seen_issue_id: Option<u64>,
+    #[serde(default)]
+    pub last_error: Option<String>,
+    #[serde(default)]
+    pub last_error_at: Option<DateTime<Utc>>,
+    /// When we started counting new eligible issues
+    #[serde(default)]
+    pub counting_since: Option<DateTime<Utc>>,
+    /// New eligible issues seen since `counting_since`
+    #[serde(default)]
+    pub arrivals: f64,
+}
+
+impl RepoCursor {
+    pub fn polled(&mut self, issues: &[Issue]) {
+        let now = Utc::now();
+        // Before the first poll every open issue would look new
+        if let Some(last_seen) = self.last_seen_issue_id {
+            self.arrivals += issues.iter().filter(|issue| issue.id > last_seen).count() as f64;
+        }
+        let since = *self.counting_since.get_or_insert(now);
+        let days = days_between(since, now);
+        if days > ARRIVAL_WINDOW_DAYS {
+            self.arrivals *= ARRIVAL_WINDOW_DAYS / days;
+            self.counting_since = Some(now - Duration::days(ARRIVAL_WINDOW_DAYS as i64));
+        }
+
+        self.last_polled_at = Some(now);
+        self.last_seen_issue_id = issues
+            .iter()
+            .map(|issue| issue.id)
+            .chain(self.last_seen_issue_id)
+            .max();
+        self.last_error = None;
+        self.last_error_at = None;
+    }
+
+    pub fn failed(&mut self, error: &anyhow::Error) {
+        self.last_error = Some(error.to_string());
+        self.last_error_at = Some(Utc::now());
+    }
+
+    /// New eligible issues a day, once there's a day of polling to go by
+    pub fn arrival_rate(&self) -> Option<f64> {
+        let days = days_between(self.counting_since?, Utc::now());
+        if days < 1.0 {
+            return None;
+        }
+        // Counting half an issue lets quiet repositories slow down gradually
+        Some(self.arrivals.max(0.5) / days)
+    }
+
+    /// How long to wait between polls: `base` for a repository that gets one
+    /// new eligible issue a day, proportionally shorter for busier ones and
+    /// longer for quieter ones
+    pub fn poll_interval(&self, base: Duration, min: Duration, max: Duration) -> Duration {
+        let interval = match self.arrival_rate() {
+            Some(rate) => Duration::seconds((base.num_seconds() as f64 / rate) as i64),
+            None => base,
+        };
+        interval.clamp(min, max)
+    }
+}
+
+fn days_between(from: DateTime<Utc>, to: DateTime<Utc>) -> f64 {
+    (to - from).num_seconds() as f64 / 86400.0
+}
+
+pub fn key(repo: &Repository) -> String {
+    format!("{}/{}", repo.owner, repo.repo)
+}
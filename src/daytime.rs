@@ -0,0 +1,91 @@
+//! Working out when a repository's maintainers are around, so claims land
+//! while someone is there to answer them instead of sinking under a night's
+//! worth of notifications.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, FixedOffset, Timelike, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::f64::consts::TAU;
+
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct MaintainerHours {
+    /// Hour the maintainers' day starts, in their time
+    #[serde(default = "default_start_hour")]
+    pub start_hour: u32,
+    /// Hour the maintainers' day ends, in their time
+    #[serde(default = "default_end_hour")]
+    pub end_hour: u32,
+}
+
+fn default_start_hour() -> u32 {
+    9
+}
+
+fn default_end_hour() -> u32 {
+    19
+}
+
+impl MaintainerHours {
+    /// Whether it's daytime at `offset` from UTC
+    pub fn is_daytime(&self, now: DateTime<Utc>, offset: FixedOffset) -> bool {
+        let hour = now.with_timezone(&offset).hour();
+        if self.start_hour <= self.end_hour {
+            (self.start_hour..self.end_hour).contains(&hour)
+        } else {
+            // A day that runs past midnight
+            hour >= self.start_hour || hour < self.end_hour
+        }
+    }
+}
+
+/// Fewer commits than this say too little to guess from
+const MIN_COMMITS: usize = 20;
+
+/// How tightly commit hours have to cluster, from 0 for spread evenly over
+/// the day to 1 for all in the same hour. Projects with maintainers all
+/// around the world don't have a home timezone.
+const MIN_CONCENTRATION: f64 = 0.25;
+
+/// Local hour commits cluster around in a typical working day
+const PEAK_HOUR: f64 = 15.0;
+
+/// Guess the maintainers' UTC offset from when commits landed, by taking
+/// the hour they cluster around as mid-afternoon. None when there are too
+/// few commits or they're spread around the clock.
+pub fn infer_offset(commit_times: &[DateTime<Utc>]) -> Option<FixedOffset> {
+    if commit_times.len() < MIN_COMMITS {
+        return None;
+    }
+
+    // Hours are on a circle, 23:00 and 01:00 are two hours apart
+    let (sin, cos) = commit_times.iter().fold((0.0, 0.0), |(sin, cos), time| {
+        let hour = time.hour() as f64 + time.minute() as f64 / 60.0;
+        let angle = hour / 24.0 * TAU;
+        (sin + angle.sin(), cos + angle.cos())
+    });
+    let count = commit_times.len() as f64;
+    let concentration = (sin * sin + cos * cos).sqrt() / count;
+    if concentration < MIN_CONCENTRATION {
+        return None;
+    }
+
+    let peak_utc = sin.atan2(cos).rem_euclid(TAU) / TAU * 24.0;
+    let mut hours = (PEAK_HOUR - peak_utc).round() as i32;
+    if hours > 14 {
+        hours -= 24;
+    } else if hours < -12 {
+        hours += 24;
+    }
+
+    FixedOffset::east_opt(hours * 3600)
+}
+
+/// A UTC offset as written in the config, e.g. `+02:00` or `-05:30`
+pub fn parse_offset(offset: &str) -> Result<FixedOffset> {
+    offset
+        .parse()
+        .ok()
+        .with_context(|| format!("Invalid UTC offset {:?}, use e.g. +02:00", offset))
+}
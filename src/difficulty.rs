@@ -0,0 +1,91 @@
+use crate::github::Issue;
+
+/// Easiest and hardest possible estimates
+pub const MIN: u8 = 1;
+pub const MAX: u8 = 5;
+
+/// Label fragments that mark an issue as easy, e.g. "good first issue" or "E-easy"
+const EASY_LABELS: [&str; 6] = [
+    "good first issue",
+    "good-first-issue",
+    "easy",
+    "beginner",
+    "starter",
+    "trivial",
+];
+
+/// Label fragments that mark an issue as hard, e.g. "E-hard" or "complex"
+const HARD_LABELS: [&str; 4] = ["hard", "difficult", "complex", "expert"];
+
+/// Extensions that make a word in the body look like a file reference
+const SOURCE_EXTENSIONS: [&str; 14] = [
+    ".rs", ".toml", ".py", ".js", ".ts", ".tsx", ".go", ".c", ".h", ".cpp", ".java", ".rb", ".md",
+    ".yml",
+];
+
+/// A rough guess at how hard an issue is, from `MIN` to `MAX`, based on its
+/// labels, how long its description is, how many checklist items it has and
+/// how many files or crates it mentions
+pub fn estimate(issue: &Issue) -> u8 {
+    let mut score: i32 = 3;
+
+    let labels: Vec<String> = issue
+        .labels
+        .iter()
+        .map(|label| label.name.to_lowercase())
+        .collect();
+    if labels
+        .iter()
+        .any(|label| EASY_LABELS.iter().any(|easy| label.contains(easy)))
+    {
+        score -= 2;
+    } else if labels
+        .iter()
+        .any(|label| HARD_LABELS.iter().any(|hard| label.contains(hard)))
+    {
+        score += 2;
+    }
+
+    let body = issue.body.as_deref().unwrap_or_default();
+    match body.len() {
+        0..=300 => score -= 1,
+        301..=3000 => {}
+        _ => score += 1,
+    }
+
+    let checklist = body
+        .lines()
+        .map(str::trim_start)
+        .filter(|line| {
+            ["- [ ]", "- [x]", "* [ ]", "* [x]"]
+                .iter()
+                .any(|item| line.to_lowercase().starts_with(item))
+        })
+        .count();
+    if checklist >= 5 {
+        score += 1;
+    }
+
+    if referenced_paths(body) >= 4 {
+        score += 1;
+    }
+
+    score.clamp(MIN as i32, MAX as i32) as u8
+}
+
+/// Distinct words that look like source files or crate directories
+fn referenced_paths(body: &str) -> usize {
+    let mut paths: Vec<&str> = body
+        .split(|c: char| c.is_whitespace() || "`'\"()[]<>,:;".contains(c))
+        .map(|word| word.trim_end_matches('.'))
+        .filter(|word| !word.starts_with("http"))
+        .filter(|word| {
+            word.starts_with("src/")
+                || word.starts_with("crates/")
+                || SOURCE_EXTENSIONS.iter().any(|ext| word.ends_with(ext))
+        })
+        .collect();
+    paths.sort_unstable();
+    paths.dedup();
+    paths.len()
+}
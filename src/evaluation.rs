@@ -0,0 +1,233 @@
+//! What issue selection makes of a snapshot of open issues, issue by issue,
+//! for `replay --report`: which filter or rule ruled each one out, how the
+//! rest rank and what the claim comment would have said.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use rand::{seq::SliceRandom, thread_rng};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::config::{ClaimMethod, Config, Repository};
+use crate::difficulty;
+use crate::github::{filter_rejection, Issue};
+use crate::rules::{self, RuleAction};
+
+/// Every snapshot replayed, in the order they were recorded
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct Evaluation {
+    pub snapshots: Vec<Snapshot>,
+}
+
+/// The open issues of one repository at one point in time
+#[derive(Debug, Clone, Serialize)]
+pub struct Snapshot {
+    pub recorded_at: DateTime<Utc>,
+    pub repo: String,
+    pub issues: Vec<IssueEvaluation>,
+    /// The issue that would have been claimed
+    pub claimed: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IssueEvaluation {
+    pub number: u64,
+    pub title: String,
+    pub url: String,
+    pub created_at: DateTime<Utc>,
+    pub difficulty: u8,
+    /// Why the issue was ruled out, None if it wasn't
+    pub rejected_by: Option<String>,
+    /// The rule the issue matched, if any
+    pub rule: Option<String>,
+    /// Place among the eligible issues, oldest first, from 1
+    pub rank: Option<usize>,
+    /// The claim comment, for the issue that would have been claimed
+    pub comment: Option<String>,
+}
+
+impl Snapshot {
+    /// Run a repository's filters, the difficulty range and the rules over
+    /// its open issues as they were at `recorded_at`. Issues in `claimed`
+    /// were picked from an earlier snapshot, and the one picked from this
+    /// snapshot is added to it.
+    pub fn evaluate(
+        config: &Config,
+        repo: &Repository,
+        recorded_at: DateTime<Utc>,
+        issues: &[Issue],
+        claimed: &mut HashSet<u64>,
+    ) -> Self {
+        let mut issues: Vec<&Issue> = issues.iter().collect();
+        issues.sort_by_key(|issue| issue.created_at);
+
+        let mut evaluations = Vec::with_capacity(issues.len());
+        let mut eligible = 0;
+        let mut picked = None;
+        for issue in issues {
+            let difficulty = difficulty::estimate(issue);
+            let rule = rules::find(&config.rules, &repo.owner, &repo.repo, issue, recorded_at);
+            let rejected_by = filter_rejection(repo, issue, recorded_at)
+                .or_else(|| {
+                    (!config.allows_difficulty(difficulty))
+                        .then(|| format!("difficulty {} is out of range", difficulty))
+                })
+                .or_else(|| match rule {
+                    Some(rule) if rule.action == RuleAction::Skip => {
+                        Some(format!("rule {:?} skips it", rule.name))
+                    }
+                    Some(rule) if rule.action == RuleAction::Notify => {
+                        Some(format!("rule {:?} only notifies", rule.name))
+                    }
+                    _ => None,
+                })
+                .or_else(|| {
+                    claimed
+                        .contains(&issue.id)
+                        .then(|| "claimed from an earlier snapshot".to_string())
+                });
+
+            let rank = rejected_by.is_none().then(|| {
+                eligible += 1;
+                eligible
+            });
+            let comment = if rank == Some(1) {
+                picked = Some(issue.number);
+                claimed.insert(issue.id);
+                claim_comment(config, repo, rule.map(|rule| rule.templates.as_slice()))
+            } else {
+                None
+            };
+
+            evaluations.push(IssueEvaluation {
+                number: issue.number,
+                title: issue.title.clone(),
+                url: issue.html_url.clone(),
+                created_at: issue.created_at,
+                difficulty,
+                rejected_by,
+                rule: rule.map(|rule| rule.name.clone()),
+                rank,
+                comment,
+            });
+        }
+
+        Self {
+            recorded_at,
+            repo: format!("{}/{}", repo.owner, repo.repo),
+            issues: evaluations,
+            claimed: picked,
+        }
+    }
+
+    /// How many issues got through every filter
+    pub fn eligible(&self) -> usize {
+        self.issues
+            .iter()
+            .filter(|issue| issue.rank.is_some())
+            .count()
+    }
+}
+
+/// The comment a claim would be made with, picked like the bot picks it
+/// apart from conventions detected in the repository, which aren't looked
+/// up offline. None if the repository isn't claimed with comments.
+fn claim_comment(
+    config: &Config,
+    repo: &Repository,
+    rule_templates: Option<&[String]>,
+) -> Option<String> {
+    if !config
+        .claim_methods(&repo.owner, &repo.repo)
+        .contains(&ClaimMethod::Comment)
+    {
+        return None;
+    }
+    if let Some(ref keyword) = repo.claim_keyword {
+        return Some(keyword.clone());
+    }
+
+    let templates = rule_templates
+        .filter(|templates| !templates.is_empty())
+        .unwrap_or(&config.comment_templates);
+    let comment = templates
+        .choose(&mut thread_rng())
+        .cloned()
+        .unwrap_or_else(|| "Hi, I'd like to work on this issue!".to_string());
+    Some(match config.claim_mention(&repo.owner, &repo.repo) {
+        Some(mention) => format!("{}\n\n{}", comment, mention.trim()),
+        None => comment,
+    })
+}
+
+impl Evaluation {
+    pub fn json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// A section per snapshot with a table of its issues
+    pub fn markdown(&self) -> String {
+        fn cell(field: &str) -> String {
+            field.replace('|', "\\|").replace('\n', " ")
+        }
+
+        let mut out = String::from("# Issue selection\n");
+        for snapshot in &self.snapshots {
+            out.push_str(&format!(
+                "\n## {} at {}\n\n{} of {} open issues eligible",
+                snapshot.repo,
+                snapshot.recorded_at.format("%Y-%m-%d %H:%M"),
+                snapshot.eligible(),
+                snapshot.issues.len()
+            ));
+            match snapshot.claimed {
+                Some(number) => out.push_str(&format!(", would claim #{}\n", number)),
+                None => out.push_str(", nothing to claim\n"),
+            }
+            if snapshot.issues.is_empty() {
+                continue;
+            }
+
+            out.push_str("\n| rank | issue | title | opened | difficulty | rule | result |\n");
+            out.push_str("| --- | --- | --- | --- | --- | --- | --- |\n");
+            for issue in &snapshot.issues {
+                let result = match (&issue.rejected_by, &issue.comment) {
+                    (Some(reason), _) => reason.clone(),
+                    (None, Some(comment)) => format!("claim with \"{}\"", comment),
+                    (None, None) if snapshot.claimed == Some(issue.number) => "claim".to_string(),
+                    (None, None) => "eligible".to_string(),
+                };
+                out.push_str(&format!(
+                    "| {} | [#{}]({}) | {} | {} | {} | {} | {} |\n",
+                    issue.rank.map(|rank| rank.to_string()).unwrap_or_default(),
+                    issue.number,
+                    issue.url,
+                    cell(&issue.title),
+                    issue.created_at.format("%Y-%m-%d"),
+                    issue.difficulty,
+                    cell(issue.rule.as_deref().unwrap_or_default()),
+                    cell(&result)
+                ));
+            }
+        }
+        out
+    }
+
+    /// Write the evaluation as JSON or markdown, by the file's extension
+    pub async fn write(&self, path: &Path) -> Result<()> {
+        let content = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => self.json()?,
+            Some("md") => self.markdown(),
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "Don't know how to write {}, use a .json or .md file",
+                    path.display()
+                ))
+            }
+        };
+        tokio::fs::write(path, content)
+            .await
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
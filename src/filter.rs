@@ -0,0 +1,472 @@
+//! The `filter` expressions repositories can use in place of, or on top of,
+//! the individual filter options, e.g.
+//! `label:'good first issue' and not label:blocked and age > 2d and comments < 5`.
+//!
+//! - `label:NAME` holds when the issue has the label, ignoring case
+//! - `title:TEXT` and `author:LOGIN` match the title and the issue's author,
+//!   ignoring case, the title anywhere in it
+//! - `age`, `updated` and `comments` compare how long ago the issue was
+//!   opened and last updated, e.g. `age > 2d` with `m`, `h`, `d` or `w`,
+//!   and its number of comments, with `<`, `<=`, `>`, `>=`, `=` or `!=`
+//! - `and`, `or`, `not` and parentheses combine them, `and` binding tighter
+//!   than `or`
+//!
+//! Values with spaces or other punctuation go in single or double quotes.
+//! Expressions are parsed when the config is loaded, so a typo stops the bot
+//! from starting instead of quietly matching nothing.
+
+use chrono::{DateTime, Duration, Utc};
+use schemars::gen::SchemaGenerator;
+use schemars::schema::Schema;
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use thiserror::Error;
+
+use crate::github::Issue;
+
+/// A parsed `filter` expression, along with the text it came from
+#[derive(Debug, Clone)]
+pub struct Filter {
+    source: String,
+    expr: Expr,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("{message} at character {position} of the filter")]
+pub struct FilterError {
+    pub message: String,
+    /// Where in the expression, counting from 1
+    pub position: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Or(Vec<Expr>),
+    And(Vec<Expr>),
+    Not(Box<Expr>),
+    Label(String),
+    Title(String),
+    Author(String),
+    Age(Op, Duration),
+    Updated(Op, Duration),
+    Comments(Op, u64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+}
+
+impl Op {
+    fn compare<T: PartialOrd>(self, left: T, right: T) -> bool {
+        match self {
+            Op::Lt => left < right,
+            Op::Le => left <= right,
+            Op::Gt => left > right,
+            Op::Ge => left >= right,
+            Op::Eq => left == right,
+            Op::Ne => left != right,
+        }
+    }
+}
+
+impl Filter {
+    pub fn parse(source: &str) -> Result<Self, FilterError> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser {
+            tokens: &tokens,
+            next: 0,
+            end: source.chars().count() + 1,
+        };
+        let expr = parser.or()?;
+        if let Some(token) = parser.peek() {
+            return Err(token.error("Expected and, or or the end"));
+        }
+
+        Ok(Self {
+            source: source.to_string(),
+            expr,
+        })
+    }
+
+    /// Whether an issue passes the filter at the time given
+    pub fn matches(&self, issue: &Issue, now: DateTime<Utc>) -> bool {
+        self.expr.matches(issue, now)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+}
+
+impl Expr {
+    fn matches(&self, issue: &Issue, now: DateTime<Utc>) -> bool {
+        match self {
+            Expr::Or(exprs) => exprs.iter().any(|expr| expr.matches(issue, now)),
+            Expr::And(exprs) => exprs.iter().all(|expr| expr.matches(issue, now)),
+            Expr::Not(expr) => !expr.matches(issue, now),
+            Expr::Label(name) => issue
+                .labels
+                .iter()
+                .any(|label| label.name.eq_ignore_ascii_case(name)),
+            Expr::Title(text) => issue.title.to_lowercase().contains(&text.to_lowercase()),
+            Expr::Author(login) => issue
+                .user
+                .as_ref()
+                .is_some_and(|user| user.login.eq_ignore_ascii_case(login)),
+            Expr::Age(op, age) => op.compare(now - issue.created_at, *age),
+            Expr::Updated(op, age) => op.compare(now - issue.updated_at, *age),
+            Expr::Comments(op, count) => op.compare(issue.comments, *count),
+        }
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl<'de> Deserialize<'de> for Filter {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let source = String::deserialize(deserializer)?;
+        Filter::parse(&source).map_err(serde::de::Error::custom)
+    }
+}
+
+impl Serialize for Filter {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.source)
+    }
+}
+
+impl JsonSchema for Filter {
+    fn schema_name() -> String {
+        "Filter".to_string()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        String::json_schema(gen)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Word(String),
+    Quoted(String),
+    Colon,
+    Op(Op),
+    Open,
+    Close,
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    position: usize,
+}
+
+impl Token {
+    fn error(&self, message: &str) -> FilterError {
+        FilterError {
+            message: message.to_string(),
+            position: self.position,
+        }
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, FilterError> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let position = i + 1;
+        let mut push = |kind, len| {
+            tokens.push(Token { kind, position });
+            len
+        };
+
+        i += match c {
+            c if c.is_whitespace() => 1,
+            '(' => push(TokenKind::Open, 1),
+            ')' => push(TokenKind::Close, 1),
+            ':' => push(TokenKind::Colon, 1),
+            '<' | '>' | '=' | '!' => {
+                let double = chars.get(i + 1) == Some(&'=');
+                let op = match (c, double) {
+                    ('<', false) => Op::Lt,
+                    ('<', true) => Op::Le,
+                    ('>', false) => Op::Gt,
+                    ('>', true) => Op::Ge,
+                    ('=', _) => Op::Eq,
+                    ('!', true) => Op::Ne,
+                    _ => {
+                        return Err(FilterError {
+                            message: "Expected !=".to_string(),
+                            position,
+                        })
+                    }
+                };
+                push(TokenKind::Op(op), if double { 2 } else { 1 })
+            }
+            '\'' | '"' => {
+                let Some(len) = chars[i + 1..].iter().position(|&end| end == c) else {
+                    return Err(FilterError {
+                        message: format!("Unclosed {}", c),
+                        position,
+                    });
+                };
+                let text = chars[i + 1..i + 1 + len].iter().collect();
+                push(TokenKind::Quoted(text), len + 2)
+            }
+            _ => {
+                let len = chars[i..]
+                    .iter()
+                    .position(|&c| c.is_whitespace() || "():<>=!'\"".contains(c))
+                    .unwrap_or(chars.len() - i);
+                let word = chars[i..i + len].iter().collect();
+                push(TokenKind::Word(word), len)
+            }
+        };
+    }
+
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    next: usize,
+    /// Position just past the expression, for errors at its end
+    end: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next)
+    }
+
+    fn take(&mut self, what: &str) -> Result<&Token, FilterError> {
+        let token = self.tokens.get(self.next).ok_or_else(|| FilterError {
+            message: format!("Expected {}", what),
+            position: self.end,
+        })?;
+        self.next += 1;
+        Ok(token)
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        let found = matches!(
+            self.peek(),
+            Some(Token { kind: TokenKind::Word(word), .. }) if word.eq_ignore_ascii_case(keyword)
+        );
+        if found {
+            self.next += 1;
+        }
+        found
+    }
+
+    fn or(&mut self) -> Result<Expr, FilterError> {
+        let mut exprs = vec![self.and()?];
+        while self.keyword("or") {
+            exprs.push(self.and()?);
+        }
+        Ok(if exprs.len() == 1 {
+            exprs.remove(0)
+        } else {
+            Expr::Or(exprs)
+        })
+    }
+
+    fn and(&mut self) -> Result<Expr, FilterError> {
+        let mut exprs = vec![self.unary()?];
+        while self.keyword("and") {
+            exprs.push(self.unary()?);
+        }
+        Ok(if exprs.len() == 1 {
+            exprs.remove(0)
+        } else {
+            Expr::And(exprs)
+        })
+    }
+
+    fn unary(&mut self) -> Result<Expr, FilterError> {
+        if self.keyword("not") {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+
+        let token = self.take("a condition")?.clone();
+        match &token.kind {
+            TokenKind::Open => {
+                let expr = self.or()?;
+                match self.take(")")? {
+                    Token {
+                        kind: TokenKind::Close,
+                        ..
+                    } => Ok(expr),
+                    token => Err(token.error("Expected )")),
+                }
+            }
+            TokenKind::Word(field) => self.condition(&token, &field.to_lowercase()),
+            _ => Err(token.error("Expected a condition")),
+        }
+    }
+
+    fn condition(&mut self, field_token: &Token, field: &str) -> Result<Expr, FilterError> {
+        match field {
+            "label" | "title" | "author" => {
+                match self.take(":")?.kind {
+                    TokenKind::Colon => {}
+                    _ => return Err(self.tokens[self.next - 1].error("Expected :")),
+                }
+                let value = self.take("a value")?;
+                let value = match value.kind {
+                    TokenKind::Word(ref text) | TokenKind::Quoted(ref text) => text.clone(),
+                    _ => return Err(value.error("Expected a value")),
+                };
+                Ok(match field {
+                    "label" => Expr::Label(value),
+                    "title" => Expr::Title(value),
+                    _ => Expr::Author(value),
+                })
+            }
+            "age" | "updated" | "comments" => {
+                let op = match self.take("<, <=, >, >=, = or !=")?.kind {
+                    TokenKind::Op(op) => op,
+                    _ => {
+                        return Err(
+                            self.tokens[self.next - 1].error("Expected <, <=, >, >=, = or !=")
+                        )
+                    }
+                };
+                let value = self.take("a number")?;
+                let TokenKind::Word(ref text) = value.kind else {
+                    return Err(value.error("Expected a number"));
+                };
+                if field == "comments" {
+                    let count = text.parse().map_err(|_| value.error("Expected a number"))?;
+                    return Ok(Expr::Comments(op, count));
+                }
+                let period = parse_period(text)
+                    .ok_or_else(|| value.error("Expected a period like 30m, 12h, 2d or 1w"))?;
+                Ok(match field {
+                    "age" => Expr::Age(op, period),
+                    _ => Expr::Updated(op, period),
+                })
+            }
+            _ => Err(field_token.error(&format!(
+                "Unknown condition {:?}, use label, title, author, age, updated or comments",
+                field
+            ))),
+        }
+    }
+}
+
+/// `30m`, `12h`, `2d` or `1w`
+fn parse_period(text: &str) -> Option<Duration> {
+    let unit = text.chars().last()?;
+    // Parsed unsigned so `-2d` is rejected, then checked against what a
+    // Duration can hold
+    let amount: u64 = text[..text.len() - unit.len_utf8()].parse().ok()?;
+    let amount = i64::try_from(amount).ok()?;
+    match unit {
+        'm' => Duration::try_minutes(amount),
+        'h' => Duration::try_hours(amount),
+        'd' => Duration::try_days(amount),
+        'w' => Duration::try_weeks(amount),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn issue(title: &str, labels: &[&str], comments: u64) -> Issue {
+        let labels: Vec<_> = labels
+            .iter()
+            .map(|name| serde_json::json!({ "name": name, "color": "ffffff", "description": null }))
+            .collect();
+        serde_json::from_value(serde_json::json!({
+            "id": 1,
+            "number": 1,
+            "title": title,
+            "html_url": "https://github.com/owner/repo/issues/1",
+            "state": "open",
+            "created_at": "2024-01-01T00:00:00Z",
+            "updated_at": "2024-01-09T00:00:00Z",
+            "assignee": null,
+            "assignees": [],
+            "labels": labels,
+            "user": { "login": "Alice" },
+            "comments": comments,
+        }))
+        .unwrap()
+    }
+
+    fn now() -> DateTime<Utc> {
+        "2024-01-10T00:00:00Z".parse().unwrap()
+    }
+
+    fn matches(filter: &str, issue: &Issue) -> bool {
+        Filter::parse(filter).unwrap().matches(issue, now())
+    }
+
+    #[test]
+    fn matches_labels_titles_and_authors_ignoring_case() {
+        let issue = issue("Fix the Parser", &["Good First Issue"], 0);
+
+        assert!(matches("label:'good first issue'", &issue));
+        assert!(!matches("label:bug", &issue));
+        assert!(matches("title:parser", &issue));
+        assert!(matches("author:alice", &issue));
+    }
+
+    #[test]
+    fn compares_ages_and_comments() {
+        let issue = issue("Fix the parser", &[], 3);
+
+        assert!(matches("age > 8d", &issue));
+        assert!(!matches("age > 2w", &issue));
+        assert!(matches("updated <= 24h", &issue));
+        assert!(matches("comments < 5 and comments != 2", &issue));
+        assert!(!matches("comments >= 4", &issue));
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let issue = issue("Fix the parser", &["bug"], 0);
+
+        assert!(matches("label:docs and label:x or label:bug", &issue));
+        assert!(!matches("label:docs and (label:x or label:bug)", &issue));
+        assert!(matches("not label:blocked and label:bug", &issue));
+    }
+
+    #[test]
+    fn reports_where_parsing_failed() {
+        let error = Filter::parse("label:bug and age > soon").unwrap_err();
+        assert_eq!(error.position, 21);
+        assert!(error.message.starts_with("Expected a period"));
+
+        assert!(Filter::parse("label:bug or").is_err());
+        assert!(Filter::parse("stars > 5").is_err());
+        assert!(Filter::parse("(label:bug").is_err());
+    }
+
+    #[test]
+    fn rejects_periods_out_of_range() {
+        assert_eq!(parse_period("2d"), Some(Duration::days(2)));
+        assert_eq!(parse_period("-2d"), None);
+        assert_eq!(parse_period("2y"), None);
+        assert_eq!(parse_period("d"), None);
+        assert_eq!(parse_period("99999999999999w"), None);
+        assert!(Filter::parse("age > 99999999999999w").is_err());
+    }
+}
@@ -0,0 +1,105 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+use crate::github::Issue;
+use crate::recording::RecordedResponse;
+use crate::secret;
+
+/// The open issues of one repository, captured for reproducing what the bot
+/// does with them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Fixture {
+    pub captured_at: DateTime<Utc>,
+    pub owner: String,
+    pub repo: String,
+    pub issues: Vec<Issue>,
+}
+
+impl Fixture {
+    /// Strip the issues down to what filtering looks at, so fixtures can be
+    /// shared in bug reports
+    pub fn new(owner: &str, repo: &str, issues: Vec<Issue>) -> Self {
+        Self {
+            captured_at: Utc::now(),
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+            issues: issues.into_iter().map(sanitize).collect(),
+        }
+    }
+
+    /// As if the issues had been recorded with `run --record`, for `replay`
+    pub fn to_recorded_response(&self) -> Result<RecordedResponse> {
+        Ok(RecordedResponse {
+            recorded_at: self.captured_at,
+            owner: self.owner.clone(),
+            repo: self.repo.clone(),
+            body: serde_json::to_value(&self.issues)?,
+        })
+    }
+}
+
+/// Only the logins of assignees, not their profiles
+fn sanitize(mut issue: Issue) -> Issue {
+    let login_only = |user: &serde_json::Value| serde_json::json!({ "login": user["login"] });
+    issue.assignee = issue.assignee.as_ref().map(login_only);
+    issue.assignees = issue.assignees.iter().map(login_only).collect();
+    issue
+}
+
+fn fixture_path(dir: &Path, owner: &str, repo: &str) -> PathBuf {
+    dir.join(owner).join(format!("{}.json", repo))
+}
+
+/// Write a fixture to `<dir>/<owner>/<repo>.json`, returning the path
+pub async fn save(dir: &Path, fixture: &Fixture) -> Result<PathBuf> {
+    let path = fixture_path(dir, &fixture.owner, &fixture.repo);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .await
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+
+    // Titles and labels are free text, anything that looks like a token goes
+    let content = serde_json::to_string_pretty(fixture)?;
+    fs::write(&path, secret::redact(&content).as_bytes())
+        .await
+        .with_context(|| format!("Failed to write fixture {}", path.display()))?;
+
+    Ok(path)
+}
+
+/// Read every fixture under a directory written by `save`
+pub async fn load_dir(dir: &Path) -> Result<Vec<Fixture>> {
+    let mut fixtures = Vec::new();
+    let mut owners = fs::read_dir(dir)
+        .await
+        .with_context(|| format!("Failed to read fixtures in {}", dir.display()))?;
+
+    while let Some(owner) = owners.next_entry().await? {
+        if !owner.file_type().await?.is_dir() {
+            continue;
+        }
+
+        let mut repos = fs::read_dir(owner.path()).await?;
+        while let Some(entry) = repos.next_entry().await? {
+            let path = entry.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+
+            let content = fs::read_to_string(&path)
+                .await
+                .with_context(|| format!("Failed to read fixture {}", path.display()))?;
+            let fixture: Fixture = serde_json::from_str(&content)
+                .with_context(|| format!("Failed to parse fixture {}", path.display()))?;
+            fixtures.push(fixture);
+        }
+    }
+
+    fixtures.sort_by(|a, b| (&a.owner, &a.repo).cmp(&(&b.owner, &b.repo)));
+
+    Ok(fixtures)
+}
@@ -0,0 +1,150 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+use crate::config::Repository;
+use crate::github::{
+    Comment, GitHubError, Issue, LinkedPullRequest, NotificationThread, PullRequest,
+    RepositoryInfo, Result,
+};
+use crate::ratelimit::{ApiUsage, RateLimit};
+
+/// A code host the bot can claim issues on. Hosts map their API onto the
+/// GitHub-shaped types in `github`.
+///
+/// Listing issues, commenting and assigning are required. The rest have
+/// defaults for hosts without an equivalent API: replies, linked pull
+/// requests and commit times are never seen, and reacting, searching or
+/// opening draft pull requests fails with `GitHubError::Unsupported`.
+#[async_trait]
+pub trait Forge: Send + Sync {
+    async fn get_open_issues(&self, repo: &Repository) -> Result<Vec<Issue>>;
+    async fn get_issue(&self, owner: &str, repo: &str, issue_number: u64) -> Result<Issue>;
+    async fn comment_on_issue(
+        &self,
+        owner: &str,
+        repo: &str,
+        issue_number: u64,
+        comment: &str,
+    ) -> Result<u64>;
+    async fn edit_comment(
+        &self,
+        owner: &str,
+        repo: &str,
+        comment_id: u64,
+        comment: &str,
+    ) -> Result<()>;
+    async fn delete_comment(&self, owner: &str, repo: &str, comment_id: u64) -> Result<()>;
+    async fn get_rate_limit(&self) -> Result<RateLimit>;
+    async fn can_assign_self(&self, owner: &str, repo: &str) -> Result<bool>;
+    async fn assign_self(&self, owner: &str, repo: &str, issue_number: u64) -> Result<()>;
+    async fn unassign_self(&self, owner: &str, repo: &str, issue_number: u64) -> Result<()>;
+    async fn get_recent_comments(&self, owner: &str, repo: &str) -> Result<Vec<Comment>>;
+    async fn get_issue_comments(
+        &self,
+        owner: &str,
+        repo: &str,
+        issue_number: u64,
+    ) -> Result<Vec<Comment>>;
+    async fn get_comment(&self, owner: &str, repo: &str, comment_id: u64) -> Result<Comment>;
+    async fn get_file_content(&self, owner: &str, repo: &str, path: &str)
+        -> Result<Option<String>>;
+
+    /// Whether a repository's issues may have changed since the last call,
+    /// so that listing them again is worth it
+    async fn has_issue_activity(&self, _owner: &str, _repo: &str) -> Result<bool> {
+        Ok(true)
+    }
+
+    /// React to an issue, e.g. with `eyes`
+    async fn add_reaction(
+        &self,
+        _owner: &str,
+        _repo: &str,
+        _issue_number: u64,
+        _content: &str,
+    ) -> Result<()> {
+        Err(GitHubError::Unsupported("Reacting to issues"))
+    }
+
+    async fn get_notifications(&self, _since: DateTime<Utc>) -> Result<Vec<NotificationThread>> {
+        Ok(Vec::new())
+    }
+
+    /// When the latest commits on the default branch were committed
+    async fn get_commit_times(&self, _owner: &str, _repo: &str) -> Result<Vec<DateTime<Utc>>> {
+        Ok(Vec::new())
+    }
+
+    async fn get_linked_pull_requests(
+        &self,
+        _owner: &str,
+        _repo: &str,
+        _issue_number: u64,
+    ) -> Result<Vec<LinkedPullRequest>> {
+        Ok(Vec::new())
+    }
+
+    async fn search_issues(&self, _query: &str) -> Result<Vec<Issue>> {
+        Err(GitHubError::Unsupported("Searching issues"))
+    }
+
+    async fn count_issues(&self, _query: &str) -> Result<u64> {
+        Err(GitHubError::Unsupported("Counting issues"))
+    }
+
+    /// `owner/name` of the repositories holding the commits a search finds
+    async fn search_commit_repos(&self, _query: &str) -> Result<Vec<String>> {
+        Err(GitHubError::Unsupported("Searching commits"))
+    }
+
+    /// A repository as the forge knows it, with the owner and name spelled
+    /// the way it does
+    async fn get_repository(&self, _owner: &str, _repo: &str) -> Result<RepositoryInfo> {
+        Err(GitHubError::Unsupported("Looking up repositories"))
+    }
+
+    async fn create_fork(&self, _owner: &str, _repo: &str) -> Result<RepositoryInfo> {
+        Err(GitHubError::Unsupported("Forking"))
+    }
+
+    async fn get_branch_sha(&self, _owner: &str, _repo: &str, _branch: &str) -> Result<String> {
+        Err(GitHubError::Unsupported("Reading branches"))
+    }
+
+    async fn create_branch(
+        &self,
+        _owner: &str,
+        _repo: &str,
+        _branch: &str,
+        _sha: &str,
+    ) -> Result<()> {
+        Err(GitHubError::Unsupported("Creating branches"))
+    }
+
+    async fn create_empty_commit(
+        &self,
+        _owner: &str,
+        _repo: &str,
+        _branch: &str,
+        _message: &str,
+    ) -> Result<()> {
+        Err(GitHubError::Unsupported("Committing"))
+    }
+
+    async fn create_draft_pull_request(
+        &self,
+        _owner: &str,
+        _repo: &str,
+        _head: &str,
+        _base: &str,
+        _title: &str,
+        _body: &str,
+    ) -> Result<PullRequest> {
+        Err(GitHubError::Unsupported("Opening draft pull requests"))
+    }
+
+    /// Requests sent so far. Hosts that don't keep count report none.
+    fn api_usage(&self) -> ApiUsage {
+        ApiUsage::default()
+    }
+}
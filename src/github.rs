@@ -1,24 +1,69 @@
-use anyhow::{Context, Result};
+use anyhow::Context;
 use async_trait::async_trait;
+use bytes::Bytes;
 use chrono::{DateTime, Utc};
-use octocrab::Octocrab;
+use http_body_util::Full;
+use octocrab::{params, Octocrab, OctocrabBuilder, Page};
 use reqwest::header;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::task::{Context as TaskContext, Poll};
+use tracing::{instrument, warn};
 
-use crate::config::Repository;
+use crate::config::{HttpConfig, Repository};
+use crate::forge::Forge;
+use crate::ratelimit::{ApiUsage, RateLimit};
+use crate::recording::Recorder;
+use crate::retry::{self, RetryStats};
+use crate::secret::Secret;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Issue {
     pub id: u64,
     pub number: u64,
     pub title: String,
+    #[serde(default)]
+    pub body: Option<String>,
     pub html_url: String,
     pub state: String,
+    #[serde(default)]
+    pub state_reason: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub assignee: Option<serde_json::Value>,
     pub assignees: Vec<serde_json::Value>,
     pub labels: Vec<Label>,
+    #[serde(default)]
+    pub user: Option<User>,
+    #[serde(default)]
+    pub comments: u64,
+    /// Only collaborators can comment on a locked conversation
+    #[serde(default)]
+    pub locked: bool,
+}
+
+impl Issue {
+    pub fn assignee_logins(&self) -> Vec<String> {
+        let mut logins: Vec<String> = Vec::new();
+        for assignee in self.assignees.iter().chain(self.assignee.iter()) {
+            if let Some(login) = assignee["login"].as_str() {
+                if !logins.iter().any(|l| l.eq_ignore_ascii_case(login)) {
+                    logins.push(login.to_string());
+                }
+            }
+        }
+        logins
+    }
+
+    pub fn is_assigned_to(&self, login: &str) -> bool {
+        self.assignee_logins()
+            .iter()
+            .any(|l| l.eq_ignore_ascii_case(login))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -28,157 +73,1688 @@ pub struct Label {
     pub description: Option<String>,
 }
 
-#[async_trait]
-pub trait GitHubClient {
-    async fn get_open_issues(&self, repo: &Repository) -> Result<Vec<Issue>>;
-    async fn comment_on_issue(
-        &self,
-        owner: &str,
-        repo: &str,
-        issue_number: u64,
-        comment: &str,
-    ) -> Result<()>;
-    async fn get_rate_limit(&self) -> Result<u32>;
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Comment {
+    pub id: u64,
+    #[serde(default)]
+    pub body: String,
+    pub user: User,
+    pub html_url: String,
+    pub created_at: DateTime<Utc>,
+    /// How the author relates to the repository, e.g. `MEMBER`
+    #[serde(default)]
+    pub author_association: Option<String>,
+}
+
+impl Comment {
+    /// Whether the author can speak for the repository
+    pub fn is_from_maintainer(&self) -> bool {
+        matches!(
+            self.author_association.as_deref(),
+            Some("OWNER" | "MEMBER" | "COLLABORATOR")
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct User {
+    pub login: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RepositoryInfo {
+    pub name: String,
+    pub owner: User,
+    pub default_branch: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PullRequest {
+    pub number: u64,
+    pub html_url: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PullRequestState {
+    Draft,
+    Open,
+    Merged,
+    Closed,
+}
+
+impl std::fmt::Display for PullRequestState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = match self {
+            PullRequestState::Draft => "draft",
+            PullRequestState::Open => "open",
+            PullRequestState::Merged => "merged",
+            PullRequestState::Closed => "closed",
+        };
+        f.write_str(state)
+    }
+}
+
+/// A pull request that references an issue
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinkedPullRequest {
+    pub number: u64,
+    pub html_url: String,
+    pub author: String,
+    pub state: PullRequestState,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitCommit {
+    sha: String,
+    tree: GitObject,
+}
+
+#[derive(Debug, Deserialize)]
+struct CommitEntry {
+    commit: CommitDetails,
+}
+
+#[derive(Debug, Deserialize)]
+struct CommitDetails {
+    committer: Option<CommitSignature>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CommitSignature {
+    date: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitRef {
+    object: GitObject,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitObject {
+    sha: String,
+}
+
+/// A thread from the notifications API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationThread {
+    pub id: String,
+    pub reason: String,
+    pub updated_at: DateTime<Utc>,
+    pub subject: NotificationSubject,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationSubject {
+    pub title: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub url: Option<String>,
+    pub latest_comment_url: Option<String>,
+}
+
+impl NotificationThread {
+    /// The issue this thread is about, parsed from its API URL
+    pub fn issue(&self) -> Option<(String, String, u64)> {
+        if self.subject.kind != "Issue" {
+            return None;
+        }
+
+        let url = self.subject.url.as_deref()?;
+        let parts: Vec<&str> = url.rsplitn(4, '/').collect();
+        match parts.as_slice() {
+            [number, "issues", repo, rest] => {
+                let owner = rest.rsplit('/').next()?;
+                Some((owner.to_string(), repo.to_string(), number.parse().ok()?))
+            }
+            _ => None,
+        }
+    }
+
+    /// ID of the latest comment in the thread, if it was a comment
+    pub fn latest_comment_id(&self) -> Option<u64> {
+        let url = self.subject.latest_comment_url.as_deref()?;
+        if !url.contains("/issues/comments/") {
+            return None;
+        }
+
+        url.rsplit('/').next()?.parse().ok()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResults {
+    total_count: u64,
+    items: Vec<Issue>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CommitSearchResults {
+    items: Vec<CommitSearchItem>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CommitSearchItem {
+    repository: CommitRepository,
+}
+
+#[derive(Debug, Deserialize)]
+struct CommitRepository {
+    full_name: String,
+}
+
+/// Whether an open issue is unassigned and matches the repository's filters
+pub fn matches_filters(repo: &Repository, issue: &Issue) -> bool {
+    filter_rejection(repo, issue, Utc::now()).is_none()
+}
+
+/// The repository's label and title filters, whoever is assigned
+pub fn matches_labels_and_title(repo: &Repository, issue: &Issue) -> bool {
+    labels_and_title_rejection(repo, issue, Utc::now()).is_none()
+}
+
+/// Why an open issue doesn't pass the repository's filters at `now`, None
+/// if it does
+pub fn filter_rejection(repo: &Repository, issue: &Issue, now: DateTime<Utc>) -> Option<String> {
+    // Skip issues that are already assigned
+    if let Some(assignee) = issue.assignee_logins().first() {
+        return Some(format!("assigned to {}", assignee));
+    }
+    if issue.assignee.is_some() || !issue.assignees.is_empty() {
+        return Some("assigned".to_string());
+    }
+
+    // Or given to someone with a label, in projects that work that way
+    let labeled_assigned = issue.labels.iter().find(|label| {
+        repo.assigned_labels
+            .iter()
+            .any(|assigned| assigned.eq_ignore_ascii_case(&label.name))
+    });
+    if let Some(label) = labeled_assigned {
+        return Some(format!("labeled {:?}, which means assigned", label.name));
+    }
+
+    labels_and_title_rejection(repo, issue, now)
+}
+
+fn labels_and_title_rejection(
+    repo: &Repository,
+    issue: &Issue,
+    now: DateTime<Utc>,
+) -> Option<String> {
+    let issue_label_names: Vec<&str> = issue.labels.iter().map(|l| l.name.as_str()).collect();
+
+    // Make sure the issue has all required labels
+    for required_label in &repo.labels {
+        if !issue_label_names.contains(&required_label.as_str()) {
+            return Some(format!("missing label {:?}", required_label));
+        }
+    }
+
+    // Skip issues with excluded labels
+    for exclude_label in &repo.exclude_labels {
+        if issue_label_names.contains(&exclude_label.as_str()) {
+            return Some(format!("excluded label {:?}", exclude_label));
+        }
+    }
+
+    // Apply title regex filter if specified
+    if let Some(ref regex_str) = repo.title_regex {
+        if let Ok(regex) = regex::Regex::new(regex_str) {
+            if !regex.is_match(&issue.title) {
+                return Some(format!("title doesn't match {:?}", regex_str));
+            }
+        }
+    }
+
+    if let Some(ref filter) = repo.filter {
+        if !filter.matches(issue, now) {
+            return Some(format!("doesn't match filter {:?}", filter.as_str()));
+        }
+    }
+
+    None
+}
+
+/// Split an issue URL like `https://github.com/owner/repo/issues/123` into its parts
+pub fn parse_issue_url(url: &str) -> anyhow::Result<(String, String, u64)> {
+    let path = url
+        .trim_end_matches('/')
+        .trim_start_matches("https://")
+        .trim_start_matches("http://");
+    let parts: Vec<&str> = path.split('/').collect();
+
+    // The host is github.com or a GitHub Enterprise Server
+    match parts.as_slice() {
+        [owner, repo, "issues" | "pull", number] | [_, owner, repo, "issues" | "pull", number] => {
+            let number = number
+                .parse()
+                .with_context(|| format!("Invalid issue number in {}", url))?;
+            Ok((owner.to_string(), repo.to_string(), number))
+        }
+        _ => Err(anyhow::anyhow!("Not a GitHub issue URL: {}", url)),
+    }
 }
 
-pub struct OctocrabClient {
-    client: Octocrab,
-    reqwest_client: reqwest::Client,
-    #[allow(dead_code)]
-    token: String,
-    #[allow(dead_code)]
+pub struct RestClient {
+    transport: Transport,
+    /// Typed endpoints, sending their requests through `transport`. Built on
+    /// first use, so it picks up `with_max_attempts` and friends.
+    octocrab: OnceLock<Octocrab>,
     username: String,
+    /// REST API root without a trailing slash
+    api_url: String,
+    recorder: Option<Recorder>,
+    /// Where we left off in each repository's events feed
+    events: Mutex<HashMap<String, EventsCursor>>,
 }
 
-impl OctocrabClient {
-    pub fn new(token: String, username: String) -> Result<Self> {
-        let client = Octocrab::builder()
-            .personal_token(token.clone())
-            .build()
-            .context("Failed to build GitHub client")?;
+/// Sends every request to GitHub, whether we build it ourselves or octocrab
+/// does, so both get the same timeouts, proxy, retries and rate limit
+/// handling
+#[derive(Clone)]
+struct Transport {
+    client: reqwest::Client,
+    /// What octocrab's relative routes are resolved against
+    api_url: String,
+    /// Set after GitHub told us to slow down, no requests go out until then
+    blocked_until: Arc<Mutex<Option<DateTime<Utc>>>>,
+    max_attempts: u32,
+    retry_stats: Arc<RetryStats>,
+    /// Requests sent, retries included
+    requests: Arc<AtomicU64>,
+    /// `x-ratelimit-remaining` of the latest response counting against the
+    /// core quota, searches have a smaller one of their own
+    rate_limit_remaining: Arc<Mutex<Option<u32>>>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct EventsCursor {
+    etag: Option<String>,
+    last_event_id: u64,
+}
+
+/// What went wrong talking to GitHub, telling callers whether trying again
+/// can help
+#[derive(Debug, thiserror::Error)]
+pub enum GitHubError {
+    /// GitHub asked us to back off, through its secondary rate limits, abuse
+    /// detection or an exhausted quota
+    #[error("GitHub is rate limiting us, not sending requests until {retry_at}")]
+    RateLimited { retry_at: DateTime<Utc> },
+    /// The token is invalid, expired or revoked
+    #[error("{0}")]
+    Unauthorized(String),
+    /// The token can't do this, e.g. on an archived or private repository
+    #[error("{0}")]
+    Forbidden(String),
+    #[error("{0}")]
+    NotFound(String),
+    /// GitHub understood the request but refused it, e.g. an invalid assignee
+    #[error("{0}")]
+    Validation(String),
+    /// Server errors, timeouts and dropped connections
+    #[error("{0}")]
+    Transient(String),
+    #[error("{0}")]
+    Unexpected(String),
+    /// The forge has no API for this
+    #[error("{0} isn't supported by this forge")]
+    Unsupported(&'static str),
+}
+
+impl GitHubError {
+    /// Whether the same request may succeed later
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            GitHubError::RateLimited { .. } | GitHubError::Transient(_)
+        )
+    }
+
+    /// The error for a failed response, with GitHub's own message
+    async fn from_response(what: &str, response: reqwest::Response) -> Self {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        let message = serde_json::from_str::<serde_json::Value>(&body)
+            .ok()
+            .and_then(|body| body["message"].as_str().map(str::to_string))
+            .unwrap_or_else(|| body.trim().to_string());
+
+        Self::from_status(what, status, &message)
+    }
+
+    /// The error for a request that failed in octocrab. Our own errors come
+    /// back out of the transport unchanged.
+    fn from_octocrab(what: &str, error: octocrab::Error) -> Self {
+        match error {
+            octocrab::Error::Service { source, .. } => match source.downcast::<GitHubError>() {
+                Ok(error) => *error,
+                Err(source) => {
+                    GitHubError::Unexpected(format!("GitHub API {} failed: {}", what, source))
+                }
+            },
+            octocrab::Error::GitHub { source, .. } => {
+                let status = reqwest::StatusCode::from_u16(source.status_code.as_u16())
+                    .unwrap_or(reqwest::StatusCode::INTERNAL_SERVER_ERROR);
+                Self::from_status(what, status, &source.message)
+            }
+            octocrab::Error::Serde { source, .. } => source.into(),
+            error => GitHubError::Unexpected(format!("GitHub API {} failed: {}", what, error)),
+        }
+    }
 
+    fn from_status(what: &str, status: reqwest::StatusCode, message: &str) -> Self {
+        let mut description = format!("GitHub API {} failed with status: {}", what, status);
+        if !message.is_empty() {
+            description = format!("{}: {}", description, message);
+        }
+
+        match status {
+            reqwest::StatusCode::UNAUTHORIZED => GitHubError::Unauthorized(description),
+            reqwest::StatusCode::FORBIDDEN => GitHubError::Forbidden(description),
+            reqwest::StatusCode::NOT_FOUND | reqwest::StatusCode::GONE => {
+                GitHubError::NotFound(description)
+            }
+            reqwest::StatusCode::BAD_REQUEST | reqwest::StatusCode::UNPROCESSABLE_ENTITY => {
+                GitHubError::Validation(description)
+            }
+            status if status.is_server_error() => GitHubError::Transient(description),
+            _ => GitHubError::Unexpected(description),
+        }
+    }
+}
+
+impl From<reqwest::Error> for GitHubError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_connect() || e.is_timeout() {
+            GitHubError::Transient(format!("Could not reach GitHub: {}", e))
+        } else {
+            GitHubError::Unexpected(format!("GitHub API request failed: {}", e))
+        }
+    }
+}
+
+impl From<serde_json::Error> for GitHubError {
+    fn from(e: serde_json::Error) -> Self {
+        GitHubError::Unexpected(format!("Unexpected response from GitHub: {}", e))
+    }
+}
+
+/// The GitHub error behind an error, if there is one
+pub fn github_error(error: &anyhow::Error) -> Option<&GitHubError> {
+    error.downcast_ref::<GitHubError>()
+}
+
+pub type Result<T, E = GitHubError> = std::result::Result<T, E>;
+
+/// How long to back off when GitHub doesn't say, per its docs
+const DEFAULT_BACKOFF_SECS: i64 = 60;
+
+/// Most pages of 100 to fetch from a list endpoint, to spare the rate limit
+/// on repositories with thousands of open issues
+const MAX_PAGES: usize = 10;
+
+/// The `rel="next"` URL of a `Link` header
+fn next_page(link: &str) -> Option<String> {
+    link.split(',').find_map(|part| {
+        let (url, params) = part.split_once(';')?;
+        params
+            .split(';')
+            .any(|param| param.trim() == "rel=\"next\"")
+            .then(|| {
+                url.trim()
+                    .trim_start_matches('<')
+                    .trim_end_matches('>')
+                    .to_string()
+            })
+    })
+}
+
+impl RestClient {
+    pub fn new(token: Secret, username: String, http: &HttpConfig) -> anyhow::Result<Self> {
         let mut headers = header::HeaderMap::new();
-        let auth_value = format!("token {}", token);
+        let auth_value = format!("token {}", token.expose());
         let mut auth_header = header::HeaderValue::from_str(&auth_value)?;
         auth_header.set_sensitive(true);
         headers.insert(header::AUTHORIZATION, auth_header);
+        if let Some(ref version) = http.api_version {
+            let version = header::HeaderValue::from_str(version)
+                .with_context(|| format!("Invalid GitHub API version {:?}", version))?;
+            headers.insert("X-GitHub-Api-Version", version);
+        }
 
-        let reqwest_client = reqwest::Client::builder()
+        let reqwest_client = http
+            .client_builder()?
             .default_headers(headers)
-            .user_agent("gh-issues-bot")
-            .build()?;
+            .build()
+            .context("Failed to build GitHub client")?;
+
+        let api_url = http.api_url.trim_end_matches('/').to_string();
 
         Ok(Self {
-            client,
-            reqwest_client,
-            token,
+            transport: Transport {
+                client: reqwest_client,
+                api_url: api_url.clone(),
+                blocked_until: Arc::new(Mutex::new(None)),
+                max_attempts: 1,
+                retry_stats: Arc::new(RetryStats::default()),
+                requests: Arc::new(AtomicU64::new(0)),
+                rate_limit_remaining: Arc::new(Mutex::new(None)),
+            },
+            octocrab: OnceLock::new(),
             username,
+            api_url,
+            recorder: None,
+            events: Mutex::new(HashMap::new()),
         })
     }
-}
 
-#[async_trait]
-impl GitHubClient for OctocrabClient {
-    async fn get_open_issues(&self, repo: &Repository) -> Result<Vec<Issue>> {
-        // Build the URL with query parameters
-        let url = format!(
-            "https://api.github.com/repos/{}/{}/issues?state=open&per_page=100",
-            repo.owner, repo.repo
-        );
+    /// Save every list of open issues we fetch, for replaying later
+    pub fn with_recorder(mut self, recorder: Recorder) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    /// Try requests that fail for transient reasons up to this many times
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.transport.max_attempts = max_attempts.max(1);
+        self
+    }
 
-        // Send the request
-        let response = self.reqwest_client.get(&url).send().await?;
+    pub fn retry_stats(&self) -> Arc<RetryStats> {
+        self.transport.retry_stats.clone()
+    }
 
-        // Check for success
-        if !response.status().is_success() {
-            return Err(anyhow::anyhow!(
-                "GitHub API request failed with status: {}",
-                response.status()
-            ));
+    /// Count retries in these stats, shared with other clients
+    pub fn with_retry_stats(mut self, retry_stats: Arc<RetryStats>) -> Self {
+        self.transport.retry_stats = retry_stats;
+        self
+    }
+
+    /// Octocrab, sending through our transport. The `Authorization` and
+    /// other default headers come from the reqwest client.
+    fn octocrab(&self) -> &Octocrab {
+        self.octocrab.get_or_init(|| {
+            OctocrabBuilder::new_empty()
+                .with_service(self.transport.clone())
+                .with_auth(octocrab::AuthState::None)
+                .build()
+                .expect("building octocrab with a service can't fail")
+        })
+    }
+
+    /// Every open issue of a repository, before any filtering
+    pub async fn get_all_open_issues(&self, owner: &str, repo: &str) -> Result<Vec<Issue>> {
+        let what = "issues request";
+        let octocrab = self.octocrab();
+        let mut page = octocrab
+            .issues(owner, repo)
+            .list()
+            .state(params::State::Open)
+            .per_page(100)
+            .send()
+            .await
+            .map_err(|e| GitHubError::from_octocrab(what, e))?;
+
+        let mut issues = page.take_items();
+        let mut pages = 1;
+        while let Some(next) = page.next.take() {
+            if pages == MAX_PAGES {
+                warn!(
+                    "Stopped following pages for the {} after {}, the rest is left out",
+                    what, MAX_PAGES
+                );
+                break;
+            }
+            page = octocrab
+                .get::<Page<_>, _, ()>(next.to_string(), None)
+                .await
+                .map_err(|e| GitHubError::from_octocrab(what, e))?;
+            issues.append(&mut page.take_items());
+            pages += 1;
         }
+        let issues = serde_json::to_value(issues)?;
 
-        // Parse the response
-        let issues: Vec<Issue> = response.json().await?;
+        // Keep the issues around if we're recording
+        if let Some(ref recorder) = self.recorder {
+            let body = serde_json::to_string(&issues)?;
+            if let Err(e) = recorder.record(owner, repo, &body).await {
+                warn!("Failed to record issues of {}/{}: {}", owner, repo, e);
+            }
+        }
 
-        // Filter issues that are not already assigned and match our criteria
-        let filtered_issues = issues
-            .into_iter()
-            .filter(|issue| {
-                // Skip issues that are already assigned
-                if issue.assignee.is_some() || !issue.assignees.is_empty() {
-                    return false;
-                }
+        Ok(serde_json::from_value(issues)?)
+    }
 
-                // Make sure the issue has all required labels
-                if !repo.labels.is_empty() {
-                    let issue_label_names: Vec<String> =
-                        issue.labels.iter().map(|l| l.name.clone()).collect();
+    /// Follow a list endpoint's `Link: <...>; rel="next"` headers, up to
+    /// `MAX_PAGES` pages with a warning when there were more
+    async fn get_pages<T: serde::de::DeserializeOwned>(
+        &self,
+        what: &str,
+        url: String,
+    ) -> Result<Vec<T>> {
+        let mut items = Vec::new();
+        let mut next = Some(url);
+        let mut pages = 0;
 
-                    // Check if all required labels are present
-                    for required_label in &repo.labels {
-                        if !issue_label_names.contains(required_label) {
-                            return false;
-                        }
-                    }
+        while let Some(url) = next.take() {
+            let response = self.send(self.transport.client.get(&url)).await?;
+            if !response.status().is_success() {
+                return Err(GitHubError::from_response(what, response).await);
+            }
+
+            pages += 1;
+            next = response
+                .headers()
+                .get(header::LINK)
+                .and_then(|value| value.to_str().ok())
+                .and_then(next_page);
+            if pages == MAX_PAGES && next.take().is_some() {
+                warn!(
+                    "Stopped following pages for the {} after {}, the rest is left out",
+                    what, MAX_PAGES
+                );
+            }
+            items.extend(response.json::<Vec<T>>().await?);
+        }
+
+        Ok(items)
+    }
+
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        self.transport.send(request).await
+    }
+}
+
+impl Transport {
+    /// Send a request unless we're backing off, and turn rate limit
+    /// responses into a `RateLimited` error. Hammering on after one of those
+    /// is what gets accounts flagged.
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        if let Some(retry_at) = *self.blocked_until.lock().unwrap() {
+            if Utc::now() < retry_at {
+                return Err(GitHubError::RateLimited { retry_at });
+            }
+        }
+
+        let response = self.send_with_retries(request).await?;
+        let headers = response.headers();
+        let core = headers
+            .get("x-ratelimit-resource")
+            .is_some_and(|resource| resource == "core");
+        if let Some(remaining) = headers
+            .get("x-ratelimit-remaining")
+            .filter(|_| core)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse().ok())
+        {
+            *self.rate_limit_remaining.lock().unwrap() = Some(remaining);
+        }
+        let status = response.status();
+        if status != reqwest::StatusCode::FORBIDDEN
+            && status != reqwest::StatusCode::TOO_MANY_REQUESTS
+        {
+            return Ok(response);
+        }
+
+        let header = |name: &str| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse::<i64>().ok())
+        };
+        let retry_after = header("retry-after");
+        let reset =
+            header("x-ratelimit-reset").filter(|_| header("x-ratelimit-remaining") == Some(0));
+
+        let retry_at = match (retry_after, reset) {
+            (Some(secs), _) => Utc::now() + chrono::Duration::seconds(secs),
+            (None, Some(reset)) => DateTime::from_timestamp(reset, 0).unwrap_or_else(Utc::now),
+            (None, None) => {
+                // Without headers, only the message tells a secondary rate
+                // limit apart from a missing permission
+                let body = response.text().await.unwrap_or_default();
+                let lower = body.to_lowercase();
+                if status == reqwest::StatusCode::FORBIDDEN
+                    && !lower.contains("rate limit")
+                    && !lower.contains("abuse")
+                {
+                    return Err(GitHubError::Forbidden(format!(
+                        "GitHub API request failed with status: {}: {}",
+                        status,
+                        body.trim()
+                    )));
                 }
+                Utc::now() + chrono::Duration::seconds(DEFAULT_BACKOFF_SECS)
+            }
+        };
 
-                // Skip issues with excluded labels
-                if !repo.exclude_labels.is_empty() {
-                    let issue_label_names: Vec<String> =
-                        issue.labels.iter().map(|l| l.name.clone()).collect();
+        warn!(
+            "GitHub is rate limiting us ({}), backing off until {}",
+            status, retry_at
+        );
+        *self.blocked_until.lock().unwrap() = Some(retry_at);
 
-                    for exclude_label in &repo.exclude_labels {
-                        if issue_label_names.contains(exclude_label) {
-                            return false;
-                        }
-                    }
+        Err(GitHubError::RateLimited { retry_at })
+    }
+
+    /// Retry connection errors, and timeouts and server errors where sending
+    /// the request twice is harmless. A POST that timed out may still have
+    /// created a comment, so those are only retried if they never got out.
+    async fn send_with_retries(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response> {
+        let request = request.build()?;
+        let idempotent = request.method() != reqwest::Method::POST;
+        let mut attempt = 1;
+
+        loop {
+            // Our bodies are always buffered JSON, which can be cloned
+            let this_try = request.try_clone().ok_or_else(|| {
+                GitHubError::Unexpected("Request body can't be retried".to_string())
+            })?;
+            self.requests.fetch_add(1, Ordering::Relaxed);
+            let result = self.client.execute(this_try).await;
+
+            let failure = match &result {
+                Ok(response) if idempotent && response.status().is_server_error() => {
+                    Some(response.status().to_string())
                 }
+                Err(e) if e.is_connect() || (idempotent && e.is_timeout()) => Some(e.to_string()),
+                _ => None,
+            };
 
-                // Apply title regex filter if specified
-                if let Some(ref regex_str) = repo.title_regex {
-                    if let Ok(regex) = regex::Regex::new(regex_str) {
-                        return regex.is_match(&issue.title);
-                    }
+            let Some(failure) = failure else {
+                if attempt > 1 && result.is_ok() {
+                    self.retry_stats.record_recovered();
                 }
+                return Ok(result?);
+            };
 
-                true
-            })
+            if attempt >= self.max_attempts {
+                if self.max_attempts > 1 {
+                    self.retry_stats.record_exhausted();
+                    warn!(
+                        "{} {} still failing after {} attempts",
+                        request.method(),
+                        request.url().path(),
+                        attempt
+                    );
+                }
+                return Ok(result?);
+            }
+
+            let delay = retry::backoff(attempt);
+            warn!(
+                "{} {} failed ({}), retrying in {:.1}s ({}/{})",
+                request.method(),
+                request.url().path(),
+                failure,
+                delay.as_secs_f64(),
+                attempt,
+                self.max_attempts
+            );
+            self.retry_stats.record_retry();
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// Send a request octocrab built, resolving its relative routes against
+    /// the API root
+    async fn forward(&self, request: http::Request<String>) -> Result<http::Response<Full<Bytes>>> {
+        let (parts, body) = request.into_parts();
+        let url = match parts.uri.authority() {
+            // The next page links octocrab follows are absolute already
+            Some(_) => parts.uri.to_string(),
+            None => format!(
+                "{}{}",
+                self.api_url,
+                parts.uri.path_and_query().map_or("/", |path| path.as_str())
+            ),
+        };
+        let method = reqwest::Method::from_bytes(parts.method.as_str().as_bytes())
+            .map_err(|e| GitHubError::Unexpected(format!("Invalid request method: {}", e)))?;
+
+        let mut request = self.client.request(method, url).body(body);
+        for (name, value) in &parts.headers {
+            // Worked out again for the body reqwest sends
+            if name != http::header::CONTENT_LENGTH {
+                request = request.header(name.as_str(), value.as_bytes());
+            }
+        }
+        let response = self.send(request).await?;
+
+        let mut forwarded = http::Response::builder().status(response.status().as_u16());
+        for (name, value) in response.headers() {
+            forwarded = forwarded.header(name.as_str(), value.as_bytes());
+        }
+        let body = response.bytes().await?;
+        forwarded
+            .body(Full::new(body))
+            .map_err(|e| GitHubError::Unexpected(format!("Invalid response from GitHub: {}", e)))
+    }
+}
+
+impl tower_service::Service<http::Request<String>> for Transport {
+    type Response = http::Response<Full<Bytes>>;
+    type Error = GitHubError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response>> + Send>>;
+
+    fn poll_ready(&mut self, _: &mut TaskContext<'_>) -> Poll<Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<String>) -> Self::Future {
+        let transport = self.clone();
+        Box::pin(async move { transport.forward(request).await })
+    }
+}
+
+#[async_trait]
+impl Forge for RestClient {
+    #[instrument(level = "debug", skip_all, fields(repo = %format!("{}/{}", repo.owner, repo.repo)))]
+    async fn get_open_issues(&self, repo: &Repository) -> Result<Vec<Issue>> {
+        let issues = self.get_all_open_issues(&repo.owner, &repo.repo).await?;
+
+        // Filter issues that are not already assigned and match our criteria
+        let filtered_issues = issues
+            .into_iter()
+            .filter(|issue| matches_filters(repo, issue))
             .collect();
 
         Ok(filtered_issues)
     }
 
+    #[instrument(level = "debug", skip(self))]
+    async fn get_issue(&self, owner: &str, repo: &str, issue_number: u64) -> Result<Issue> {
+        let url = format!(
+            "{}/repos/{}/{}/issues/{}",
+            self.api_url, owner, repo, issue_number
+        );
+        let response = self.send(self.transport.client.get(&url)).await?;
+
+        if !response.status().is_success() {
+            return Err(GitHubError::from_response("issue request", response).await);
+        }
+
+        let issue: Issue = response.json().await?;
+
+        Ok(issue)
+    }
+
+    #[instrument(level = "debug", skip(self, comment))]
     async fn comment_on_issue(
         &self,
         owner: &str,
         repo: &str,
         issue_number: u64,
         comment: &str,
+    ) -> Result<u64> {
+        let url = format!(
+            "{}/repos/{}/{}/issues/{}/comments",
+            self.api_url, owner, repo, issue_number
+        );
+        let body = serde_json::json!({ "body": comment });
+        let response = self
+            .send(self.transport.client.post(&url).json(&body))
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(GitHubError::from_response("create comment request", response).await);
+        }
+
+        let created: Comment = response.json().await?;
+
+        Ok(created.id)
+    }
+
+    #[instrument(level = "debug", skip(self, comment))]
+    async fn edit_comment(
+        &self,
+        owner: &str,
+        repo: &str,
+        comment_id: u64,
+        comment: &str,
     ) -> Result<()> {
-        self.client
-            .issues(owner, repo)
-            .create_comment(issue_number, comment)
+        let url = format!(
+            "{}/repos/{}/{}/issues/comments/{}",
+            self.api_url, owner, repo, comment_id
+        );
+        let body = serde_json::json!({ "body": comment });
+        let response = self
+            .send(self.transport.client.patch(&url).json(&body))
             .await?;
 
+        if !response.status().is_success() {
+            return Err(GitHubError::from_response("edit comment request", response).await);
+        }
+
         Ok(())
     }
 
-    async fn get_rate_limit(&self) -> Result<u32> {
-        let url = "https://api.github.com/rate_limit";
-        let response = self.reqwest_client.get(url).send().await?;
+    #[instrument(level = "debug", skip(self))]
+    async fn delete_comment(&self, owner: &str, repo: &str, comment_id: u64) -> Result<()> {
+        let url = format!(
+            "{}/repos/{}/{}/issues/comments/{}",
+            self.api_url, owner, repo, comment_id
+        );
+        let response = self.send(self.transport.client.delete(&url)).await?;
 
         if !response.status().is_success() {
-            return Err(anyhow::anyhow!(
-                "GitHub API rate_limit request failed with status: {}",
-                response.status()
-            ));
+            return Err(GitHubError::from_response("delete comment request", response).await);
         }
 
-        let rate_limit: serde_json::Value = response.json().await?;
-        let remaining = rate_limit["resources"]["core"]["remaining"]
-            .as_u64()
-            .unwrap_or(0) as u32;
+        Ok(())
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    async fn add_reaction(
+        &self,
+        owner: &str,
+        repo: &str,
+        issue_number: u64,
+        content: &str,
+    ) -> Result<()> {
+        // Reacting again is a no-op, so a retried claim adds no second one
+        let url = format!(
+            "{}/repos/{}/{}/issues/{}/reactions",
+            self.api_url, owner, repo, issue_number
+        );
+        let body = serde_json::json!({ "content": content });
+        let response = self
+            .send(self.transport.client.post(&url).json(&body))
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(GitHubError::from_response("reaction request", response).await);
+        }
+
+        Ok(())
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    async fn get_rate_limit(&self) -> Result<RateLimit> {
+        let rate_limit = self
+            .octocrab()
+            .ratelimit()
+            .get()
+            .await
+            .map_err(|e| GitHubError::from_octocrab("rate_limit request", e))?;
+        let core = rate_limit.resources.core;
+        let reset_at = DateTime::from_timestamp(core.reset as i64, 0)
+            .unwrap_or_else(|| Utc::now() + chrono::Duration::hours(1));
+
+        Ok(RateLimit {
+            remaining: core.remaining as u32,
+            reset_at,
+        })
+    }
+
+    /// Read the repository's events feed, which answers 304 without using
+    /// up the rate limit while nothing happened
+    #[instrument(level = "debug", skip(self))]
+    async fn has_issue_activity(&self, owner: &str, repo: &str) -> Result<bool> {
+        let key = format!("{}/{}", owner, repo);
+        let cursor = self.events.lock().unwrap().get(&key).cloned();
+
+        let url = format!(
+            "{}/repos/{}/{}/events?per_page=100",
+            self.api_url, owner, repo
+        );
+        let mut request = self.transport.client.get(&url);
+        if let Some(etag) = cursor.as_ref().and_then(|cursor| cursor.etag.as_ref()) {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+        let response = self.send(request).await?;
+
+        if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+            return Ok(false);
+        }
+        if !response.status().is_success() {
+            return Err(GitHubError::from_response("events request", response).await);
+        }
+
+        let etag = response
+            .headers()
+            .get(header::ETAG)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let events: Vec<serde_json::Value> = response.json().await?;
+        let since = cursor.as_ref().map_or(0, |cursor| cursor.last_event_id);
+        let new_events: Vec<(u64, &str)> = events
+            .iter()
+            .filter_map(|event| {
+                let id = event["id"].as_str()?.parse::<u64>().ok()?;
+                Some((id, event["type"].as_str().unwrap_or_default()))
+            })
+            .filter(|(id, _)| *id > since)
+            .collect();
+
+        self.events.lock().unwrap().insert(
+            key,
+            EventsCursor {
+                etag,
+                last_event_id: new_events.iter().map(|(id, _)| *id).max().unwrap_or(since),
+            },
+        );
+
+        // Nothing to compare against the first time, so look at the issues
+        Ok(cursor.is_none() || new_events.iter().any(|(_, kind)| *kind == "IssuesEvent"))
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    async fn can_assign_self(&self, owner: &str, repo: &str) -> Result<bool> {
+        // GitHub answers 204 if the user can be assigned and 404 otherwise
+        let url = format!(
+            "{}/repos/{}/{}/assignees/{}",
+            self.api_url, owner, repo, self.username
+        );
+        let response = self.send(self.transport.client.get(&url)).await?;
+
+        match response.status() {
+            reqwest::StatusCode::NO_CONTENT => Ok(true),
+            reqwest::StatusCode::NOT_FOUND => Ok(false),
+            _ => Err(GitHubError::from_response("assignee check", response).await),
+        }
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    async fn assign_self(&self, owner: &str, repo: &str, issue_number: u64) -> Result<()> {
+        let url = format!(
+            "{}/repos/{}/{}/issues/{}/assignees",
+            self.api_url, owner, repo, issue_number
+        );
+        let body = serde_json::json!({ "assignees": [self.username] });
+        let response = self
+            .send(self.transport.client.post(&url).json(&body))
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(GitHubError::from_response("add assignee request", response).await);
+        }
+
+        Ok(())
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    async fn unassign_self(&self, owner: &str, repo: &str, issue_number: u64) -> Result<()> {
+        let url = format!(
+            "{}/repos/{}/{}/issues/{}/assignees",
+            self.api_url, owner, repo, issue_number
+        );
+        let body = serde_json::json!({ "assignees": [self.username] });
+        let response = self
+            .send(self.transport.client.delete(&url).json(&body))
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(GitHubError::from_response("remove assignee request", response).await);
+        }
+
+        Ok(())
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    async fn get_recent_comments(&self, owner: &str, repo: &str) -> Result<Vec<Comment>> {
+        let url = format!(
+            "{}/repos/{}/{}/issues/comments?sort=created&direction=desc&per_page=100",
+            self.api_url, owner, repo
+        );
+        let response = self.send(self.transport.client.get(&url)).await?;
+
+        if !response.status().is_success() {
+            return Err(GitHubError::from_response("comments request", response).await);
+        }
+
+        let comments: Vec<Comment> = response.json().await?;
+
+        Ok(comments)
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    async fn get_commit_times(&self, owner: &str, repo: &str) -> Result<Vec<DateTime<Utc>>> {
+        let url = format!(
+            "{}/repos/{}/{}/commits?per_page=100",
+            self.api_url, owner, repo
+        );
+        let response = self.send(self.transport.client.get(&url)).await?;
+
+        if !response.status().is_success() {
+            return Err(GitHubError::from_response("commits request", response).await);
+        }
+
+        let commits: Vec<CommitEntry> = response.json().await?;
+
+        Ok(commits
+            .into_iter()
+            .filter_map(|entry| entry.commit.committer)
+            .map(|committer| committer.date)
+            .collect())
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    async fn get_file_content(
+        &self,
+        owner: &str,
+        repo: &str,
+        path: &str,
+    ) -> Result<Option<String>> {
+        let url = format!(
+            "{}/repos/{}/{}/contents/{}",
+            self.api_url, owner, repo, path
+        );
+        let response = self
+            .send(
+                self.transport
+                    .client
+                    .get(&url)
+                    .header(header::ACCEPT, "application/vnd.github.raw"),
+            )
+            .await?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        if !response.status().is_success() {
+            return Err(GitHubError::from_response("contents request", response).await);
+        }
+
+        Ok(Some(response.text().await?))
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    async fn get_issue_comments(
+        &self,
+        owner: &str,
+        repo: &str,
+        issue_number: u64,
+    ) -> Result<Vec<Comment>> {
+        let url = format!(
+            "{}/repos/{}/{}/issues/{}/comments?per_page=100",
+            self.api_url, owner, repo, issue_number
+        );
+
+        self.get_pages("issue comments request", url).await
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    async fn get_comment(&self, owner: &str, repo: &str, comment_id: u64) -> Result<Comment> {
+        let url = format!(
+            "{}/repos/{}/{}/issues/comments/{}",
+            self.api_url, owner, repo, comment_id
+        );
+        let response = self.send(self.transport.client.get(&url)).await?;
+
+        if !response.status().is_success() {
+            return Err(GitHubError::from_response("comment request", response).await);
+        }
+
+        let comment: Comment = response.json().await?;
+
+        Ok(comment)
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    async fn get_notifications(&self, since: DateTime<Utc>) -> Result<Vec<NotificationThread>> {
+        let since = since.to_rfc3339();
+        let response = self
+            .send(
+                self.transport
+                    .client
+                    .get(format!("{}/notifications", self.api_url))
+                    .query(&[("participating", "true"), ("since", since.as_str())]),
+            )
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(GitHubError::from_response("notifications request", response).await);
+        }
+
+        let threads: Vec<NotificationThread> = response.json().await?;
+
+        Ok(threads)
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    async fn search_issues(&self, query: &str) -> Result<Vec<Issue>> {
+        let response = self
+            .send(
+                self.transport
+                    .client
+                    .get(format!("{}/search/issues", self.api_url))
+                    .query(&[("q", query), ("per_page", "100")]),
+            )
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(GitHubError::from_response("search request", response).await);
+        }
+
+        let results: SearchResults = response.json().await?;
+
+        Ok(results.items)
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    async fn count_issues(&self, query: &str) -> Result<u64> {
+        let response = self
+            .send(
+                self.transport
+                    .client
+                    .get(format!("{}/search/issues", self.api_url))
+                    .query(&[("q", query), ("per_page", "1")]),
+            )
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(GitHubError::from_response("search request", response).await);
+        }
+
+        let results: SearchResults = response.json().await?;
+
+        Ok(results.total_count)
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    async fn search_commit_repos(&self, query: &str) -> Result<Vec<String>> {
+        let response = self
+            .send(
+                self.transport
+                    .client
+                    .get(format!("{}/search/commits", self.api_url))
+                    .query(&[("q", query), ("per_page", "100")]),
+            )
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(GitHubError::from_response("commit search request", response).await);
+        }
+
+        let results: CommitSearchResults = response.json().await?;
+
+        Ok(results
+            .items
+            .into_iter()
+            .map(|item| item.repository.full_name)
+            .collect())
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    async fn get_repository(&self, owner: &str, repo: &str) -> Result<RepositoryInfo> {
+        let url = format!("{}/repos/{}/{}", self.api_url, owner, repo);
+        let response = self.send(self.transport.client.get(&url)).await?;
+
+        if !response.status().is_success() {
+            return Err(GitHubError::from_response("repository request", response).await);
+        }
+
+        let repository: RepositoryInfo = response.json().await?;
+
+        Ok(repository)
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    async fn create_fork(&self, owner: &str, repo: &str) -> Result<RepositoryInfo> {
+        // GitHub returns the existing fork if we already have one
+        let url = format!("{}/repos/{}/{}/forks", self.api_url, owner, repo);
+        let response = self.send(self.transport.client.post(&url)).await?;
+
+        if !response.status().is_success() {
+            return Err(GitHubError::from_response("fork request", response).await);
+        }
+
+        let fork: RepositoryInfo = response.json().await?;
+
+        Ok(fork)
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    async fn get_branch_sha(&self, owner: &str, repo: &str, branch: &str) -> Result<String> {
+        let url = format!(
+            "{}/repos/{}/{}/git/ref/heads/{}",
+            self.api_url, owner, repo, branch
+        );
+        let response = self.send(self.transport.client.get(&url)).await?;
+
+        if !response.status().is_success() {
+            return Err(GitHubError::from_response("ref request", response).await);
+        }
+
+        let git_ref: GitRef = response.json().await?;
+
+        Ok(git_ref.object.sha)
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    async fn create_branch(&self, owner: &str, repo: &str, branch: &str, sha: &str) -> Result<()> {
+        let url = format!("{}/repos/{}/{}/git/refs", self.api_url, owner, repo);
+        let body = serde_json::json!({
+            "ref": format!("refs/heads/{}", branch),
+            "sha": sha,
+        });
+        let response = self
+            .send(self.transport.client.post(&url).json(&body))
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(GitHubError::from_response("create ref request", response).await);
+        }
+
+        Ok(())
+    }
+
+    #[instrument(level = "debug", skip(self, message))]
+    async fn create_empty_commit(
+        &self,
+        owner: &str,
+        repo: &str,
+        branch: &str,
+        message: &str,
+    ) -> Result<()> {
+        let parent = self.get_branch_sha(owner, repo, branch).await?;
+
+        // Reuse the parent's tree so the commit changes nothing
+        let url = format!(
+            "{}/repos/{}/{}/git/commits/{}",
+            self.api_url, owner, repo, parent
+        );
+        let response = self.send(self.transport.client.get(&url)).await?;
+
+        if !response.status().is_success() {
+            return Err(GitHubError::from_response("commit request", response).await);
+        }
+
+        let parent_commit: GitCommit = response.json().await?;
+
+        let url = format!("{}/repos/{}/{}/git/commits", self.api_url, owner, repo);
+        let body = serde_json::json!({
+            "message": message,
+            "tree": parent_commit.tree.sha,
+            "parents": [parent_commit.sha],
+        });
+        let response = self
+            .send(self.transport.client.post(&url).json(&body))
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(GitHubError::from_response("create commit request", response).await);
+        }
+
+        let commit: GitCommit = response.json().await?;
+
+        let url = format!(
+            "{}/repos/{}/{}/git/refs/heads/{}",
+            self.api_url, owner, repo, branch
+        );
+        let body = serde_json::json!({ "sha": commit.sha });
+        let response = self
+            .send(self.transport.client.patch(&url).json(&body))
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(GitHubError::from_response("update ref request", response).await);
+        }
+
+        Ok(())
+    }
+
+    #[instrument(level = "debug", skip(self, title, body))]
+    async fn create_draft_pull_request(
+        &self,
+        owner: &str,
+        repo: &str,
+        head: &str,
+        base: &str,
+        title: &str,
+        body: &str,
+    ) -> Result<PullRequest> {
+        let url = format!("{}/repos/{}/{}/pulls", self.api_url, owner, repo);
+        let body = serde_json::json!({
+            "title": title,
+            "head": head,
+            "base": base,
+            "body": body,
+            "draft": true,
+        });
+        let response = self
+            .send(self.transport.client.post(&url).json(&body))
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(GitHubError::from_response("create pull request", response).await);
+        }
+
+        let pull_request: PullRequest = response.json().await?;
+
+        Ok(pull_request)
+    }
+
+    fn api_usage(&self) -> ApiUsage {
+        ApiUsage {
+            requests: self.transport.requests.load(Ordering::Relaxed),
+            remaining: *self.transport.rate_limit_remaining.lock().unwrap(),
+        }
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    async fn get_linked_pull_requests(
+        &self,
+        owner: &str,
+        repo: &str,
+        issue_number: u64,
+    ) -> Result<Vec<LinkedPullRequest>> {
+        let url = format!(
+            "{}/repos/{}/{}/issues/{}/timeline?per_page=100",
+            self.api_url, owner, repo, issue_number
+        );
+        let response = self.send(self.transport.client.get(&url)).await?;
+
+        if !response.status().is_success() {
+            return Err(GitHubError::from_response("timeline request", response).await);
+        }
+
+        let events: Vec<serde_json::Value> = response.json().await?;
+
+        // PRs mentioning the issue show up as cross-references to an "issue"
+        // that has a pull_request section
+        let mut linked: Vec<LinkedPullRequest> = Vec::new();
+        for event in &events {
+            if event["event"] != "cross-referenced" {
+                continue;
+            }
+
+            let source = &event["source"]["issue"];
+            if source["pull_request"].is_null() {
+                continue;
+            }
+
+            let state = if !source["pull_request"]["merged_at"].is_null() {
+                PullRequestState::Merged
+            } else if source["state"] == "closed" {
+                PullRequestState::Closed
+            } else if source["draft"].as_bool().unwrap_or(false) {
+                PullRequestState::Draft
+            } else {
+                PullRequestState::Open
+            };
+
+            let html_url = source["html_url"].as_str().unwrap_or_default().to_string();
+            if linked.iter().any(|pr| pr.html_url == html_url) {
+                continue;
+            }
+
+            linked.push(LinkedPullRequest {
+                number: source["number"].as_u64().unwrap_or(0),
+                html_url,
+                author: source["user"]["login"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+                state,
+            });
+        }
+
+        Ok(linked)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_issue_and_pull_request_urls() {
+        assert_eq!(
+            parse_issue_url("https://github.com/rust-lang/rust/issues/123").unwrap(),
+            ("rust-lang".to_string(), "rust".to_string(), 123)
+        );
+        assert_eq!(
+            parse_issue_url("https://github.example.com/owner/repo/pull/7/").unwrap(),
+            ("owner".to_string(), "repo".to_string(), 7)
+        );
+        assert_eq!(
+            parse_issue_url("github.com/owner/repo/issues/8").unwrap(),
+            ("owner".to_string(), "repo".to_string(), 8)
+        );
+    }
+
+    #[test]
+    fn rejects_urls_that_are_not_issues() {
+        assert!(parse_issue_url("https://github.com/owner/repo").is_err());
+        assert!(parse_issue_url("https://github.com/owner/repo/issues/abc").is_err());
+        assert!(parse_issue_url("https://github.com/owner/repo/discussions/1").is_err());
+    }
+
+    #[test]
+    fn finds_the_next_page_in_a_link_header() {
+        let link = "<https://api.github.com/repositories/1/issues?page=1>; rel=\"prev\", \
+            <https://api.github.com/repositories/1/issues?page=3>; rel=\"next\", \
+            <https://api.github.com/repositories/1/issues?page=9>; rel=\"last\"";
+        assert_eq!(
+            next_page(link).as_deref(),
+            Some("https://api.github.com/repositories/1/issues?page=3")
+        );
+
+        let last = "<https://api.github.com/repositories/1/issues?page=1>; rel=\"first\"";
+        assert_eq!(next_page(last), None);
+        assert_eq!(next_page(""), None);
+    }
+
+    /// A GitHub user the way the REST API returns one, which octocrab
+    /// insists on in full
+    fn user(login: &str) -> serde_json::Value {
+        let url = format!("https://api.github.com/users/{}", login);
+        let mut user = serde_json::json!({
+            "login": login,
+            "id": 1,
+            "node_id": "U_1",
+            "gravatar_id": "",
+            "url": url,
+            "html_url": format!("https://github.com/{}", login),
+            "type": "User",
+            "site_admin": false,
+        });
+        for field in [
+            "avatar_url",
+            "followers_url",
+            "following_url",
+            "gists_url",
+            "starred_url",
+            "subscriptions_url",
+            "organizations_url",
+            "repos_url",
+            "events_url",
+            "received_events_url",
+        ] {
+            user[field] = serde_json::json!(format!("{}/{}", url, field));
+        }
+        user
+    }
+
+    fn issue(number: u64) -> serde_json::Value {
+        let url = format!("https://api.github.com/repos/owner/repo/issues/{}", number);
+        serde_json::json!({
+            "id": 1000 + number,
+            "node_id": "I_1",
+            "url": url,
+            "repository_url": "https://api.github.com/repos/owner/repo",
+            "labels_url": format!("{}/labels{{/name}}", url),
+            "comments_url": format!("{}/comments", url),
+            "events_url": format!("{}/events", url),
+            "html_url": format!("https://github.com/owner/repo/issues/{}", number),
+            "number": number,
+            "state": "open",
+            "state_reason": null,
+            "title": format!("Issue {}", number),
+            "body": null,
+            "user": user("someone"),
+            "labels": [],
+            "assignee": null,
+            "assignees": [],
+            "author_association": "NONE",
+            "locked": false,
+            "comments": 0,
+            "created_at": "2024-01-01T00:00:00Z",
+            "updated_at": "2024-01-02T00:00:00Z",
+        })
+    }
+
+    /// Answer requests on a local port with `respond(path)`, keeping the
+    /// request heads around. Returns the API root, under a GHES style path.
+    async fn serve(
+        respond: impl Fn(&str, &str) -> (u16, String, String) + Send + Sync + 'static,
+    ) -> (String, Arc<Mutex<Vec<String>>>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let root = format!("http://{}/api/v3", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let (respond, seen, base) = (Arc::new(respond), requests.clone(), root.clone());
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut head = Vec::new();
+                let mut buf = [0; 1024];
+                while !head.ends_with(b"\r\n\r\n") {
+                    let n = stream.read(&mut buf).await.unwrap();
+                    if n == 0 {
+                        break;
+                    }
+                    head.extend_from_slice(&buf[..n]);
+                }
+                let head = String::from_utf8_lossy(&head).to_string();
+                let path = head.split(' ').nth(1).unwrap_or_default().to_string();
+                seen.lock().unwrap().push(head);
+                let (status, headers, body) = respond(&base, &path);
+                let response = format!(
+                    "HTTP/1.1 {} X\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n{}\r\n{}",
+                    status,
+                    body.len(),
+                    headers,
+                    body
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+
+        (root, requests)
+    }
+
+    fn client(api_url: &str) -> RestClient {
+        let http = HttpConfig {
+            api_url: api_url.to_string(),
+            ..HttpConfig::default()
+        };
+        RestClient::new(Secret::new("secret".to_string()), "me".to_string(), &http).unwrap()
+    }
+
+    #[tokio::test]
+    async fn lists_every_page_of_open_issues_through_octocrab() {
+        let (root, requests) = serve(|base, path| {
+            if path.contains("page=2") {
+                let body = serde_json::json!([issue(2)]).to_string();
+                (200, String::new(), body)
+            } else {
+                let next = format!(
+                    "link: <{}/repositories/1/issues?state=open&per_page=100&page=2>; rel=\"next\"\r\n",
+                    base
+                );
+                (200, next, serde_json::json!([issue(1)]).to_string())
+            }
+        })
+        .await;
+
+        let issues = client(&root)
+            .get_all_open_issues("owner", "repo")
+            .await
+            .unwrap();
+
+        let numbers: Vec<u64> = issues.iter().map(|issue| issue.number).collect();
+        assert_eq!(numbers, vec![1, 2]);
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[0].starts_with("GET /api/v3/repos/owner/repo/issues?"));
+        assert!(requests[0].contains("state=open"));
+        assert!(requests[1].starts_with("GET /api/v3/repositories/1/issues?"));
+        for request in requests.iter() {
+            assert!(request.contains("authorization: token secret"));
+        }
+    }
+
+    #[tokio::test]
+    async fn reads_the_core_rate_limit_through_octocrab() {
+        let (root, _) = serve(|_, _| {
+            let rate = serde_json::json!({ "limit": 5000, "used": 1, "remaining": 4999, "reset": 1700000000 });
+            let search = serde_json::json!({ "limit": 30, "used": 0, "remaining": 30, "reset": 1700000000 });
+            let body = serde_json::json!({
+                "resources": { "core": rate, "search": search },
+                "rate": rate,
+            });
+            (200, String::new(), body.to_string())
+        })
+        .await;
+
+        let rate_limit = client(&root).get_rate_limit().await.unwrap();
+
+        assert_eq!(rate_limit.remaining, 4999);
+        assert_eq!(rate_limit.reset_at.timestamp(), 1700000000);
+    }
+
+    #[tokio::test]
+    async fn classifies_failures_behind_octocrab() {
+        let (root, _) = serve(|_, path| {
+            if path.contains("/missing/") {
+                let body =
+                    r#"{"message": "Not Found", "documentation_url": "https://docs.github.com"}"#;
+                (404, String::new(), body.to_string())
+            } else {
+                let body = r#"{"message": "API rate limit exceeded"}"#;
+                (403, "retry-after: 60\r\n".to_string(), body.to_string())
+            }
+        })
+        .await;
+        let client = client(&root);
+
+        let missing = client.get_all_open_issues("owner", "missing").await;
+        assert!(matches!(missing, Err(GitHubError::NotFound(_))));
+
+        let limited = client.get_all_open_issues("owner", "repo").await;
+        assert!(matches!(limited, Err(GitHubError::RateLimited { .. })));
+        // Backing off now, so nothing else goes out either
+        assert!(matches!(
+            client.get_rate_limit().await,
+            Err(GitHubError::RateLimited { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn times_out_requests_sent_through_octocrab() {
+        // Takes the connection and never answers
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let root = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut held = Vec::new();
+            while let Ok((stream, _)) = listener.accept().await {
+                held.push(stream);
+            }
+        });
+        let http = HttpConfig {
+            api_url: root,
+            timeout_secs: 1,
+            ..HttpConfig::default()
+        };
+        let client =
+            RestClient::new(Secret::new("secret".to_string()), "me".to_string(), &http).unwrap();
+
+        let started = std::time::Instant::now();
+        let result = client.get_rate_limit().await;
 
-        Ok(remaining)
+        assert!(matches!(result, Err(GitHubError::Transient(_))));
+        assert!(started.elapsed() < std::time::Duration::from_secs(10));
     }
 }
@@ -0,0 +1,178 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use crate::persistence::Persistence;
+use crate::retry::RetryStats;
+
+/// Written to the data dir after every poll, for the `healthcheck` command
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Heartbeat {
+    pub pid: u32,
+    pub last_poll_at: DateTime<Utc>,
+    pub last_successful_poll_at: Option<DateTime<Utc>>,
+    /// Set while polling waits for the rate limit to reset
+    #[serde(default)]
+    pub sleeping_until: Option<DateTime<Utc>>,
+}
+
+impl Heartbeat {
+    /// How long the bot has gone without polling when it meant to poll
+    pub fn age(&self) -> Duration {
+        let since = self
+            .sleeping_until
+            .map_or(self.last_poll_at, |until| until.max(self.last_poll_at));
+        Utc::now() - since
+    }
+}
+
+/// When the bot last got through a poll, shared with the health endpoints
+#[derive(Debug)]
+pub struct Health {
+    started_at: DateTime<Utc>,
+    last_poll: Mutex<Option<DateTime<Utc>>>,
+    last_success: Mutex<Option<DateTime<Utc>>>,
+    sleeping_until: Mutex<Option<DateTime<Utc>>>,
+}
+
+impl Health {
+    pub fn new() -> Self {
+        Self {
+            started_at: Utc::now(),
+            last_poll: Mutex::new(None),
+            last_success: Mutex::new(None),
+            sleeping_until: Mutex::new(None),
+        }
+    }
+
+    pub fn record_poll(&self, success: bool) {
+        let now = Utc::now();
+        *self.last_poll.lock().unwrap() = Some(now);
+        if success {
+            *self.last_success.lock().unwrap() = Some(now);
+        }
+    }
+
+    /// No polls are due before `until`, so the bot isn't stalled until then
+    pub fn sleep_until(&self, until: DateTime<Utc>) {
+        *self.sleeping_until.lock().unwrap() = Some(until);
+    }
+
+    /// The latest poll times, once there has been a poll
+    pub fn heartbeat(&self) -> Option<Heartbeat> {
+        let last_poll_at = (*self.last_poll.lock().unwrap())?;
+
+        Some(Heartbeat {
+            pid: std::process::id(),
+            last_poll_at,
+            last_successful_poll_at: *self.last_success.lock().unwrap(),
+            sleeping_until: *self.sleeping_until.lock().unwrap(),
+        })
+    }
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Bind the `/healthz` and `/readyz` listener, returning the future that serves it.
+///
+/// The bot counts as live while polls keep finishing within `stale_after`, and
+/// as ready once a recent poll succeeded and its state can still be written.
+pub fn serve<P>(
+    port: u16,
+    health: Arc<Health>,
+    retry_stats: Arc<RetryStats>,
+    persistence: P,
+    stale_after: Duration,
+) -> Result<impl Future<Output = hyper::Result<()>>>
+where
+    P: Persistence + Send + Sync + 'static,
+{
+    let addr = SocketAddr::from(([0, 0, 0, 0], port));
+    let persistence = Arc::new(persistence);
+
+    let make_service = make_service_fn(move |_| {
+        let health = health.clone();
+        let retry_stats = retry_stats.clone();
+        let persistence = persistence.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let health = health.clone();
+                let retry_stats = retry_stats.clone();
+                let persistence = persistence.clone();
+                async move {
+                    Ok::<_, Infallible>(
+                        respond(request, &health, &retry_stats, &*persistence, stale_after).await,
+                    )
+                }
+            }))
+        }
+    });
+
+    let server = Server::try_bind(&addr)
+        .with_context(|| format!("Failed to listen for health checks on {}", addr))?
+        .serve(make_service);
+
+    Ok(server)
+}
+
+async fn respond<P: Persistence>(
+    request: Request<Body>,
+    health: &Health,
+    retry_stats: &RetryStats,
+    persistence: &P,
+    stale_after: Duration,
+) -> Response<Body> {
+    let now = Utc::now();
+    let last_poll = *health.last_poll.lock().unwrap();
+    let last_success = *health.last_success.lock().unwrap();
+
+    let (healthy, persistence_error) = match request.uri().path() {
+        // Live until polls stop finishing, giving the first one time to run
+        "/healthz" => {
+            let since = last_poll.unwrap_or(health.started_at);
+            let since =
+                (*health.sleeping_until.lock().unwrap()).map_or(since, |until| until.max(since));
+            (now - since <= stale_after, None)
+        }
+        "/readyz" => {
+            let persistence_error = persistence.check().await.err().map(|e| e.to_string());
+            let recent = last_success.is_some_and(|at| now - at <= stale_after);
+            (recent && persistence_error.is_none(), persistence_error)
+        }
+        _ => {
+            return Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body(Body::empty())
+                .unwrap()
+        }
+    };
+
+    let body = serde_json::json!({
+        "status": if healthy { "ok" } else { "unavailable" },
+        "started_at": health.started_at,
+        "last_poll_at": last_poll,
+        "last_successful_poll_at": last_success,
+        "persistence_error": persistence_error,
+        "github_retries": retry_stats.to_json(),
+    });
+
+    Response::builder()
+        .status(if healthy {
+            StatusCode::OK
+        } else {
+            StatusCode::SERVICE_UNAVAILABLE
+        })
+        .header("Content-Type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
@@ -0,0 +1,280 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::bot::{ActiveIssue, AssignedIssue};
+
+/// How a claim ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    /// Nobody assigned us before the claim timed out
+    TimedOut,
+    /// The maintainers turned us down
+    Rejected,
+    /// Someone else got assigned while we waited
+    Lost,
+    /// A pull request was merged or the issue was closed as completed
+    Completed,
+    /// The issue was closed without being completed
+    Closed,
+    /// We gave up on the issue ourselves
+    Abandoned,
+    /// The issue was deleted, or we can't see it any more
+    Deleted,
+}
+
+impl std::fmt::Display for Outcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let outcome = match self {
+            Outcome::TimedOut => "timed out",
+            Outcome::Rejected => "rejected",
+            Outcome::Lost => "lost",
+            Outcome::Completed => "completed",
+            Outcome::Closed => "closed",
+            Outcome::Abandoned => "abandoned",
+            Outcome::Deleted => "deleted",
+        };
+        f.write_str(outcome)
+    }
+}
+
+/// A finished claim, kept around for stats
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub repo_owner: String,
+    pub repo_name: String,
+    pub issue_number: u64,
+    pub issue_url: String,
+    pub issue_title: String,
+    pub requested_at: DateTime<Utc>,
+    pub assigned_at: Option<DateTime<Utc>>,
+    pub finished_at: DateTime<Utc>,
+    pub outcome: Outcome,
+    #[serde(default)]
+    pub pull_request_url: Option<String>,
+    /// The comment template the claim was made with
+    #[serde(default)]
+    pub template: Option<String>,
+    /// Asked about an issue someone else had gone quiet on
+    #[serde(default)]
+    pub stale: bool,
+    /// The rule the issue was claimed through
+    #[serde(default)]
+    pub rule: Option<String>,
+}
+
+impl HistoryEntry {
+    pub fn timed_out(active: &ActiveIssue) -> Self {
+        Self::unassigned(active, Outcome::TimedOut, active.timeout)
+    }
+
+    /// A claim that ended before we got assigned
+    pub fn unassigned(active: &ActiveIssue, outcome: Outcome, finished_at: DateTime<Utc>) -> Self {
+        Self {
+            repo_owner: active.repo_owner.clone(),
+            repo_name: active.repo_name.clone(),
+            issue_number: active.issue_number,
+            issue_url: active.issue_url.clone(),
+            issue_title: active.issue_title.clone(),
+            requested_at: active.requested_at,
+            assigned_at: None,
+            finished_at,
+            outcome,
+            pull_request_url: None,
+            template: active.template.clone(),
+            stale: !active.stale_assignees.is_empty(),
+            rule: active.rule.clone(),
+        }
+    }
+
+    pub fn finished(
+        assigned: &AssignedIssue,
+        outcome: Outcome,
+        pull_request_url: Option<String>,
+    ) -> Self {
+        Self {
+            repo_owner: assigned.repo_owner.clone(),
+            repo_name: assigned.repo_name.clone(),
+            issue_number: assigned.issue_number,
+            issue_url: assigned.issue_url.clone(),
+            issue_title: assigned.issue_title.clone(),
+            requested_at: assigned.requested_at,
+            assigned_at: Some(assigned.assigned_at),
+            finished_at: Utc::now(),
+            outcome,
+            pull_request_url,
+            template: assigned.template.clone(),
+            stale: assigned.stale,
+            rule: assigned.rule.clone(),
+        }
+    }
+}
+
+/// Claim counts across the history and the issues still in progress
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Summary {
+    pub claims: usize,
+    pub assigned: usize,
+    pub completed: usize,
+}
+
+impl Summary {
+    pub fn new(history: &[HistoryEntry], in_progress: usize) -> Self {
+        let mut summary = Summary {
+            claims: history.len() + in_progress,
+            assigned: in_progress,
+            completed: 0,
+        };
+
+        for entry in history {
+            if entry.assigned_at.is_some() {
+                summary.assigned += 1;
+            }
+            if entry.outcome == Outcome::Completed {
+                summary.completed += 1;
+            }
+        }
+
+        summary
+    }
+
+    pub fn assignment_rate(&self) -> f64 {
+        ratio(self.assigned, self.claims)
+    }
+
+    pub fn completion_rate(&self) -> f64 {
+        ratio(self.completed, self.claims)
+    }
+}
+
+/// How often claims made with a comment template got us assigned
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TemplateStats {
+    pub claims: usize,
+    pub assigned: usize,
+}
+
+impl TemplateStats {
+    /// Per template, from the finished claims and the issues in progress
+    pub fn by_template(
+        history: &[HistoryEntry],
+        in_progress: &[AssignedIssue],
+    ) -> HashMap<String, TemplateStats> {
+        let mut templates: HashMap<String, TemplateStats> = HashMap::new();
+        let claims = history
+            .iter()
+            .map(|entry| (&entry.template, entry.assigned_at.is_some()))
+            .chain(in_progress.iter().map(|issue| (&issue.template, true)));
+        for (template, assigned) in claims {
+            let Some(template) = template else {
+                continue;
+            };
+            let stats = templates.entry(template.clone()).or_default();
+            stats.claims += 1;
+            if assigned {
+                stats.assigned += 1;
+            }
+        }
+        templates
+    }
+
+    pub fn assignment_rate(&self) -> f64 {
+        ratio(self.assigned, self.claims)
+    }
+
+    /// The assignment rate, pulled towards one half while there are few
+    /// claims so a new template still gets picked
+    pub fn weight(&self) -> f64 {
+        (self.assigned as f64 + 1.0) / (self.claims as f64 + 2.0)
+    }
+}
+
+/// Answered claims needed before a repository's median response is trusted
+const MIN_ANSWERED: usize = 3;
+
+/// How a repository's maintainers answered our claims
+#[derive(Debug, Default, Clone)]
+pub struct Responsiveness {
+    pub claims: usize,
+    pub assigned: usize,
+    /// Claims that were assigned or turned down rather than ignored
+    pub answered: usize,
+    /// Typical time from a claim to being assigned or turned down
+    pub median_response: Option<Duration>,
+    /// How many of the latest claims timed out without an answer
+    pub ignored_in_a_row: usize,
+}
+
+impl Responsiveness {
+    /// Per `owner/repo`, from the finished claims
+    pub fn by_repo(history: &[HistoryEntry]) -> HashMap<String, Responsiveness> {
+        let mut entries: Vec<&HistoryEntry> = history.iter().collect();
+        entries.sort_by_key(|entry| entry.requested_at);
+
+        let mut response_times: HashMap<String, Vec<Duration>> = HashMap::new();
+        let mut repos: HashMap<String, Responsiveness> = HashMap::new();
+        for entry in entries {
+            let key = format!("{}/{}", entry.repo_owner, entry.repo_name);
+            let repo = repos.entry(key.clone()).or_default();
+            repo.claims += 1;
+            if entry.assigned_at.is_some() {
+                repo.assigned += 1;
+            }
+
+            let answered_at = match entry.outcome {
+                Outcome::TimedOut => {
+                    repo.ignored_in_a_row += 1;
+                    continue;
+                }
+                // Giving up ourselves says nothing about the maintainers
+                Outcome::Abandoned | Outcome::Deleted if entry.assigned_at.is_none() => continue,
+                _ => entry.assigned_at.unwrap_or(entry.finished_at),
+            };
+            repo.answered += 1;
+            repo.ignored_in_a_row = 0;
+            response_times
+                .entry(key)
+                .or_default()
+                .push(answered_at - entry.requested_at);
+        }
+
+        for (key, mut times) in response_times {
+            times.sort();
+            if let Some(repo) = repos.get_mut(&key) {
+                repo.median_response = Some(times[times.len() / 2]);
+            }
+        }
+
+        repos
+    }
+
+    /// Higher for maintainers who assign claims, and do it quickly. A repo
+    /// without history counts as assigning half its claims within a day.
+    pub fn score(&self) -> f64 {
+        let rate = (self.assigned as f64 + 1.0) / (self.claims as f64 + 2.0);
+        let days = self
+            .median_response
+            .map_or(1.0, |response| response.num_minutes() as f64 / 1440.0);
+        rate / (1.0 + days)
+    }
+
+    /// A claim timeout that gives the maintainers twice their usual time to
+    /// answer, between an hour and a week, once they've answered enough
+    /// claims to go by
+    pub fn suggested_timeout(&self) -> Option<Duration> {
+        if self.answered < MIN_ANSWERED {
+            return None;
+        }
+        let median = self.median_response?;
+        Some((median * 2).clamp(Duration::hours(1), Duration::days(7)))
+    }
+}
+
+fn ratio(part: usize, total: usize) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        part as f64 / total as f64
+    }
+}
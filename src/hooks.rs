@@ -0,0 +1,56 @@
+use crate::bot::AssignedIssue;
+use crate::config::Repository;
+use crate::github::Issue;
+
+/// What a hook wants the bot to do about the action it was shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    Proceed,
+    Veto,
+}
+
+type IssueHook = Box<dyn Fn(&Repository, &Issue) -> Decision + Send + Sync>;
+type AssignmentHook = Box<dyn Fn(&AssignedIssue) + Send + Sync>;
+type ErrorHook = Box<dyn Fn(&anyhow::Error) + Send + Sync>;
+
+/// Callbacks set up through `BotBuilder`, for embedders that want to watch
+/// the bot or overrule it
+#[derive(Default)]
+pub struct Hooks {
+    pub(crate) on_candidate: Vec<IssueHook>,
+    pub(crate) on_claim: Vec<IssueHook>,
+    pub(crate) on_assignment: Vec<AssignmentHook>,
+    pub(crate) on_error: Vec<ErrorHook>,
+}
+
+impl Hooks {
+    /// Whether every candidate hook lets the issue through
+    pub(crate) fn candidate(&self, repo: &Repository, issue: &Issue) -> Decision {
+        decide(&self.on_candidate, repo, issue)
+    }
+
+    /// Whether every claim hook lets the claim go out
+    pub(crate) fn claim(&self, repo: &Repository, issue: &Issue) -> Decision {
+        decide(&self.on_claim, repo, issue)
+    }
+
+    pub(crate) fn assignment(&self, assigned: &AssignedIssue) {
+        for hook in &self.on_assignment {
+            hook(assigned);
+        }
+    }
+
+    pub(crate) fn error(&self, error: &anyhow::Error) {
+        for hook in &self.on_error {
+            hook(error);
+        }
+    }
+}
+
+fn decide(hooks: &[IssueHook], repo: &Repository, issue: &Issue) -> Decision {
+    if hooks.iter().any(|hook| hook(repo, issue) == Decision::Veto) {
+        Decision::Veto
+    } else {
+        Decision::Proceed
+    }
+}
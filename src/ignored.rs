@@ -0,0 +1,86 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::processed;
+
+/// Issues set aside for good with `ignore`, keyed by `owner/repo#number`
+/// with when they were added
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct IgnoredIssues {
+    issues: BTreeMap<String, DateTime<Utc>>,
+}
+
+impl IgnoredIssues {
+    pub fn contains(&self, owner: &str, repo: &str, issue_number: u64) -> bool {
+        self.issues
+            .contains_key(&processed::key(owner, repo, issue_number))
+    }
+
+    /// Returns false if the issue was already ignored
+    pub fn insert(&mut self, owner: &str, repo: &str, issue_number: u64) -> bool {
+        self.issues
+            .insert(processed::key(owner, repo, issue_number), Utc::now())
+            .is_none()
+    }
+
+    /// Returns false if the issue wasn't ignored
+    pub fn remove(&mut self, owner: &str, repo: &str, issue_number: u64) -> bool {
+        self.issues
+            .remove(&processed::key(owner, repo, issue_number))
+            .is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.issues.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// Issues set aside with `snooze`, keyed by `owner/repo#number` with when
+/// they come back
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SnoozedIssues {
+    issues: BTreeMap<String, DateTime<Utc>>,
+}
+
+impl SnoozedIssues {
+    /// When the issue comes back, if it's snoozed right now
+    pub fn until(&self, owner: &str, repo: &str, issue_number: u64) -> Option<DateTime<Utc>> {
+        self.issues
+            .get(&processed::key(owner, repo, issue_number))
+            .copied()
+            .filter(|until| *until > Utc::now())
+    }
+
+    pub fn snooze(&mut self, owner: &str, repo: &str, issue_number: u64, until: DateTime<Utc>) {
+        self.issues
+            .insert(processed::key(owner, repo, issue_number), until);
+    }
+
+    /// Returns false if the issue wasn't snoozed
+    pub fn wake(&mut self, owner: &str, repo: &str, issue_number: u64) -> bool {
+        self.issues
+            .remove(&processed::key(owner, repo, issue_number))
+            .is_some_and(|until| until > Utc::now())
+    }
+
+    /// Forget snoozes that ran out
+    pub fn prune(&mut self) {
+        let now = Utc::now();
+        self.issues.retain(|_, until| *until > now);
+    }
+
+    pub fn len(&self) -> usize {
+        self.issues.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
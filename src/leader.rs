@@ -0,0 +1,147 @@
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time;
+use tracing::{info, warn};
+
+use crate::redis::{RedisClient, Reply};
+use crate::secret::Secret;
+
+/// Extend the lease, but only while it's still ours
+const RENEW_SCRIPT: &str = "if redis.call('GET', KEYS[1]) == ARGV[1] then \
+     return redis.call('PEXPIRE', KEYS[1], ARGV[2]) else return 0 end";
+
+/// Give the lease up, but only if it's still ours
+const RESIGN_SCRIPT: &str = "if redis.call('GET', KEYS[1]) == ARGV[1] then \
+     return redis.call('DEL', KEYS[1]) else return 0 end";
+
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct HaConfig {
+    /// Redis shared by the replicas, which holds the leader's lease
+    pub redis_url: Secret,
+    /// Name of this replica, the host name and pid if not set
+    #[serde(default)]
+    pub instance: Option<String>,
+    /// How long the leader may go without renewing its lease before a
+    /// standby takes over
+    #[serde(default = "default_lease_secs")]
+    pub lease_secs: u64,
+    /// Prefix of the Redis keys, to share one Redis between deployments
+    #[serde(default = "default_key_prefix")]
+    pub key_prefix: String,
+}
+
+fn default_lease_secs() -> u64 {
+    30
+}
+
+fn default_key_prefix() -> String {
+    "gh-issues-bot".to_string()
+}
+
+/// One of several replicas running the same account, of which only the one
+/// holding the lease polls and comments. The lease is renewed in the
+/// background, so a long poll doesn't let it lapse.
+pub struct Leader {
+    redis: RedisClient,
+    key: String,
+    instance: String,
+    lease: Duration,
+    leading: AtomicBool,
+}
+
+impl Leader {
+    pub fn new(config: &HaConfig, user_login: &str) -> Result<Self> {
+        let instance = config.instance.clone().unwrap_or_else(default_instance);
+
+        Ok(Self {
+            redis: RedisClient::new(config.redis_url.expose())?,
+            key: format!("{}:leader:{}", config.key_prefix, user_login),
+            instance,
+            lease: Duration::from_secs(config.lease_secs),
+            leading: AtomicBool::new(false),
+        })
+    }
+
+    pub fn instance(&self) -> &str {
+        &self.instance
+    }
+
+    /// Whether we held the lease when we last checked
+    pub fn is_leader(&self) -> bool {
+        self.leading.load(Ordering::SeqCst)
+    }
+
+    /// Take the lease if nobody holds it, or extend it if we do. Not being
+    /// able to reach Redis counts as losing it, as a standby may have taken
+    /// over in the meantime.
+    pub async fn campaign(&self) -> Result<bool> {
+        let result = self.try_campaign().await;
+        let leading = matches!(result, Ok(true));
+        let was_leading = self.leading.swap(leading, Ordering::SeqCst);
+
+        match (was_leading, leading) {
+            (false, true) => info!("{} is now the leader", self.instance),
+            (true, false) => warn!("{} lost the lead, standing by", self.instance),
+            _ => {}
+        }
+        result
+    }
+
+    async fn try_campaign(&self) -> Result<bool> {
+        let lease = self.lease.as_millis().to_string();
+
+        let reply = self
+            .redis
+            .command(&["SET", &self.key, &self.instance, "NX", "PX", &lease])
+            .await?;
+        if reply != Reply::Nil {
+            return Ok(true);
+        }
+
+        let renewed = self
+            .redis
+            .command(&["EVAL", RENEW_SCRIPT, "1", &self.key, &self.instance, &lease])
+            .await?;
+        Ok(renewed == Reply::Integer(1))
+    }
+
+    /// Keep campaigning until the task is aborted, often enough that the
+    /// lease never runs out while we're healthy
+    pub fn spawn(self: &Arc<Self>) -> JoinHandle<()> {
+        let leader = Arc::clone(self);
+        tokio::spawn(async move {
+            loop {
+                time::sleep(leader.lease / 3).await;
+                if let Err(e) = leader.campaign().await {
+                    warn!("Failed to renew the leader lease: {}", e);
+                }
+            }
+        })
+    }
+
+    /// Hand the lead over straight away instead of letting the lease run out
+    pub async fn resign(&self) -> Result<()> {
+        if self.leading.swap(false, Ordering::SeqCst) {
+            self.redis
+                .command(&["EVAL", RESIGN_SCRIPT, "1", &self.key, &self.instance])
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+fn default_instance() -> String {
+    let host = std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|host| host.trim().to_string())
+        .filter(|host| !host.is_empty())
+        .unwrap_or_else(|| "localhost".to_string());
+    format!("{}-{}", host, std::process::id())
+}
@@ -0,0 +1,57 @@
+//! The issue claiming engine behind the `gh-issues-bot` binary, for tools
+//! that want to embed it.
+//!
+//! Build a [`Bot`] from a [`Config`], a [`Forge`] such as [`RestClient`]
+//! and a [`Persistence`] backend, then call [`Bot::initialize`] and [`Bot::start`].
+
+pub mod audit;
+pub mod bot;
+pub mod buffered;
+pub mod config;
+pub mod config_edit;
+pub mod conventions;
+pub mod crypto;
+pub mod cursor;
+pub mod daytime;
+pub mod difficulty;
+pub mod evaluation;
+pub mod filter;
+pub mod fixtures;
+pub mod forge;
+pub mod github;
+pub mod health;
+pub mod history;
+pub mod hooks;
+pub mod ignored;
+pub mod leader;
+pub mod lint;
+pub mod logging;
+pub mod notes;
+pub mod notify;
+pub mod output;
+pub mod pacing;
+pub mod persistence;
+pub mod preview;
+pub mod processed;
+pub mod queue;
+pub mod ratelimit;
+pub mod recording;
+pub mod redis;
+pub mod replies;
+pub mod report;
+pub mod retry;
+pub mod rules;
+pub mod schema;
+pub mod secret;
+pub mod team;
+pub mod telemetry;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod workspace;
+
+pub use bot::{Bot, BotBuilder};
+pub use config::Config;
+pub use forge::Forge;
+pub use github::{GitHubError, RestClient};
+pub use hooks::Decision;
+pub use persistence::{FilePersistence, Persistence};
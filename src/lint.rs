@@ -0,0 +1,191 @@
+//! Checks a comment has to pass before it's posted, so a broken template
+//! never ends up on someone else's issue.
+
+use thiserror::Error;
+
+/// Longest comment GitHub accepts, in characters
+pub const MAX_COMMENT_CHARS: usize = 65536;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum LintError {
+    #[error("the comment is empty")]
+    Empty,
+    #[error("the comment is {0} characters long, GitHub allows {MAX_COMMENT_CHARS}")]
+    TooLong(usize),
+    #[error("the comment would notify @{0}")]
+    Mention(String),
+    #[error("the comment has an unfilled placeholder {0}")]
+    Placeholder(String),
+    #[error("the comment repeats our previous comment on the issue")]
+    Duplicate,
+}
+
+/// Make sure a rendered comment is fit to post. `previous` is our last
+/// comment on the issue, if any, and `allowed_mentions` the users and teams
+/// the comment is meant to notify. Mentions and placeholders in code spans
+/// are left alone, as GitHub doesn't render them either.
+pub fn check(
+    comment: &str,
+    previous: Option<&str>,
+    allowed_mentions: &[&str],
+) -> Result<(), LintError> {
+    let trimmed = comment.trim();
+    if trimmed.is_empty() {
+        return Err(LintError::Empty);
+    }
+
+    let length = comment.chars().count();
+    if length > MAX_COMMENT_CHARS {
+        return Err(LintError::TooLong(length));
+    }
+
+    let prose = outside_code(comment);
+    for mention in mentions(&prose) {
+        if !allowed_mentions.iter().any(|allowed| {
+            allowed
+                .trim_start_matches('@')
+                .eq_ignore_ascii_case(mention)
+        }) {
+            return Err(LintError::Mention(mention.to_string()));
+        }
+    }
+    if let Some(placeholder) = placeholder(&prose) {
+        return Err(LintError::Placeholder(placeholder.to_string()));
+    }
+
+    if previous.is_some_and(|previous| previous.trim() == trimmed) {
+        return Err(LintError::Duplicate);
+    }
+
+    Ok(())
+}
+
+/// The comment with inline code and code blocks blanked out
+fn outside_code(comment: &str) -> String {
+    let mut prose = String::with_capacity(comment.len());
+    let mut in_code = false;
+    for c in comment.chars() {
+        if c == '`' {
+            in_code = !in_code;
+            prose.push(' ');
+        } else if in_code {
+            prose.push(if c == '\n' { '\n' } else { ' ' });
+        } else {
+            prose.push(c);
+        }
+    }
+    prose
+}
+
+/// Users and `org/team`s mentioned the way GitHub recognizes them: an `@`
+/// that doesn't follow a word character, so email addresses don't count
+pub fn mentions(text: &str) -> Vec<&str> {
+    let mut found = Vec::new();
+    let mut previous = ' ';
+    for (start, c) in text.char_indices() {
+        if c == '@' && !(previous.is_alphanumeric() || previous == '_') {
+            let name = &text[start + 1..];
+            let end = name
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '/'))
+                .unwrap_or(name.len());
+            let name = name[..end].trim_end_matches(['-', '/']);
+            if !name.is_empty() {
+                found.push(name);
+            }
+        }
+        previous = c;
+    }
+    found
+}
+
+/// The first `{name}` or `{{ name }}` left over from rendering a template
+fn placeholder(text: &str) -> Option<&str> {
+    let mut rest = text;
+    while let Some(open) = rest.find('{') {
+        let after = &rest[open..];
+        let inner = after.trim_start_matches('{');
+        let double = after.len() - inner.len() > 1;
+        // Spaces inside are only usual with double braces
+        let name = if double { inner.trim_start() } else { inner };
+        let name_len = name
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(name.len());
+        let starts_like_a_name = name
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_');
+        let tail = &name[name_len..];
+        let tail = if double { tail.trim_start() } else { tail };
+        if starts_like_a_name && tail.starts_with('}') {
+            let end = after.len() - tail.trim_start_matches('}').len();
+            return Some(&after[..end]);
+        }
+        rest = &rest[open + 1..];
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn passes_a_plain_comment() {
+        assert_eq!(check("Hi, I'd love to take this one!", None, &[]), Ok(()));
+    }
+
+    #[test]
+    fn rejects_empty_and_overlong_comments() {
+        assert_eq!(check(" \n ", None, &[]), Err(LintError::Empty));
+
+        let long = "a".repeat(MAX_COMMENT_CHARS + 1);
+        assert_eq!(
+            check(&long, None, &[]),
+            Err(LintError::TooLong(MAX_COMMENT_CHARS + 1))
+        );
+    }
+
+    #[test]
+    fn only_allows_expected_mentions() {
+        assert_eq!(
+            check("cc @octocat", None, &[]),
+            Err(LintError::Mention("octocat".to_string()))
+        );
+        assert_eq!(check("cc @Octocat", None, &["@octocat"]), Ok(()));
+        assert_eq!(check("mail me@example.com", None, &[]), Ok(()));
+        assert_eq!(check("run `@octocat` or\n```\n@x\n```", None, &[]), Ok(()));
+    }
+
+    #[test]
+    fn finds_mentions_of_users_and_teams() {
+        assert_eq!(
+            mentions("@alice, @org/team- and (@bob)"),
+            vec!["alice", "org/team", "bob"]
+        );
+    }
+
+    #[test]
+    fn rejects_unfilled_placeholders() {
+        assert_eq!(
+            check("Hi {name}!", None, &[]),
+            Err(LintError::Placeholder("{name}".to_string()))
+        );
+        assert_eq!(
+            check("Hi {{ user }}!", None, &[]),
+            Err(LintError::Placeholder("{{ user }}".to_string()))
+        );
+        assert_eq!(check("Use `{name}` or {1} or { x }", None, &[]), Ok(()));
+    }
+
+    #[test]
+    fn rejects_repeating_the_previous_comment() {
+        assert_eq!(
+            check("May I work on this?\n", Some("May I work on this?"), &[]),
+            Err(LintError::Duplicate)
+        );
+        assert_eq!(
+            check("Still on it", Some("May I work on this?"), &[]),
+            Ok(())
+        );
+    }
+}
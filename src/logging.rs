@@ -0,0 +1,292 @@
+use anyhow::{Context, Result};
+use flexi_logger::{
+    Age, Cleanup, Criterion, DeferredNow, Duplicate, FileSpec, LogSpecification, Logger,
+    LoggerHandle, Naming,
+};
+use log::{LevelFilter, Record};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::{self, Write as _};
+use std::path::PathBuf;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::layer::{Context as LayerContext, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+use crate::secret;
+
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct LoggingConfig {
+    /// Level of everything without one in `levels`
+    #[serde(default)]
+    pub level: Level,
+    /// Levels of single modules, e.g. `github = "debug"`. Plain names are
+    /// the bot's modules or other crates, paths with `::` are taken as they
+    /// are.
+    #[serde(default)]
+    pub levels: BTreeMap<String, Level>,
+    /// Also write logs to this file, rotating it as configured
+    #[serde(default)]
+    pub file: Option<PathBuf>,
+    /// Start a new file once the current one reaches this size
+    #[serde(default)]
+    pub max_size_mb: Option<u64>,
+    /// Start a new file every hour or day
+    #[serde(default)]
+    pub rotate_every: Option<RotateEvery>,
+    /// How many rotated files to keep around
+    #[serde(default = "default_keep_files")]
+    pub keep_files: usize,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            level: Level::default(),
+            levels: BTreeMap::new(),
+            file: None,
+            max_size_mb: None,
+            rotate_every: None,
+            keep_files: default_keep_files(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Level {
+    Off,
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    const ALL: [Level; 6] = [
+        Level::Off,
+        Level::Error,
+        Level::Warn,
+        Level::Info,
+        Level::Debug,
+        Level::Trace,
+    ];
+
+    /// This many levels more verbose, up to `trace`
+    pub fn raised(self, steps: u8) -> Self {
+        let index = Self::ALL
+            .iter()
+            .position(|level| *level == self)
+            .unwrap_or(0);
+        Self::ALL[(index + steps as usize).min(Self::ALL.len() - 1)]
+    }
+
+    fn filter(self) -> LevelFilter {
+        match self {
+            Level::Off => LevelFilter::Off,
+            Level::Error => LevelFilter::Error,
+            Level::Warn => LevelFilter::Warn,
+            Level::Info => LevelFilter::Info,
+            Level::Debug => LevelFilter::Debug,
+            Level::Trace => LevelFilter::Trace,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RotateEvery {
+    Hour,
+    Day,
+}
+
+fn default_keep_files() -> usize {
+    7
+}
+
+/// Start logging to stderr and, if configured, to a rotated file, with the
+/// default level raised a step for each `-v`. Logging stops when the
+/// returned handle is dropped.
+pub fn init(config: &LoggingConfig, verbose: u8) -> Result<LoggerHandle> {
+    let logger = Logger::with(spec(config, verbose)).format(format);
+
+    let Some(ref file) = config.file else {
+        return Ok(logger.log_to_stderr().start()?);
+    };
+
+    let age = config.rotate_every.map(|every| match every {
+        RotateEvery::Hour => Age::Hour,
+        RotateEvery::Day => Age::Day,
+    });
+    let criterion = match (age, config.max_size_mb) {
+        (Some(age), Some(size)) => Criterion::AgeOrSize(age, size * 1024 * 1024),
+        (None, Some(size)) => Criterion::Size(size * 1024 * 1024),
+        (Some(age), None) => Criterion::Age(age),
+        // Rotate daily so the history stays bounded
+        (None, None) => Criterion::Age(Age::Day),
+    };
+
+    let handle = logger
+        .log_to_file(FileSpec::try_from(file)?)
+        .duplicate_to_stderr(Duplicate::All)
+        .rotate(
+            criterion,
+            Naming::Timestamps,
+            Cleanup::KeepLogFiles(config.keep_files),
+        )
+        .append()
+        .start()
+        .with_context(|| format!("Failed to log to {}", file.display()))?;
+
+    Ok(handle)
+}
+
+/// `RUST_LOG` if it's set and valid, the configured levels otherwise
+fn spec(config: &LoggingConfig, verbose: u8) -> LogSpecification {
+    if let Some(spec) = std::env::var("RUST_LOG")
+        .ok()
+        .and_then(|spec| LogSpecification::parse(spec).ok())
+    {
+        return spec;
+    }
+
+    let mut builder = LogSpecification::builder();
+    builder.default(config.level.raised(verbose).filter());
+    for (target, level) in &config.levels {
+        builder.module(target, level.filter());
+        // Our own modules are logged under the crate's name
+        if !target.contains("::") {
+            builder.module(
+                format!("{}::{}", env!("CARGO_CRATE_NAME"), target),
+                level.filter(),
+            );
+        }
+    }
+    builder.build()
+}
+
+/// Passes tracing events on to the logger, followed by the fields of the
+/// spans they happened in, e.g. `repo=owner/name issue_number=12 cycle_id=3`,
+/// so one poll can be followed through the log
+pub struct LogLayer;
+
+/// A span's fields, formatted when they're recorded
+struct SpanFields(Vec<(&'static str, String)>);
+
+struct FieldVisitor<'a>(&'a mut Vec<(&'static str, String)>);
+
+impl Visit for FieldVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.push((field.name(), value.to_string()));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0.push((field.name(), format!("{:?}", value)));
+    }
+}
+
+impl<S> Layer<S> for LogLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: LayerContext<'_, S>) {
+        let mut fields = Vec::new();
+        attrs.record(&mut FieldVisitor(&mut fields));
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(SpanFields(fields));
+        }
+    }
+
+    fn on_record(&self, id: &Id, values: &tracing::span::Record<'_>, ctx: LayerContext<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut recorded = Vec::new();
+        values.record(&mut FieldVisitor(&mut recorded));
+
+        let mut extensions = span.extensions_mut();
+        let Some(SpanFields(fields)) = extensions.get_mut::<SpanFields>() else {
+            return;
+        };
+        for (name, value) in recorded {
+            match fields.iter_mut().find(|(field, _)| *field == name) {
+                Some(field) => field.1 = value,
+                None => fields.push((name, value)),
+            }
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: LayerContext<'_, S>) {
+        let metadata = event.metadata();
+        let level = match *metadata.level() {
+            tracing::Level::ERROR => log::Level::Error,
+            tracing::Level::WARN => log::Level::Warn,
+            tracing::Level::INFO => log::Level::Info,
+            tracing::Level::DEBUG => log::Level::Debug,
+            tracing::Level::TRACE => log::Level::Trace,
+        };
+        let log_metadata = log::Metadata::builder()
+            .level(level)
+            .target(metadata.target())
+            .build();
+        if !log::logger().enabled(&log_metadata) {
+            return;
+        }
+
+        let mut fields = Vec::new();
+        event.record(&mut FieldVisitor(&mut fields));
+        // Each field once, as the outermost span has it: inner spans of
+        // GitHub calls name the repository without its owner
+        if let Some(scope) = ctx.event_scope(event) {
+            for span in scope.from_root() {
+                if let Some(SpanFields(span_fields)) = span.extensions().get::<SpanFields>() {
+                    for (name, value) in span_fields {
+                        if !fields.iter().any(|(field, _)| field == name) {
+                            fields.push((name, value.clone()));
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut line = String::new();
+        if let Some(index) = fields.iter().position(|(name, _)| *name == "message") {
+            line = fields.remove(index).1;
+        }
+        for (name, value) in fields {
+            let separator = if line.is_empty() { "" } else { " " };
+            let _ = write!(line, "{}{}={}", separator, name, value);
+        }
+
+        log::logger().log(
+            &Record::builder()
+                .metadata(log_metadata)
+                .args(format_args!("{}", line))
+                .module_path(metadata.module_path())
+                .file(metadata.file())
+                .line(metadata.line())
+                .build(),
+        );
+    }
+}
+
+/// Same layout as env_logger's default format, with secrets scrubbed
+fn format(
+    w: &mut dyn std::io::Write,
+    now: &mut DeferredNow,
+    record: &Record,
+) -> std::io::Result<()> {
+    write!(
+        w,
+        "[{} {:<5} {}] {}",
+        now.now_utc_owned().format("%Y-%m-%dT%H:%M:%SZ"),
+        record.level(),
+        record.module_path().unwrap_or("<unnamed>"),
+        secret::redact(&record.args().to_string())
+    )
+}
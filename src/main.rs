@@ -1,24 +1,44 @@
-mod bot;
-mod config;
-mod github;
-mod persistence;
-
 use anyhow::{Context, Result};
-use clap::{Parser, Subcommand};
+use chrono::{NaiveDate, Utc};
+use clap::{Parser, Subcommand, ValueEnum};
 use dotenv::dotenv;
-use log::info;
+use std::collections::HashSet;
 use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::task::JoinSet;
+use tracing::{info, warn};
 
-use crate::bot::Bot;
-use crate::config::Config;
-use crate::github::OctocrabClient;
-use crate::persistence::FilePersistence;
+use gh_issues_bot::buffered::BufferedPersistence;
+use gh_issues_bot::config::Repository;
+use gh_issues_bot::config_edit::ConfigFile;
+use gh_issues_bot::difficulty;
+use gh_issues_bot::evaluation::{Evaluation, Snapshot};
+use gh_issues_bot::fixtures::{self, Fixture};
+use gh_issues_bot::github::{matches_filters, parse_issue_url, Issue};
+use gh_issues_bot::health::Health;
+use gh_issues_bot::history::{Responsiveness, Summary, TemplateStats};
+use gh_issues_bot::notes::Note;
+use gh_issues_bot::output;
+use gh_issues_bot::preview::{self, Terminal};
+use gh_issues_bot::processed::{self, ProcessedReason};
+use gh_issues_bot::queue::CandidateStatus;
+use gh_issues_bot::recording::Recorder;
+use gh_issues_bot::report;
+use gh_issues_bot::retry::RetryStats;
+use gh_issues_bot::{
+    health, logging, recording, secret, telemetry, Bot, BotBuilder, Config, FilePersistence, Forge,
+    Persistence, RestClient,
+};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
     #[command(subcommand)]
     command: Commands,
+
+    /// Log more, -v for debug and -vv for trace, unless RUST_LOG is set
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
 }
 
 #[derive(Subcommand, Debug)]
@@ -32,57 +52,1530 @@ enum Commands {
         /// Directory to store state
         #[arg(short, long, value_name = "DIR", default_value = ".gh-issues-bot")]
         data_dir: PathBuf,
+
+        /// Append the open issues fetched from GitHub to this file, for `replay`
+        #[arg(long, value_name = "FILE")]
+        record: Option<PathBuf>,
+
+        /// Poll once, save the state and exit
+        #[arg(long)]
+        once: bool,
+
+        #[command(flatten)]
+        ad_hoc: AdHocRepos,
+    },
+    /// Re-run issue selection against a recording made with `run --record`
+    Replay {
+        /// Recording to replay, or a directory of fixtures made with `capture`
+        recording: PathBuf,
+
+        /// Path to config file
+        #[arg(short, long, value_name = "FILE")]
+        config: Option<PathBuf>,
+
+        /// Show the description of each issue that would be claimed
+        #[arg(long)]
+        preview: bool,
+
+        /// Write every issue considered, why it was ruled out or how it
+        /// ranked, and the comment that would have been posted, to a .json
+        /// or .md file
+        #[arg(long, value_name = "FILE", value_parser = parse_report_path)]
+        report: Option<PathBuf>,
+    },
+    /// Save the open issues of the configured repositories as fixtures, for reproducing filter problems
+    Capture {
+        /// Path to config file
+        #[arg(short, long, value_name = "FILE")]
+        config: Option<PathBuf>,
+
+        /// Directory to write the fixtures to
+        #[arg(short, long, value_name = "DIR", default_value = "fixtures")]
+        out: PathBuf,
+
+        #[command(flatten)]
+        ad_hoc: AdHocRepos,
+    },
+    /// Show the active claim and the issues you're working on
+    Status {
+        /// Directory to store state
+        #[arg(short, long, value_name = "DIR", default_value = ".gh-issues-bot")]
+        data_dir: PathBuf,
+
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Table)]
+        output: OutputFormat,
+
+        /// Account from `accounts` in the config, instead of the top-level one
+        #[arg(long, value_name = "NAME")]
+        account: Option<String>,
+    },
+    /// Give up on an issue, posting a withdrawal comment and unassigning yourself
+    /// if you were assigned
+    Abandon {
+        /// URL of the issue to abandon
+        issue_url: String,
+
+        /// Path to config file
+        #[arg(short, long, value_name = "FILE")]
+        config: Option<PathBuf>,
+
+        /// Directory to store state
+        #[arg(short, long, value_name = "DIR", default_value = ".gh-issues-bot")]
+        data_dir: PathBuf,
+
+        /// Account from `accounts` in the config, instead of the top-level one
+        #[arg(long, value_name = "NAME")]
+        account: Option<String>,
+    },
+    /// Exit with an error if the running bot hasn't polled recently, for container health checks
+    Healthcheck {
+        /// Directory to store state
+        #[arg(short, long, value_name = "DIR", default_value = ".gh-issues-bot")]
+        data_dir: PathBuf,
+
+        /// Longest time since the last poll that still counts as healthy
+        #[arg(long, value_name = "SECS", default_value_t = 600)]
+        max_age: u64,
+
+        /// Account from `accounts` in the config, instead of the top-level one
+        #[arg(long, value_name = "NAME")]
+        account: Option<String>,
+    },
+    /// Review the issues queued by `review_queue` before the bot claims them
+    Queue {
+        #[command(subcommand)]
+        action: QueueAction,
+
+        /// Directory to store state
+        #[arg(
+            short,
+            long,
+            value_name = "DIR",
+            default_value = ".gh-issues-bot",
+            global = true
+        )]
+        data_dir: PathBuf,
+
+        /// Account from `accounts` in the config, instead of the top-level one
+        #[arg(long, value_name = "NAME", global = true)]
+        account: Option<String>,
+    },
+    /// Never claim an issue, even if it matches every filter
+    Ignore {
+        /// URL of the issue
+        issue_url: String,
+
+        /// Consider the issue again
+        #[arg(long)]
+        undo: bool,
+
+        /// Directory to store state
+        #[arg(short, long, value_name = "DIR", default_value = ".gh-issues-bot")]
+        data_dir: PathBuf,
+
+        /// Account from `accounts` in the config, instead of the top-level one
+        #[arg(long, value_name = "NAME")]
+        account: Option<String>,
+    },
+    /// Leave an issue alone for a while, then consider it again
+    Snooze {
+        /// URL of the issue
+        issue_url: String,
+
+        /// How long, e.g. 14d, 2w, 12h or 30m
+        #[arg(
+            long = "for",
+            value_name = "PERIOD",
+            value_parser = parse_period,
+            required_unless_present = "undo"
+        )]
+        period: Option<chrono::Duration>,
+
+        /// Consider the issue again right away
+        #[arg(long)]
+        undo: bool,
+
+        /// Directory to store state
+        #[arg(short, long, value_name = "DIR", default_value = ".gh-issues-bot")]
+        data_dir: PathBuf,
+
+        /// Account from `accounts` in the config, instead of the top-level one
+        #[arg(long, value_name = "NAME")]
+        account: Option<String>,
+    },
+    /// Keep notes on issues, shown by `status` and `history`
+    Note {
+        #[command(subcommand)]
+        action: NoteAction,
+
+        /// Directory to store state
+        #[arg(
+            short,
+            long,
+            value_name = "DIR",
+            default_value = ".gh-issues-bot",
+            global = true
+        )]
+        data_dir: PathBuf,
+
+        /// Account from `accounts` in the config, instead of the top-level one
+        #[arg(long, value_name = "NAME", global = true)]
+        account: Option<String>,
+    },
+    /// Work with the config file
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// List the watched repositories, or add and remove them in the config file
+    Repos {
+        #[command(subcommand)]
+        action: ReposAction,
+
+        /// Path to config file
+        #[arg(
+            short,
+            long,
+            value_name = "FILE",
+            default_value = "config.toml",
+            global = true
+        )]
+        config: PathBuf,
+    },
+    /// Show past claims, their outcomes and completion rates
+    History {
+        #[command(subcommand)]
+        action: Option<HistoryAction>,
+
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Table)]
+        output: OutputFormat,
+
+        /// Directory to store state
+        #[arg(
+            short,
+            long,
+            value_name = "DIR",
+            default_value = ".gh-issues-bot",
+            global = true
+        )]
+        data_dir: PathBuf,
+
+        /// Account from `accounts` in the config, instead of the top-level one
+        #[arg(long, value_name = "NAME", global = true)]
+        account: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum NoteAction {
+    /// Write a note on an issue
+    Add {
+        /// URL of the issue
+        issue_url: String,
+        text: String,
+    },
+    /// Delete all notes on an issue
+    Clear {
+        /// URL of the issue
+        issue_url: String,
+    },
+}
+
+/// Repositories to use instead of the configured ones, for trying the bot
+/// on a project without adding it to the config
+#[derive(clap::Args, Debug)]
+struct AdHocRepos {
+    /// Watch only this repository, as owner/name, instead of the configured
+    /// ones; can be repeated
+    #[arg(long = "repo", value_name = "OWNER/NAME")]
+    repos: Vec<String>,
+
+    /// Labels an issue in the --repo repositories needs, comma separated
+    #[arg(
+        long,
+        value_delimiter = ',',
+        default_value = "good first issue",
+        requires = "repos"
+    )]
+    labels: Vec<String>,
+}
+
+impl AdHocRepos {
+    /// Point the config at the repositories given, if any. Other accounts
+    /// are left out, the top-level one watches them.
+    fn apply(&self, config: &mut Config) -> Result<()> {
+        if self.repos.is_empty() {
+            return Ok(());
+        }
+
+        let labels: Vec<String> = self
+            .labels
+            .iter()
+            .map(|label| label.trim().to_string())
+            .filter(|label| !label.is_empty())
+            .collect();
+        config.repositories = self
+            .repos
+            .iter()
+            .map(|repo| {
+                let (owner, repo) = parse_repo(repo)?;
+                Ok(Repository {
+                    owner,
+                    repo,
+                    labels: labels.clone(),
+                    ..Repository::default()
+                })
+            })
+            .collect::<Result<_>>()?;
+        config.accounts.clear();
+
+        Ok(())
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum ConfigAction {
+    /// Print a JSON Schema of the config file, for editor validation and
+    /// completion
+    Schema,
+}
+
+#[derive(Subcommand, Debug)]
+enum ReposAction {
+    /// Show the watched repositories and their filters
+    List,
+    /// Watch a repository, after checking it exists on GitHub
+    Add {
+        /// Repository as owner/name
+        repo: String,
+
+        /// Labels an issue needs, comma separated
+        #[arg(long, value_delimiter = ',', required = true)]
+        labels: Vec<String>,
+
+        /// Labels that rule an issue out, comma separated
+        #[arg(long, value_delimiter = ',')]
+        exclude: Vec<String>,
+
+        /// Add the repository without asking GitHub about it
+        #[arg(long)]
+        no_check: bool,
+    },
+    /// Stop watching a repository
+    Remove {
+        /// Repository as owner/name
+        repo: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum HistoryAction {
+    /// Print every claim and its outcome as a report
+    Export {
+        #[arg(long, value_enum, default_value_t = ExportFormat::Csv)]
+        format: ExportFormat,
+
+        /// Only claims made on or after this day
+        #[arg(long, value_name = "YYYY-MM-DD")]
+        since: Option<NaiveDate>,
+    },
+}
+
+/// How commands that show state print it
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum OutputFormat {
+    /// For reading
+    Table,
+    /// For scripts, see `gh_issues_bot::output`
+    Json,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum ExportFormat {
+    Csv,
+    Md,
+    Json,
+}
+
+#[derive(Subcommand, Debug)]
+enum QueueAction {
+    /// Show the candidates waiting for review
+    List,
+    /// Show a candidate's description, labels and details
+    Show {
+        /// Candidate ID from `queue list`
+        id: u64,
+    },
+    /// Let the bot claim a candidate on its next poll
+    Approve {
+        /// Candidate ID from `queue list`
+        id: u64,
+    },
+    /// Never claim a candidate
+    Reject {
+        /// Candidate ID from `queue list`
+        id: u64,
     },
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() {
+    if let Err(e) = try_main().await {
+        // Error chains can quote request URLs and headers
+        eprintln!("Error: {}", secret::redact(&format!("{:?}", e)));
+        std::process::exit(1);
+    }
+}
+
+async fn try_main() -> Result<()> {
     // Initialize environment variables from .env file (if it exists)
     dotenv().ok();
 
-    // Initialize logging
-    env_logger::init_from_env(env_logger::Env::default().default_filter_or("info"));
-
     // Parse command line arguments
     let args = Args::parse();
 
+    // Only a running bot logs to a file and exports traces. If the config
+    // can't be read here, run_bot reports why once logging is up.
+    let config = match args.command {
+        Commands::Run { ref config, .. } => load_config(config.clone()).ok(),
+        _ => None,
+    };
+    let logging = config
+        .as_ref()
+        .map(|config| config.logging.clone())
+        .unwrap_or_default();
+    let _logger = logging::init(&logging, args.verbose)?;
+    // Export traces if configured, flushing them when the bot stops
+    let _telemetry = telemetry::init(config.and_then(|config| config.otlp_endpoint).as_deref())
+        .context("Failed to initialize trace export")?;
+
     match args.command {
-        Commands::Run { config, data_dir } => {
-            run_bot(config, data_dir).await?;
+        Commands::Run {
+            config,
+            data_dir,
+            record,
+            once,
+            ad_hoc,
+        } => {
+            run_bot(config, data_dir, record, once, ad_hoc).await?;
+        }
+        Commands::Replay {
+            recording,
+            config,
+            preview,
+            report,
+        } => {
+            replay(recording, config, preview, report).await?;
+        }
+        Commands::Capture {
+            config,
+            out,
+            ad_hoc,
+        } => {
+            capture(config, out, ad_hoc).await?;
+        }
+        Commands::Status {
+            data_dir,
+            output,
+            account,
+        } => {
+            show_status(account_dir(data_dir, account.as_deref()), output).await?;
+        }
+        Commands::Abandon {
+            issue_url,
+            config,
+            data_dir,
+            account,
+        } => {
+            abandon_issue(&issue_url, config, data_dir, account.as_deref()).await?;
+        }
+        Commands::Queue {
+            action,
+            data_dir,
+            account,
+        } => {
+            review_queue(action, account_dir(data_dir, account.as_deref())).await?;
+        }
+        Commands::Ignore {
+            issue_url,
+            undo,
+            data_dir,
+            account,
+        } => {
+            ignore_issue(&issue_url, undo, account_dir(data_dir, account.as_deref())).await?;
+        }
+        Commands::Snooze {
+            issue_url,
+            period,
+            undo,
+            data_dir,
+            account,
+        } => {
+            let period = period.filter(|_| !undo);
+            snooze_issue(
+                &issue_url,
+                period,
+                account_dir(data_dir, account.as_deref()),
+            )
+            .await?;
+        }
+        Commands::Note {
+            action,
+            data_dir,
+            account,
+        } => {
+            edit_notes(action, account_dir(data_dir, account.as_deref())).await?;
+        }
+        Commands::Config {
+            action: ConfigAction::Schema,
+        } => {
+            println!("{}", Config::schema()?);
+        }
+        Commands::Repos { action, config } => {
+            edit_repos(action, config).await?;
+        }
+        Commands::History {
+            action: None,
+            output,
+            data_dir,
+            account,
+        } => {
+            show_history(account_dir(data_dir, account.as_deref()), output).await?;
+        }
+        Commands::History {
+            action: Some(HistoryAction::Export { format, since }),
+            data_dir,
+            account,
+            ..
+        } => {
+            export_history(account_dir(data_dir, account.as_deref()), format, since).await?;
+        }
+        Commands::Healthcheck {
+            data_dir,
+            max_age,
+            account,
+        } => {
+            healthcheck(account_dir(data_dir, account.as_deref()), max_age).await?;
         }
     }
 
     Ok(())
 }
 
-async fn run_bot(config_path: Option<PathBuf>, data_dir: PathBuf) -> Result<()> {
-    // Load configuration
-    let config = match config_path {
+fn load_config(config_path: Option<PathBuf>) -> Result<Config> {
+    match config_path {
         Some(path) => Config::from_file(&path)
-            .with_context(|| format!("Failed to load config from {}", path.display()))?,
-        None => Config::from_env().context("Failed to load config from environment")?,
+            .with_context(|| format!("Failed to load config from {}", path.display())),
+        None => Config::from_env().context("Failed to load config from environment"),
+    }
+}
+
+/// Where an account keeps its state: the data dir itself for the top-level
+/// account, a directory named after the account inside it otherwise
+fn account_dir(data_dir: PathBuf, account: Option<&str>) -> PathBuf {
+    match account {
+        Some(name) => data_dir.join(name),
+        None => data_dir,
+    }
+}
+
+async fn run_bot(
+    config_path: Option<PathBuf>,
+    data_dir: PathBuf,
+    record: Option<PathBuf>,
+    once: bool,
+    ad_hoc: AdHocRepos,
+) -> Result<()> {
+    // Load configuration
+    let mut config = load_config(config_path)?;
+    ad_hoc.apply(&mut config)?;
+
+    // All accounts report to the same health endpoints
+    let health = Arc::new(Health::new());
+    let retry_stats = Arc::new(RetryStats::default());
+
+    let mut bots = Vec::new();
+    let mut locks = Vec::new();
+    for (account, account_config) in config.split_accounts() {
+        let label = account.as_deref().unwrap_or(&account_config.user_login);
+
+        // Each account gets its own client, and with it its own rate limit
+        let mut github_client = RestClient::new(
+            account_config.auth_token.clone(),
+            account_config.user_login.clone(),
+            &account_config.http,
+        )
+        .with_context(|| format!("Failed to initialize GitHub client for {}", label))?
+        .with_max_attempts(account_config.max_retries)
+        .with_retry_stats(retry_stats.clone());
+        if let Some(ref path) = record {
+            info!("Recording open issues to {}", path.display());
+            github_client = github_client.with_recorder(Recorder::new(path));
+        }
+
+        let account_dir = account_dir(data_dir.clone(), account.as_deref());
+        let persistence = FilePersistence::new(&account_dir)
+            .await
+            .context("Failed to initialize persistence")?;
+        locks.push(persistence.lock()?);
+        // Processed issues and cursors are written once a poll at most
+        let persistence = BufferedPersistence::new(persistence);
+
+        info!(
+            "Watching for issues in {} repositories as {}",
+            account_config.repositories.len(),
+            account_config.user_login
+        );
+        let mut bot = BotBuilder::new(account_config, github_client, persistence)
+            .health(health.clone())
+            .build();
+        bot.initialize().await?;
+        bots.push(bot);
+    }
+
+    // Nothing would be around long enough to check on a single poll
+    if let Some(port) = config.health_port.filter(|_| !once) {
+        // Polls can take a while with jitter, so allow a few missed ones
+        let stale_after = chrono::Duration::seconds(
+            (config.tick_interval_secs() + config.max_poll_delay().as_secs()) as i64 * 3 + 60,
+        );
+        let persistence = FilePersistence::new(&data_dir)
+            .await
+            .context("Failed to initialize persistence")?;
+        let server = health::serve(port, health, retry_stats, persistence, stale_after)?;
+        tokio::spawn(async move {
+            if let Err(e) = server.await {
+                warn!("Health check listener stopped: {}", e);
+            }
+        });
+        info!("Serving health checks on port {}", port);
+    }
+
+    info!("Bot initialized successfully");
+
+    // A bot that stops, e.g. over a revoked token, leaves the others running
+    let mut running = JoinSet::new();
+    for bot in bots {
+        running.spawn(async move {
+            if once {
+                bot.run_once().await
+            } else {
+                bot.start().await
+            }
+        });
+    }
+    let mut first_error = None;
+    while let Some(result) = running.join_next().await {
+        if let Err(e) = result
+            .context("Bot task panicked")
+            .and_then(|result| result)
+        {
+            warn!("A bot stopped: {}", e);
+            first_error.get_or_insert(e);
+        }
+    }
+
+    match first_error {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+/// Show which issues the current config would pick from each recorded
+/// snapshot, without talking to GitHub
+async fn replay(
+    recording: PathBuf,
+    config_path: Option<PathBuf>,
+    preview: bool,
+    report: Option<PathBuf>,
+) -> Result<()> {
+    let config = load_config(config_path)?;
+    let responses = if recording.is_dir() {
+        fixtures::load_dir(&recording)
+            .await?
+            .iter()
+            .map(Fixture::to_recorded_response)
+            .collect::<Result<Vec<_>>>()?
+    } else {
+        recording::load(&recording).await?
     };
 
-    // Initialize GitHub client
-    let github_client = OctocrabClient::new(config.auth_token.clone(), config.user_login.clone())
-        .context("Failed to initialize GitHub client")?;
+    // Like processed issues, an issue is only claimed once
+    let mut claimed = HashSet::new();
+    let mut evaluation = Evaluation::default();
+
+    for response in responses {
+        let Some(repo) = config
+            .repositories
+            .iter()
+            .find(|repo| repo.owner == response.owner && repo.repo == response.repo)
+        else {
+            println!(
+                "{} {}/{}: not in the config, skipped",
+                response.recorded_at.format("%Y-%m-%d %H:%M"),
+                response.owner,
+                response.repo
+            );
+            continue;
+        };
+
+        let issues: Vec<Issue> = serde_json::from_value(response.body)
+            .with_context(|| format!("Failed to parse issues of {}/{}", repo.owner, repo.repo))?;
+        let snapshot =
+            Snapshot::evaluate(&config, repo, response.recorded_at, &issues, &mut claimed);
+
+        println!(
+            "{} {}/{}: {} of {} open issues match",
+            response.recorded_at.format("%Y-%m-%d %H:%M"),
+            repo.owner,
+            repo.repo,
+            snapshot.eligible(),
+            snapshot.issues.len()
+        );
+        let picked = snapshot
+            .claimed
+            .and_then(|number| issues.iter().find(|issue| issue.number == number));
+        match picked {
+            Some(issue) => {
+                println!("  would claim #{} {}", issue.number, issue.title);
+                if preview {
+                    let labels: Vec<String> = issue
+                        .labels
+                        .iter()
+                        .map(|label| label.name.clone())
+                        .collect();
+                    let details = [
+                        format!("opened {}", issue.created_at.format("%Y-%m-%d")),
+                        format!(
+                            "difficulty {}/{}",
+                            difficulty::estimate(issue),
+                            difficulty::MAX
+                        ),
+                        issue.html_url.clone(),
+                    ];
+                    println!();
+                    println!(
+                        "{}",
+                        preview::render(
+                            &issue.title,
+                            &labels,
+                            &details,
+                            issue.body.as_deref(),
+                            Terminal::detect()
+                        )
+                    );
+                    println!();
+                }
+            }
+            None => println!("  nothing new to claim"),
+        }
+        evaluation.snapshots.push(snapshot);
+    }
+
+    println!();
+    println!("{} issues would have been claimed", claimed.len());
 
-    // Initialize persistence
+    if let Some(path) = report {
+        evaluation.write(&path).await?;
+        println!("Wrote the evaluation of every issue to {}", path.display());
+    }
+
+    Ok(())
+}
+
+/// Fetch the open issues of every configured repository, unfiltered, and
+/// write them out as fixtures
+async fn capture(config_path: Option<PathBuf>, out: PathBuf, ad_hoc: AdHocRepos) -> Result<()> {
+    let mut config = load_config(config_path)?;
+    ad_hoc.apply(&mut config)?;
+    let github_client = RestClient::new(
+        config.auth_token.clone(),
+        config.user_login.clone(),
+        &config.http,
+    )
+    .context("Failed to initialize GitHub client")?
+    .with_max_attempts(config.max_retries);
+
+    for repo in &config.repositories {
+        let issues = github_client
+            .get_all_open_issues(&repo.owner, &repo.repo)
+            .await
+            .with_context(|| format!("Failed to fetch issues of {}/{}", repo.owner, repo.repo))?;
+        let matching = issues
+            .iter()
+            .filter(|issue| matches_filters(repo, issue))
+            .count();
+
+        let fixture = Fixture::new(&repo.owner, &repo.repo, issues);
+        let path = fixtures::save(&out, &fixture).await?;
+        println!(
+            "{}/{}: {} open issues, {} matching, saved to {}",
+            repo.owner,
+            repo.repo,
+            fixture.issues.len(),
+            matching,
+            path.display()
+        );
+    }
+
+    Ok(())
+}
+
+async fn edit_repos(action: ReposAction, config_path: PathBuf) -> Result<()> {
+    match action {
+        ReposAction::List => {
+            let config = Config::from_file(&config_path)
+                .with_context(|| format!("Failed to load config from {}", config_path.display()))?;
+            if config.repositories.is_empty() {
+                println!("No repositories in {}", config_path.display());
+            }
+            for repo in &config.repositories {
+                print!(
+                    "{}/{}: labels {}",
+                    repo.owner,
+                    repo.repo,
+                    repo.labels.join(", ")
+                );
+                if !repo.exclude_labels.is_empty() {
+                    print!(", excluding {}", repo.exclude_labels.join(", "));
+                }
+                if let Some(ref title_regex) = repo.title_regex {
+                    print!(", titles matching {}", title_regex);
+                }
+                if let Some(ref filter) = repo.filter {
+                    print!(", filter {}", filter);
+                }
+                println!();
+            }
+        }
+        ReposAction::Add {
+            repo,
+            labels,
+            exclude,
+            no_check,
+        } => {
+            let (mut owner, mut name) = parse_repo(&repo)?;
+            let mut file = ConfigFile::read(&config_path).await?;
+
+            if !no_check {
+                let config = Config::from_file(&config_path).with_context(|| {
+                    format!("Failed to load config from {}", config_path.display())
+                })?;
+                let github_client = RestClient::new(
+                    config.auth_token.clone(),
+                    config.user_login.clone(),
+                    &config.http,
+                )
+                .context("Failed to initialize GitHub client")?;
+                // Take GitHub's spelling, which also follows renames
+                let info = github_client
+                    .get_repository(&owner, &name)
+                    .await
+                    .with_context(|| format!("Can't find {}/{} on GitHub", owner, name))?;
+                (owner, name) = (info.owner.login, info.name);
+            }
+
+            file.add_repository(&Repository {
+                owner: owner.clone(),
+                repo: name.clone(),
+                labels: labels.into_iter().map(|l| l.trim().to_string()).collect(),
+                exclude_labels: exclude.into_iter().map(|l| l.trim().to_string()).collect(),
+                ..Repository::default()
+            })?;
+            file.write(&config_path).await?;
+            println!(
+                "Added {}/{} to {}, the running bot picks it up after a restart",
+                owner,
+                name,
+                config_path.display()
+            );
+        }
+        ReposAction::Remove { repo } => {
+            let (owner, name) = parse_repo(&repo)?;
+            let mut file = ConfigFile::read(&config_path).await?;
+            file.remove_repository(&owner, &name)?;
+            file.write(&config_path).await?;
+            println!("Removed {}/{} from {}", owner, name, config_path.display());
+        }
+    }
+
+    Ok(())
+}
+
+/// `owner/name`, as given on the command line
+fn parse_repo(repo: &str) -> Result<(String, String)> {
+    match repo.trim().trim_end_matches('/').split_once('/') {
+        Some((owner, name)) if !owner.is_empty() && !name.is_empty() && !name.contains('/') => {
+            Ok((owner.to_string(), name.to_string()))
+        }
+        _ => Err(anyhow::anyhow!(
+            "Invalid repository {:?}, use owner/name",
+            repo
+        )),
+    }
+}
+
+async fn abandon_issue(
+    issue_url: &str,
+    config_path: Option<PathBuf>,
+    data_dir: PathBuf,
+    account: Option<&str>,
+) -> Result<()> {
+    let (owner, repo, issue_number) = parse_issue_url(issue_url)?;
+    let config = load_config(config_path)?.account(account)?;
+    let data_dir = account_dir(data_dir, account);
+
+    let github_client = RestClient::new(
+        config.auth_token.clone(),
+        config.user_login.clone(),
+        &config.http,
+    )
+    .context("Failed to initialize GitHub client")?
+    .with_max_attempts(config.max_retries);
     let persistence = FilePersistence::new(&data_dir)
         .await
         .context("Failed to initialize persistence")?;
+    let _lock = persistence.lock()?;
 
-    // Initialize and run bot
-    let mut bot = Bot::new(config.clone(), github_client, persistence);
-    bot.initialize().await?;
+    let mut bot = Bot::new(config, github_client, persistence);
+    bot.join_team()?;
+    bot.load_state().await?;
+    bot.abandon(&owner, &repo, issue_number).await?;
 
-    info!("Bot initialized successfully");
-    info!(
-        "Watching for issues in {} repositories",
-        config.repositories.len()
+    println!("Abandoned {}", issue_url);
+
+    Ok(())
+}
+
+async fn show_status(data_dir: PathBuf, output: OutputFormat) -> Result<()> {
+    let persistence = FilePersistence::new(&data_dir)
+        .await
+        .context("Failed to initialize persistence")?;
+    if output == OutputFormat::Json {
+        let status = status_json(&persistence).await?;
+        println!("{}", serde_json::to_string_pretty(&status)?);
+        return Ok(());
+    }
+    let notes = persistence.load_notes().await?;
+
+    match persistence.load_active_issue().await? {
+        Some(active) => {
+            println!(
+                "Waiting for assignment: {}/{}#{} {}",
+                active.repo_owner, active.repo_name, active.issue_number, active.issue_title
+            );
+            println!("  {}", active.issue_url);
+            println!(
+                "  requested {}, times out {}",
+                active.requested_at.format("%Y-%m-%d %H:%M"),
+                active.timeout.format("%Y-%m-%d %H:%M")
+            );
+            print_notes(
+                notes.get(&active.repo_owner, &active.repo_name, active.issue_number),
+                "  ",
+            );
+
+            let history = persistence.load_history().await?;
+            let repo = format!("{}/{}", active.repo_owner, active.repo_name);
+            if let Some(median) = Responsiveness::by_repo(&history)
+                .get(&repo)
+                .and_then(|responsiveness| responsiveness.median_response)
+            {
+                println!(
+                    "  median response in {}: {:.1} days, expect an answer around {}",
+                    repo,
+                    median.num_minutes() as f64 / 1440.0,
+                    (active.requested_at + median).format("%Y-%m-%d %H:%M")
+                );
+            }
+        }
+        None => println!("No pending assignment request"),
+    }
+
+    let cursors = persistence.load_repo_cursors().await?;
+    if !cursors.is_empty() {
+        println!();
+        println!("Repositories:");
+        for (repo, cursor) in &cursors {
+            let polled = cursor
+                .last_polled_at
+                .map(|at| at.format("%Y-%m-%d %H:%M").to_string())
+                .unwrap_or_else(|| "never".to_string());
+            println!("  {} polled {}", repo, polled);
+            if let Some(id) = cursor.last_seen_issue_id {
+                println!("    last seen issue ID {}", id);
+            }
+            if let Some(rate) = cursor.arrival_rate() {
+                println!("    {:.1} new eligible issues a day", rate);
+            }
+            if let (Some(error), Some(at)) = (&cursor.last_error, cursor.last_error_at) {
+                println!("    failed {}: {}", at.format("%Y-%m-%d %H:%M"), error);
+            }
+        }
+        println!();
+    }
+
+    let processed = persistence.load_processed_issues().await?;
+    if !processed.is_empty() {
+        let mut reasons: Vec<(ProcessedReason, usize)> = Vec::new();
+        for record in processed.values() {
+            match reasons
+                .iter_mut()
+                .find(|(reason, _)| *reason == record.reason)
+            {
+                Some((_, count)) => *count += 1,
+                None => reasons.push((record.reason, 1)),
+            }
+        }
+        reasons.sort();
+        let mut reasons: Vec<String> = reasons
+            .iter()
+            .map(|(reason, count)| format!("{} {}", count, reason))
+            .collect();
+        let archive = processed.archive();
+        if !archive.is_empty() {
+            reasons.push(format!(
+                "{} archived from before {}",
+                archive.len(),
+                processed::compaction_cutoff(Utc::now())
+            ));
+        }
+        println!(
+            "{} issues won't be claimed again ({})",
+            processed.len(),
+            reasons.join(", ")
+        );
+    }
+
+    let ignored = persistence.load_ignored_issues().await?;
+    if !ignored.is_empty() {
+        println!("{} issues ignored with `ignore`", ignored.len());
+    }
+    let mut snoozed = persistence.load_snoozed_issues().await?;
+    snoozed.prune();
+    if !snoozed.is_empty() {
+        println!("{} issues snoozed", snoozed.len());
+    }
+
+    let assigned = persistence.load_assigned_issues().await?;
+    if assigned.is_empty() {
+        return Ok(());
+    }
+
+    println!();
+    println!("In progress:");
+    for issue in assigned {
+        let overdue = if issue.due_at < Utc::now() && !issue.has_pull_request() {
+            ", overdue"
+        } else {
+            ""
+        };
+        println!(
+            "  {}/{}#{} {} (due {}{})",
+            issue.repo_owner,
+            issue.repo_name,
+            issue.issue_number,
+            issue.issue_title,
+            issue.due_at.format("%Y-%m-%d"),
+            overdue
+        );
+        if issue.linked_prs.is_empty() {
+            println!("    no linked pull requests");
+        }
+        for pr in &issue.linked_prs {
+            println!(
+                "    PR #{} by {} ({}) {}",
+                pr.number, pr.author, pr.state, pr.html_url
+            );
+        }
+        print_notes(
+            notes.get(&issue.repo_owner, &issue.repo_name, issue.issue_number),
+            "    ",
+        );
+    }
+
+    Ok(())
+}
+
+async fn status_json(persistence: &FilePersistence) -> Result<output::Status> {
+    let notes = persistence.load_notes().await?;
+    let history = persistence.load_history().await?;
+    let responsiveness = Responsiveness::by_repo(&history);
+    let days = |duration: chrono::Duration| duration.num_minutes() as f64 / 1440.0;
+
+    let pending = persistence.load_active_issue().await?.map(|active| {
+        let repo = format!("{}/{}", active.repo_owner, active.repo_name);
+        output::PendingClaim {
+            median_response_days: responsiveness
+                .get(&repo)
+                .and_then(|responsiveness| responsiveness.median_response)
+                .map(days),
+            repo,
+            number: active.issue_number,
+            title: active.issue_title.clone(),
+            url: active.issue_url.clone(),
+            requested_at: active.requested_at,
+            timeout: active.timeout,
+            notes: notes
+                .get(&active.repo_owner, &active.repo_name, active.issue_number)
+                .to_vec(),
+        }
+    });
+
+    let repositories = persistence
+        .load_repo_cursors()
+        .await?
+        .iter()
+        .map(|(repo, cursor)| output::RepositoryStatus {
+            repo: repo.clone(),
+            last_polled_at: cursor.last_polled_at,
+            last_seen_issue_id: cursor.last_seen_issue_id,
+            arrival_rate: cursor.arrival_rate(),
+            last_error: cursor.last_error.clone(),
+            last_error_at: cursor.last_error_at,
+        })
+        .collect();
+
+    let processed = persistence.load_processed_issues().await?;
+    let mut by_reason = std::collections::BTreeMap::new();
+    for record in processed.values() {
+        *by_reason.entry(record.reason).or_insert(0) += 1;
+    }
+
+    let mut snoozed = persistence.load_snoozed_issues().await?;
+    snoozed.prune();
+
+    let in_progress = persistence
+        .load_assigned_issues()
+        .await?
+        .into_iter()
+        .map(|issue| output::InProgress {
+            repo: format!("{}/{}", issue.repo_owner, issue.repo_name),
+            number: issue.issue_number,
+            overdue: issue.due_at < Utc::now() && !issue.has_pull_request(),
+            title: issue.issue_title,
+            url: issue.issue_url,
+            assigned_at: issue.assigned_at,
+            due_at: issue.due_at,
+            notes: notes
+                .get(&issue.repo_owner, &issue.repo_name, issue.issue_number)
+                .to_vec(),
+            pull_requests: issue.linked_prs,
+        })
+        .collect();
+
+    Ok(output::Status {
+        pending,
+        repositories,
+        processed: output::ProcessedCounts {
+            total: processed.len(),
+            by_reason,
+            archived: processed.archive().len(),
+        },
+        ignored: persistence.load_ignored_issues().await?.len(),
+        snoozed: snoozed.len(),
+        in_progress,
+    })
+}
+
+async fn healthcheck(data_dir: PathBuf, max_age: u64) -> Result<()> {
+    let persistence = FilePersistence::new(&data_dir)
+        .await
+        .context("Failed to initialize persistence")?;
+
+    let heartbeat = persistence
+        .load_heartbeat()
+        .await?
+        .context("The bot hasn't finished a poll yet")?;
+
+    let age = heartbeat.age();
+    if age > chrono::Duration::seconds(max_age as i64) {
+        return Err(anyhow::anyhow!(
+            "Last poll was {} seconds ago, at {}",
+            age.num_seconds(),
+            heartbeat.last_poll_at
+        ));
+    }
+
+    println!(
+        "Healthy: last poll {} seconds ago (pid {})",
+        age.num_seconds(),
+        heartbeat.pid
+    );
+
+    Ok(())
+}
+
+async fn show_history(data_dir: PathBuf, output: OutputFormat) -> Result<()> {
+    let persistence = FilePersistence::new(&data_dir)
+        .await
+        .context("Failed to initialize persistence")?;
+    if output == OutputFormat::Json {
+        let history = history_json(&persistence).await?;
+        println!("{}", serde_json::to_string_pretty(&history)?);
+        return Ok(());
+    }
+
+    let history = persistence.load_history().await?;
+    let in_progress = persistence.load_assigned_issues().await?;
+    let notes = persistence.load_notes().await?;
+
+    for entry in &history {
+        println!(
+            "{} {}/{}#{} {} ({})",
+            entry.finished_at.format("%Y-%m-%d"),
+            entry.repo_owner,
+            entry.repo_name,
+            entry.issue_number,
+            entry.issue_title,
+            entry.outcome
+        );
+        if let Some(ref url) = entry.pull_request_url {
+            println!("    PR {}", url);
+        }
+        print_notes(
+            notes.get(&entry.repo_owner, &entry.repo_name, entry.issue_number),
+            "    ",
+        );
+    }
+    for issue in &in_progress {
+        println!(
+            "{} {}/{}#{} {} (in progress)",
+            issue.assigned_at.format("%Y-%m-%d"),
+            issue.repo_owner,
+            issue.repo_name,
+            issue.issue_number,
+            issue.issue_title
+        );
+        for pr in &issue.linked_prs {
+            println!("    PR #{} ({}) {}", pr.number, pr.state, pr.html_url);
+        }
+        print_notes(
+            notes.get(&issue.repo_owner, &issue.repo_name, issue.issue_number),
+            "    ",
+        );
+    }
+
+    let summary = Summary::new(&history, in_progress.len());
+    println!();
+    println!(
+        "{} claims, {} assigned ({:.0}%), {} completed ({:.0}%)",
+        summary.claims,
+        summary.assigned,
+        summary.assignment_rate() * 100.0,
+        summary.completed,
+        summary.completion_rate() * 100.0
     );
 
-    bot.start().await?;
+    let mut templates: Vec<(String, TemplateStats)> =
+        TemplateStats::by_template(&history, &in_progress)
+            .into_iter()
+            .collect();
+    templates.sort_by(|(_, a), (_, b)| b.assignment_rate().total_cmp(&a.assignment_rate()));
+    if !templates.is_empty() {
+        println!();
+    }
+    for (template, stats) in templates {
+        println!(
+            "{:>3.0}% assigned ({} of {}): {}",
+            stats.assignment_rate() * 100.0,
+            stats.assigned,
+            stats.claims,
+            template
+        );
+    }
+
+    let mut repos: Vec<(String, Responsiveness)> =
+        Responsiveness::by_repo(&history).into_iter().collect();
+    repos.sort_by(|(a, _), (b, _)| a.cmp(b));
+    if !repos.is_empty() {
+        println!();
+    }
+    for (repo, responsiveness) in repos {
+        let median = match responsiveness.median_response {
+            Some(response) => format!(
+                "median response {:.1} days",
+                response.num_minutes() as f64 / 1440.0
+            ),
+            None => "never answered".to_string(),
+        };
+        let ignored = match responsiveness.ignored_in_a_row {
+            0 => String::new(),
+            n => format!(", last {} ignored", n),
+        };
+        println!(
+            "{}: {} claims, {} assigned, {}{}",
+            repo, responsiveness.claims, responsiveness.assigned, median, ignored
+        );
+    }
+
+    Ok(())
+}
+
+async fn history_json(persistence: &FilePersistence) -> Result<output::History> {
+    let history = persistence.load_history().await?;
+    let in_progress = persistence.load_assigned_issues().await?;
+    let days = |duration: chrono::Duration| duration.num_minutes() as f64 / 1440.0;
+
+    let summary = Summary::new(&history, in_progress.len());
+    let mut templates: Vec<output::TemplateSummary> =
+        TemplateStats::by_template(&history, &in_progress)
+            .into_iter()
+            .map(|(template, stats)| output::TemplateSummary {
+                template,
+                claims: stats.claims,
+                assigned: stats.assigned,
+                assignment_rate: stats.assignment_rate(),
+            })
+            .collect();
+    templates.sort_by(|a, b| b.assignment_rate.total_cmp(&a.assignment_rate));
+    let mut repositories: Vec<output::RepositorySummary> = Responsiveness::by_repo(&history)
+        .into_iter()
+        .map(|(repo, responsiveness)| output::RepositorySummary {
+            repo,
+            claims: responsiveness.claims,
+            assigned: responsiveness.assigned,
+            answered: responsiveness.answered,
+            median_response_days: responsiveness.median_response.map(days),
+            ignored_in_a_row: responsiveness.ignored_in_a_row,
+        })
+        .collect();
+    repositories.sort_by(|a, b| a.repo.cmp(&b.repo));
+
+    Ok(output::History {
+        claims: report::rows(&history, &in_progress, None),
+        summary: output::HistorySummary {
+            claims: summary.claims,
+            assigned: summary.assigned,
+            completed: summary.completed,
+            assignment_rate: summary.assignment_rate(),
+            completion_rate: summary.completion_rate(),
+        },
+        templates,
+        repositories,
+    })
+}
+
+async fn export_history(
+    data_dir: PathBuf,
+    format: ExportFormat,
+    since: Option<NaiveDate>,
+) -> Result<()> {
+    let persistence = FilePersistence::new(&data_dir)
+        .await
+        .context("Failed to initialize persistence")?;
+
+    let history = persistence.load_history().await?;
+    let in_progress = persistence.load_assigned_issues().await?;
+    let rows = report::rows(&history, &in_progress, since);
+
+    let report = match format {
+        ExportFormat::Csv => report::csv(&rows),
+        ExportFormat::Md => report::markdown(&rows),
+        ExportFormat::Json => report::json(&rows)?,
+    };
+    print!("{}", report);
+    if !report.ends_with('\n') {
+        println!();
+    }
+
+    Ok(())
+}
+
+/// The bot reads the ignore list on every poll, so this works while it runs
+async fn ignore_issue(issue_url: &str, undo: bool, data_dir: PathBuf) -> Result<()> {
+    let (owner, repo, issue_number) = parse_issue_url(issue_url)?;
+    let persistence = FilePersistence::new(&data_dir)
+        .await
+        .context("Failed to initialize persistence")?;
+    let mut ignored = persistence.load_ignored_issues().await?;
+
+    let changed = if undo {
+        ignored.remove(&owner, &repo, issue_number)
+    } else {
+        ignored.insert(&owner, &repo, issue_number)
+    };
+    match (undo, changed) {
+        (false, true) => println!("Ignoring {}/{}#{}", owner, repo, issue_number),
+        (false, false) => println!("Already ignoring {}/{}#{}", owner, repo, issue_number),
+        (true, true) => println!("No longer ignoring {}/{}#{}", owner, repo, issue_number),
+        (true, false) => println!("{}/{}#{} wasn't ignored", owner, repo, issue_number),
+    }
+
+    if changed {
+        persistence.save_ignored_issues(&ignored).await?;
+    }
+    Ok(())
+}
+
+/// Like `ignore`, this works while the bot runs. None wakes the issue.
+async fn snooze_issue(
+    issue_url: &str,
+    period: Option<chrono::Duration>,
+    data_dir: PathBuf,
+) -> Result<()> {
+    let (owner, repo, issue_number) = parse_issue_url(issue_url)?;
+    let persistence = FilePersistence::new(&data_dir)
+        .await
+        .context("Failed to initialize persistence")?;
+    let mut snoozed = persistence.load_snoozed_issues().await?;
+    snoozed.prune();
+
+    match period {
+        Some(period) => {
+            let until = Utc::now() + period;
+            snoozed.snooze(&owner, &repo, issue_number, until);
+            println!(
+                "Snoozed {}/{}#{} until {}",
+                owner,
+                repo,
+                issue_number,
+                until.format("%Y-%m-%d %H:%M")
+            );
+        }
+        None if snoozed.wake(&owner, &repo, issue_number) => {
+            println!("Woke {}/{}#{}", owner, repo, issue_number)
+        }
+        None => println!("{}/{}#{} wasn't snoozed", owner, repo, issue_number),
+    }
+
+    persistence.save_snoozed_issues(&snoozed).await
+}
+
+/// A number followed by m, h, d or w
+/// A `--report` file, which has to say what format it wants
+fn parse_report_path(path: &str) -> Result<PathBuf, String> {
+    let path = PathBuf::from(path);
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("json" | "md") => Ok(path),
+        _ => Err(format!("{} should end in .json or .md", path.display())),
+    }
+}
+
+fn parse_period(period: &str) -> Result<chrono::Duration, String> {
+    let split = period.len() - period.chars().last().map_or(0, char::len_utf8);
+    let (amount, unit) = period.split_at(split);
+    let amount: i64 = amount
+        .parse()
+        .ok()
+        .filter(|amount| *amount > 0)
+        .ok_or_else(|| format!("{:?} isn't a number followed by m, h, d or w", period))?;
+    match unit {
+        "m" => Ok(chrono::Duration::minutes(amount)),
+        "h" => Ok(chrono::Duration::hours(amount)),
+        "d" => Ok(chrono::Duration::days(amount)),
+        "w" => Ok(chrono::Duration::weeks(amount)),
+        _ => Err(format!("Unknown unit in {:?}, use m, h, d or w", period)),
+    }
+}
+
+/// Notes are only ever written here, so this works while the bot runs
+async fn edit_notes(action: NoteAction, data_dir: PathBuf) -> Result<()> {
+    let persistence = FilePersistence::new(&data_dir)
+        .await
+        .context("Failed to initialize persistence")?;
+    let mut notes = persistence.load_notes().await?;
+
+    match action {
+        NoteAction::Add { issue_url, text } => {
+            let (owner, repo, issue_number) = parse_issue_url(&issue_url)?;
+            notes.add(&owner, &repo, issue_number, &text);
+            println!("Added a note to {}/{}#{}", owner, repo, issue_number);
+        }
+        NoteAction::Clear { issue_url } => {
+            let (owner, repo, issue_number) = parse_issue_url(&issue_url)?;
+            let cleared = notes.clear(&owner, &repo, issue_number);
+            println!(
+                "Deleted {} notes from {}/{}#{}",
+                cleared, owner, repo, issue_number
+            );
+        }
+    }
+
+    persistence.save_notes(&notes).await
+}
+
+fn print_notes(notes: &[Note], indent: &str) {
+    for note in notes {
+        println!(
+            "{}note {}: {}",
+            indent,
+            note.written_at.format("%Y-%m-%d"),
+            note.text
+        );
+    }
+}
+
+/// List or decide on review candidates. Works while the bot runs, which
+/// picks up decisions on its next poll.
+async fn review_queue(action: QueueAction, data_dir: PathBuf) -> Result<()> {
+    let persistence = FilePersistence::new(&data_dir)
+        .await
+        .context("Failed to initialize persistence")?;
+    let mut candidates = persistence.load_candidates().await?;
+
+    let (id, status) = match action {
+        QueueAction::List => {
+            if candidates.is_empty() {
+                println!("No candidates waiting for review");
+            }
+            for candidate in candidates.iter() {
+                println!(
+                    "{:>4} {}/{}#{} {} ({}, found {})",
+                    candidate.id,
+                    candidate.repo_owner,
+                    candidate.repo_name,
+                    candidate.issue_number,
+                    candidate.issue_title,
+                    candidate.status,
+                    candidate.found_at.format("%Y-%m-%d %H:%M")
+                );
+                println!("     {}", candidate.issue_url);
+            }
+            return Ok(());
+        }
+        QueueAction::Show { id } => {
+            let candidate = candidates
+                .find(id)
+                .with_context(|| format!("No candidate with ID {} in the queue", id))?;
+            let details = [
+                format!(
+                    "{}/{}#{}, {}, found {}",
+                    candidate.repo_owner,
+                    candidate.repo_name,
+                    candidate.issue_number,
+                    candidate.status,
+                    candidate.found_at.format("%Y-%m-%d %H:%M")
+                ),
+                candidate.issue_url.clone(),
+            ];
+            println!(
+                "{}",
+                preview::render(
+                    &candidate.issue_title,
+                    &candidate.issue_labels,
+                    &details,
+                    candidate.issue_body.as_deref(),
+                    Terminal::detect(),
+                )
+            );
+            return Ok(());
+        }
+        QueueAction::Approve { id } => (id, CandidateStatus::Approved),
+        QueueAction::Reject { id } => (id, CandidateStatus::Rejected),
+    };
+
+    let candidate = candidates.decide(id, status)?;
+    println!(
+        "Candidate {} {}: {}/{}#{} {}",
+        id,
+        status,
+        candidate.repo_owner,
+        candidate.repo_name,
+        candidate.issue_number,
+        candidate.issue_title
+    );
+    persistence.save_candidates(&candidates).await?;
 
     Ok(())
 }
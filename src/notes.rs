@@ -0,0 +1,44 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::processed;
+
+/// Something written down about an issue with `note add`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Note {
+    pub written_at: DateTime<Utc>,
+    pub text: String,
+}
+
+/// Notes keyed by `owner/repo#number`, oldest first for each issue
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Notes {
+    notes: BTreeMap<String, Vec<Note>>,
+}
+
+impl Notes {
+    pub fn get(&self, owner: &str, repo: &str, issue_number: u64) -> &[Note] {
+        self.notes
+            .get(&processed::key(owner, repo, issue_number))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    pub fn add(&mut self, owner: &str, repo: &str, issue_number: u64, text: &str) {
+        self.notes
+            .entry(processed::key(owner, repo, issue_number))
+            .or_default()
+            .push(Note {
+                written_at: Utc::now(),
+                text: text.to_string(),
+            });
+    }
+
+    /// Drop an issue's notes, returning how many there were
+    pub fn clear(&mut self, owner: &str, repo: &str, issue_number: u64) -> usize {
+        self.notes
+            .remove(&processed::key(owner, repo, issue_number))
+            .map_or(0, |notes| notes.len())
+    }
+}
@@ -0,0 +1,1107 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use rand::{thread_rng, Rng};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::sync::Mutex;
+use tracing::{info, warn};
+
+use crate::bot::{ActiveIssue, AssignedIssue};
+use crate::config::HttpConfig;
+use crate::github::Issue;
+use crate::secret::Secret;
+
+#[derive(Debug, Default, Deserialize, Serialize, Clone, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct NotificationsConfig {
+    #[serde(default)]
+    pub slack: Option<SlackConfig>,
+    #[serde(default)]
+    pub discord: Option<DiscordConfig>,
+    #[serde(default)]
+    pub telegram: Option<TelegramConfig>,
+    #[serde(default)]
+    pub ntfy: Option<NtfyConfig>,
+    #[serde(default)]
+    pub matrix: Option<MatrixConfig>,
+    /// Generic endpoints that receive every event as JSON
+    #[serde(default)]
+    pub webhooks: Vec<WebhookConfig>,
+    /// Show native desktop notifications for claims and assignments
+    #[serde(default)]
+    pub desktop: bool,
+    /// Event kinds that are only logged, never sent anywhere
+    #[serde(default)]
+    pub disabled_events: Vec<String>,
+}
+
+/// How urgent an event is
+#[derive(
+    Debug, Default, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    #[default]
+    Info,
+    Warning,
+    Error,
+}
+
+/// Which events a backend receives
+#[derive(Debug, Default, Deserialize, Serialize, Clone, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct Route {
+    /// Event kinds to send, all of them if empty
+    #[serde(default)]
+    pub events: Vec<String>,
+    /// Least severe events to send
+    #[serde(default)]
+    pub min_severity: Severity,
+}
+
+impl Route {
+    fn accepts(&self, event: &Event) -> bool {
+        let wanted = self.events.is_empty() || self.events.iter().any(|kind| kind == event.kind());
+        wanted && event.severity() >= self.min_severity
+    }
+}
+
+/// Posts to a Slack incoming webhook
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct SlackConfig {
+    pub webhook_url: Secret,
+    #[serde(default)]
+    pub channel: Option<String>,
+    #[serde(flatten)]
+    pub route: Route,
+}
+
+/// Posts to a Discord webhook
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct DiscordConfig {
+    pub webhook_url: Secret,
+    #[serde(flatten)]
+    pub route: Route,
+}
+
+/// Messages a Telegram chat through a bot
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct TelegramConfig {
+    pub bot_token: Secret,
+    pub chat_id: i64,
+    /// Add "Withdraw" and "Snooze repo" buttons to claim messages
+    #[serde(default)]
+    pub actions: bool,
+    #[serde(flatten)]
+    pub route: Route,
+}
+
+/// Publishes to an ntfy topic
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct NtfyConfig {
+    #[serde(default = "default_ntfy_server")]
+    pub server: String,
+    pub topic: String,
+    /// Access token, for protected topics
+    #[serde(default)]
+    pub token: Option<Secret>,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<Secret>,
+    #[serde(flatten)]
+    pub route: Route,
+}
+
+fn default_ntfy_server() -> String {
+    "https://ntfy.sh".to_string()
+}
+
+/// Sends to a Matrix room
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct MatrixConfig {
+    pub homeserver_url: String,
+    pub access_token: Secret,
+    pub room_id: String,
+    #[serde(flatten)]
+    pub route: Route,
+}
+
+/// POSTs each event as JSON to any URL
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct WebhookConfig {
+    pub url: String,
+    /// Shared secret used to sign payloads with HMAC-SHA256
+    #[serde(default)]
+    pub secret: Option<Secret>,
+    #[serde(flatten)]
+    pub route: Route,
+}
+
+/// Something the user asked for from a notification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    Withdraw {
+        owner: String,
+        repo: String,
+        issue_number: u64,
+    },
+    SnoozeRepo {
+        owner: String,
+        repo: String,
+    },
+}
+
+impl Action {
+    /// Telegram allows at most 64 bytes of callback data
+    const MAX_CALLBACK_LEN: usize = 64;
+
+    fn callback_data(&self) -> Option<String> {
+        let data = match self {
+            Action::Withdraw {
+                owner,
+                repo,
+                issue_number,
+            } => format!("withdraw:{}/{}#{}", owner, repo, issue_number),
+            Action::SnoozeRepo { owner, repo } => format!("snooze:{}/{}", owner, repo),
+        };
+
+        (data.len() <= Self::MAX_CALLBACK_LEN).then_some(data)
+    }
+
+    fn from_callback_data(data: &str) -> Option<Self> {
+        let (kind, target) = data.split_once(':')?;
+        match kind {
+            "withdraw" => {
+                let (repo, number) = target.split_once('#')?;
+                let (owner, repo) = repo.split_once('/')?;
+                Some(Action::Withdraw {
+                    owner: owner.to_string(),
+                    repo: repo.to_string(),
+                    issue_number: number.parse().ok()?,
+                })
+            }
+            "snooze" => {
+                let (owner, repo) = target.split_once('/')?;
+                Some(Action::SnoozeRepo {
+                    owner: owner.to_string(),
+                    repo: repo.to_string(),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The issue an event is about
+#[derive(Debug, Clone, Serialize)]
+pub struct IssueInfo {
+    pub repo: String,
+    pub number: u64,
+    pub title: String,
+    pub url: String,
+    pub labels: Vec<String>,
+    pub comment_url: Option<String>,
+}
+
+impl IssueInfo {
+    pub fn new(owner: &str, repo: &str, issue: &Issue) -> Self {
+        Self {
+            repo: format!("{}/{}", owner, repo),
+            number: issue.number,
+            title: issue.title.clone(),
+            url: issue.html_url.clone(),
+            labels: issue.labels.iter().map(|l| l.name.clone()).collect(),
+            comment_url: None,
+        }
+    }
+
+    pub fn with_comment(mut self, comment_id: Option<u64>) -> Self {
+        self.comment_url = comment_id.map(|id| format!("{}#issuecomment-{}", self.url, id));
+        self
+    }
+}
+
+impl From<&ActiveIssue> for IssueInfo {
+    fn from(active: &ActiveIssue) -> Self {
+        Self {
+            repo: format!("{}/{}", active.repo_owner, active.repo_name),
+            number: active.issue_number,
+            title: active.issue_title.clone(),
+            url: active.issue_url.clone(),
+            labels: active.labels.clone(),
+            comment_url: None,
+        }
+        .with_comment(active.comment_id)
+    }
+}
+
+impl From<&AssignedIssue> for IssueInfo {
+    fn from(assigned: &AssignedIssue) -> Self {
+        Self {
+            repo: format!("{}/{}", assigned.repo_owner, assigned.repo_name),
+            number: assigned.issue_number,
+            title: assigned.issue_title.clone(),
+            url: assigned.issue_url.clone(),
+            labels: Vec::new(),
+            comment_url: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    Claimed {
+        issue: IssueInfo,
+    },
+    /// A wishlist issue was free and we claimed it
+    WishGranted {
+        issue: IssueInfo,
+    },
+    /// An issue matched a `notify` rule, which leaves claiming it to us
+    Matched {
+        issue: IssueInfo,
+        rule: String,
+    },
+    Assigned {
+        issue: IssueInfo,
+    },
+    /// A maintainer's reply handed us the claimed issue without assigning it
+    Accepted {
+        issue: IssueInfo,
+        author: String,
+        url: String,
+        body: String,
+    },
+    TimedOut {
+        issue: IssueInfo,
+    },
+    Rejected {
+        issue: IssueInfo,
+        reason: String,
+    },
+    Reply {
+        issue: IssueInfo,
+        author: String,
+        url: String,
+        body: String,
+    },
+    DeadlineApproaching {
+        issue: IssueInfo,
+        due_at: DateTime<Utc>,
+    },
+    DeadlinePassed {
+        issue: IssueInfo,
+        due_at: DateTime<Utc>,
+    },
+    RateLimitLow {
+        remaining: u32,
+        resets_at: DateTime<Utc>,
+    },
+    RateLimited {
+        retry_at: DateTime<Utc>,
+    },
+    /// More repositories than `failed_repos_alert` failed in one poll
+    ReposFailed {
+        /// Each repository with its error
+        failures: Vec<(String, String)>,
+        polled: usize,
+    },
+    Error {
+        message: String,
+    },
+}
+
+impl Event {
+    /// Short name used to pick which events a backend receives
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Event::Claimed { .. } => "claimed",
+            Event::WishGranted { .. } => "wish_granted",
+            Event::Matched { .. } => "matched",
+            Event::Assigned { .. } => "assigned",
+            Event::Accepted { .. } => "accepted",
+            Event::TimedOut { .. } => "timed_out",
+            Event::Rejected { .. } => "rejected",
+            Event::Reply { .. } => "reply",
+            Event::DeadlineApproaching { .. } => "deadline_approaching",
+            Event::DeadlinePassed { .. } => "deadline_passed",
+            Event::RateLimitLow { .. } => "rate_limit_low",
+            Event::RateLimited { .. } => "rate_limited",
+            Event::ReposFailed { .. } => "repos_failed",
+            Event::Error { .. } => "error",
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        match self {
+            Event::Claimed { .. }
+            | Event::WishGranted { .. }
+            | Event::Matched { .. }
+            | Event::Assigned { .. }
+            | Event::Accepted { .. }
+            | Event::Reply { .. } => Severity::Info,
+            Event::TimedOut { .. }
+            | Event::Rejected { .. }
+            | Event::DeadlineApproaching { .. }
+            | Event::RateLimitLow { .. }
+            | Event::ReposFailed { .. } => Severity::Warning,
+            Event::DeadlinePassed { .. } | Event::RateLimited { .. } | Event::Error { .. } => {
+                Severity::Error
+            }
+        }
+    }
+
+    pub fn issue(&self) -> Option<&IssueInfo> {
+        match self {
+            Event::Claimed { issue }
+            | Event::WishGranted { issue }
+            | Event::Matched { issue, .. }
+            | Event::Assigned { issue }
+            | Event::Accepted { issue, .. }
+            | Event::TimedOut { issue }
+            | Event::Rejected { issue, .. }
+            | Event::Reply { issue, .. }
+            | Event::DeadlineApproaching { issue, .. }
+            | Event::DeadlinePassed { issue, .. } => Some(issue),
+            Event::RateLimitLow { .. }
+            | Event::RateLimited { .. }
+            | Event::ReposFailed { .. }
+            | Event::Error { .. } => None,
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Event::Claimed { .. } => "Assignment requested",
+            Event::WishGranted { .. } => "Wishlist issue up for grabs",
+            Event::Matched { .. } => "Issue found",
+            Event::Assigned { .. } => "Assigned",
+            Event::Accepted { .. } => "Claim accepted",
+            Event::TimedOut { .. } => "Claim timed out",
+            Event::Rejected { .. } => "Claim turned down",
+            Event::Reply { .. } => "New reply",
+            Event::DeadlineApproaching { .. } => "Deadline approaching",
+            Event::DeadlinePassed { .. } => "Deadline passed",
+            Event::RateLimitLow { .. } => "Rate limit low",
+            Event::RateLimited { .. } => "Rate limited",
+            Event::ReposFailed { .. } => "Repositories failing",
+            Event::Error { .. } => "Error",
+        }
+    }
+
+    /// Timestamp the event refers to, other than when it was sent
+    pub fn due_at(&self) -> Option<DateTime<Utc>> {
+        match self {
+            Event::DeadlineApproaching { due_at, .. } | Event::DeadlinePassed { due_at, .. } => {
+                Some(*due_at)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            Event::Claimed { issue } => format!(
+                "Requested assignment on {}#{}: {} ({})",
+                issue.repo, issue.number, issue.title, issue.url
+            ),
+            Event::WishGranted { issue } => format!(
+                "Nobody is assigned to {}#{} any more, requested assignment: {} ({})",
+                issue.repo, issue.number, issue.title, issue.url
+            ),
+            Event::Matched { issue, rule } => format!(
+                "{}#{} matches rule {}: {} ({})",
+                issue.repo, issue.number, rule, issue.title, issue.url
+            ),
+            Event::Assigned { issue } => format!(
+                "You've been assigned to {}#{}: {} ({})",
+                issue.repo, issue.number, issue.title, issue.url
+            ),
+            Event::Accepted {
+                issue,
+                author,
+                url,
+                body,
+            } => format!(
+                "{} handed you {}#{} {}: \"{}\" ({})",
+                author,
+                issue.repo,
+                issue.number,
+                issue.title,
+                excerpt(body),
+                url
+            ),
+            Event::TimedOut { issue } => format!(
+                "Nobody answered your claim on {}#{}: {} ({})",
+                issue.repo, issue.number, issue.title, issue.url
+            ),
+            Event::Rejected { issue, reason } => format!(
+                "Your claim on {}#{} {} was turned down, {} ({})",
+                issue.repo,
+                issue.number,
+                issue.title,
+                excerpt(reason),
+                issue.url
+            ),
+            Event::Reply {
+                issue,
+                author,
+                url,
+                body,
+            } => format!(
+                "{} replied on {}#{} {}: \"{}\" ({})",
+                author,
+                issue.repo,
+                issue.number,
+                issue.title,
+                excerpt(body),
+                url
+            ),
+            Event::DeadlineApproaching { issue, due_at } => format!(
+                "{}#{} is due {} and has no pull request yet: {} ({})",
+                issue.repo,
+                issue.number,
+                due_at.format("%Y-%m-%d %H:%M"),
+                issue.title,
+                issue.url
+            ),
+            Event::DeadlinePassed { issue, due_at } => format!(
+                "{}#{} was due {} and still has no pull request: {} ({})",
+                issue.repo,
+                issue.number,
+                due_at.format("%Y-%m-%d %H:%M"),
+                issue.title,
+                issue.url
+            ),
+            Event::RateLimitLow {
+                remaining,
+                resets_at,
+            } => format!(
+                "GitHub API rate limit is low ({} requests left), pausing until it resets at {}",
+                remaining,
+                resets_at.format("%H:%M UTC")
+            ),
+            Event::RateLimited { retry_at } => format!(
+                "GitHub flagged the bot for making requests too quickly, pausing until {}",
+                retry_at.format("%H:%M UTC")
+            ),
+            Event::ReposFailed { failures, polled } => format!(
+                "{} of {} repositories failed this poll: {}",
+                failures.len(),
+                polled,
+                failures
+                    .iter()
+                    .map(|(repo, error)| format!("{} ({})", repo, excerpt(error)))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Event::Error { message } => format!("The bot ran into an error: {}", message),
+        }
+    }
+}
+
+/// Shorten a comment to something that fits in a notification
+fn excerpt(body: &str) -> String {
+    const MAX_CHARS: usize = 200;
+
+    let body = body.split_whitespace().collect::<Vec<_>>().join(" ");
+    if body.chars().count() <= MAX_CHARS {
+        return body;
+    }
+
+    let mut short: String = body.chars().take(MAX_CHARS).collect();
+    short.push('…');
+    short
+}
+
+/// Delivers an event to one destination
+#[async_trait]
+trait Notifier: Send + Sync {
+    /// Name used in logs when delivery fails
+    fn name(&self) -> &'static str;
+
+    async fn notify(&self, event: &Event, message: &str) -> anyhow::Result<()>;
+}
+
+/// A notifier along with the events routed to it
+struct Routed {
+    route: Route,
+    notifier: Box<dyn Notifier>,
+}
+
+/// Delivers bot events to the user
+pub struct Notifications {
+    notifiers: Vec<Routed>,
+    disabled_events: Vec<String>,
+    telegram: Option<TelegramConfig>,
+    client: reqwest::Client,
+    telegram_offset: Mutex<i64>,
+}
+
+impl Notifications {
+    pub fn new(config: NotificationsConfig, http: &HttpConfig) -> Self {
+        // The GitHub client is built from the same settings first, so this
+        // doesn't fail in practice
+        let client = http
+            .client_builder()
+            .and_then(|builder| Ok(builder.build()?))
+            .unwrap_or_default();
+        let mut notifiers = Vec::new();
+
+        if let Some(slack) = config.slack {
+            notifiers.push(Routed {
+                route: slack.route.clone(),
+                notifier: Box::new(SlackNotifier {
+                    config: slack,
+                    client: client.clone(),
+                }),
+            });
+        }
+
+        if config.desktop {
+            if cfg!(feature = "desktop-notifications") {
+                notifiers.push(Routed {
+                    route: Route {
+                        events: vec!["claimed".to_string(), "assigned".to_string()],
+                        ..Route::default()
+                    },
+                    notifier: Box::new(DesktopNotifier),
+                });
+            } else {
+                warn!(
+                    "Desktop notifications need the desktop-notifications feature, ignoring them"
+                );
+            }
+        }
+
+        if let Some(ref telegram) = config.telegram {
+            notifiers.push(Routed {
+                route: telegram.route.clone(),
+                notifier: Box::new(TelegramNotifier {
+                    config: telegram.clone(),
+                    client: client.clone(),
+                }),
+            });
+        }
+
+        if let Some(ntfy) = config.ntfy {
+            notifiers.push(Routed {
+                route: ntfy.route.clone(),
+                notifier: Box::new(NtfyNotifier {
+                    config: ntfy,
+                    client: client.clone(),
+                }),
+            });
+        }
+
+        if let Some(matrix) = config.matrix {
+            notifiers.push(Routed {
+                route: matrix.route.clone(),
+                notifier: Box::new(MatrixNotifier {
+                    config: matrix,
+                    client: client.clone(),
+                }),
+            });
+        }
+
+        for webhook in config.webhooks {
+            notifiers.push(Routed {
+                route: webhook.route.clone(),
+                notifier: Box::new(WebhookNotifier {
+                    config: webhook,
+                    client: client.clone(),
+                }),
+            });
+        }
+
+        if let Some(discord) = config.discord {
+            notifiers.push(Routed {
+                route: discord.route.clone(),
+                notifier: Box::new(DiscordNotifier {
+                    config: discord,
+                    client: client.clone(),
+                }),
+            });
+        }
+
+        Self {
+            notifiers,
+            disabled_events: config.disabled_events,
+            telegram: config.telegram,
+            client,
+            telegram_offset: Mutex::new(0),
+        }
+    }
+
+    pub async fn send(&self, event: Event) {
+        let message = event.message();
+        info!("{}", message);
+
+        if self.disabled_events.iter().any(|kind| kind == event.kind()) {
+            return;
+        }
+
+        for routed in &self.notifiers {
+            if !routed.route.accepts(&event) {
+                continue;
+            }
+
+            if let Err(e) = routed.notifier.notify(&event, &message).await {
+                warn!(
+                    "Failed to send {} notification: {}",
+                    routed.notifier.name(),
+                    e
+                );
+            }
+        }
+    }
+
+    /// Whether Telegram buttons are enabled and should be polled for
+    pub fn has_actions(&self) -> bool {
+        self.telegram
+            .as_ref()
+            .is_some_and(|telegram| telegram.actions)
+    }
+
+    /// Fetch button presses from Telegram, acknowledging each of them
+    pub async fn poll_actions(&self) -> anyhow::Result<Vec<Action>> {
+        let Some(ref telegram) = self.telegram else {
+            return Ok(Vec::new());
+        };
+
+        let offset = *self.telegram_offset.lock().unwrap();
+        let url = format!(
+            "https://api.telegram.org/bot{}/getUpdates",
+            telegram.bot_token.expose()
+        );
+        let response = self
+            .client
+            .post(&url)
+            .json(&serde_json::json!({
+                "offset": offset,
+                "allowed_updates": ["callback_query"],
+            }))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!(
+                "Telegram getUpdates failed with status: {}",
+                response.status()
+            ));
+        }
+
+        let updates: serde_json::Value = response.json().await?;
+        let mut actions = Vec::new();
+
+        for update in updates["result"].as_array().into_iter().flatten() {
+            if let Some(update_id) = update["update_id"].as_i64() {
+                *self.telegram_offset.lock().unwrap() = update_id + 1;
+            }
+
+            let callback = &update["callback_query"];
+            let Some(callback_id) = callback["id"].as_str() else {
+                continue;
+            };
+
+            // Only take orders from the chat we send notifications to
+            let from_our_chat =
+                callback["message"]["chat"]["id"].as_i64() == Some(telegram.chat_id);
+            let action = callback["data"]
+                .as_str()
+                .and_then(Action::from_callback_data)
+                .filter(|_| from_our_chat);
+
+            let answer = if action.is_some() {
+                "On it!"
+            } else {
+                "Unknown action"
+            };
+            let url = format!(
+                "https://api.telegram.org/bot{}/answerCallbackQuery",
+                telegram.bot_token.expose()
+            );
+            self.client
+                .post(&url)
+                .json(&serde_json::json!({
+                    "callback_query_id": callback_id,
+                    "text": answer,
+                }))
+                .send()
+                .await?;
+
+            actions.extend(action);
+        }
+
+        Ok(actions)
+    }
+}
+
+struct SlackNotifier {
+    config: SlackConfig,
+    client: reqwest::Client,
+}
+
+#[async_trait]
+impl Notifier for SlackNotifier {
+    fn name(&self) -> &'static str {
+        "Slack"
+    }
+
+    async fn notify(&self, _event: &Event, message: &str) -> anyhow::Result<()> {
+        let mut payload = serde_json::json!({ "text": message });
+        if let Some(ref channel) = self.config.channel {
+            payload["channel"] = serde_json::Value::String(channel.clone());
+        }
+
+        let response = self
+            .client
+            .post(self.config.webhook_url.expose())
+            .json(&payload)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!(
+                "Slack webhook failed with status: {}",
+                response.status()
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+struct DiscordNotifier {
+    config: DiscordConfig,
+    client: reqwest::Client,
+}
+
+#[async_trait]
+impl Notifier for DiscordNotifier {
+    fn name(&self) -> &'static str {
+        "Discord"
+    }
+
+    async fn notify(&self, event: &Event, message: &str) -> anyhow::Result<()> {
+        let mut embed = serde_json::json!({
+            "title": event.title(),
+            "description": message,
+            "color": discord_color(event),
+        });
+
+        if let Some(issue) = event.issue() {
+            embed["title"] = serde_json::Value::String(format!(
+                "{}: {}#{} {}",
+                event.title(),
+                issue.repo,
+                issue.number,
+                issue.title
+            ));
+            embed["url"] = serde_json::Value::String(issue.url.clone());
+
+            let mut fields = vec![serde_json::json!({
+                "name": "Repository",
+                "value": issue.repo,
+                "inline": true,
+            })];
+            if !issue.labels.is_empty() {
+                fields.push(serde_json::json!({
+                    "name": "Labels",
+                    "value": issue.labels.join(", "),
+                    "inline": true,
+                }));
+            }
+            if let Some(ref comment_url) = issue.comment_url {
+                fields.push(serde_json::json!({
+                    "name": "Claim comment",
+                    "value": comment_url,
+                }));
+            }
+            embed["fields"] = serde_json::Value::Array(fields);
+        }
+
+        let payload = serde_json::json!({ "embeds": [embed] });
+        let response = self
+            .client
+            .post(self.config.webhook_url.expose())
+            .json(&payload)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!(
+                "Discord webhook failed with status: {}",
+                response.status()
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+fn discord_color(event: &Event) -> u32 {
+    match event.severity() {
+        Severity::Info if matches!(event, Event::Assigned { .. } | Event::Accepted { .. }) => {
+            0x2da44e
+        }
+        Severity::Info => 0x0969da,
+        Severity::Warning => 0xbf8700,
+        Severity::Error => 0xcf222e,
+    }
+}
+
+struct TelegramNotifier {
+    config: TelegramConfig,
+    client: reqwest::Client,
+}
+
+#[async_trait]
+impl Notifier for TelegramNotifier {
+    fn name(&self) -> &'static str {
+        "Telegram"
+    }
+
+    async fn notify(&self, event: &Event, message: &str) -> anyhow::Result<()> {
+        let mut payload = serde_json::json!({
+            "chat_id": self.config.chat_id,
+            "text": message,
+        });
+
+        // Offer buttons while there's still a claim to act on
+        if let (true, Event::Claimed { issue }) = (self.config.actions, event) {
+            if let Some((owner, repo)) = issue.repo.split_once('/') {
+                let withdraw = Action::Withdraw {
+                    owner: owner.to_string(),
+                    repo: repo.to_string(),
+                    issue_number: issue.number,
+                };
+                let snooze = Action::SnoozeRepo {
+                    owner: owner.to_string(),
+                    repo: repo.to_string(),
+                };
+                if let (Some(withdraw), Some(snooze)) =
+                    (withdraw.callback_data(), snooze.callback_data())
+                {
+                    payload["reply_markup"] = serde_json::json!({
+                        "inline_keyboard": [[
+                            { "text": "Withdraw", "callback_data": withdraw },
+                            { "text": "Snooze repo", "callback_data": snooze },
+                        ]]
+                    });
+                }
+            }
+        }
+
+        let url = format!(
+            "https://api.telegram.org/bot{}/sendMessage",
+            self.config.bot_token.expose()
+        );
+        let response = self.client.post(&url).json(&payload).send().await?;
+
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!(
+                "Telegram sendMessage failed with status: {}",
+                response.status()
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+struct NtfyNotifier {
+    config: NtfyConfig,
+    client: reqwest::Client,
+}
+
+#[async_trait]
+impl Notifier for NtfyNotifier {
+    fn name(&self) -> &'static str {
+        "ntfy"
+    }
+
+    async fn notify(&self, event: &Event, message: &str) -> anyhow::Result<()> {
+        let ntfy = &self.config;
+        let url = format!("{}/{}", ntfy.server.trim_end_matches('/'), ntfy.topic);
+        let mut request = self
+            .client
+            .post(&url)
+            .header("Title", event.title())
+            .header("Tags", event.kind())
+            .body(message.to_string());
+
+        if let Some(issue) = event.issue() {
+            request = request.header("Click", issue.url.as_str());
+        }
+
+        if let Some(ref token) = ntfy.token {
+            request = request.bearer_auth(token.expose());
+        } else if let Some(ref username) = ntfy.username {
+            request = request.basic_auth(username, ntfy.password.as_ref().map(Secret::expose));
+        }
+
+        let response = request.send().await?;
+
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!(
+                "ntfy publish failed with status: {}",
+                response.status()
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+struct MatrixNotifier {
+    config: MatrixConfig,
+    client: reqwest::Client,
+}
+
+#[async_trait]
+impl Notifier for MatrixNotifier {
+    fn name(&self) -> &'static str {
+        "Matrix"
+    }
+
+    async fn notify(&self, _event: &Event, message: &str) -> anyhow::Result<()> {
+        let matrix = &self.config;
+
+        // Each message needs a transaction ID that is unique for our access token
+        let txn_id = format!(
+            "gh-issues-bot-{}-{}",
+            Utc::now().timestamp_millis(),
+            thread_rng().gen::<u32>()
+        );
+
+        let mut url = reqwest::Url::parse(&matrix.homeserver_url)?;
+        url.path_segments_mut()
+            .map_err(|_| anyhow::anyhow!("Invalid Matrix homeserver URL"))?
+            .pop_if_empty()
+            .extend([
+                "_matrix",
+                "client",
+                "v3",
+                "rooms",
+                matrix.room_id.as_str(),
+                "send",
+                "m.room.message",
+                txn_id.as_str(),
+            ]);
+
+        let response = self
+            .client
+            .put(url)
+            .bearer_auth(matrix.access_token.expose())
+            .json(&serde_json::json!({ "msgtype": "m.text", "body": message }))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!(
+                "Matrix send failed with status: {}",
+                response.status()
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+struct WebhookNotifier {
+    config: WebhookConfig,
+    client: reqwest::Client,
+}
+
+#[async_trait]
+impl Notifier for WebhookNotifier {
+    fn name(&self) -> &'static str {
+        "webhook"
+    }
+
+    async fn notify(&self, event: &Event, message: &str) -> anyhow::Result<()> {
+        let payload = serde_json::json!({
+            "event": event.kind(),
+            "severity": event.severity(),
+            "title": event.title(),
+            "message": message,
+            "repo": event.issue().map(|issue| issue.repo.as_str()),
+            "issue": event.issue(),
+            "due_at": event.due_at(),
+            "sent_at": Utc::now(),
+        });
+        let body = serde_json::to_vec(&payload)?;
+
+        let mut request = self
+            .client
+            .post(&self.config.url)
+            .header("Content-Type", "application/json")
+            .header("X-Gh-Issues-Bot-Event", event.kind());
+
+        if let Some(ref secret) = self.config.secret {
+            let mut mac = Hmac::<Sha256>::new_from_slice(secret.expose().as_bytes())?;
+            mac.update(&body);
+            let signature = hex::encode(mac.finalize().into_bytes());
+            request = request.header("X-Gh-Issues-Bot-Signature", format!("sha256={}", signature));
+        }
+
+        let response = request.body(body).send().await?;
+
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!(
+                "Webhook {} failed with status: {}",
+                self.config.url,
+                response.status()
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+struct DesktopNotifier;
+
+#[async_trait]
+impl Notifier for DesktopNotifier {
+    fn name(&self) -> &'static str {
+        "desktop"
+    }
+
+    async fn notify(&self, event: &Event, message: &str) -> anyhow::Result<()> {
+        send_desktop(event.title(), message).await
+    }
+}
+
+#[cfg(feature = "desktop-notifications")]
+async fn send_desktop(summary: &str, body: &str) -> anyhow::Result<()> {
+    let summary = summary.to_string();
+    let body = body.to_string();
+
+    // Showing a notification blocks on the platform's notification service
+    tokio::task::spawn_blocking(move || {
+        notify_rust::Notification::new()
+            .appname("gh-issues-bot")
+            .summary(&summary)
+            .body(&body)
+            .show()
+            .map(|_| ())
+    })
+    .await??;
+
+    Ok(())
+}
+
+#[cfg(not(feature = "desktop-notifications"))]
+async fn send_desktop(_summary: &str, _body: &str) -> anyhow::Result<()> {
+    Ok(())
+}
@@ -0,0 +1,110 @@
+//! What `status --output json` and `history --output json` print, for
+//! scripts. Fields may be added in later versions but are never renamed or
+//! removed.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::github::LinkedPullRequest;
+use crate::notes::Note;
+use crate::processed::ProcessedReason;
+use crate::report::ReportRow;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Status {
+    /// The claim waiting for an answer
+    pub pending: Option<PendingClaim>,
+    pub repositories: Vec<RepositoryStatus>,
+    pub processed: ProcessedCounts,
+    /// Issues set aside with `ignore`
+    pub ignored: usize,
+    /// Issues set aside with `snooze` that haven't come back yet
+    pub snoozed: usize,
+    pub in_progress: Vec<InProgress>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PendingClaim {
+    pub repo: String,
+    pub number: u64,
+    pub title: String,
+    pub url: String,
+    pub requested_at: DateTime<Utc>,
+    pub timeout: DateTime<Utc>,
+    /// How long the repository's maintainers usually take to answer
+    pub median_response_days: Option<f64>,
+    pub notes: Vec<Note>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RepositoryStatus {
+    pub repo: String,
+    pub last_polled_at: Option<DateTime<Utc>>,
+    pub last_seen_issue_id: Option<u64>,
+    /// New eligible issues a day
+    pub arrival_rate: Option<f64>,
+    pub last_error: Option<String>,
+    pub last_error_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProcessedCounts {
+    pub total: usize,
+    pub by_reason: BTreeMap<ProcessedReason, usize>,
+    /// Compacted from older months, whose reasons aren't kept
+    pub archived: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InProgress {
+    pub repo: String,
+    pub number: u64,
+    pub title: String,
+    pub url: String,
+    pub assigned_at: DateTime<Utc>,
+    pub due_at: DateTime<Utc>,
+    /// Past due without a pull request
+    pub overdue: bool,
+    pub pull_requests: Vec<LinkedPullRequest>,
+    pub notes: Vec<Note>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct History {
+    /// Every claim, oldest first, as `history export --format json` has them
+    pub claims: Vec<ReportRow>,
+    pub summary: HistorySummary,
+    /// Best assignment rate first
+    pub templates: Vec<TemplateSummary>,
+    pub repositories: Vec<RepositorySummary>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HistorySummary {
+    pub claims: usize,
+    pub assigned: usize,
+    pub completed: usize,
+    pub assignment_rate: f64,
+    pub completion_rate: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TemplateSummary {
+    pub template: String,
+    pub claims: usize,
+    pub assigned: usize,
+    pub assignment_rate: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RepositorySummary {
+    pub repo: String,
+    pub claims: usize,
+    pub assigned: usize,
+    /// Claims that were assigned or turned down rather than ignored
+    pub answered: usize,
+    pub median_response_days: Option<f64>,
+    /// How many of the latest claims timed out without an answer
+    pub ignored_in_a_row: usize,
+}
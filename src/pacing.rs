@@ -0,0 +1,92 @@
+use chrono::{Datelike, Local, Timelike};
+use rand::rngs::StdRng;
+use rand::{thread_rng, Rng, SeedableRng};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Makes the bot's traffic look less like a cron job: longer and more
+/// varied delays, polls sat out now and then, and quiet nights
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct HumanProfile {
+    /// Chance of sitting a poll out, from 0 to 1
+    #[serde(default = "default_skip_chance")]
+    pub skip_chance: f64,
+    /// Longest extra delay before a poll, on top of the jitter
+    #[serde(default = "default_max_delay_secs")]
+    pub max_delay_secs: u64,
+    /// Local hour the active part of the day starts around
+    #[serde(default = "default_day_start_hour")]
+    pub day_start_hour: u32,
+    /// Local hour the active part of the day ends around
+    #[serde(default = "default_day_end_hour")]
+    pub day_end_hour: u32,
+    /// Share of polls that still happen outside the active hours
+    #[serde(default = "default_night_activity")]
+    pub night_activity: f64,
+}
+
+fn default_skip_chance() -> f64 {
+    0.1
+}
+
+fn default_max_delay_secs() -> u64 {
+    300
+}
+
+fn default_day_start_hour() -> u32 {
+    8
+}
+
+fn default_day_end_hour() -> u32 {
+    23
+}
+
+fn default_night_activity() -> f64 {
+    0.1
+}
+
+/// The longest the active hours move around from day to day
+const DAY_DRIFT_MINUTES: i64 = 60;
+
+impl HumanProfile {
+    /// How active a person would be right now, from 0 to 1. The day's start
+    /// and end drift by up to an hour, the same way all day long.
+    fn activity(&self) -> f64 {
+        let now = Local::now();
+        let mut day = StdRng::seed_from_u64(now.num_days_from_ce() as u64);
+        let drift = |rng: &mut StdRng| rng.gen_range(-DAY_DRIFT_MINUTES..=DAY_DRIFT_MINUTES);
+
+        let minute = (now.hour() * 60 + now.minute()) as i64;
+        let start = self.day_start_hour as i64 * 60 + drift(&mut day);
+        let end = self.day_end_hour as i64 * 60 + drift(&mut day);
+        let active = if start <= end {
+            (start..end).contains(&minute)
+        } else {
+            // A day that runs past midnight
+            minute >= start || minute < end
+        };
+
+        if active {
+            1.0
+        } else {
+            self.night_activity
+        }
+    }
+}
+
+/// How long to wait before the next poll, or None to sit it out
+pub fn delay_before_poll(jitter_secs: u64, profile: Option<&HumanProfile>) -> Option<Duration> {
+    let mut rng = thread_rng();
+    let mut delay = rng.gen_range(0..=jitter_secs);
+
+    if let Some(profile) = profile {
+        if rng.gen::<f64>() < profile.skip_chance || rng.gen::<f64>() >= profile.activity() {
+            return None;
+        }
+        delay += rng.gen_range(0..=profile.max_delay_secs);
+    }
+
+    Some(Duration::from_secs(delay))
+}
@@ -1,21 +1,79 @@
 use anyhow::{Context, Result};
 use async_trait::async_trait;
-use std::collections::HashSet;
+use chrono::Utc;
+use fs2::FileExt;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+use std::io::{Read, Seek, Write};
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 use tokio::fs;
+use tokio::io::AsyncWriteExt;
+use tracing::{info, warn};
 
-use crate::bot::ActiveIssue;
+use crate::audit::AuditEntry;
+use crate::bot::{ActiveIssue, AssignedIssue, ClaimIntent};
+use crate::crypto::{self, StateKey};
+use crate::cursor::RepoCursors;
+use crate::health::Heartbeat;
+use crate::history::HistoryEntry;
+use crate::ignored::{IgnoredIssues, SnoozedIssues};
+use crate::notes::Notes;
+use crate::processed::{self, ProcessedArchive, ProcessedIssue, ProcessedIssues};
+use crate::queue::CandidateQueue;
+use crate::schema::{self, StateFile};
 
 #[async_trait]
-pub trait Persistence {
+pub trait Persistence: Send + Sync {
     async fn save_active_issue(&self, issue: &ActiveIssue) -> Result<()>;
     async fn load_active_issue(&self) -> Result<Option<ActiveIssue>>;
-    async fn save_processed_issues(&self, issues: &HashSet<u64>) -> Result<()>;
-    async fn load_processed_issues(&self) -> Result<HashSet<u64>>;
+    async fn clear_active_issue(&self) -> Result<()>;
+    async fn save_claim_intent(&self, intent: &ClaimIntent) -> Result<()>;
+    async fn load_claim_intent(&self) -> Result<Option<ClaimIntent>>;
+    async fn clear_claim_intent(&self) -> Result<()>;
+    async fn save_processed_issues(&self, issues: &ProcessedIssues) -> Result<()>;
+    async fn load_processed_issues(&self) -> Result<ProcessedIssues>;
+    async fn save_repo_cursors(&self, cursors: &RepoCursors) -> Result<()>;
+    async fn load_repo_cursors(&self) -> Result<RepoCursors>;
+    async fn save_assigned_issues(&self, issues: &[AssignedIssue]) -> Result<()>;
+    async fn load_assigned_issues(&self) -> Result<Vec<AssignedIssue>>;
+    async fn save_history(&self, history: &[HistoryEntry]) -> Result<()>;
+    async fn load_history(&self) -> Result<Vec<HistoryEntry>>;
+    async fn save_candidates(&self, queue: &CandidateQueue) -> Result<()>;
+    async fn load_candidates(&self) -> Result<CandidateQueue>;
+    async fn save_notes(&self, notes: &Notes) -> Result<()>;
+    async fn load_notes(&self) -> Result<Notes>;
+    async fn save_ignored_issues(&self, issues: &IgnoredIssues) -> Result<()>;
+    async fn load_ignored_issues(&self) -> Result<IgnoredIssues>;
+    async fn save_snoozed_issues(&self, issues: &SnoozedIssues) -> Result<()>;
+    async fn load_snoozed_issues(&self) -> Result<SnoozedIssues>;
+    async fn append_audit(&self, entry: &AuditEntry) -> Result<()>;
+    /// Make sure state can still be written
+    async fn check(&self) -> Result<()>;
+    async fn save_heartbeat(&self, heartbeat: &Heartbeat) -> Result<()>;
+    async fn load_heartbeat(&self) -> Result<Option<Heartbeat>>;
+    /// Write out anything saved but held back. Backends that write every
+    /// save straight away have nothing to do.
+    async fn flush(&self) -> Result<()> {
+        Ok(())
+    }
 }
 
+/// Name the processed issue archive goes by among the months
+const ARCHIVE: &str = "archive";
+
 pub struct FilePersistence {
     data_dir: PathBuf,
+    key: Option<StateKey>,
+    /// A hash of each processed issues file as last read or written, so
+    /// saves only touch the months that changed
+    processed_written: Mutex<HashMap<String, u64>>,
+}
+
+/// Keeps other instances out of a data directory until dropped
+pub struct DataDirLock {
+    _file: std::fs::File,
 }
 
 impl FilePersistence {
@@ -27,25 +85,237 @@ impl FilePersistence {
             fs::create_dir_all(&data_dir).await?;
         }
 
-        Ok(Self { data_dir })
+        let key = StateKey::from_env()?;
+
+        Ok(Self {
+            data_dir,
+            key,
+            processed_written: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Encrypt state on its way to disk when a state key is set
+    fn seal(&self, content: String) -> Result<String> {
+        match self.key {
+            Some(ref key) => key.seal(&content),
+            None => Ok(content),
+        }
+    }
+
+    fn open(&self, content: String) -> Result<String> {
+        match self.key {
+            Some(ref key) => key.open(&content),
+            None => crypto::ensure_plaintext(&content).map(|_| content),
+        }
+    }
+
+    /// Take exclusive ownership of the data directory. Two bots sharing it
+    /// would comment twice and overwrite each other's state.
+    pub fn lock(&self) -> Result<DataDirLock> {
+        let path = self.lock_path();
+        let mut file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .with_context(|| format!("Failed to open lock file {}", path.display()))?;
+
+        if file.try_lock_exclusive().is_err() {
+            let mut pid = String::new();
+            file.read_to_string(&mut pid).ok();
+            let holder = match pid.trim() {
+                "" => String::new(),
+                pid => format!(" (pid {})", pid),
+            };
+            return Err(anyhow::anyhow!(
+                "Another gh-issues-bot instance{} is already using {}",
+                holder,
+                self.data_dir.display()
+            ));
+        }
+
+        // Leave our pid behind so the next instance can tell who holds the lock
+        file.set_len(0)?;
+        file.rewind()?;
+        write!(file, "{}", std::process::id())?;
+
+        Ok(DataDirLock { _file: file })
+    }
+
+    /// Write one of the processed issues files, unless it's unchanged
+    async fn write_processed<T: serde::Serialize>(
+        &self,
+        name: &str,
+        path: &Path,
+        data: &T,
+    ) -> Result<()> {
+        let content = schema::encode(data)?;
+        let hash = content_hash(&content);
+        if self.processed_written.lock().unwrap().get(name) == Some(&hash) {
+            return Ok(());
+        }
+
+        let content = self.seal(content)?;
+        backup(path).await;
+        write_atomic(path, &content)
+            .await
+            .with_context(|| format!("Failed to write processed issues to {}", path.display()))?;
+
+        self.processed_written
+            .lock()
+            .unwrap()
+            .insert(name.to_string(), hash);
+
+        Ok(())
+    }
+
+    async fn read_processed(&self, path: &Path) -> Result<Option<String>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = fs::read_to_string(path)
+            .await
+            .with_context(|| format!("Failed to read processed issues from {}", path.display()))?;
+
+        Ok(Some(self.open(content)?))
+    }
+
+    /// The months there are processed issues files for, oldest first
+    async fn processed_months(&self) -> Result<Vec<String>> {
+        let dir = self.processed_dir();
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut months = Vec::new();
+        let mut entries = fs::read_dir(&dir)
+            .await
+            .with_context(|| format!("Failed to list {}", dir.display()))?;
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name();
+            let Some(month) = name.to_str().and_then(|name| name.strip_suffix(".json")) else {
+                continue;
+            };
+            if chrono::NaiveDate::parse_from_str(&format!("{}-01", month), "%Y-%m-%d").is_ok() {
+                months.push(month.to_string());
+            }
+        }
+        months.sort();
+
+        Ok(months)
+    }
+
+    /// Move processed issues from the single file older versions kept them
+    /// in to one file per month
+    async fn split_processed_issues(&self) -> Result<()> {
+        let legacy_path = self.legacy_processed_issues_path();
+        let content = fs::read_to_string(&legacy_path).await.with_context(|| {
+            format!(
+                "Failed to read processed issues from {}",
+                legacy_path.display()
+            )
+        })?;
+        let content = self.open(content)?;
+        let records: HashMap<String, ProcessedIssue> =
+            schema::decode(StateFile::ProcessedIssues, &content)
+                .with_context(|| "Failed to parse processed issues JSON")?;
+
+        self.save_processed_issues(&ProcessedIssues::new(records, ProcessedArchive::default()))
+            .await?;
+        self.processed_written.lock().unwrap().clear();
+
+        fs::rename(&legacy_path, with_suffix(&legacy_path, ".bak"))
+            .await
+            .with_context(|| format!("Failed to move {} aside", legacy_path.display()))?;
+        info!(
+            "Split {} into one file per month in {}",
+            legacy_path.display(),
+            self.processed_dir().display()
+        );
+
+        Ok(())
+    }
+
+    fn lock_path(&self) -> PathBuf {
+        self.data_dir.join("bot.lock")
     }
 
     fn active_issue_path(&self) -> PathBuf {
         self.data_dir.join("active_issue.json")
     }
 
-    fn processed_issues_path(&self) -> PathBuf {
+    fn claim_intent_path(&self) -> PathBuf {
+        self.data_dir.join("claim_intent.json")
+    }
+
+    /// Where processed issues were kept before they were split by month
+    fn legacy_processed_issues_path(&self) -> PathBuf {
         self.data_dir.join("processed_issues.json")
     }
+
+    fn processed_dir(&self) -> PathBuf {
+        self.data_dir.join("processed")
+    }
+
+    fn processed_month_path(&self, month: &str) -> PathBuf {
+        self.processed_dir().join(format!("{}.json", month))
+    }
+
+    fn processed_archive_path(&self) -> PathBuf {
+        self.processed_dir().join("archive.json")
+    }
+
+    fn repo_cursors_path(&self) -> PathBuf {
+        self.data_dir.join("repo_cursors.json")
+    }
+
+    fn assigned_issues_path(&self) -> PathBuf {
+        self.data_dir.join("assigned_issues.json")
+    }
+
+    fn history_path(&self) -> PathBuf {
+        self.data_dir.join("history.json")
+    }
+
+    fn candidates_path(&self) -> PathBuf {
+        self.data_dir.join("candidates.json")
+    }
+
+    fn notes_path(&self) -> PathBuf {
+        self.data_dir.join("notes.json")
+    }
+
+    fn ignored_issues_path(&self) -> PathBuf {
+        self.data_dir.join("ignored_issues.json")
+    }
+
+    fn snoozed_issues_path(&self) -> PathBuf {
+        self.data_dir.join("snoozed_issues.json")
+    }
+
+    fn audit_log_path(&self) -> PathBuf {
+        self.data_dir.join("audit.jsonl")
+    }
+
+    fn heartbeat_path(&self) -> PathBuf {
+        self.data_dir.join("heartbeat.json")
+    }
+
+    fn health_probe_path(&self) -> PathBuf {
+        self.data_dir.join(".health_probe")
+    }
 }
 
 #[async_trait]
 impl Persistence for FilePersistence {
     async fn save_active_issue(&self, issue: &ActiveIssue) -> Result<()> {
-        let content = serde_json::to_string_pretty(issue)?;
+        let content = self.seal(schema::encode(issue)?)?;
         let path = self.active_issue_path();
 
-        fs::write(&path, content)
+        backup(&path).await;
+        write_atomic(&path, &content)
             .await
             .with_context(|| format!("Failed to write active issue to {}", path.display()))?;
 
@@ -62,38 +332,485 @@ impl Persistence for FilePersistence {
         let content = fs::read_to_string(&path)
             .await
             .with_context(|| format!("Failed to read active issue from {}", path.display()))?;
+        let content = self.open(content)?;
 
-        let issue: ActiveIssue =
-            serde_json::from_str(&content).with_context(|| "Failed to parse active issue JSON")?;
+        let issue: ActiveIssue = schema::decode(StateFile::ActiveIssue, &content)
+            .with_context(|| "Failed to parse active issue JSON")?;
 
         Ok(Some(issue))
     }
 
-    async fn save_processed_issues(&self, issues: &HashSet<u64>) -> Result<()> {
-        let content = serde_json::to_string_pretty(issues)?;
-        let path = self.processed_issues_path();
+    async fn clear_active_issue(&self) -> Result<()> {
+        let path = self.active_issue_path();
 
-        fs::write(&path, content)
+        if path.exists() {
+            fs::remove_file(&path)
+                .await
+                .with_context(|| format!("Failed to remove active issue {}", path.display()))?;
+        }
+
+        Ok(())
+    }
+
+    async fn save_claim_intent(&self, intent: &ClaimIntent) -> Result<()> {
+        let content = self.seal(schema::encode(intent)?)?;
+        let path = self.claim_intent_path();
+
+        write_atomic(&path, &content)
             .await
-            .with_context(|| format!("Failed to write processed issues to {}", path.display()))?;
+            .with_context(|| format!("Failed to write claim intent to {}", path.display()))?;
 
         Ok(())
     }
 
-    async fn load_processed_issues(&self) -> Result<HashSet<u64>> {
-        let path = self.processed_issues_path();
+    async fn load_claim_intent(&self) -> Result<Option<ClaimIntent>> {
+        let path = self.claim_intent_path();
 
         if !path.exists() {
-            return Ok(HashSet::new());
+            return Ok(None);
         }
 
         let content = fs::read_to_string(&path)
             .await
-            .with_context(|| format!("Failed to read processed issues from {}", path.display()))?;
+            .with_context(|| format!("Failed to read claim intent from {}", path.display()))?;
+        let content = self.open(content)?;
+
+        let intent: ClaimIntent = schema::decode(StateFile::ClaimIntent, &content)
+            .with_context(|| "Failed to parse claim intent JSON")?;
+
+        Ok(Some(intent))
+    }
+
+    async fn clear_claim_intent(&self) -> Result<()> {
+        let path = self.claim_intent_path();
+
+        if path.exists() {
+            fs::remove_file(&path)
+                .await
+                .with_context(|| format!("Failed to remove claim intent {}", path.display()))?;
+        }
+
+        Ok(())
+    }
+
+    async fn save_processed_issues(&self, issues: &ProcessedIssues) -> Result<()> {
+        fs::create_dir_all(self.processed_dir()).await?;
+
+        let mut months: BTreeMap<String, BTreeMap<&String, &ProcessedIssue>> = BTreeMap::new();
+        for (key, record) in issues.records() {
+            months
+                .entry(record.month())
+                .or_default()
+                .insert(key, record);
+        }
+
+        // Months whose records all moved on need emptying, unless they were
+        // compacted and are only kept for the record now
+        let archive = issues.archive();
+        for month in self.processed_written.lock().unwrap().keys() {
+            if month != ARCHIVE && !archive.months.contains(month) {
+                months.entry(month.clone()).or_default();
+            }
+        }
+
+        for (month, records) in &months {
+            self.write_processed(month, &self.processed_month_path(month), records)
+                .await?;
+        }
+        self.write_processed(ARCHIVE, &self.processed_archive_path(), archive)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn load_processed_issues(&self) -> Result<ProcessedIssues> {
+        if self.legacy_processed_issues_path().exists() {
+            self.split_processed_issues().await?;
+        }
+
+        let archive_path = self.processed_archive_path();
+        let mut archive: ProcessedArchive = match self.read_processed(&archive_path).await? {
+            Some(content) => schema::decode(StateFile::ProcessedArchive, &content)
+                .with_context(|| "Failed to parse processed issue archive JSON")?,
+            None => ProcessedArchive::default(),
+        };
+        self.processed_written.lock().unwrap().insert(
+            ARCHIVE.to_string(),
+            content_hash(&schema::encode(&archive)?),
+        );
+
+        let cutoff = processed::compaction_cutoff(Utc::now());
+        let mut records: HashMap<String, ProcessedIssue> = HashMap::new();
+        for month in self.processed_months().await? {
+            // Compacted months are only read by hand
+            if archive.months.contains(&month) {
+                continue;
+            }
+
+            let path = self.processed_month_path(&month);
+            let Some(content) = self.read_processed(&path).await? else {
+                continue;
+            };
+            let chunk: HashMap<String, ProcessedIssue> =
+                schema::decode(StateFile::ProcessedIssues, &content).with_context(|| {
+                    format!(
+                        "Failed to parse processed issues JSON in {}",
+                        path.display()
+                    )
+                })?;
+
+            if month < cutoff {
+                for (key, record) in &chunk {
+                    archive.add(key, record);
+                }
+                // Even a month with nothing left in it is done with
+                if !archive.months.contains(&month) {
+                    archive.months.push(month);
+                }
+                continue;
+            }
+
+            // Hashed the way it would be written, so an untouched month
+            // isn't rewritten
+            let sorted: BTreeMap<&String, &ProcessedIssue> = chunk.iter().collect();
+            let hash = content_hash(&schema::encode(&sorted)?);
+            self.processed_written.lock().unwrap().insert(month, hash);
+            for (key, record) in chunk {
+                // An issue whose reason changed later shows up in two months
+                match records.get(&key) {
+                    Some(newer) if newer.processed_at >= record.processed_at => {}
+                    _ => {
+                        records.insert(key, record);
+                    }
+                }
+            }
+        }
+        archive.finish();
+
+        let issues = ProcessedIssues::new(records, archive);
+        // Write out anything compacted just now
+        self.write_processed(ARCHIVE, &archive_path, issues.archive())
+            .await?;
+
+        Ok(issues)
+    }
+
+    async fn save_repo_cursors(&self, cursors: &RepoCursors) -> Result<()> {
+        let content = self.seal(schema::encode(cursors)?)?;
+        let path = self.repo_cursors_path();
+
+        backup(&path).await;
+        write_atomic(&path, &content)
+            .await
+            .with_context(|| format!("Failed to write repo cursors to {}", path.display()))?;
+
+        Ok(())
+    }
+
+    async fn load_repo_cursors(&self) -> Result<RepoCursors> {
+        let path = self.repo_cursors_path();
+
+        if !path.exists() {
+            return Ok(RepoCursors::new());
+        }
+
+        let content = fs::read_to_string(&path)
+            .await
+            .with_context(|| format!("Failed to read repo cursors from {}", path.display()))?;
+        let content = self.open(content)?;
+
+        let cursors: RepoCursors = schema::decode(StateFile::RepoCursors, &content)
+            .with_context(|| "Failed to parse repo cursors JSON")?;
+
+        Ok(cursors)
+    }
+
+    async fn save_assigned_issues(&self, issues: &[AssignedIssue]) -> Result<()> {
+        let content = self.seal(schema::encode(issues)?)?;
+        let path = self.assigned_issues_path();
+
+        backup(&path).await;
+        write_atomic(&path, &content)
+            .await
+            .with_context(|| format!("Failed to write assigned issues to {}", path.display()))?;
 
-        let issues: HashSet<u64> = serde_json::from_str(&content)
-            .with_context(|| "Failed to parse processed issues JSON")?;
+        Ok(())
+    }
+
+    async fn load_assigned_issues(&self) -> Result<Vec<AssignedIssue>> {
+        let path = self.assigned_issues_path();
+
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let content = fs::read_to_string(&path)
+            .await
+            .with_context(|| format!("Failed to read assigned issues from {}", path.display()))?;
+        let content = self.open(content)?;
+
+        let issues: Vec<AssignedIssue> = schema::decode(StateFile::AssignedIssues, &content)
+            .with_context(|| "Failed to parse assigned issues JSON")?;
+
+        Ok(issues)
+    }
+
+    async fn save_history(&self, history: &[HistoryEntry]) -> Result<()> {
+        let content = self.seal(schema::encode(history)?)?;
+        let path = self.history_path();
+
+        backup(&path).await;
+        write_atomic(&path, &content)
+            .await
+            .with_context(|| format!("Failed to write history to {}", path.display()))?;
+
+        Ok(())
+    }
+
+    async fn load_history(&self) -> Result<Vec<HistoryEntry>> {
+        let path = self.history_path();
+
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let content = fs::read_to_string(&path)
+            .await
+            .with_context(|| format!("Failed to read history from {}", path.display()))?;
+        let content = self.open(content)?;
+
+        let history: Vec<HistoryEntry> = schema::decode(StateFile::History, &content)
+            .with_context(|| "Failed to parse history JSON")?;
+
+        Ok(history)
+    }
+
+    async fn save_candidates(&self, queue: &CandidateQueue) -> Result<()> {
+        let content = self.seal(schema::encode(queue)?)?;
+        let path = self.candidates_path();
+
+        backup(&path).await;
+        write_atomic(&path, &content)
+            .await
+            .with_context(|| format!("Failed to write candidates to {}", path.display()))?;
+
+        Ok(())
+    }
+
+    async fn load_candidates(&self) -> Result<CandidateQueue> {
+        let path = self.candidates_path();
+
+        if !path.exists() {
+            return Ok(CandidateQueue::default());
+        }
+
+        let content = fs::read_to_string(&path)
+            .await
+            .with_context(|| format!("Failed to read candidates from {}", path.display()))?;
+        let content = self.open(content)?;
+
+        let queue: CandidateQueue = schema::decode(StateFile::Candidates, &content)
+            .with_context(|| "Failed to parse candidates JSON")?;
+
+        Ok(queue)
+    }
+
+    async fn save_notes(&self, notes: &Notes) -> Result<()> {
+        let content = self.seal(schema::encode(notes)?)?;
+        let path = self.notes_path();
+
+        backup(&path).await;
+        write_atomic(&path, &content)
+            .await
+            .with_context(|| format!("Failed to write notes to {}", path.display()))?;
+
+        Ok(())
+    }
+
+    async fn load_notes(&self) -> Result<Notes> {
+        let path = self.notes_path();
+
+        if !path.exists() {
+            return Ok(Notes::default());
+        }
+
+        let content = fs::read_to_string(&path)
+            .await
+            .with_context(|| format!("Failed to read notes from {}", path.display()))?;
+        let content = self.open(content)?;
+
+        let notes: Notes = schema::decode(StateFile::Notes, &content)
+            .with_context(|| "Failed to parse notes JSON")?;
+
+        Ok(notes)
+    }
+
+    async fn save_ignored_issues(&self, issues: &IgnoredIssues) -> Result<()> {
+        let content = self.seal(schema::encode(issues)?)?;
+        let path = self.ignored_issues_path();
+
+        backup(&path).await;
+        write_atomic(&path, &content)
+            .await
+            .with_context(|| format!("Failed to write ignored issues to {}", path.display()))?;
+
+        Ok(())
+    }
+
+    async fn load_ignored_issues(&self) -> Result<IgnoredIssues> {
+        let path = self.ignored_issues_path();
+
+        if !path.exists() {
+            return Ok(IgnoredIssues::default());
+        }
+
+        let content = fs::read_to_string(&path)
+            .await
+            .with_context(|| format!("Failed to read ignored issues from {}", path.display()))?;
+        let content = self.open(content)?;
+
+        let issues: IgnoredIssues = schema::decode(StateFile::IgnoredIssues, &content)
+            .with_context(|| "Failed to parse ignored issues JSON")?;
 
         Ok(issues)
     }
+
+    async fn save_snoozed_issues(&self, issues: &SnoozedIssues) -> Result<()> {
+        let content = self.seal(schema::encode(issues)?)?;
+        let path = self.snoozed_issues_path();
+
+        backup(&path).await;
+        write_atomic(&path, &content)
+            .await
+            .with_context(|| format!("Failed to write snoozed issues to {}", path.display()))?;
+
+        Ok(())
+    }
+
+    async fn load_snoozed_issues(&self) -> Result<SnoozedIssues> {
+        let path = self.snoozed_issues_path();
+
+        if !path.exists() {
+            return Ok(SnoozedIssues::default());
+        }
+
+        let content = fs::read_to_string(&path)
+            .await
+            .with_context(|| format!("Failed to read snoozed issues from {}", path.display()))?;
+        let content = self.open(content)?;
+
+        let issues: SnoozedIssues = schema::decode(StateFile::SnoozedIssues, &content)
+            .with_context(|| "Failed to parse snoozed issues JSON")?;
+
+        Ok(issues)
+    }
+
+    async fn append_audit(&self, entry: &AuditEntry) -> Result<()> {
+        let mut line = self.seal(serde_json::to_string(entry)?)?;
+        line.push('\n');
+        let path = self.audit_log_path();
+
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .await
+            .with_context(|| format!("Failed to open audit log {}", path.display()))?;
+        file.write_all(line.as_bytes())
+            .await
+            .with_context(|| format!("Failed to write to audit log {}", path.display()))?;
+
+        Ok(())
+    }
+
+    async fn check(&self) -> Result<()> {
+        let path = self.health_probe_path();
+
+        fs::write(&path, Utc::now().to_rfc3339())
+            .await
+            .with_context(|| format!("Failed to write to {}", path.display()))?;
+        fs::remove_file(&path)
+            .await
+            .with_context(|| format!("Failed to remove {}", path.display()))?;
+
+        Ok(())
+    }
+
+    async fn save_heartbeat(&self, heartbeat: &Heartbeat) -> Result<()> {
+        let content = serde_json::to_string_pretty(heartbeat)?;
+        let path = self.heartbeat_path();
+
+        write_atomic(&path, &content)
+            .await
+            .with_context(|| format!("Failed to write heartbeat to {}", path.display()))?;
+
+        Ok(())
+    }
+
+    async fn load_heartbeat(&self) -> Result<Option<Heartbeat>> {
+        let path = self.heartbeat_path();
+
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = fs::read_to_string(&path)
+            .await
+            .with_context(|| format!("Failed to read heartbeat from {}", path.display()))?;
+
+        let heartbeat: Heartbeat =
+            serde_json::from_str(&content).with_context(|| "Failed to parse heartbeat JSON")?;
+
+        Ok(Some(heartbeat))
+    }
+}
+
+/// Replace a file so that a crash leaves either the old or the new content
+pub(crate) async fn write_atomic(path: &Path, content: &str) -> std::io::Result<()> {
+    let tmp_path = with_suffix(path, ".tmp");
+
+    let mut file = fs::File::create(&tmp_path).await?;
+    file.write_all(content.as_bytes()).await?;
+    file.sync_all().await?;
+    drop(file);
+
+    fs::rename(&tmp_path, path).await?;
+
+    // Make the rename itself durable
+    #[cfg(unix)]
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::File::open(dir).await?.sync_all().await?;
+    }
+
+    Ok(())
+}
+
+/// Keep the previous version of a state file as `<name>.bak`, unless it's
+/// corrupt and the existing backup is the better copy
+async fn backup(path: &Path) {
+    let Ok(previous) = fs::read_to_string(path).await else {
+        return;
+    };
+
+    if serde_json::from_str::<serde_json::Value>(&previous).is_err() {
+        warn!("{} is corrupt, keeping the previous backup", path.display());
+        return;
+    }
+
+    let backup_path = with_suffix(path, ".bak");
+    if let Err(e) = write_atomic(&backup_path, &previous).await {
+        warn!("Failed to back up {}: {}", path.display(), e);
+    }
+}
+
+fn content_hash(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
 }
@@ -0,0 +1,403 @@
+//! Issues formatted for reading in a terminal, so candidates can be judged
+//! without opening a browser. Covers the markdown issues are usually written
+//! in, not all of CommonMark.
+
+use std::io::IsTerminal;
+
+/// How to print a preview
+#[derive(Debug, Clone, Copy)]
+pub struct Terminal {
+    pub width: usize,
+    /// Whether to use ANSI colors and bold text
+    pub ansi: bool,
+}
+
+impl Terminal {
+    /// Colors only when stdout is a terminal and `NO_COLOR` isn't set, the
+    /// width from `COLUMNS`
+    pub fn detect() -> Self {
+        let width = std::env::var("COLUMNS")
+            .ok()
+            .and_then(|columns| columns.parse().ok())
+            .unwrap_or(80);
+        Self {
+            width: usize::max(width, 40),
+            ansi: std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Style {
+    bold: bool,
+    italic: bool,
+    code: bool,
+    link: bool,
+    dim: bool,
+}
+
+impl Style {
+    fn paint(self, text: &str, ansi: bool) -> String {
+        if !ansi || self == Style::default() {
+            return text.to_string();
+        }
+
+        let mut codes = Vec::new();
+        if self.bold {
+            codes.push("1");
+        }
+        if self.dim {
+            codes.push("2");
+        }
+        if self.italic {
+            codes.push("3");
+        }
+        if self.link {
+            codes.push("4");
+        }
+        if self.code {
+            codes.push("36");
+        }
+        format!("\x1b[{}m{}\x1b[0m", codes.join(";"), text)
+    }
+}
+
+/// An issue with a header of labels and details above its rendered body
+pub fn render(
+    title: &str,
+    labels: &[String],
+    details: &[String],
+    body: Option<&str>,
+    terminal: Terminal,
+) -> String {
+    let bold = Style {
+        bold: true,
+        ..Style::default()
+    };
+    let dim = Style {
+        dim: true,
+        ..Style::default()
+    };
+
+    let mut lines = wrap(&[(title.to_string(), bold)], "", "", terminal);
+    if !labels.is_empty() {
+        let labels: Vec<String> = labels.iter().map(|label| format!("[{}]", label)).collect();
+        lines.extend(wrap(
+            &[(labels.join(" "), Style::default())],
+            "",
+            "",
+            terminal,
+        ));
+    }
+    for detail in details {
+        lines.extend(wrap(&[(detail.clone(), dim)], "", "  ", terminal));
+    }
+    lines.push(dim.paint(&"─".repeat(terminal.width), terminal.ansi));
+
+    match body.map(str::trim).filter(|body| !body.is_empty()) {
+        Some(body) => lines.extend(markdown(body, terminal)),
+        None => lines.push(dim.paint("No description", terminal.ansi)),
+    }
+
+    lines.join("\n")
+}
+
+/// Format markdown line by line: headings, lists, quotes, rules and fenced
+/// code, with emphasis, code spans and links inside them
+fn markdown(text: &str, terminal: Terminal) -> Vec<String> {
+    let code_style = Style {
+        code: true,
+        ..Style::default()
+    };
+    let dim = Style {
+        dim: true,
+        ..Style::default()
+    };
+
+    let mut lines = Vec::new();
+    let mut in_code = false;
+    let mut blank = false;
+
+    for line in strip_comments(text).lines() {
+        let trimmed = line.trim();
+
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            lines.push(format!("    {}", code_style.paint(line, terminal.ansi)));
+            continue;
+        }
+
+        // Collapse the blank lines issue templates leave behind
+        if trimmed.is_empty() {
+            if !blank && !lines.is_empty() {
+                lines.push(String::new());
+            }
+            blank = true;
+            continue;
+        }
+        blank = false;
+
+        if let Some(heading) = heading(trimmed) {
+            let segments: Vec<(String, Style)> = inline(heading)
+                .into_iter()
+                .map(|(text, style)| {
+                    (
+                        text,
+                        Style {
+                            bold: true,
+                            ..style
+                        },
+                    )
+                })
+                .collect();
+            lines.extend(wrap(&segments, "", "", terminal));
+        } else if is_rule(trimmed) {
+            lines.push(dim.paint(&"─".repeat(terminal.width / 2), terminal.ansi));
+        } else if let Some(quote) = trimmed.strip_prefix('>') {
+            let bar = dim.paint("│ ", terminal.ansi);
+            lines.extend(wrap(&inline(quote.trim_start()), &bar, &bar, terminal));
+        } else if let Some((marker, item)) = list_item(line) {
+            let indent = " ".repeat(line.len() - line.trim_start().len());
+            let first = format!("{}{} ", indent, marker);
+            let rest = " ".repeat(first.chars().count());
+            lines.extend(wrap(&inline(item), &first, &rest, terminal));
+        } else {
+            lines.extend(wrap(&inline(trimmed), "", "", terminal));
+        }
+    }
+
+    while lines.last().is_some_and(|line| line.is_empty()) {
+        lines.pop();
+    }
+    lines
+}
+
+/// Drop `<!-- -->` comments, which issue templates are full of
+fn strip_comments(text: &str) -> String {
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("<!--") {
+        out.push_str(&rest[..start]);
+        match rest[start..].find("-->") {
+            Some(end) => rest = &rest[start + end + 3..],
+            None => return out,
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn heading(line: &str) -> Option<&str> {
+    let hashes = line.chars().take_while(|c| *c == '#').count();
+    if (1..=6).contains(&hashes) && line[hashes..].starts_with(' ') {
+        Some(line[hashes..].trim().trim_end_matches('#').trim_end())
+    } else {
+        None
+    }
+}
+
+fn is_rule(line: &str) -> bool {
+    let chars: Vec<char> = line.chars().filter(|c| !c.is_whitespace()).collect();
+    chars.len() >= 3
+        && ['-', '*', '_']
+            .iter()
+            .any(|marker| chars.iter().all(|c| c == marker))
+}
+
+/// The marker to print and the text of a bullet, task or numbered item
+fn list_item(line: &str) -> Option<(String, &str)> {
+    let trimmed = line.trim_start();
+    for bullet in ["- ", "* ", "+ "] {
+        if let Some(item) = trimmed.strip_prefix(bullet) {
+            if let Some(task) = item.strip_prefix("[ ] ") {
+                return Some(("☐".to_string(), task));
+            }
+            if let Some(task) = item
+                .strip_prefix("[x] ")
+                .or_else(|| item.strip_prefix("[X] "))
+            {
+                return Some(("☑".to_string(), task));
+            }
+            return Some(("•".to_string(), item));
+        }
+    }
+
+    let digits = trimmed.chars().take_while(char::is_ascii_digit).count();
+    if digits > 0 {
+        let rest = &trimmed[digits..];
+        if let Some(item) = rest.strip_prefix(". ").or_else(|| rest.strip_prefix(") ")) {
+            return Some((format!("{}.", &trimmed[..digits]), item));
+        }
+    }
+    None
+}
+
+/// Split a line into runs of text that share a style
+fn inline(text: &str) -> Vec<(String, Style)> {
+    let mut segments = Vec::new();
+    let mut style = Style::default();
+    let mut current = String::new();
+    let mut rest = text;
+
+    fn flush(segments: &mut Vec<(String, Style)>, current: &mut String, style: Style) {
+        if !current.is_empty() {
+            segments.push((std::mem::take(current), style));
+        }
+    }
+
+    while let Some(c) = rest.chars().next() {
+        if rest.starts_with("**") || rest.starts_with("__") {
+            flush(&mut segments, &mut current, style);
+            style.bold = !style.bold;
+            rest = &rest[2..];
+        } else if c == '*' && (style.italic || rest[1..].starts_with(|c: char| !c.is_whitespace()))
+        {
+            flush(&mut segments, &mut current, style);
+            style.italic = !style.italic;
+            rest = &rest[1..];
+        } else if c == '`' {
+            match rest[1..].find('`') {
+                Some(end) => {
+                    flush(&mut segments, &mut current, style);
+                    let code = Style {
+                        code: true,
+                        ..Style::default()
+                    };
+                    segments.push((rest[1..end + 1].to_string(), code));
+                    rest = &rest[end + 2..];
+                }
+                None => {
+                    current.push(c);
+                    rest = &rest[1..];
+                }
+            }
+        } else if let Some((label, url, len)) = link(rest) {
+            flush(&mut segments, &mut current, style);
+            let dim = Style {
+                dim: true,
+                ..Style::default()
+            };
+            if rest.starts_with('!') {
+                segments.push((format!("[image: {}]", label), dim));
+            } else {
+                segments.push((
+                    label.to_string(),
+                    Style {
+                        link: true,
+                        ..style
+                    },
+                ));
+                if url != label {
+                    segments.push((format!(" ({})", url), dim));
+                }
+            }
+            rest = &rest[len..];
+        } else {
+            current.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    flush(&mut segments, &mut current, style);
+
+    segments
+}
+
+/// `[label](url)` or `![alt](url)` at the start of the text, with the length
+/// it takes up
+fn link(text: &str) -> Option<(&str, &str, usize)> {
+    let start = if text.starts_with("![") {
+        2
+    } else if text.starts_with('[') {
+        1
+    } else {
+        return None;
+    };
+
+    let label_end = start + text[start..].find("](")?;
+    let url_start = label_end + 2;
+    let url_end = url_start + text[url_start..].find(')')?;
+    if text[start..label_end].contains('\n') {
+        return None;
+    }
+
+    Some((
+        &text[start..label_end],
+        &text[url_start..url_end],
+        url_end + 1,
+    ))
+}
+
+/// Lay styled text out in lines no wider than the terminal, breaking
+/// between words
+fn wrap(
+    segments: &[(String, Style)],
+    first_indent: &str,
+    indent: &str,
+    terminal: Terminal,
+) -> Vec<String> {
+    // A word can span styles, e.g. a code span followed by a comma
+    let mut words: Vec<(bool, Vec<(String, Style)>)> = Vec::new();
+    let mut spaced = false;
+    for (text, style) in segments {
+        for (i, part) in text.split(' ').enumerate() {
+            if i > 0 {
+                spaced = true;
+            }
+            if part.is_empty() {
+                continue;
+            }
+            match words.last_mut() {
+                Some((_, pieces)) if !spaced => pieces.push((part.to_string(), *style)),
+                _ => words.push((spaced, vec![(part.to_string(), *style)])),
+            }
+            spaced = false;
+        }
+    }
+
+    let mut lines = Vec::new();
+    let mut line = first_indent.to_string();
+    let mut width = strip_ansi(first_indent).chars().count();
+    let mut empty = true;
+
+    for (spaced, pieces) in words {
+        let len: usize = pieces.iter().map(|(text, _)| text.chars().count()).sum();
+        let space = usize::from(spaced && !empty);
+        if !empty && width + space + len > terminal.width {
+            lines.push(line);
+            line = indent.to_string();
+            width = strip_ansi(indent).chars().count();
+        } else if space == 1 {
+            line.push(' ');
+            width += 1;
+        }
+
+        for (text, style) in pieces {
+            line.push_str(&style.paint(&text, terminal.ansi));
+        }
+        width += len;
+        empty = false;
+    }
+
+    lines.push(line);
+    lines
+}
+
+fn strip_ansi(text: &str) -> String {
+    let mut out = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            for c in chars.by_ref() {
+                if c == 'm' {
+                    break;
+                }
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
@@ -0,0 +1,258 @@
+use chrono::{DateTime, Datelike, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::github::Issue;
+
+/// Months of records kept in memory in full, the current one included.
+/// Older months are compacted down to what it takes to recognize an issue.
+pub const KEEP_MONTHS: i32 = 3;
+
+/// Issues the bot won't claim again, keyed by `owner/repo#number`. An issue
+/// counts as processed if either that key or its GitHub ID matches, so
+/// transferred issues and hand-written entries are both recognized.
+#[derive(Debug, Clone, Default)]
+pub struct ProcessedIssues {
+    records: HashMap<String, ProcessedIssue>,
+    /// The key of each record with an ID, so lookups by ID stay cheap
+    ids: HashMap<u64, String>,
+    /// Shared between clones, it only changes when months are compacted
+    archive: Arc<ProcessedArchive>,
+}
+
+impl ProcessedIssues {
+    pub fn new(records: HashMap<String, ProcessedIssue>, archive: ProcessedArchive) -> Self {
+        let ids = records
+            .iter()
+            .filter_map(|(key, record)| Some((record.id?, key.clone())))
+            .collect();
+
+        Self {
+            records,
+            ids,
+            archive: Arc::new(archive),
+        }
+    }
+
+    pub fn contains(&self, owner: &str, repo: &str, issue: &Issue) -> bool {
+        let key = key(owner, repo, issue.number);
+        self.records.contains_key(&key)
+            || self.ids.contains_key(&issue.id)
+            || self.archive.contains(&key, issue.id)
+    }
+
+    pub fn insert(&mut self, record: ProcessedIssue) {
+        let key = key(&record.repo_owner, &record.repo_name, record.issue_number);
+        if let Some(id) = record.id {
+            self.ids.insert(id, key.clone());
+        }
+        self.records.insert(key, record);
+    }
+
+    /// Only for updating why and when, the ID must stay the same
+    pub fn get_mut(
+        &mut self,
+        owner: &str,
+        repo: &str,
+        issue_number: u64,
+    ) -> Option<&mut ProcessedIssue> {
+        self.records.get_mut(&key(owner, repo, issue_number))
+    }
+
+    /// Records of the months still kept in full
+    pub fn values(&self) -> impl Iterator<Item = &ProcessedIssue> {
+        self.records.values()
+    }
+
+    pub fn records(&self) -> impl Iterator<Item = (&String, &ProcessedIssue)> {
+        self.records.iter()
+    }
+
+    pub fn archive(&self) -> &ProcessedArchive {
+        &self.archive
+    }
+
+    /// Move the records processed before `month` (as `YYYY-MM`) into the
+    /// archive. Returns whether there was anything to move.
+    pub fn compact(&mut self, month: &str) -> bool {
+        let old: Vec<String> = self
+            .records
+            .iter()
+            .filter(|(_, record)| record.month().as_str() < month)
+            .map(|(key, _)| key.clone())
+            .collect();
+        if old.is_empty() {
+            return false;
+        }
+
+        let archive = Arc::make_mut(&mut self.archive);
+        for key in old {
+            let Some(record) = self.records.remove(&key) else {
+                continue;
+            };
+            if let Some(id) = record.id {
+                if self.ids.get(&id) == Some(&key) {
+                    self.ids.remove(&id);
+                }
+            }
+            archive.add(&key, &record);
+        }
+        archive.finish();
+
+        true
+    }
+
+    /// Every processed issue, archived ones included
+    pub fn len(&self) -> usize {
+        self.records.len() + self.archive.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty() && self.archive.is_empty()
+    }
+}
+
+/// What's left of compacted months: enough to recognize their issues, but
+/// not why they were processed. The months themselves stay on disk in full.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProcessedArchive {
+    /// Months compacted so far, as `YYYY-MM`
+    pub months: Vec<String>,
+    /// Sorted, so lookups are a binary search
+    ids: Vec<u64>,
+    /// Records without an ID are recognized by key only
+    keys: Vec<String>,
+}
+
+impl ProcessedArchive {
+    pub fn contains(&self, key: &str, id: u64) -> bool {
+        self.ids.binary_search(&id).is_ok()
+            || self.keys.binary_search_by(|k| k.as_str().cmp(key)).is_ok()
+    }
+
+    /// Add a record without sorting, call `finish()` once done
+    pub fn add(&mut self, key: &str, record: &ProcessedIssue) {
+        match record.id {
+            Some(id) => self.ids.push(id),
+            None => self.keys.push(key.to_string()),
+        }
+        let month = record.month();
+        if !self.months.contains(&month) {
+            self.months.push(month);
+        }
+    }
+
+    pub fn finish(&mut self) {
+        self.ids.sort_unstable();
+        self.ids.dedup();
+        self.keys.sort_unstable();
+        self.keys.dedup();
+        self.months.sort_unstable();
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len() + self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty() && self.keys.is_empty()
+    }
+}
+
+/// The first month, as `YYYY-MM`, whose records are kept in full
+pub fn compaction_cutoff(now: DateTime<Utc>) -> String {
+    let months = now.year() * 12 + now.month0() as i32 - (KEEP_MONTHS - 1);
+    format!(
+        "{:04}-{:02}",
+        months.div_euclid(12),
+        months.rem_euclid(12) + 1
+    )
+}
+
+pub fn key(owner: &str, repo: &str, issue_number: u64) -> String {
+    format!("{}/{}#{}", owner, repo, issue_number)
+}
+
+/// Why an issue was set aside
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessedReason {
+    /// We asked to be assigned
+    Claimed,
+    /// Nobody answered our claim in time
+    TimedOut,
+    /// The maintainers turned our claim down
+    Rejected,
+    /// Someone else got the issue
+    AssignedToOther,
+    /// We turned it down in the review queue
+    Declined,
+    /// The issue was closed while we waited
+    Closed,
+    /// Someone already opened a pull request for it
+    HasPullRequest,
+    /// The conversation is locked, so we can't comment
+    Locked,
+    /// The issue was deleted while we waited
+    Deleted,
+    /// A `notify` rule told us about it instead of claiming it
+    Notified,
+}
+
+impl std::fmt::Display for ProcessedReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let reason = match self {
+            ProcessedReason::Claimed => "claimed",
+            ProcessedReason::TimedOut => "timed out",
+            ProcessedReason::Rejected => "rejected",
+            ProcessedReason::AssignedToOther => "assigned to someone else",
+            ProcessedReason::Declined => "declined in review",
+            ProcessedReason::Closed => "closed",
+            ProcessedReason::HasPullRequest => "has a pull request",
+            ProcessedReason::Locked => "locked",
+            ProcessedReason::Deleted => "deleted",
+            ProcessedReason::Notified => "notified about",
+        };
+        f.write_str(reason)
+    }
+}
+
+/// An issue the bot is done with, and why. Records migrated from the old
+/// list of IDs don't know which issue they belong to, so those fields are
+/// left empty and they are kept under their ID instead.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessedIssue {
+    /// GitHub's issue ID, which survives transfers to another repository
+    #[serde(default)]
+    pub id: Option<u64>,
+    #[serde(default)]
+    pub repo_owner: String,
+    #[serde(default)]
+    pub repo_name: String,
+    #[serde(default)]
+    pub issue_number: u64,
+    #[serde(default)]
+    pub issue_url: String,
+    pub processed_at: DateTime<Utc>,
+    pub reason: ProcessedReason,
+}
+
+impl ProcessedIssue {
+    pub fn new(owner: &str, repo: &str, issue: &Issue, reason: ProcessedReason) -> Self {
+        Self {
+            id: Some(issue.id),
+            repo_owner: owner.to_string(),
+            repo_name: repo.to_string(),
+            issue_number: issue.number,
+            issue_url: issue.html_url.clone(),
+            processed_at: Utc::now(),
+            reason,
+        }
+    }
+
+    /// The month it was processed in, as `YYYY-MM`
+    pub fn month(&self) -> String {
+        self.processed_at.format("%Y-%m").to_string()
+    }
+}
@@ -0,0 +1,116 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::github::Issue;
+
+/// Where a candidate stands in review
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CandidateStatus {
+    Pending,
+    /// Claim it on the next poll
+    Approved,
+    /// Never claim it, the bot moves it to the processed issues
+    Rejected,
+}
+
+impl std::fmt::Display for CandidateStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let status = match self {
+            CandidateStatus::Pending => "pending",
+            CandidateStatus::Approved => "approved",
+            CandidateStatus::Rejected => "rejected",
+        };
+        f.write_str(status)
+    }
+}
+
+/// An eligible issue waiting for review before the bot claims it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Candidate {
+    /// Short number to approve or reject the candidate by
+    pub id: u64,
+    pub repo_owner: String,
+    pub repo_name: String,
+    pub issue_number: u64,
+    pub issue_title: String,
+    pub issue_url: String,
+    #[serde(default)]
+    pub issue_labels: Vec<String>,
+    /// The issue's description as it was when found, for `queue show`
+    #[serde(default)]
+    pub issue_body: Option<String>,
+    pub found_at: DateTime<Utc>,
+    pub status: CandidateStatus,
+}
+
+/// Issues found in review mode, in the order they were found
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct CandidateQueue {
+    candidates: Vec<Candidate>,
+}
+
+impl CandidateQueue {
+    pub fn find(&self, id: u64) -> Option<&Candidate> {
+        self.candidates.iter().find(|candidate| candidate.id == id)
+    }
+
+    pub fn get(&self, owner: &str, repo: &str, issue_number: u64) -> Option<&Candidate> {
+        self.candidates.iter().find(|candidate| {
+            candidate.repo_owner == owner
+                && candidate.repo_name == repo
+                && candidate.issue_number == issue_number
+        })
+    }
+
+    /// Queue an issue for review, returning its candidate ID
+    pub fn push(&mut self, owner: &str, repo: &str, issue: &Issue) -> u64 {
+        let id = self.candidates.iter().map(|c| c.id).max().unwrap_or(0) + 1;
+        self.candidates.push(Candidate {
+            id,
+            repo_owner: owner.to_string(),
+            repo_name: repo.to_string(),
+            issue_number: issue.number,
+            issue_title: issue.title.clone(),
+            issue_url: issue.html_url.clone(),
+            issue_labels: issue
+                .labels
+                .iter()
+                .map(|label| label.name.clone())
+                .collect(),
+            issue_body: issue.body.clone(),
+            found_at: Utc::now(),
+            status: CandidateStatus::Pending,
+        });
+        id
+    }
+
+    /// Record a review decision
+    pub fn decide(&mut self, id: u64, status: CandidateStatus) -> Result<&Candidate> {
+        let candidate = self
+            .candidates
+            .iter_mut()
+            .find(|candidate| candidate.id == id)
+            .ok_or_else(|| anyhow::anyhow!("No candidate with ID {} in the queue", id))?;
+        candidate.status = status;
+        Ok(candidate)
+    }
+
+    pub fn remove(&mut self, owner: &str, repo: &str, issue_number: u64) {
+        self.candidates.retain(|candidate| {
+            !(candidate.repo_owner == owner
+                && candidate.repo_name == repo
+                && candidate.issue_number == issue_number)
+        });
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Candidate> {
+        self.candidates.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.candidates.is_empty()
+    }
+}
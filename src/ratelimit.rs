@@ -0,0 +1,71 @@
+use chrono::{DateTime, Duration, Utc};
+use std::sync::Mutex;
+
+/// Requests one repository costs per poll, roughly: the issue list plus the
+/// occasional comment lookup or claim
+const REQUESTS_PER_REPO: u32 = 2;
+
+/// GitHub's core API quota, as reported by `/rate_limit`
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimit {
+    pub remaining: u32,
+    pub reset_at: DateTime<Utc>,
+}
+
+/// The requests a client has sent so far, for the summary of each poll
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ApiUsage {
+    /// Retries included
+    pub requests: u64,
+    /// What's left of the quota, as of the latest response
+    pub remaining: Option<u32>,
+}
+
+/// Spreads the remaining quota over the polls left until it resets, and
+/// pauses polling altogether once only the reserve is left
+pub struct RateBudget {
+    reserve: u32,
+    poll_interval: Duration,
+    paused_until: Mutex<Option<DateTime<Utc>>>,
+}
+
+impl RateBudget {
+    pub fn new(reserve: u32, poll_interval_secs: u64) -> Self {
+        Self {
+            reserve,
+            poll_interval: Duration::seconds(poll_interval_secs.max(1) as i64),
+            paused_until: Mutex::new(None),
+        }
+    }
+
+    /// When polling is paused, the time it resumes
+    pub fn paused_until(&self) -> Option<DateTime<Utc>> {
+        *self.paused_until.lock().unwrap()
+    }
+
+    pub fn pause_until(&self, until: DateTime<Utc>) {
+        let mut paused_until = self.paused_until.lock().unwrap();
+        *paused_until = Some(paused_until.map_or(until, |current| current.max(until)));
+    }
+
+    pub fn resume(&self) {
+        *self.paused_until.lock().unwrap() = None;
+    }
+
+    /// How many repositories we can afford to poll this cycle. Returns None,
+    /// and pauses until the reset, once the quota is used up.
+    pub fn allowance(&self, limit: &RateLimit, repositories: usize) -> Option<usize> {
+        let usable = limit.remaining.saturating_sub(self.reserve);
+        if usable < REQUESTS_PER_REPO {
+            *self.paused_until.lock().unwrap() = Some(limit.reset_at);
+            return None;
+        }
+
+        let until_reset = (limit.reset_at - Utc::now()).max(self.poll_interval);
+        let polls_left = (until_reset.num_seconds() / self.poll_interval.num_seconds()).max(1);
+        let per_poll = usable as i64 / polls_left;
+        let affordable = (per_poll / REQUESTS_PER_REPO as i64).max(1) as usize;
+
+        Some(affordable.min(repositories))
+    }
+}
@@ -0,0 +1,73 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+
+/// A raw list of open issues as GitHub returned it, before any filtering
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    pub recorded_at: DateTime<Utc>,
+    pub owner: String,
+    pub repo: String,
+    pub body: serde_json::Value,
+}
+
+/// Appends API responses to a JSONL file so a run can be replayed later
+#[derive(Debug, Clone)]
+pub struct Recorder {
+    path: PathBuf,
+}
+
+impl Recorder {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    pub async fn record(&self, owner: &str, repo: &str, body: &str) -> Result<()> {
+        let response = RecordedResponse {
+            recorded_at: Utc::now(),
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+            body: serde_json::from_str(body)?,
+        };
+        let mut line = serde_json::to_string(&response)?;
+        line.push('\n');
+
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await
+            .with_context(|| format!("Failed to open recording {}", self.path.display()))?;
+        file.write_all(line.as_bytes())
+            .await
+            .with_context(|| format!("Failed to write to recording {}", self.path.display()))?;
+
+        Ok(())
+    }
+}
+
+/// Read back every response in a recording, oldest first
+pub async fn load<P: AsRef<Path>>(path: P) -> Result<Vec<RecordedResponse>> {
+    let path = path.as_ref();
+    let content = fs::read_to_string(path)
+        .await
+        .with_context(|| format!("Failed to read recording {}", path.display()))?;
+
+    let mut responses = content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .map(|(index, line)| {
+            serde_json::from_str::<RecordedResponse>(line)
+                .with_context(|| format!("Failed to parse line {} of the recording", index + 1))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    responses.sort_by_key(|response| response.recorded_at);
+
+    Ok(responses)
+}
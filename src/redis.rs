@@ -0,0 +1,236 @@
+//! Just enough of the Redis protocol for the team claim queue, so team mode
+//! doesn't pull in a client library.
+
+use anyhow::{Context, Result};
+use std::time::Duration;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::sync::Mutex;
+use tokio::time;
+
+/// Longest we wait for Redis to answer a command, connecting included
+const COMMAND_TIMEOUT_SECS: u64 = 10;
+
+/// A reply to a command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reply {
+    Nil,
+    Status(String),
+    Integer(i64),
+    Bulk(String),
+    Array(Vec<Reply>),
+}
+
+impl Reply {
+    /// The value of a status or bulk string reply
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Reply::Status(value) | Reply::Bulk(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+/// A single connection to Redis, opened on first use and reopened after an
+/// error
+pub struct RedisClient {
+    address: String,
+    username: Option<String>,
+    password: Option<String>,
+    database: Option<u32>,
+    connection: Mutex<Option<BufReader<TcpStream>>>,
+}
+
+impl RedisClient {
+    /// Parse a `redis://[[user]:password@]host[:port][/db]` URL
+    pub fn new(url: &str) -> Result<Self> {
+        let parsed = reqwest::Url::parse(url).context("Invalid Redis URL")?;
+        if parsed.scheme() != "redis" {
+            return Err(anyhow::anyhow!(
+                "Unsupported Redis URL scheme {}, only redis:// is supported",
+                parsed.scheme()
+            ));
+        }
+
+        let host = parsed.host_str().context("Redis URL has no host")?;
+        let database = match parsed.path().trim_start_matches('/') {
+            "" => None,
+            db => Some(db.parse().context("Invalid database number in Redis URL")?),
+        };
+
+        Ok(Self {
+            address: format!("{}:{}", host, parsed.port().unwrap_or(6379)),
+            username: Some(parsed.username())
+                .filter(|username| !username.is_empty())
+                .map(str::to_string),
+            password: parsed.password().map(str::to_string),
+            database,
+            connection: Mutex::new(None),
+        })
+    }
+
+    /// Run a command, e.g. `["SET", "key", "value", "NX"]`
+    pub async fn command(&self, args: &[&str]) -> Result<Reply> {
+        let mut connection = self.connection.lock().await;
+        let result = time::timeout(Duration::from_secs(COMMAND_TIMEOUT_SECS), async {
+            if connection.is_none() {
+                *connection = Some(self.connect().await?);
+            }
+            let stream = connection.as_mut().expect("connected above");
+            send(stream, args).await
+        })
+        .await
+        .unwrap_or_else(|_| Err(anyhow::anyhow!("Redis didn't answer in time")));
+
+        // The connection may be mid-reply, start over next time
+        if result.is_err() {
+            *connection = None;
+        }
+        result
+    }
+
+    async fn connect(&self) -> Result<BufReader<TcpStream>> {
+        let stream = TcpStream::connect(&self.address)
+            .await
+            .with_context(|| format!("Failed to connect to Redis at {}", self.address))?;
+        let mut stream = BufReader::new(stream);
+
+        if let Some(ref password) = self.password {
+            let mut auth = vec!["AUTH"];
+            auth.extend(self.username.as_deref());
+            auth.push(password);
+            send(&mut stream, &auth)
+                .await
+                .context("Redis authentication failed")?;
+        }
+        if let Some(database) = self.database {
+            send(&mut stream, &["SELECT", &database.to_string()]).await?;
+        }
+
+        Ok(stream)
+    }
+}
+
+async fn send(stream: &mut BufReader<TcpStream>, args: &[&str]) -> Result<Reply> {
+    stream.get_mut().write_all(encode(args).as_bytes()).await?;
+
+    read_reply(stream).await
+}
+
+fn encode(args: &[&str]) -> String {
+    let mut request = format!("*{}\r\n", args.len());
+    for arg in args {
+        request.push_str(&format!("${}\r\n{}\r\n", arg.len(), arg));
+    }
+    request
+}
+
+async fn read_reply<R: AsyncBufRead + Unpin>(stream: &mut R) -> Result<Reply> {
+    let mut line = String::new();
+    if stream.read_line(&mut line).await? == 0 {
+        return Err(anyhow::anyhow!("Redis closed the connection"));
+    }
+    let line = line.trim_end_matches("\r\n");
+    let (kind, rest) = line.split_at(line.len().min(1));
+
+    match kind {
+        "+" => Ok(Reply::Status(rest.to_string())),
+        "-" => Err(anyhow::anyhow!("Redis error: {}", rest)),
+        ":" => Ok(Reply::Integer(rest.parse()?)),
+        "$" => {
+            let Ok(len) = usize::try_from(rest.parse::<i64>()?) else {
+                return Ok(Reply::Nil);
+            };
+            let mut data = vec![0; len + 2];
+            stream.read_exact(&mut data).await?;
+            data.truncate(len);
+            Ok(Reply::Bulk(String::from_utf8(data)?))
+        }
+        "*" => {
+            let Ok(len) = usize::try_from(rest.parse::<i64>()?) else {
+                return Ok(Reply::Nil);
+            };
+            let mut items = Vec::with_capacity(len);
+            for _ in 0..len {
+                items.push(Box::pin(read_reply(stream)).await?);
+            }
+            Ok(Reply::Array(items))
+        }
+        _ => Err(anyhow::anyhow!("Unexpected reply from Redis: {}", line)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn decode(data: &str) -> Result<Reply> {
+        read_reply(&mut data.as_bytes()).await
+    }
+
+    #[test]
+    fn encodes_commands_as_bulk_string_arrays() {
+        assert_eq!(
+            encode(&["SET", "key", "two words"]),
+            "*3\r\n$3\r\nSET\r\n$3\r\nkey\r\n$9\r\ntwo words\r\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn decodes_simple_replies() {
+        assert_eq!(decode("+OK\r\n").await.unwrap(), Reply::Status("OK".into()));
+        assert_eq!(decode(":-42\r\n").await.unwrap(), Reply::Integer(-42));
+        assert_eq!(decode("$-1\r\n").await.unwrap(), Reply::Nil);
+        assert_eq!(decode("*-1\r\n").await.unwrap(), Reply::Nil);
+
+        let error = decode("-ERR wrong type\r\n").await.unwrap_err();
+        assert_eq!(error.to_string(), "Redis error: ERR wrong type");
+        assert!(decode("?\r\n").await.is_err());
+        assert!(decode("").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn decodes_bulk_strings_with_line_breaks() {
+        assert_eq!(
+            decode("$7\r\nab\r\ncde\r\n").await.unwrap(),
+            Reply::Bulk("ab\r\ncde".into())
+        );
+        assert_eq!(
+            decode("$0\r\n\r\n").await.unwrap(),
+            Reply::Bulk(String::new())
+        );
+    }
+
+    #[tokio::test]
+    async fn decodes_nested_arrays() {
+        assert_eq!(
+            decode("*3\r\n$5\r\nalice\r\n*2\r\n:1\r\n$-1\r\n+OK\r\n")
+                .await
+                .unwrap(),
+            Reply::Array(vec![
+                Reply::Bulk("alice".into()),
+                Reply::Array(vec![Reply::Integer(1), Reply::Nil]),
+                Reply::Status("OK".into()),
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn waits_for_a_reply_split_across_reads() {
+        let (client, mut server) = tokio::io::duplex(64);
+        let writer = tokio::spawn(async move {
+            for part in ["*2\r\n$5\r\nal", "ice\r", "\n:7", "\r\n"] {
+                server.write_all(part.as_bytes()).await.unwrap();
+                server.flush().await.unwrap();
+                tokio::task::yield_now().await;
+            }
+        });
+
+        let reply = read_reply(&mut BufReader::new(client)).await.unwrap();
+        writer.await.unwrap();
+        assert_eq!(
+            reply,
+            Reply::Array(vec![Reply::Bulk("alice".into()), Reply::Integer(7)])
+        );
+    }
+}
@@ -0,0 +1,65 @@
+/// Phrases maintainers use when an issue isn't up for grabs
+pub const REJECTION_PHRASES: [&str; 14] = [
+    "already being worked on",
+    "already working on",
+    "someone is working on",
+    "someone else is working on",
+    "already assigned",
+    "has been assigned to",
+    "already taken",
+    "already claimed",
+    "already has a pr",
+    "already has an open pr",
+    "not accepting contributions",
+    "not open for contribution",
+    "needs discussion first",
+    "needs more discussion",
+];
+
+/// Phrases maintainers use when handing an issue over without assigning it
+pub const ACCEPTANCE_PHRASES: [&str; 8] = [
+    "all yours",
+    "go ahead",
+    "go for it",
+    "it's yours",
+    "feel free to take",
+    "feel free to work on",
+    "you can take it",
+    "you can work on it",
+];
+
+/// What a reply to our claim says about it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Accepted,
+    Rejected,
+}
+
+/// Read a reply to our claim for one of the phrases. A reply with both kinds
+/// counts as turning the claim down, e.g. "go ahead and look at #12 instead,
+/// this one is already taken".
+pub fn interpret<S: AsRef<str>>(body: &str, accept: &[S], reject: &[S]) -> Option<Verdict> {
+    let text = normalize(body);
+    let said = |phrases: &[S]| {
+        phrases
+            .iter()
+            .map(|phrase| normalize(phrase.as_ref()))
+            .any(|phrase| !phrase.is_empty() && text.contains(&phrase))
+    };
+
+    if said(reject) {
+        Some(Verdict::Rejected)
+    } else if said(accept) {
+        Some(Verdict::Accepted)
+    } else {
+        None
+    }
+}
+
+fn normalize(text: &str) -> String {
+    text.to_lowercase()
+        .replace('\u{2019}', "'")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -0,0 +1,136 @@
+//! Claims and their outcomes in portable formats, for keeping track of
+//! contributions outside the bot
+
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+
+use crate::bot::AssignedIssue;
+use crate::history::HistoryEntry;
+
+/// One claim, finished or still in progress
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportRow {
+    pub repo: String,
+    pub issue_number: u64,
+    pub issue_title: String,
+    pub issue_url: String,
+    pub requested_at: DateTime<Utc>,
+    pub assigned_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub outcome: String,
+    pub pull_request_url: Option<String>,
+    pub template: Option<String>,
+}
+
+/// Every claim made on or after `since`, oldest first
+pub fn rows(
+    history: &[HistoryEntry],
+    in_progress: &[AssignedIssue],
+    since: Option<NaiveDate>,
+) -> Vec<ReportRow> {
+    let finished = history.iter().map(|entry| ReportRow {
+        repo: format!("{}/{}", entry.repo_owner, entry.repo_name),
+        issue_number: entry.issue_number,
+        issue_title: entry.issue_title.clone(),
+        issue_url: entry.issue_url.clone(),
+        requested_at: entry.requested_at,
+        assigned_at: entry.assigned_at,
+        finished_at: Some(entry.finished_at),
+        outcome: entry.outcome.to_string(),
+        pull_request_url: entry.pull_request_url.clone(),
+        template: entry.template.clone(),
+    });
+    let open = in_progress.iter().map(|issue| ReportRow {
+        repo: format!("{}/{}", issue.repo_owner, issue.repo_name),
+        issue_number: issue.issue_number,
+        issue_title: issue.issue_title.clone(),
+        issue_url: issue.issue_url.clone(),
+        requested_at: issue.requested_at,
+        assigned_at: Some(issue.assigned_at),
+        finished_at: None,
+        outcome: "in progress".to_string(),
+        pull_request_url: issue
+            .linked_prs
+            .first()
+            .map(|pr| pr.html_url.clone())
+            .or_else(|| issue.draft_pr_url.clone()),
+        template: issue.template.clone(),
+    });
+
+    let mut rows: Vec<ReportRow> = finished
+        .chain(open)
+        .filter(|row| since.is_none_or(|since| row.requested_at.date_naive() >= since))
+        .collect();
+    rows.sort_by_key(|row| row.requested_at);
+    rows
+}
+
+const COLUMNS: [&str; 9] = [
+    "repo",
+    "issue",
+    "title",
+    "url",
+    "requested",
+    "assigned",
+    "finished",
+    "outcome",
+    "pull request",
+];
+
+fn fields(row: &ReportRow) -> [String; 9] {
+    let date = |at: Option<DateTime<Utc>>| {
+        at.map(|at| at.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default()
+    };
+    [
+        row.repo.clone(),
+        row.issue_number.to_string(),
+        row.issue_title.clone(),
+        row.issue_url.clone(),
+        date(Some(row.requested_at)),
+        date(row.assigned_at),
+        date(row.finished_at),
+        row.outcome.clone(),
+        row.pull_request_url.clone().unwrap_or_default(),
+    ]
+}
+
+/// RFC 4180 CSV with a header line
+pub fn csv(rows: &[ReportRow]) -> String {
+    fn quote(field: &str) -> String {
+        if field.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", field.replace('"', "\"\""))
+        } else {
+            field.to_string()
+        }
+    }
+
+    let mut out = COLUMNS.join(",");
+    out.push_str("\r\n");
+    for row in rows {
+        let line: Vec<String> = fields(row).iter().map(|field| quote(field)).collect();
+        out.push_str(&line.join(","));
+        out.push_str("\r\n");
+    }
+    out
+}
+
+/// A markdown table
+pub fn markdown(rows: &[ReportRow]) -> String {
+    fn cell(field: &str) -> String {
+        field.replace('|', "\\|").replace('\n', " ")
+    }
+
+    let mut out = format!("| {} |\n", COLUMNS.join(" | "));
+    out.push_str(&format!("|{}\n", " --- |".repeat(COLUMNS.len())));
+    for row in rows {
+        let cells: Vec<String> = fields(row).iter().map(|field| cell(field)).collect();
+        out.push_str(&format!("| {} |\n", cells.join(" | ")));
+    }
+    out
+}
+
+pub fn json(rows: &[ReportRow]) -> Result<String> {
+    Ok(serde_json::to_string_pretty(rows)?)
+}
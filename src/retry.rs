@@ -0,0 +1,52 @@
+use rand::{thread_rng, Rng};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Delay before the first retry, doubled for every one after it
+const BASE_DELAY_MS: u64 = 500;
+
+/// Longest we ever wait between two attempts
+const MAX_DELAY_MS: u64 = 30_000;
+
+/// How GitHub requests fared with retries, reported by the health endpoints
+#[derive(Debug, Default)]
+pub struct RetryStats {
+    retries: AtomicU64,
+    recovered: AtomicU64,
+    exhausted: AtomicU64,
+}
+
+impl RetryStats {
+    /// A request failed and will be tried again
+    pub fn record_retry(&self) {
+        self.retries.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// A request succeeded after failing before
+    pub fn record_recovered(&self) {
+        self.recovered.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// A request still failed after the last attempt
+    pub fn record_exhausted(&self) {
+        self.exhausted.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "retries": self.retries.load(Ordering::Relaxed),
+            "recovered": self.recovered.load(Ordering::Relaxed),
+            "exhausted": self.exhausted.load(Ordering::Relaxed),
+        })
+    }
+}
+
+/// Exponential backoff with full jitter, so retries from several bots
+/// don't line up
+pub fn backoff(attempt: u32) -> Duration {
+    let ceiling = BASE_DELAY_MS
+        .saturating_mul(1 << attempt.saturating_sub(1).min(16))
+        .min(MAX_DELAY_MS);
+
+    Duration::from_millis(thread_rng().gen_range(0..=ceiling))
+}
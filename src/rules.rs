@@ -0,0 +1,83 @@
+//! Rules that decide what happens to an issue that got through the filters:
+//! claim it, skip it, queue it for review or only tell us about it. The
+//! first rule an issue matches wins, issues no rule matches are claimed.
+
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::filter::Filter;
+use crate::github::Issue;
+
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct Rule {
+    /// Names the rule in logs, notifications and its budget
+    pub name: String,
+    /// Repositories the rule applies to, `owner/repo` or `owner/*`, all of
+    /// them if empty
+    #[serde(default)]
+    pub repos: Vec<String>,
+    /// Labels the issue has to have, all of them
+    #[serde(default)]
+    pub labels: Vec<String>,
+    /// Expression the issue has to match, e.g. `age > 2d and title:docs`
+    #[serde(default)]
+    pub filter: Option<Filter>,
+    pub action: RuleAction,
+    /// Claim comment templates to pick from instead of the global ones
+    #[serde(default)]
+    pub templates: Vec<String>,
+    /// At most this many claims through the rule in any seven days
+    #[serde(default)]
+    pub claims_per_week: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleAction {
+    Claim,
+    /// Leave the issue alone
+    Skip,
+    /// Queue the issue for `queue approve`, as with `review_queue`
+    Review,
+    /// Send a `matched` notification and leave the issue alone
+    Notify,
+}
+
+impl Rule {
+    pub fn matches(&self, owner: &str, repo: &str, issue: &Issue, now: DateTime<Utc>) -> bool {
+        let in_repo = self.repos.is_empty()
+            || self.repos.iter().any(|pattern| {
+                let (pattern_owner, pattern_repo) =
+                    pattern.split_once('/').unwrap_or((pattern, ""));
+                pattern_owner.eq_ignore_ascii_case(owner)
+                    && (pattern_repo == "*" || pattern_repo.eq_ignore_ascii_case(repo))
+            });
+
+        in_repo
+            && self.labels.iter().all(|name| {
+                issue
+                    .labels
+                    .iter()
+                    .any(|label| label.name.eq_ignore_ascii_case(name))
+            })
+            && self
+                .filter
+                .as_ref()
+                .is_none_or(|filter| filter.matches(issue, now))
+    }
+}
+
+/// The first rule the issue matches
+pub fn find<'a>(
+    rules: &'a [Rule],
+    owner: &str,
+    repo: &str,
+    issue: &Issue,
+    now: DateTime<Utc>,
+) -> Option<&'a Rule> {
+    rules
+        .iter()
+        .find(|rule| rule.matches(owner, repo, issue, now))
+}
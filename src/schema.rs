@@ -0,0 +1,189 @@
+use anyhow::{Context, Result};
+use chrono::Utc;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::processed;
+
+/// The state files that carry a schema version
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateFile {
+    ActiveIssue,
+    ClaimIntent,
+    ProcessedIssues,
+    ProcessedArchive,
+    RepoCursors,
+    AssignedIssues,
+    History,
+    Candidates,
+    Notes,
+    IgnoredIssues,
+    SnoozedIssues,
+}
+
+type Migration = fn(StateFile, Value) -> Result<Value>;
+
+/// Each migration upgrades a file's data from the version at its index to
+/// the next one. Add new steps to the end, never change existing ones.
+const MIGRATIONS: &[Migration] = &[
+    // 0 -> 1: files gained the version envelope, the data itself is unchanged
+    |_, data| Ok(data),
+    // 1 -> 2: processed issues went from a list of IDs to records keyed by ID
+    |file, data| match file {
+        StateFile::ProcessedIssues => processed_ids_to_records(data),
+        _ => Ok(data),
+    },
+    // 2 -> 3: processed issues are keyed by owner/repo#number, the ID moved
+    // into the record
+    |file, data| match file {
+        StateFile::ProcessedIssues => key_processed_by_number(data),
+        _ => Ok(data),
+    },
+];
+
+/// The old list only ever held issues we claimed
+fn processed_ids_to_records(data: Value) -> Result<Value> {
+    let ids: Vec<u64> = serde_json::from_value(data)?;
+    let now = Utc::now();
+
+    Ok(ids
+        .into_iter()
+        .map(|id| {
+            let record = json!({ "processed_at": now, "reason": "claimed" });
+            (id.to_string(), record)
+        })
+        .collect::<serde_json::Map<_, _>>()
+        .into())
+}
+
+fn key_processed_by_number(data: Value) -> Result<Value> {
+    let records: serde_json::Map<String, Value> = serde_json::from_value(data)?;
+
+    Ok(records
+        .into_iter()
+        .map(|(id, mut record)| {
+            let owner = record["repo_owner"].as_str().unwrap_or_default();
+            let repo = record["repo_name"].as_str().unwrap_or_default();
+            let number = record["issue_number"].as_u64().unwrap_or_default();
+            // Records migrated from bare IDs have nothing better to go by
+            let key = if owner.is_empty() {
+                id.clone()
+            } else {
+                processed::key(owner, repo, number)
+            };
+            record["id"] = json!(id.parse::<u64>().ok());
+            (key, record)
+        })
+        .collect::<serde_json::Map<_, _>>()
+        .into())
+}
+
+pub const CURRENT_VERSION: u32 = MIGRATIONS.len() as u32;
+
+#[derive(Serialize)]
+struct Versioned<'a, T: ?Sized> {
+    version: u32,
+    data: &'a T,
+}
+
+/// Serialize state along with the current schema version
+pub fn encode<T: Serialize + ?Sized>(data: &T) -> Result<String> {
+    Ok(serde_json::to_string_pretty(&Versioned {
+        version: CURRENT_VERSION,
+        data,
+    })?)
+}
+
+/// Parse a state file written by this or any earlier version of the bot,
+/// upgrading it to the current schema first
+pub fn decode<T: DeserializeOwned>(file: StateFile, content: &str) -> Result<T> {
+    let value: Value = serde_json::from_str(content)?;
+
+    // Files from before versioning hold the data directly
+    let (version, mut data) = match value {
+        Value::Object(mut map)
+            if map.len() == 2 && map.contains_key("version") && map.contains_key("data") =>
+        {
+            let version = map["version"]
+                .as_u64()
+                .context("Schema version is not a number")? as u32;
+            (version, map.remove("data").unwrap_or_default())
+        }
+        data => (0, data),
+    };
+
+    if version > CURRENT_VERSION {
+        return Err(anyhow::anyhow!(
+            "Schema version {} is newer than this bot supports ({}), please upgrade",
+            version,
+            CURRENT_VERSION
+        ));
+    }
+
+    for (from, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        data = migration(file, data)
+            .with_context(|| format!("Failed to migrate {:?} from version {}", file, from))?;
+    }
+
+    Ok(serde_json::from_value(data)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_current_files() {
+        let data = json!({ "rust-lang/rust#1": { "reason": "claimed" } });
+        let encoded = encode(&data).unwrap();
+
+        let decoded: Value = decode(StateFile::ProcessedIssues, &encoded).unwrap();
+        assert_eq!(decoded, data);
+    }
+
+    #[test]
+    fn migrates_unversioned_processed_ids() {
+        let decoded: Value = decode(StateFile::ProcessedIssues, "[11, 12]").unwrap();
+
+        let records = decoded.as_object().unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records["11"]["reason"], "claimed");
+        assert_eq!(records["11"]["id"], 11);
+        assert!(records["12"]["processed_at"].is_string());
+    }
+
+    #[test]
+    fn keys_processed_records_by_issue() {
+        let content = json!({
+            "version": 2,
+            "data": {
+                "42": {
+                    "repo_owner": "owner",
+                    "repo_name": "repo",
+                    "issue_number": 7,
+                    "reason": "timed_out",
+                },
+            },
+        })
+        .to_string();
+
+        let decoded: Value = decode(StateFile::ProcessedIssues, &content).unwrap();
+        assert_eq!(decoded["owner/repo#7"]["id"], 42);
+        assert_eq!(decoded["owner/repo#7"]["reason"], "timed_out");
+    }
+
+    #[test]
+    fn leaves_other_files_alone() {
+        let decoded: Vec<u64> = decode(StateFile::History, "[1, 2]").unwrap();
+        assert_eq!(decoded, vec![1, 2]);
+    }
+
+    #[test]
+    fn refuses_files_from_a_newer_bot() {
+        let content = json!({ "version": CURRENT_VERSION + 1, "data": [] }).to_string();
+
+        let error = decode::<Value>(StateFile::History, &content).unwrap_err();
+        assert!(error.to_string().contains("please upgrade"));
+    }
+}
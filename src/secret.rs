@@ -0,0 +1,110 @@
+use regex::Regex;
+use schemars::gen::SchemaGenerator;
+use schemars::schema::Schema;
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::Cow;
+use std::fmt;
+use std::sync::{OnceLock, RwLock};
+
+const REDACTED: &str = "[REDACTED]";
+
+/// Every secret loaded so far, scrubbed from logs and errors by `redact`
+static KNOWN_SECRETS: RwLock<Vec<String>> = RwLock::new(Vec::new());
+
+/// A token or other credential that must never end up in logs. Debug prints
+/// a placeholder; use `expose` where the real value is needed.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: String) -> Self {
+        remember(&value);
+        Self(value)
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for Secret {
+    fn from(value: String) -> Self {
+        Self::new(value)
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl<'de> Deserialize<'de> for Secret {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self::new)
+    }
+}
+
+impl JsonSchema for Secret {
+    fn schema_name() -> String {
+        "Secret".to_string()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        String::json_schema(gen)
+    }
+}
+
+impl Serialize for Secret {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(REDACTED)
+    }
+}
+
+fn remember(value: &str) {
+    // Very short values would mangle unrelated text
+    if value.len() < 8 {
+        return;
+    }
+    if let Ok(mut secrets) = KNOWN_SECRETS.write() {
+        if !secrets.iter().any(|known| known == value) {
+            secrets.push(value.to_string());
+        }
+    }
+}
+
+/// Token shapes worth scrubbing even if we never loaded them ourselves
+fn patterns() -> &'static Regex {
+    static PATTERNS: OnceLock<Regex> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        Regex::new(concat!(
+            r"(?i)(authorization:\s*\S+\s+|\b(?:token|bearer)\s+)[A-Za-z0-9_.\-]{20,}",
+            r"|\b(?:gh[pousr]_[A-Za-z0-9]{20,}|github_pat_[A-Za-z0-9_]{20,})",
+            r"|\bbot\d+:[A-Za-z0-9_\-]+",
+        ))
+        .expect("valid redaction pattern")
+    })
+}
+
+/// Replace known secrets and anything that looks like a credential
+pub fn redact(text: &str) -> Cow<'_, str> {
+    let mut text = Cow::Borrowed(text);
+
+    if let Ok(secrets) = KNOWN_SECRETS.read() {
+        for secret in secrets.iter() {
+            if text.contains(secret.as_str()) {
+                text = Cow::Owned(text.replace(secret.as_str(), REDACTED));
+            }
+        }
+    }
+
+    match patterns().replace_all(&text, |caps: &regex::Captures| match caps.get(1) {
+        Some(prefix) => format!("{}{}", prefix.as_str(), REDACTED),
+        None if caps[0].starts_with("bot") => format!("bot{}", REDACTED),
+        None => REDACTED.to_string(),
+    }) {
+        Cow::Borrowed(_) => text,
+        Cow::Owned(scrubbed) => Cow::Owned(scrubbed),
+    }
+}
@@ -0,0 +1,261 @@
+use anyhow::Result;
+use chrono::Utc;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::github::Issue;
+use crate::processed;
+use crate::redis::{RedisClient, Reply};
+use crate::secret::Secret;
+
+/// How a team splits up the issues its members' bots find
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Distribution {
+    /// Whoever's bot gets to an issue first claims it
+    #[default]
+    FirstCome,
+    /// Issues are spread evenly over the members
+    RoundRobin,
+    /// Issues go to the member whose skills match the most labels
+    Skills,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, JsonSchema)]
+#[schemars(deny_unknown_fields)]
+pub struct TeamConfig {
+    /// Shared Redis that keeps track of who claimed what
+    pub redis_url: Secret,
+    /// Our name in the team, the GitHub username if not set
+    #[serde(default)]
+    pub member: Option<String>,
+    /// Every member, in the same order for all of them
+    #[serde(default)]
+    pub members: Vec<String>,
+    #[serde(default)]
+    pub distribution: Distribution,
+    /// Labels each member would like to work on, for the skills distribution
+    #[serde(default)]
+    pub skills: HashMap<String, Vec<String>>,
+    /// How long an issue waits for the member it went to before anyone in
+    /// the team may claim it
+    #[serde(default = "default_handoff_hours")]
+    pub handoff_hours: u32,
+    /// Prefix of the Redis keys, to share one Redis between teams
+    #[serde(default = "default_key_prefix")]
+    pub key_prefix: String,
+}
+
+/// How long a reservation keeps teammates off an issue while we post the
+/// claim. A bot that dies before claiming holds it up no longer than this.
+const RESERVATION_SECS: &str = "300";
+
+/// Hand over the member holding the claim, or take the reservation if nobody
+/// claimed the issue and no teammate is busy claiming it
+const RESERVE_SCRIPT: &str = "local claimed = redis.call('GET', KEYS[1]) \
+     if claimed then return claimed end \
+     redis.call('SET', KEYS[2], ARGV[1], 'NX', 'EX', ARGV[2]) \
+     return redis.call('GET', KEYS[2])";
+
+/// Turn our reservation into a claim, unless a teammate got there first
+const CLAIM_SCRIPT: &str = "redis.call('SET', KEYS[1], ARGV[1], 'NX') \
+     if redis.call('GET', KEYS[2]) == ARGV[1] then redis.call('DEL', KEYS[2]) end \
+     return redis.call('GET', KEYS[1])";
+
+/// Drop the claim and reservation, but only the ones that are ours
+const RELEASE_SCRIPT: &str = "local released = 0 \
+     for _, key in ipairs(KEYS) do \
+     if redis.call('GET', key) == ARGV[1] then released = released + redis.call('DEL', key) end \
+     end return released";
+
+fn default_handoff_hours() -> u32 {
+    24
+}
+
+fn default_key_prefix() -> String {
+    "gh-issues-bot".to_string()
+}
+
+/// Our seat in a team sharing one claim queue, so no issue gets claimed by
+/// two members
+pub struct Team {
+    config: TeamConfig,
+    member: String,
+    redis: RedisClient,
+}
+
+impl Team {
+    pub fn new(config: TeamConfig, user_login: &str) -> Result<Self> {
+        let redis = RedisClient::new(config.redis_url.expose())?;
+        let member = config
+            .member
+            .clone()
+            .unwrap_or_else(|| user_login.to_string());
+        if !config.members.is_empty()
+            && !config
+                .members
+                .iter()
+                .any(|other| other.eq_ignore_ascii_case(&member))
+        {
+            return Err(anyhow::anyhow!(
+                "Team member {} is missing from the members list",
+                member
+            ));
+        }
+
+        Ok(Self {
+            config,
+            member,
+            redis,
+        })
+    }
+
+    pub fn member(&self) -> &str {
+        &self.member
+    }
+
+    /// The member an issue should go to, None if anyone may take it
+    pub fn preferred_member(&self, issue: &Issue) -> Option<&str> {
+        let members = &self.config.members;
+        if members.is_empty() {
+            return None;
+        }
+
+        let everyone: Vec<&String> = members.iter().collect();
+
+        match self.config.distribution {
+            Distribution::FirstCome => None,
+            Distribution::RoundRobin => Some(round_robin(&everyone, issue)),
+            Distribution::Skills => {
+                let score = |member: &String| {
+                    let skills = self.config.skills.get(member.as_str());
+                    issue
+                        .labels
+                        .iter()
+                        .filter(|label| {
+                            skills.is_some_and(|skills| {
+                                skills
+                                    .iter()
+                                    .any(|skill| skill.eq_ignore_ascii_case(&label.name))
+                            })
+                        })
+                        .count()
+                };
+                let best = members.iter().map(score).max().unwrap_or(0);
+                if best == 0 {
+                    return Some(round_robin(&everyone, issue));
+                }
+                let matching: Vec<&String> = members
+                    .iter()
+                    .filter(|member| score(member) == best)
+                    .collect();
+                Some(round_robin(&matching, issue))
+            }
+        }
+    }
+
+    /// Reserve the issue for us while we post the claim, if it's ours to
+    /// take. Returns false when it's meant for a teammate or one of them
+    /// claimed it or is about to.
+    pub async fn reserve(&self, owner: &str, repo: &str, issue: &Issue) -> Result<bool> {
+        let key = processed::key(owner, repo, issue.number);
+
+        if let Some(preferred) = self.preferred_member(issue) {
+            if !preferred.eq_ignore_ascii_case(&self.member) && !self.handed_off(&key).await? {
+                return Ok(false);
+            }
+        }
+
+        // Held by us too when we claimed it, or reserved it, on an earlier poll
+        let holder = self
+            .redis
+            .command(&[
+                "EVAL",
+                RESERVE_SCRIPT,
+                "2",
+                &self.key("claim", &key),
+                &self.key("reservation", &key),
+                &self.member,
+                RESERVATION_SECS,
+            ])
+            .await?;
+        Ok(holder.as_str() == Some(self.member.as_str()))
+    }
+
+    /// Record that we claimed the issue we reserved. The claim stays until
+    /// released, so no teammate claims the issue after ours times out.
+    pub async fn claim(&self, owner: &str, repo: &str, issue_number: u64) -> Result<()> {
+        let key = processed::key(owner, repo, issue_number);
+        let holder = self
+            .redis
+            .command(&[
+                "EVAL",
+                CLAIM_SCRIPT,
+                "2",
+                &self.key("claim", &key),
+                &self.key("reservation", &key),
+                &self.member,
+            ])
+            .await?;
+
+        match holder {
+            Reply::Bulk(ref holder) if *holder != self.member => Err(anyhow::anyhow!(
+                "{} claimed issue #{} in {}/{} as well",
+                holder,
+                issue_number,
+                owner,
+                repo
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Give up our claim or reservation, e.g. after failing to post the claim
+    /// or abandoning the issue
+    pub async fn release(&self, owner: &str, repo: &str, issue_number: u64) -> Result<()> {
+        let key = processed::key(owner, repo, issue_number);
+        self.redis
+            .command(&[
+                "EVAL",
+                RELEASE_SCRIPT,
+                "2",
+                &self.key("claim", &key),
+                &self.key("reservation", &key),
+                &self.member,
+            ])
+            .await?;
+        Ok(())
+    }
+
+    /// Whether the member an issue went to had their chance. The first bot in
+    /// the team to see the issue starts the clock.
+    async fn handed_off(&self, key: &str) -> Result<bool> {
+        let seen_key = self.key("seen", key);
+        let now = Utc::now().timestamp();
+        let handoff_secs = self.config.handoff_hours as i64 * 3600;
+        let expiry = (handoff_secs * 2).max(3600).to_string();
+
+        self.redis
+            .command(&["SET", &seen_key, &now.to_string(), "NX", "EX", &expiry])
+            .await?;
+        let first_seen = self
+            .redis
+            .command(&["GET", &seen_key])
+            .await?
+            .as_str()
+            .and_then(|seen| seen.parse::<i64>().ok())
+            .unwrap_or(now);
+
+        Ok(now - first_seen >= handoff_secs)
+    }
+
+    fn key(&self, kind: &str, issue_key: &str) -> String {
+        format!("{}:{}:{}", self.config.key_prefix, kind, issue_key)
+    }
+}
+
+/// Spread issues over members by id, the same way on every bot
+fn round_robin<'a>(candidates: &[&'a String], issue: &Issue) -> &'a str {
+    candidates[(issue.id % candidates.len() as u64) as usize].as_str()
+}
@@ -0,0 +1,66 @@
+use anyhow::Result;
+use tracing::warn;
+use tracing_subscriber::layer::SubscriberExt;
+
+use crate::logging::LogLayer;
+
+/// Keeps the OTLP exporter alive, flushing pending spans when dropped
+pub struct Telemetry {
+    #[cfg(feature = "otel")]
+    provider: opentelemetry_sdk::trace::SdkTracerProvider,
+}
+
+/// Pass tracing events on to the logger and, if an endpoint is configured,
+/// export spans to an OTLP collector. Call once, after `logging::init`.
+#[cfg(feature = "otel")]
+pub fn init(endpoint: Option<&str>) -> Result<Option<Telemetry>> {
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry_otlp::WithExportConfig;
+
+    let Some(endpoint) = endpoint else {
+        tracing::subscriber::set_global_default(tracing_subscriber::registry().with(LogLayer))?;
+        return Ok(None);
+    };
+
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(endpoint)
+        .build()?;
+    let provider = opentelemetry_sdk::trace::SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(
+            opentelemetry_sdk::Resource::builder()
+                .with_service_name(env!("CARGO_PKG_NAME"))
+                .build(),
+        )
+        .build();
+
+    let tracer = provider.tracer(env!("CARGO_PKG_NAME"));
+    let subscriber = tracing_subscriber::registry()
+        .with(LogLayer)
+        .with(tracing_opentelemetry::layer().with_tracer(tracer));
+    tracing::subscriber::set_global_default(subscriber)?;
+
+    tracing::info!("Exporting traces to {}", endpoint);
+
+    Ok(Some(Telemetry { provider }))
+}
+
+#[cfg(not(feature = "otel"))]
+pub fn init(endpoint: Option<&str>) -> Result<Option<Telemetry>> {
+    tracing::subscriber::set_global_default(tracing_subscriber::registry().with(LogLayer))?;
+    if endpoint.is_some() {
+        warn!("Exporting traces needs the otel feature, ignoring otlp_endpoint");
+    }
+
+    Ok(None)
+}
+
+#[cfg(feature = "otel")]
+impl Drop for Telemetry {
+    fn drop(&mut self) {
+        if let Err(e) = self.provider.shutdown() {
+            warn!("Failed to flush traces: {}", e);
+        }
+    }
+}
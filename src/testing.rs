@@ -0,0 +1,573 @@
+//! A scriptable stand-in for GitHub, to run the bot against without a
+//! network or a token.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+use crate::config::Repository;
+use crate::fixtures::Fixture;
+use crate::forge::Forge;
+use crate::github::{
+    matches_filters, Comment, GitHubError, Issue, LinkedPullRequest, NotificationThread,
+    PullRequest, RepositoryInfo, Result, User,
+};
+use crate::ratelimit::{ApiUsage, RateLimit};
+
+/// A call the bot made, e.g. `comment_on_issue` with `["owner", "repo", "12"]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Call {
+    pub method: &'static str,
+    pub args: Vec<String>,
+}
+
+#[derive(Default)]
+struct MockState {
+    issues: HashMap<String, Vec<Issue>>,
+    comments: HashMap<String, Vec<Comment>>,
+    search_results: HashMap<String, Vec<Issue>>,
+    commit_search_results: HashMap<String, Vec<String>>,
+    linked_prs: HashMap<String, Vec<LinkedPullRequest>>,
+    files: HashMap<String, String>,
+    notifications: Vec<NotificationThread>,
+    rate_limit: Option<RateLimit>,
+    can_assign: bool,
+    failures: HashMap<&'static str, VecDeque<GitHubError>>,
+    calls: Vec<Call>,
+    next_id: u64,
+}
+
+/// A `Forge` that answers from the issues and comments it was given,
+/// keeps track of what the bot posts, and records every call.
+///
+/// Comments and assignments made through it show up in later responses, so
+/// a bot running against it sees its own claims like it would on GitHub.
+pub struct MockGitHubClient {
+    username: String,
+    state: Mutex<MockState>,
+}
+
+impl MockGitHubClient {
+    pub fn new(username: &str) -> Self {
+        Self {
+            username: username.to_string(),
+            state: Mutex::new(MockState {
+                next_id: 1,
+                ..MockState::default()
+            }),
+        }
+    }
+
+    /// A client serving the issues of fixtures made with `capture`
+    pub fn from_fixtures(username: &str, fixtures: &[Fixture]) -> Self {
+        fixtures
+            .iter()
+            .fold(Self::new(username), |client, fixture| {
+                client.with_issues(&fixture.owner, &fixture.repo, fixture.issues.clone())
+            })
+    }
+
+    /// The open issues of a repository, replacing any given before
+    pub fn with_issues(self, owner: &str, repo: &str, issues: Vec<Issue>) -> Self {
+        self.state
+            .lock()
+            .unwrap()
+            .issues
+            .insert(repo_key(owner, repo), issues);
+        self
+    }
+
+    pub fn with_comments(
+        self,
+        owner: &str,
+        repo: &str,
+        issue_number: u64,
+        comments: Vec<Comment>,
+    ) -> Self {
+        self.state
+            .lock()
+            .unwrap()
+            .comments
+            .insert(issue_key(owner, repo, issue_number), comments);
+        self
+    }
+
+    /// Issues returned for an exact search query, which also sets its count
+    pub fn with_search_results(self, query: &str, issues: Vec<Issue>) -> Self {
+        self.state
+            .lock()
+            .unwrap()
+            .search_results
+            .insert(query.to_string(), issues);
+        self
+    }
+
+    /// Repositories, as `owner/name`, a commit search query finds commits in
+    pub fn with_commit_search_results(self, query: &str, repos: &[&str]) -> Self {
+        self.state.lock().unwrap().commit_search_results.insert(
+            query.to_string(),
+            repos.iter().map(|repo| repo.to_string()).collect(),
+        );
+        self
+    }
+
+    pub fn with_linked_pull_requests(
+        self,
+        owner: &str,
+        repo: &str,
+        issue_number: u64,
+        pull_requests: Vec<LinkedPullRequest>,
+    ) -> Self {
+        self.state
+            .lock()
+            .unwrap()
+            .linked_prs
+            .insert(issue_key(owner, repo, issue_number), pull_requests);
+        self
+    }
+
+    pub fn with_file(self, owner: &str, repo: &str, path: &str, content: &str) -> Self {
+        self.state.lock().unwrap().files.insert(
+            format!("{}/{}", repo_key(owner, repo), path),
+            content.to_string(),
+        );
+        self
+    }
+
+    pub fn with_notifications(self, notifications: Vec<NotificationThread>) -> Self {
+        self.state.lock().unwrap().notifications = notifications;
+        self
+    }
+
+    /// Defaults to the full quota of 5000 requests, resetting in an hour
+    pub fn with_rate_limit(self, remaining: u32, reset_at: DateTime<Utc>) -> Self {
+        self.state.lock().unwrap().rate_limit = Some(RateLimit {
+            remaining,
+            reset_at,
+        });
+        self
+    }
+
+    /// Whether we may assign ourselves directly, no by default
+    pub fn with_assign_permission(self, can_assign: bool) -> Self {
+        self.state.lock().unwrap().can_assign = can_assign;
+        self
+    }
+
+    /// Make the next call of `method` fail with `error`. Queued failures are
+    /// used up in order, one per call.
+    pub fn fail_next(&self, method: &'static str, error: GitHubError) {
+        self.state
+            .lock()
+            .unwrap()
+            .failures
+            .entry(method)
+            .or_default()
+            .push_back(error);
+    }
+
+    /// Every call made so far, oldest first
+    pub fn calls(&self) -> Vec<Call> {
+        self.state.lock().unwrap().calls.clone()
+    }
+
+    /// The calls made to one method
+    pub fn calls_to(&self, method: &str) -> Vec<Call> {
+        self.calls()
+            .into_iter()
+            .filter(|call| call.method == method)
+            .collect()
+    }
+
+    /// Comments on an issue, including the ones the bot posted
+    pub fn comments(&self, owner: &str, repo: &str, issue_number: u64) -> Vec<Comment> {
+        self.state
+            .lock()
+            .unwrap()
+            .comments
+            .get(&issue_key(owner, repo, issue_number))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Record a call, returning the failure queued for it if there is one
+    fn call(&self, method: &'static str, args: &[&dyn ToString]) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.calls.push(Call {
+            method,
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+        });
+
+        match state
+            .failures
+            .get_mut(method)
+            .and_then(|queue| queue.pop_front())
+        {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    fn not_found(what: String) -> GitHubError {
+        GitHubError::NotFound(format!("{} not found in the mock", what))
+    }
+}
+
+fn repo_key(owner: &str, repo: &str) -> String {
+    format!("{}/{}", owner, repo)
+}
+
+fn issue_key(owner: &str, repo: &str, issue_number: u64) -> String {
+    format!("{}/{}#{}", owner, repo, issue_number)
+}
+
+#[async_trait]
+impl Forge for MockGitHubClient {
+    async fn get_open_issues(&self, repo: &Repository) -> Result<Vec<Issue>> {
+        self.call("get_open_issues", &[&repo.owner, &repo.repo])?;
+
+        let state = self.state.lock().unwrap();
+        let issues = state
+            .issues
+            .get(&repo_key(&repo.owner, &repo.repo))
+            .map(|issues| {
+                issues
+                    .iter()
+                    .filter(|issue| issue.state == "open" && matches_filters(repo, issue))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(issues)
+    }
+
+    async fn get_issue(&self, owner: &str, repo: &str, issue_number: u64) -> Result<Issue> {
+        self.call("get_issue", &[&owner, &repo, &issue_number])?;
+
+        let state = self.state.lock().unwrap();
+        state
+            .issues
+            .get(&repo_key(owner, repo))
+            .and_then(|issues| issues.iter().find(|issue| issue.number == issue_number))
+            .cloned()
+            .ok_or_else(|| Self::not_found(issue_key(owner, repo, issue_number)))
+    }
+
+    async fn comment_on_issue(
+        &self,
+        owner: &str,
+        repo: &str,
+        issue_number: u64,
+        comment: &str,
+    ) -> Result<u64> {
+        self.call(
+            "comment_on_issue",
+            &[&owner, &repo, &issue_number, &comment],
+        )?;
+
+        let mut state = self.state.lock().unwrap();
+        let id = state.next_id;
+        state.next_id += 1;
+        state
+            .comments
+            .entry(issue_key(owner, repo, issue_number))
+            .or_default()
+            .push(Comment {
+                id,
+                body: comment.to_string(),
+                user: User {
+                    login: self.username.clone(),
+                },
+                html_url: format!(
+                    "https://github.com/{}/{}/issues/{}#issuecomment-{}",
+                    owner, repo, issue_number, id
+                ),
+                created_at: Utc::now(),
+                author_association: None,
+            });
+
+        Ok(id)
+    }
+
+    async fn edit_comment(
+        &self,
+        owner: &str,
+        repo: &str,
+        comment_id: u64,
+        comment: &str,
+    ) -> Result<()> {
+        self.call("edit_comment", &[&owner, &repo, &comment_id, &comment])?;
+
+        let mut state = self.state.lock().unwrap();
+        let prefix = format!("{}#", repo_key(owner, repo));
+        let existing = state
+            .comments
+            .iter_mut()
+            .filter(|(key, _)| key.starts_with(&prefix))
+            .flat_map(|(_, comments)| comments.iter_mut())
+            .find(|existing| existing.id == comment_id)
+            .ok_or_else(|| Self::not_found(format!("Comment {}", comment_id)))?;
+        existing.body = comment.to_string();
+
+        Ok(())
+    }
+
+    async fn delete_comment(&self, owner: &str, repo: &str, comment_id: u64) -> Result<()> {
+        self.call("delete_comment", &[&owner, &repo, &comment_id])?;
+
+        let mut state = self.state.lock().unwrap();
+        let prefix = format!("{}#", repo_key(owner, repo));
+        for (_, comments) in state
+            .comments
+            .iter_mut()
+            .filter(|(key, _)| key.starts_with(&prefix))
+        {
+            comments.retain(|existing| existing.id != comment_id);
+        }
+
+        Ok(())
+    }
+
+    async fn add_reaction(
+        &self,
+        owner: &str,
+        repo: &str,
+        issue_number: u64,
+        content: &str,
+    ) -> Result<()> {
+        self.call("add_reaction", &[&owner, &repo, &issue_number, &content])
+    }
+
+    async fn get_rate_limit(&self) -> Result<RateLimit> {
+        self.call("get_rate_limit", &[])?;
+
+        let rate_limit = self.state.lock().unwrap().rate_limit;
+        Ok(rate_limit.unwrap_or_else(|| RateLimit {
+            remaining: 5000,
+            reset_at: Utc::now() + chrono::Duration::hours(1),
+        }))
+    }
+
+    async fn can_assign_self(&self, owner: &str, repo: &str) -> Result<bool> {
+        self.call("can_assign_self", &[&owner, &repo])?;
+
+        Ok(self.state.lock().unwrap().can_assign)
+    }
+
+    async fn assign_self(&self, owner: &str, repo: &str, issue_number: u64) -> Result<()> {
+        self.call("assign_self", &[&owner, &repo, &issue_number])?;
+
+        let mut state = self.state.lock().unwrap();
+        let issue = state
+            .issues
+            .get_mut(&repo_key(owner, repo))
+            .and_then(|issues| issues.iter_mut().find(|issue| issue.number == issue_number))
+            .ok_or_else(|| Self::not_found(issue_key(owner, repo, issue_number)))?;
+        if !issue.is_assigned_to(&self.username) {
+            issue
+                .assignees
+                .push(serde_json::json!({ "login": self.username }));
+        }
+
+        Ok(())
+    }
+
+    async fn unassign_self(&self, owner: &str, repo: &str, issue_number: u64) -> Result<()> {
+        self.call("unassign_self", &[&owner, &repo, &issue_number])?;
+
+        let mut state = self.state.lock().unwrap();
+        if let Some(issue) = state
+            .issues
+            .get_mut(&repo_key(owner, repo))
+            .and_then(|issues| issues.iter_mut().find(|issue| issue.number == issue_number))
+        {
+            let is_us = |assignee: &serde_json::Value| {
+                assignee["login"]
+                    .as_str()
+                    .is_some_and(|login| login.eq_ignore_ascii_case(&self.username))
+            };
+            issue.assignees.retain(|assignee| !is_us(assignee));
+            if issue.assignee.as_ref().is_some_and(is_us) {
+                issue.assignee = None;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn get_recent_comments(&self, owner: &str, repo: &str) -> Result<Vec<Comment>> {
+        self.call("get_recent_comments", &[&owner, &repo])?;
+
+        let state = self.state.lock().unwrap();
+        let prefix = format!("{}#", repo_key(owner, repo));
+        let mut comments: Vec<Comment> = state
+            .comments
+            .iter()
+            .filter(|(key, _)| key.starts_with(&prefix))
+            .flat_map(|(_, comments)| comments.iter().cloned())
+            .collect();
+        comments.sort_by_key(|comment| std::cmp::Reverse(comment.created_at));
+
+        Ok(comments)
+    }
+
+    async fn get_issue_comments(
+        &self,
+        owner: &str,
+        repo: &str,
+        issue_number: u64,
+    ) -> Result<Vec<Comment>> {
+        self.call("get_issue_comments", &[&owner, &repo, &issue_number])?;
+
+        Ok(self.comments(owner, repo, issue_number))
+    }
+
+    async fn get_comment(&self, owner: &str, repo: &str, comment_id: u64) -> Result<Comment> {
+        self.call("get_comment", &[&owner, &repo, &comment_id])?;
+
+        let state = self.state.lock().unwrap();
+        let prefix = format!("{}#", repo_key(owner, repo));
+        state
+            .comments
+            .iter()
+            .filter(|(key, _)| key.starts_with(&prefix))
+            .flat_map(|(_, comments)| comments.iter())
+            .find(|comment| comment.id == comment_id)
+            .cloned()
+            .ok_or_else(|| Self::not_found(format!("Comment {}", comment_id)))
+    }
+
+    async fn get_notifications(&self, since: DateTime<Utc>) -> Result<Vec<NotificationThread>> {
+        self.call("get_notifications", &[&since])?;
+
+        let state = self.state.lock().unwrap();
+        Ok(state
+            .notifications
+            .iter()
+            .filter(|thread| thread.updated_at > since)
+            .cloned()
+            .collect())
+    }
+
+    async fn search_issues(&self, query: &str) -> Result<Vec<Issue>> {
+        self.call("search_issues", &[&query])?;
+
+        let state = self.state.lock().unwrap();
+        Ok(state.search_results.get(query).cloned().unwrap_or_default())
+    }
+
+    async fn count_issues(&self, query: &str) -> Result<u64> {
+        self.call("count_issues", &[&query])?;
+
+        let state = self.state.lock().unwrap();
+        Ok(state.search_results.get(query).map_or(0, Vec::len) as u64)
+    }
+
+    async fn search_commit_repos(&self, query: &str) -> Result<Vec<String>> {
+        self.call("search_commit_repos", &[&query])?;
+
+        let state = self.state.lock().unwrap();
+        Ok(state
+            .commit_search_results
+            .get(query)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    async fn create_fork(&self, owner: &str, repo: &str) -> Result<RepositoryInfo> {
+        self.call("create_fork", &[&owner, &repo])?;
+
+        Ok(RepositoryInfo {
+            name: repo.to_string(),
+            owner: User {
+                login: self.username.clone(),
+            },
+            default_branch: "main".to_string(),
+        })
+    }
+
+    async fn get_branch_sha(&self, owner: &str, repo: &str, branch: &str) -> Result<String> {
+        self.call("get_branch_sha", &[&owner, &repo, &branch])?;
+
+        Ok("0".repeat(40))
+    }
+
+    async fn create_branch(&self, owner: &str, repo: &str, branch: &str, sha: &str) -> Result<()> {
+        self.call("create_branch", &[&owner, &repo, &branch, &sha])
+    }
+
+    async fn get_linked_pull_requests(
+        &self,
+        owner: &str,
+        repo: &str,
+        issue_number: u64,
+    ) -> Result<Vec<LinkedPullRequest>> {
+        self.call("get_linked_pull_requests", &[&owner, &repo, &issue_number])?;
+
+        let state = self.state.lock().unwrap();
+        Ok(state
+            .linked_prs
+            .get(&issue_key(owner, repo, issue_number))
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    async fn create_empty_commit(
+        &self,
+        owner: &str,
+        repo: &str,
+        branch: &str,
+        message: &str,
+    ) -> Result<()> {
+        self.call("create_empty_commit", &[&owner, &repo, &branch, &message])
+    }
+
+    async fn create_draft_pull_request(
+        &self,
+        owner: &str,
+        repo: &str,
+        head: &str,
+        base: &str,
+        title: &str,
+        body: &str,
+    ) -> Result<PullRequest> {
+        self.call(
+            "create_draft_pull_request",
+            &[&owner, &repo, &head, &base, &title, &body],
+        )?;
+
+        let mut state = self.state.lock().unwrap();
+        let number = state.next_id;
+        state.next_id += 1;
+
+        Ok(PullRequest {
+            number,
+            html_url: format!("https://github.com/{}/{}/pull/{}", owner, repo, number),
+        })
+    }
+
+    async fn get_file_content(
+        &self,
+        owner: &str,
+        repo: &str,
+        path: &str,
+    ) -> Result<Option<String>> {
+        self.call("get_file_content", &[&owner, &repo, &path])?;
+
+        let state = self.state.lock().unwrap();
+        Ok(state
+            .files
+            .get(&format!("{}/{}", repo_key(owner, repo), path))
+            .cloned())
+    }
+
+    fn api_usage(&self) -> ApiUsage {
+        let state = self.state.lock().unwrap();
+        ApiUsage {
+            requests: state.calls.len() as u64,
+            remaining: state.rate_limit.map(|rate_limit| rate_limit.remaining),
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::time::Duration as StdDuration;
+use tokio::time;
+use tracing::{debug, info};
+
+use crate::bot::AssignedIssue;
+use crate::forge::Forge;
+use crate::github::PullRequest;
+
+/// Forks can take a moment to become usable after being created
+const BRANCH_ATTEMPTS: u32 = 5;
+const BRANCH_RETRY_DELAY_SECS: u64 = 5;
+
+const MAX_SLUG_LEN: usize = 40;
+
+/// The fork and branch prepared for working on an assigned issue
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkBranch {
+    pub fork_owner: String,
+    pub fork_name: String,
+    pub branch: String,
+    pub base_branch: String,
+}
+
+/// Fork the issue's repository (if not already forked) and create a branch for it
+pub async fn prepare_branch<T: Forge + ?Sized>(
+    client: &T,
+    issue: &AssignedIssue,
+) -> Result<WorkBranch> {
+    let fork_info = client
+        .create_fork(&issue.repo_owner, &issue.repo_name)
+        .await?;
+    let fork = WorkBranch {
+        fork_owner: fork_info.owner.login.clone(),
+        fork_name: fork_info.name.clone(),
+        branch: branch_name(issue.issue_number, &issue.issue_title),
+        base_branch: fork_info.default_branch.clone(),
+    };
+
+    let sha = client
+        .get_branch_sha(
+            &issue.repo_owner,
+            &issue.repo_name,
+            &fork_info.default_branch,
+        )
+        .await?;
+
+    let mut attempt = 1;
+    loop {
+        match client
+            .create_branch(&fork.fork_owner, &fork.fork_name, &fork.branch, &sha)
+            .await
+        {
+            Ok(()) => break,
+            Err(e) if attempt < BRANCH_ATTEMPTS => {
+                debug!(
+                    "Could not create branch {} yet (attempt {}): {}",
+                    fork.branch, attempt, e
+                );
+                attempt += 1;
+                time::sleep(StdDuration::from_secs(BRANCH_RETRY_DELAY_SECS)).await;
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    info!(
+        "Created branch {} in {}/{}",
+        fork.branch, fork.fork_owner, fork.fork_name
+    );
+
+    Ok(fork)
+}
+
+/// Open a draft PR from the work branch that links back to the issue
+pub async fn open_draft_pr<T: Forge + ?Sized>(
+    client: &T,
+    issue: &AssignedIssue,
+    branch: &WorkBranch,
+    body_template: &str,
+) -> Result<PullRequest> {
+    // GitHub refuses PRs without commits, so start with an empty one
+    client
+        .create_empty_commit(
+            &branch.fork_owner,
+            &branch.fork_name,
+            &branch.branch,
+            &format!("Start work on #{}", issue.issue_number),
+        )
+        .await?;
+
+    let body = body_template
+        .replace("{issue_number}", &issue.issue_number.to_string())
+        .replace("{issue_url}", &issue.issue_url)
+        .replace("{title}", &issue.issue_title);
+
+    let pull_request = client
+        .create_draft_pull_request(
+            &issue.repo_owner,
+            &issue.repo_name,
+            &format!("{}:{}", branch.fork_owner, branch.branch),
+            &branch.base_branch,
+            &format!("WIP: {}", issue.issue_title),
+            &body,
+        )
+        .await?;
+
+    info!("Opened draft pull request {}", pull_request.html_url);
+
+    Ok(pull_request)
+}
+
+/// Build a branch name like `issue-123-fix-the-parser`
+pub fn branch_name(issue_number: u64, title: &str) -> String {
+    let mut slug = String::new();
+    for word in title
+        .to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+    {
+        if slug.len() + word.len() + 1 > MAX_SLUG_LEN {
+            break;
+        }
+        slug.push('-');
+        slug.push_str(word);
+    }
+
+    format!("issue-{}{}", issue_number, slug)
+}
@@ -0,0 +1,409 @@
+//! Polls driven through `MockGitHubClient`, checking what the bot posts and
+//! what it remembers afterwards.
+
+use chrono::{Duration, Utc};
+use gh_issues_bot::bot::{AssignedIssue, ClaimIntent};
+use gh_issues_bot::crypto::StateKey;
+use gh_issues_bot::github::{Comment, GitHubError, Issue, LinkedPullRequest, PullRequestState};
+use gh_issues_bot::history::Outcome;
+use gh_issues_bot::processed::ProcessedReason;
+use gh_issues_bot::schema::CURRENT_VERSION;
+use gh_issues_bot::testing::MockGitHubClient;
+use gh_issues_bot::{Bot, Config, FilePersistence, Persistence};
+use serde_json::json;
+use std::path::PathBuf;
+
+const CONFIG: &str = r#"
+auth_token = "token"
+user_login = "me"
+comment_templates = ["May I work on this?"]
+
+[[repositories]]
+owner = "owner"
+repo = "repo"
+"#;
+
+fn issue(number: u64, assignee: Option<&str>) -> Issue {
+    let assignees: Vec<_> = assignee
+        .map(|login| json!({ "login": login }))
+        .into_iter()
+        .collect();
+    serde_json::from_value(json!({
+        "id": 1000 + number,
+        "number": number,
+        "title": format!("Issue {}", number),
+        "html_url": format!("https://github.com/owner/repo/issues/{}", number),
+        "state": "open",
+        "created_at": Utc::now() - Duration::days(10 - number as i64),
+        "updated_at": Utc::now() - Duration::days(1),
+        "assignee": null,
+        "assignees": assignees,
+        "labels": [],
+    }))
+    .unwrap()
+}
+
+fn own_comment(number: u64, created_at: chrono::DateTime<Utc>) -> Comment {
+    serde_json::from_value(json!({
+        "id": 500 + number,
+        "body": "May I work on this?",
+        "user": { "login": "me" },
+        "html_url": format!("https://github.com/owner/repo/issues/{}#issuecomment-1", number),
+        "created_at": created_at,
+    }))
+    .unwrap()
+}
+
+/// A fresh data directory for each test
+fn data_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("gh-issues-bot-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+async fn bot(dir: &PathBuf, forge: MockGitHubClient) -> Bot<MockGitHubClient, FilePersistence> {
+    let config = Config::from_toml(CONFIG).unwrap();
+    let persistence = FilePersistence::new(dir).await.unwrap();
+    let mut bot = Bot::new(config, forge, persistence);
+    bot.initialize().await.unwrap();
+    bot
+}
+
+fn claimed(bot: &Bot<MockGitHubClient, FilePersistence>) -> Vec<String> {
+    bot.forge()
+        .calls_to("comment_on_issue")
+        .into_iter()
+        .map(|call| call.args[2].clone())
+        .collect()
+}
+
+#[tokio::test]
+async fn claims_the_oldest_eligible_issue() {
+    let dir = data_dir("claim");
+    let forge = MockGitHubClient::new("me").with_issues(
+        "owner",
+        "repo",
+        vec![issue(3, None), issue(1, None), issue(2, None)],
+    );
+    let bot = bot(&dir, forge).await;
+
+    bot.run_once().await.unwrap();
+
+    assert_eq!(claimed(&bot), vec!["1"]);
+    let comments = bot.forge().comments("owner", "repo", 1);
+    assert_eq!(comments.len(), 1);
+    assert_eq!(comments[0].body, "May I work on this?");
+
+    let persistence = FilePersistence::new(&dir).await.unwrap();
+    let active = persistence.load_active_issue().await.unwrap().unwrap();
+    assert_eq!(active.issue_number, 1);
+
+    // Waiting for an answer, so the next poll doesn't claim another one
+    bot.run_once().await.unwrap();
+    assert_eq!(claimed(&bot), vec!["1"]);
+}
+
+#[tokio::test]
+async fn skips_issues_someone_is_assigned_to() {
+    let dir = data_dir("skip-assigned");
+    let forge = MockGitHubClient::new("me").with_issues(
+        "owner",
+        "repo",
+        vec![issue(1, Some("someone")), issue(2, None)],
+    );
+    let bot = bot(&dir, forge).await;
+
+    bot.run_once().await.unwrap();
+
+    assert_eq!(claimed(&bot), vec!["2"]);
+    assert!(bot.forge().comments("owner", "repo", 1).is_empty());
+}
+
+#[tokio::test]
+async fn gives_up_on_a_claim_nobody_answered() {
+    let dir = data_dir("timeout");
+    let forge = MockGitHubClient::new("me").with_issues("owner", "repo", vec![issue(1, None)]);
+    bot(&dir, forge).await.run_once().await.unwrap();
+
+    // Let the claim run out, as if the bot was stopped for a few days
+    let persistence = FilePersistence::new(&dir).await.unwrap();
+    let mut active = persistence.load_active_issue().await.unwrap().unwrap();
+    active.requested_at -= Duration::days(3);
+    active.timeout -= Duration::days(3);
+    persistence.save_active_issue(&active).await.unwrap();
+
+    let forge = MockGitHubClient::new("me")
+        .with_issues("owner", "repo", vec![issue(1, None)])
+        .with_comments(
+            "owner",
+            "repo",
+            1,
+            vec![own_comment(1, active.requested_at)],
+        );
+    let bot = bot(&dir, forge).await;
+    bot.run_once().await.unwrap();
+
+    assert!(claimed(&bot).is_empty());
+    let persistence = FilePersistence::new(&dir).await.unwrap();
+    assert!(persistence.load_active_issue().await.unwrap().is_none());
+    let history = persistence.load_history().await.unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].outcome, Outcome::TimedOut);
+    let processed = persistence.load_processed_issues().await.unwrap();
+    let record = processed
+        .values()
+        .find(|record| record.issue_number == 1)
+        .unwrap();
+    assert_eq!(record.reason, ProcessedReason::TimedOut);
+}
+
+#[tokio::test]
+async fn tries_again_after_a_failed_listing() {
+    let dir = data_dir("failure");
+    let forge = MockGitHubClient::new("me").with_issues("owner", "repo", vec![issue(1, None)]);
+    forge.fail_next(
+        "get_open_issues",
+        GitHubError::Transient("502 Bad Gateway".to_string()),
+    );
+    let bot = bot(&dir, forge).await;
+
+    bot.run_once().await.unwrap();
+    assert!(claimed(&bot).is_empty());
+
+    bot.run_once().await.unwrap();
+    assert_eq!(claimed(&bot), vec!["1"]);
+}
+
+#[tokio::test]
+async fn leaves_an_issue_alone_when_the_claim_is_refused() {
+    let dir = data_dir("claim-refused");
+    let forge = MockGitHubClient::new("me").with_issues("owner", "repo", vec![issue(1, None)]);
+    forge.fail_next(
+        "comment_on_issue",
+        GitHubError::Forbidden("Issue is locked".to_string()),
+    );
+    let bot = bot(&dir, forge).await;
+
+    bot.run_once().await.unwrap();
+    bot.run_once().await.unwrap();
+
+    // The refused attempt is the only one
+    assert_eq!(claimed(&bot), vec!["1"]);
+    assert!(bot.forge().comments("owner", "repo", 1).is_empty());
+    let persistence = FilePersistence::new(&dir).await.unwrap();
+    assert!(persistence.load_active_issue().await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn picks_up_a_pending_claim_after_losing_state() {
+    let dir = data_dir("lost-state");
+    let forge = MockGitHubClient::new("me")
+        .with_issues("owner", "repo", vec![issue(1, None), issue(2, None)])
+        .with_comments(
+            "owner",
+            "repo",
+            2,
+            vec![own_comment(2, Utc::now() - Duration::hours(1))],
+        )
+        .with_search_results(
+            "is:issue is:open commenter:me repo:owner/repo",
+            vec![issue(2, None)],
+        );
+    let bot = bot(&dir, forge).await;
+
+    bot.run_once().await.unwrap();
+
+    assert!(claimed(&bot).is_empty());
+    let persistence = FilePersistence::new(&dir).await.unwrap();
+    let active = persistence.load_active_issue().await.unwrap().unwrap();
+    assert_eq!(active.issue_number, 2);
+}
+
+#[tokio::test]
+async fn refuses_to_start_on_state_it_cannot_read() {
+    let dir = data_dir("encrypted");
+    std::fs::create_dir_all(&dir).unwrap();
+    let key = StateKey::from_hex(&"ab".repeat(32)).unwrap();
+    let sealed = key.seal("null").unwrap();
+    std::fs::write(dir.join("active_issue.json"), &sealed).unwrap();
+
+    let config = Config::from_toml(CONFIG).unwrap();
+    let persistence = FilePersistence::new(&dir).await.unwrap();
+    let mut bot = Bot::new(config, MockGitHubClient::new("me"), persistence);
+    let error = bot.initialize().await.unwrap_err();
+
+    assert!(format!("{:#}", error).contains("State is encrypted"));
+    // Left as it was rather than replaced by empty state
+    let content = std::fs::read_to_string(dir.join("active_issue.json")).unwrap();
+    assert_eq!(content, sealed);
+}
+
+#[tokio::test]
+async fn refuses_to_start_on_state_from_a_newer_version() {
+    let dir = data_dir("newer-schema");
+    std::fs::create_dir_all(&dir).unwrap();
+    let content = json!({ "version": CURRENT_VERSION + 1, "data": [] }).to_string();
+    std::fs::write(dir.join("assigned_issues.json"), &content).unwrap();
+
+    let config = Config::from_toml(CONFIG).unwrap();
+    let persistence = FilePersistence::new(&dir).await.unwrap();
+    let mut bot = Bot::new(config, MockGitHubClient::new("me"), persistence);
+    let error = bot.initialize().await.unwrap_err();
+
+    assert!(format!("{:#}", error).contains("please upgrade"));
+    let left = std::fs::read_to_string(dir.join("assigned_issues.json")).unwrap();
+    assert_eq!(left, content);
+}
+
+#[tokio::test]
+async fn claims_with_the_keyword_of_the_repositorys_bot() {
+    let dir = data_dir("rustbot");
+    let forge = MockGitHubClient::new("me")
+        .with_issues("owner", "repo", vec![issue(1, None)])
+        .with_file("owner", "repo", "triagebot.toml", "[assign]\n");
+    let bot = bot(&dir, forge).await;
+
+    bot.run_once().await.unwrap();
+
+    let comments = bot.forge().comments("owner", "repo", 1);
+    assert_eq!(comments.len(), 1);
+    assert_eq!(comments[0].body, "@rustbot claim");
+}
+
+#[tokio::test]
+async fn moves_past_a_claim_that_went_unanswered_while_stopped() {
+    let dir = data_dir("stale-claim");
+    let forge = MockGitHubClient::new("me")
+        .with_issues("owner", "repo", vec![issue(1, None), issue(2, None)])
+        .with_comments(
+            "owner",
+            "repo",
+            1,
+            vec![own_comment(1, Utc::now() - Duration::days(3))],
+        );
+    let bot = bot(&dir, forge).await;
+
+    bot.run_once().await.unwrap();
+
+    assert_eq!(claimed(&bot), vec!["2"]);
+    let persistence = FilePersistence::new(&dir).await.unwrap();
+    let active = persistence.load_active_issue().await.unwrap().unwrap();
+    assert_eq!(active.issue_number, 2);
+    assert!(persistence.load_history().await.unwrap().is_empty());
+    let processed = persistence.load_processed_issues().await.unwrap();
+    let record = processed
+        .values()
+        .find(|record| record.issue_number == 1)
+        .unwrap();
+    assert_eq!(record.reason, ProcessedReason::TimedOut);
+}
+
+fn working_on(number: u64) -> AssignedIssue {
+    let now = Utc::now();
+    AssignedIssue {
+        repo_owner: "owner".to_string(),
+        repo_name: "repo".to_string(),
+        issue_number: number,
+        issue_url: format!("https://github.com/owner/repo/issues/{}", number),
+        issue_title: format!("Issue {}", number),
+        requested_at: now - Duration::days(2),
+        assigned_at: now - Duration::days(1),
+        due_at: now + Duration::days(6),
+        branch: None,
+        draft_pr_url: None,
+        linked_prs: Vec::new(),
+        prs_checked_at: None,
+        deadline_reminder: None,
+        template: None,
+        stale: false,
+        rule: None,
+    }
+}
+
+fn merged_pr(number: u64, author: &str) -> LinkedPullRequest {
+    LinkedPullRequest {
+        number,
+        html_url: format!("https://github.com/owner/repo/pull/{}", number),
+        author: author.to_string(),
+        state: PullRequestState::Merged,
+    }
+}
+
+#[tokio::test]
+async fn finishes_an_issue_only_when_our_pull_request_is_merged() {
+    let dir = data_dir("merged");
+    let persistence = FilePersistence::new(&dir).await.unwrap();
+    persistence
+        .save_assigned_issues(&[working_on(1)])
+        .await
+        .unwrap();
+
+    // Someone else's pull request merely mentions the issue
+    let forge = MockGitHubClient::new("me")
+        .with_issues("owner", "repo", vec![issue(1, Some("me"))])
+        .with_linked_pull_requests("owner", "repo", 1, vec![merged_pr(2, "someone")]);
+    bot(&dir, forge).await.run_once().await.unwrap();
+
+    let persistence = FilePersistence::new(&dir).await.unwrap();
+    assert_eq!(persistence.load_assigned_issues().await.unwrap().len(), 1);
+    assert!(persistence.load_history().await.unwrap().is_empty());
+
+    let mut assigned = persistence.load_assigned_issues().await.unwrap();
+    assigned[0].prs_checked_at = None;
+    persistence.save_assigned_issues(&assigned).await.unwrap();
+    let forge = MockGitHubClient::new("me")
+        .with_issues("owner", "repo", vec![issue(1, Some("me"))])
+        .with_linked_pull_requests(
+            "owner",
+            "repo",
+            1,
+            vec![merged_pr(2, "someone"), merged_pr(3, "me")],
+        );
+    bot(&dir, forge).await.run_once().await.unwrap();
+
+    let persistence = FilePersistence::new(&dir).await.unwrap();
+    assert!(persistence.load_assigned_issues().await.unwrap().is_empty());
+    let history = persistence.load_history().await.unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].outcome, Outcome::Completed);
+    assert_eq!(
+        history[0].pull_request_url.as_deref(),
+        Some("https://github.com/owner/repo/pull/3")
+    );
+}
+
+#[tokio::test]
+async fn keeps_asking_about_a_stale_assignment_after_a_crash() {
+    let dir = data_dir("stale-intent");
+    let persistence = FilePersistence::new(&dir).await.unwrap();
+    persistence
+        .save_claim_intent(&ClaimIntent {
+            repo_owner: "owner".to_string(),
+            repo_name: "repo".to_string(),
+            issue_number: 1,
+            created_at: Utc::now() - Duration::minutes(5),
+            stale_assignees: vec!["someone".to_string()],
+        })
+        .await
+        .unwrap();
+
+    let forge = MockGitHubClient::new("me")
+        .with_issues("owner", "repo", vec![issue(1, Some("someone"))])
+        .with_comments(
+            "owner",
+            "repo",
+            1,
+            vec![own_comment(1, Utc::now() - Duration::minutes(5))],
+        );
+    let bot = bot(&dir, forge).await;
+    bot.run_once().await.unwrap();
+
+    // Still waiting on an answer, rather than losing the claim to the
+    // assignee we asked about
+    let persistence = FilePersistence::new(&dir).await.unwrap();
+    let active = persistence.load_active_issue().await.unwrap().unwrap();
+    assert_eq!(active.issue_number, 1);
+    assert_eq!(active.stale_assignees, vec!["someone"]);
+    assert!(persistence.load_history().await.unwrap().is_empty());
+    assert!(persistence.load_claim_intent().await.unwrap().is_none());
+}